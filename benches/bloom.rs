@@ -0,0 +1,90 @@
+// benches/bloom.rs
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use bloom::BloomFilter;
+
+const ITEMS: usize = 1_000;
+const ARRAY_SIZES: [usize; 3] = [1_000, 100_000, 1_000_000];
+const HASH_COUNTS: [usize; 2] = [3, 7];
+const LEVEL_COUNTS: [usize; 2] = [1, 4];
+
+/// Storage layouts to compare; the sparse layout needs the `roaring` feature.
+fn layouts() -> Vec<&'static str> {
+    let mut layouts = vec!["dense"];
+    if cfg!(feature = "roaring") {
+        layouts.push("sparse");
+    }
+    layouts
+}
+
+/// Every (layout, level count) pair to benchmark.
+fn configurations() -> Vec<(&'static str, usize)> {
+    layouts()
+        .into_iter()
+        .flat_map(|layout| LEVEL_COUNTS.iter().map(move |&levels| (layout, levels)))
+        .collect()
+}
+
+fn new_filter(layout: &str, levels: usize, array_size: usize, hashes: usize) -> BloomFilter {
+    match layout {
+        #[cfg(feature = "roaring")]
+        "sparse" => BloomFilter::new_sparse(levels, array_size, hashes).unwrap(),
+        _ => BloomFilter::new(levels, array_size, hashes).unwrap(),
+    }
+}
+
+fn items(prefix: &str) -> Vec<String> {
+    (0..ITEMS).map(|i| format!("{}-{}", prefix, i)).collect()
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let items = items("item");
+    let mut group = c.benchmark_group("insert");
+    group.throughput(Throughput::Elements(ITEMS as u64));
+    for &array_size in &ARRAY_SIZES {
+        for &hashes in &HASH_COUNTS {
+            for (layout, levels) in configurations() {
+                let id = BenchmarkId::from_parameter(format!("{}/m={}/k={}/levels={}", layout, array_size, hashes, levels));
+                group.bench_with_input(id, &items, |b, items| {
+                    let mut bf = new_filter(layout, levels, array_size, hashes);
+                    b.iter(|| {
+                        for item in items {
+                            bf.insert(black_box(item));
+                        }
+                    });
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+fn bench_query(c: &mut Criterion) {
+    let present = items("item");
+    let absent = items("missing");
+    let mut group = c.benchmark_group("query");
+    group.throughput(Throughput::Elements(2 * ITEMS as u64));
+    for &array_size in &ARRAY_SIZES {
+        for &hashes in &HASH_COUNTS {
+            for (layout, levels) in configurations() {
+                let mut bf = new_filter(layout, levels, array_size, hashes);
+                for item in &present {
+                    bf.insert(item);
+                }
+                let id = BenchmarkId::from_parameter(format!("{}/m={}/k={}/levels={}", layout, array_size, hashes, levels));
+                group.bench_with_input(id, &bf, |b, bf| {
+                    b.iter(|| {
+                        for item in present.iter().chain(&absent) {
+                            black_box(bf.query(black_box(item), levels));
+                        }
+                    });
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert, bench_query);
+criterion_main!(benches);
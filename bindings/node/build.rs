@@ -0,0 +1,5 @@
+// bindings/node/build.rs
+
+fn main() {
+    napi_build::setup();
+}
@@ -0,0 +1,96 @@
+// bindings/node/src/lib.rs
+
+//! Node.js bindings for `bloom`. Build the addon with `npm run build` (via
+//! `@napi-rs/cli`), or `cargo build --release` and copy the shared library to
+//! `bloom.node`; then `const { BloomFilter } = require("./bloom.node")`.
+
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, Result};
+use napi_derive::napi;
+
+use bloom::bloom_filter::BloomFilterError;
+use bloom::{BloomFilter, FileFormat};
+
+/// A multi-level Bloom filter exposed to Node.js as `BloomFilter`.
+///
+/// Files are read and written with the same code as the Rust tooling, so a
+/// filter built by `bloom generate` loads here and vice versa. Method names
+/// are camelCased on the JavaScript side (`insertBytes`, `queryBytes`), and
+/// failures throw an `Error` carrying the `BloomFilterError` message.
+#[napi(js_name = "BloomFilter")]
+pub struct NodeBloomFilter {
+    inner: BloomFilter,
+}
+
+#[napi]
+impl NodeBloomFilter {
+    /// Creates an empty filter: `new BloomFilter(levels, arraySize, hashFunctions)`.
+    #[napi(constructor)]
+    pub fn new(num_levels: u32, array_size: u32, num_hash_functions: u32) -> Result<Self> {
+        let inner = BloomFilter::new(num_levels as usize, array_size as usize, num_hash_functions as usize)
+            .map_err(to_js_error)?;
+        Ok(NodeBloomFilter { inner })
+    }
+
+    /// Loads a filter saved in any supported format: `BloomFilter.load(path)`.
+    #[napi(factory)]
+    pub fn load(path: String) -> Result<Self> {
+        let (inner, _) = BloomFilter::load_auto(&path).map_err(to_js_error)?;
+        Ok(NodeBloomFilter { inner })
+    }
+
+    /// Saves the filter, in `format` (json, binary, compressed or msgpack) or
+    /// else the format implied by the extension as the CLI would choose it.
+    #[napi]
+    pub fn save(&self, path: String, format: Option<String>) -> Result<()> {
+        let format = match format {
+            Some(name) => name.parse::<FileFormat>().map_err(Error::from_reason)?,
+            None => FileFormat::for_path(&path),
+        };
+        self.inner.save_as(&path, format).map_err(to_js_error)
+    }
+
+    /// Inserts a string, returning `true` if any bit was newly set.
+    #[napi]
+    pub fn insert(&mut self, item: String) -> bool {
+        self.inner.insert(&item)
+    }
+
+    /// Inserts a binary key from a `Buffer`.
+    #[napi]
+    pub fn insert_bytes(&mut self, item: Buffer) -> bool {
+        self.inner.insert_bytes(&item)
+    }
+
+    /// Checks whether a string may be present in the first `levels` levels (default: all).
+    #[napi]
+    pub fn query(&self, item: String, levels: Option<u32>) -> bool {
+        self.inner.query(&item, self.levels_to_search(levels))
+    }
+
+    /// Checks whether a binary key may be present in the first `levels` levels (default: all).
+    #[napi]
+    pub fn query_bytes(&self, item: Buffer, levels: Option<u32>) -> bool {
+        self.inner.query_bytes(&item, self.levels_to_search(levels))
+    }
+
+    /// The number of levels.
+    #[napi(getter)]
+    pub fn levels(&self) -> u32 {
+        self.inner.levels().len() as u32
+    }
+
+    /// The estimated false-positive rate of a query over all levels.
+    #[napi(getter)]
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        self.inner.estimated_false_positive_rate()
+    }
+
+    fn levels_to_search(&self, levels: Option<u32>) -> usize {
+        levels.map_or(self.inner.levels().len(), |levels| levels as usize)
+    }
+}
+
+fn to_js_error(error: BloomFilterError) -> Error {
+    Error::from_reason(error.to_string())
+}
@@ -0,0 +1,16 @@
+// fuzz/fuzz_targets/common.rs
+
+//! Shared by the loader targets.
+
+use bloom::BloomFilter;
+
+/// Runs the operations a loaded filter is used for; none may panic.
+pub fn exercise(mut filter: BloomFilter) {
+    let levels = filter.levels().len();
+    filter.insert("fuzz");
+    filter.insert_u64(42);
+    let _ = filter.query("fuzz", levels);
+    let _ = filter.query_u64(42, levels);
+    let _ = filter.estimated_false_positive_rate();
+    let _ = filter.to_binary();
+}
@@ -0,0 +1,24 @@
+// fuzz/fuzz_targets/load.rs
+
+//! Feeds arbitrary bytes to the loaders of the formats `load_auto` detects.
+//! Malformed input must be rejected with an error, and anything that loads
+//! must survive normal use. Every other format has a target of its own.
+
+#![no_main]
+
+mod common;
+
+use bloom::BloomFilter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((filter, _)) = BloomFilter::load_auto_from_reader(data) {
+        common::exercise(filter);
+    }
+    if let Ok(filter) = BloomFilter::load_from_reader(data) {
+        common::exercise(filter);
+    }
+    if let Ok(filter) = BloomFilter::from_binary(data) {
+        common::exercise(filter);
+    }
+});
@@ -0,0 +1,21 @@
+// fuzz/fuzz_targets/load_archived.rs
+
+//! Validates arbitrary bytes as an rkyv archive and queries whatever opens.
+
+#![no_main]
+
+use bloom::ArchivedBloomFilter;
+use libfuzzer_sys::fuzz_target;
+use rkyv::util::AlignedVec;
+
+fuzz_target!(|data: &[u8]| {
+    // Archives must be 16-byte aligned, as files read with `read_archived` are.
+    let mut bytes = AlignedVec::<16>::new();
+    bytes.extend_from_slice(data);
+    let opened = [ArchivedBloomFilter::from_bytes(&bytes), ArchivedBloomFilter::from_bytes_keyed(&bytes, [7; 16])];
+    for filter in opened.into_iter().flatten() {
+        let levels = filter.num_levels();
+        let _ = filter.query("fuzz", levels);
+        let _ = filter.query_u64(42, levels);
+    }
+});
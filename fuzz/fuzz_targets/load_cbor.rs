@@ -0,0 +1,16 @@
+// fuzz/fuzz_targets/load_cbor.rs
+
+//! Feeds arbitrary bytes to the CBOR loader.
+
+#![no_main]
+
+mod common;
+
+use bloom::BloomFilter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(filter) = BloomFilter::read_cbor(data) {
+        common::exercise(filter);
+    }
+});
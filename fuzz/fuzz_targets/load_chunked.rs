@@ -0,0 +1,16 @@
+// fuzz/fuzz_targets/load_chunked.rs
+
+//! Feeds arbitrary bytes to the chunked stream loader.
+
+#![no_main]
+
+mod common;
+
+use bloom::BloomFilter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(filter) = BloomFilter::load_chunked(data) {
+        common::exercise(filter);
+    }
+});
@@ -0,0 +1,19 @@
+// fuzz/fuzz_targets/load_encrypted.rs
+
+//! Feeds arbitrary bytes to the encrypted filter decoder.
+//!
+//! Forged input stops at authentication; what an authentic file decrypts to
+//! is the binary format, which `load` fuzzes.
+
+#![no_main]
+
+mod common;
+
+use bloom::BloomFilter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(filter) = BloomFilter::from_encrypted(data, &[7; 32]) {
+        common::exercise(filter);
+    }
+});
@@ -0,0 +1,40 @@
+// fuzz/fuzz_targets/load_mutated.rs
+
+//! Saves a valid filter in every format, corrupts it as directed by the
+//! input, and loads it back. Random bytes rarely get past the magic bytes and
+//! checksums, so this reaches the decoders' deeper paths.
+//!
+//! Input layout: one byte choosing the format, then 3-byte edits of a
+//! little-endian `u16` offset (wrapped to the encoding's length) and a byte
+//! XOR-ed into it.
+
+#![no_main]
+
+use bloom::{BloomFilter, FileFormat};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, edits)) = data.split_first() else {
+        return;
+    };
+    let formats: Vec<FileFormat> = FileFormat::ALL.into_iter().filter(|format| format.is_available()).collect();
+    let format = formats[selector as usize % formats.len()];
+
+    let mut filter = BloomFilter::new(2, 257, 3).unwrap();
+    filter.set_level_label(0, Some("hot".to_string())).unwrap();
+    for item in ["alpha", "beta", "gamma"] {
+        filter.insert(item);
+    }
+    let mut encoded = Vec::new();
+    filter.save_to_writer_as(&mut encoded, format).unwrap();
+
+    for edit in edits.chunks_exact(3) {
+        let offset = u16::from_le_bytes([edit[0], edit[1]]) as usize % encoded.len();
+        encoded[offset] ^= edit[2];
+    }
+    if let Ok((mut loaded, _)) = BloomFilter::load_auto_from_reader(encoded.as_slice()) {
+        let levels = loaded.levels().len();
+        loaded.insert("fuzz");
+        let _ = loaded.query("fuzz", levels);
+    }
+});
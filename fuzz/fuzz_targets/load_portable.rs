@@ -0,0 +1,18 @@
+// fuzz/fuzz_targets/load_portable.rs
+
+//! Feeds arbitrary bytes to the portable format decoder.
+
+#![no_main]
+
+mod common;
+
+use bloom::portable::PortableHeader;
+use bloom::BloomFilter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = PortableHeader::from_bytes(data);
+    if let Ok(filter) = BloomFilter::from_portable(data) {
+        common::exercise(filter);
+    }
+});
@@ -0,0 +1,24 @@
+// fuzz/fuzz_targets/load_proto.rs
+
+//! Decodes arbitrary bytes as a protobuf message and converts it to a filter.
+
+#![no_main]
+
+mod common;
+
+use bloom::proto;
+use bloom::BloomFilter;
+use libfuzzer_sys::fuzz_target;
+use prost::Message;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(message) = proto::BloomFilter::decode(data) else {
+        return;
+    };
+    if let Ok(filter) = BloomFilter::from_proto(message.clone()) {
+        common::exercise(filter);
+    }
+    if let Ok(filter) = BloomFilter::from_proto_keyed(message, [7; 16]) {
+        common::exercise(filter);
+    }
+});
@@ -0,0 +1,27 @@
+// fuzz/fuzz_targets/load_redis.rs
+
+//! Splits the input into `BF.SCANDUMP` chunks and loads them as a RedisBloom filter.
+//!
+//! Input layout: repeated records of a little-endian `i64` iterator, a `u16`
+//! length and that many bytes of chunk data; the first record is the header.
+
+#![no_main]
+
+use bloom::RedisBloomFilter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut chunks = Vec::new();
+    let mut rest = data;
+    while rest.len() >= 10 {
+        let iter = i64::from_le_bytes(rest[..8].try_into().unwrap());
+        let len = (u16::from_le_bytes([rest[8], rest[9]]) as usize).min(rest.len() - 10);
+        chunks.push((iter, rest[10..10 + len].to_vec()));
+        rest = &rest[10 + len..];
+    }
+    if let Ok(mut filter) = RedisBloomFilter::from_chunks(chunks) {
+        let _ = filter.insert("fuzz");
+        let _ = filter.query("fuzz");
+        let _ = filter.to_chunks(64);
+    }
+});
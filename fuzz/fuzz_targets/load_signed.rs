@@ -0,0 +1,24 @@
+// fuzz/fuzz_targets/load_signed.rs
+
+//! Feeds arbitrary bytes to the signed filter decoder.
+//!
+//! Forged input stops at signature verification; the signed body is the
+//! binary format, which `load` fuzzes.
+
+#![no_main]
+
+mod common;
+
+use bloom::signing::public_key;
+use bloom::BloomFilter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let public_key = public_key(&[7; 32]);
+    if let Ok(filter) = BloomFilter::from_signed(data, &public_key) {
+        common::exercise(filter);
+    }
+    if let Ok(filter) = BloomFilter::from_signed_keyed(data, &public_key, [7; 16]) {
+        common::exercise(filter);
+    }
+});
@@ -0,0 +1,180 @@
+// src/adaptive.rs
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError, RebuildParams};
+
+/// A set that stores items exactly while small and becomes a Bloom filter once it grows.
+///
+/// Up to `threshold` distinct items are kept in a `HashSet`, so queries have
+/// no false positives and small sets cost no more memory than their items.
+/// The insert that pushes the set past the threshold promotes it: a filter
+/// sized for `expected_items` at the target false-positive rate is created,
+/// every stored item is inserted into it, and the set is dropped. From then
+/// on the filter behaves like any other single-level `BloomFilter`.
+#[derive(Serialize, Deserialize)]
+pub struct AdaptiveFilter {
+    storage: Storage,
+    threshold: usize,
+    expected_items: usize,
+    false_positive_rate: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Storage {
+    Exact(HashSet<Vec<u8>>),
+    Bloom(BloomFilter),
+}
+
+impl AdaptiveFilter {
+    /// Creates an empty exact set that promotes itself after `threshold` distinct items.
+    ///
+    /// The filter it promotes to holds `expected_items` at `false_positive_rate`;
+    /// it is never sized for fewer items than the threshold.
+    pub fn new(threshold: usize, expected_items: usize, false_positive_rate: f64) -> Self {
+        info!(
+            "Creating AdaptiveFilter: threshold={}, expected_items={}, false_positive_rate={}",
+            threshold, expected_items, false_positive_rate
+        );
+        AdaptiveFilter {
+            storage: Storage::Exact(HashSet::new()),
+            threshold,
+            expected_items: expected_items.max(threshold).max(1),
+            false_positive_rate,
+        }
+    }
+
+    /// Inserts an item, returning `true` if it was not (probably) present.
+    pub fn insert(&mut self, item: &str) -> bool {
+        self.insert_bytes(item.as_bytes())
+    }
+
+    /// Inserts a binary key, promoting the set if it has grown past the threshold.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        let newly_added = match &mut self.storage {
+            Storage::Exact(items) => items.insert(item.to_vec()),
+            Storage::Bloom(filter) => return filter.insert_bytes(item),
+        };
+        if self.len() > self.threshold {
+            self.promote();
+        }
+        newly_added
+    }
+
+    /// Checks whether an item is present; exact until the set is promoted.
+    pub fn query(&self, item: &str) -> bool {
+        self.query_bytes(item.as_bytes())
+    }
+
+    /// Checks whether a binary key is present.
+    pub fn query_bytes(&self, item: &[u8]) -> bool {
+        match &self.storage {
+            Storage::Exact(items) => items.contains(item),
+            Storage::Bloom(filter) => filter.query_bytes(item, 1),
+        }
+    }
+
+    /// Converts the exact set into a Bloom filter now, whatever its size. Does nothing once promoted.
+    pub fn promote(&mut self) {
+        let Storage::Exact(items) = &self.storage else {
+            return;
+        };
+        info!("Promoting AdaptiveFilter of {} items to a Bloom filter", items.len());
+        let sizing = RebuildParams::for_capacity(self.expected_items, self.false_positive_rate);
+        let array_size = sizing.array_size.unwrap_or(1).max(1);
+        let mut filter = BloomFilter::new(1, array_size, sizing.num_hash_functions.unwrap_or(1).max(1))
+            .expect("capacity sizing yields a valid filter");
+        for item in items {
+            filter.insert_bytes(item);
+        }
+        self.storage = Storage::Bloom(filter);
+    }
+
+    /// Returns true while items are stored exactly.
+    pub fn is_exact(&self) -> bool {
+        matches!(self.storage, Storage::Exact(_))
+    }
+
+    /// Returns the number of distinct items while exact, or the inserts counted by the filter once promoted.
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Exact(items) => items.len(),
+            Storage::Bloom(filter) => filter.levels()[0].item_count(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of distinct items stored exactly before promotion.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Returns the Bloom filter once promoted.
+    pub fn filter(&self) -> Option<&BloomFilter> {
+        match &self.storage {
+            Storage::Exact(_) => None,
+            Storage::Bloom(filter) => Some(filter),
+        }
+    }
+
+    /// Saves the set or filter, with its promotion settings, as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving AdaptiveFilter to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a filter saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading AdaptiveFilter from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let filter: Self = serde_json::from_reader(reader)?;
+        if let Storage::Bloom(bloom) = &filter.storage {
+            bloom.validate()?;
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_until_threshold_then_bloom() {
+        let mut filter = AdaptiveFilter::new(100, 10_000, 0.001);
+        for i in 0..100 {
+            assert!(filter.insert(&format!("item{}", i)));
+        }
+        assert!(!filter.insert("item7"));
+        assert!(filter.is_exact() && filter.filter().is_none());
+        assert_eq!(filter.len(), 100);
+        // Exact: nothing that was not inserted ever matches.
+        assert!((100..10_000).all(|i| !filter.query(&format!("item{}", i))));
+
+        let path = std::env::temp_dir().join("test_bloom_adaptive.json");
+        let path = path.to_str().unwrap();
+        filter.save_to_file(path).unwrap();
+        assert!(AdaptiveFilter::load_from_file(path).unwrap().query("item99"));
+
+        assert!(filter.insert("item100"));
+        assert!(!filter.is_exact());
+        assert_eq!(filter.len(), 101);
+        assert!((0..=100).all(|i| filter.query(&format!("item{}", i))));
+        let bloom = filter.filter().unwrap();
+        assert!(bloom.array_size() > 100_000);
+
+        filter.save_to_file(path).unwrap();
+        let loaded = AdaptiveFilter::load_from_file(path).unwrap();
+        assert!(!loaded.is_exact() && loaded.query("item42") && !loaded.query("nope"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
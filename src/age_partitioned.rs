@@ -0,0 +1,222 @@
+// src/age_partitioned.rs
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// An age-partitioned Bloom filter (APBF) answering "was this item among the most recent ones?".
+///
+/// The filter has `k + l` slices, one level each, and every slice has its own
+/// hash function. An insert sets one bit in each of the `k` newest slices.
+/// Every `generation_items` inserts the slices age by one: the oldest slice
+/// is cleared and becomes the newest. A query matches when `k` consecutive
+/// slices contain the item, so an item is remembered for at least `l` full
+/// generations after the one it was inserted in, and forgotten once it has
+/// aged out of `l + 1`. Unlike `SlidingBloomFilter`, every slice holds one
+/// bit per item, which keeps the false-positive rate steady as items age.
+#[derive(Serialize, Deserialize)]
+pub struct AgePartitionedBloomFilter {
+    filter: BloomFilter,
+    /// Slices an item is inserted into and must match in a row.
+    k: usize,
+    /// Physical index of the newest slice.
+    newest: usize,
+    generation_items: usize,
+    inserted_in_generation: usize,
+}
+
+impl AgePartitionedBloomFilter {
+    /// Creates a filter of `k + l` slices of `slice_size` bits, ageing every `generation_items` inserts.
+    ///
+    /// `k` is the number of bits set per item. A `generation_items` of 0 never
+    /// ages on its own; call `shift` from a timer for a time window instead.
+    pub fn new(k: usize, l: usize, slice_size: usize, generation_items: usize) -> Result<Self, BloomFilterError> {
+        info!(
+            "Creating AgePartitionedBloomFilter: k={}, l={}, slice_size={}, generation_items={}",
+            k, l, slice_size, generation_items
+        );
+        if k == 0 {
+            return Err(BloomFilterError::ZeroHashFunctions);
+        }
+        // One hash function per slice, so a slice keeps its hash as it ages.
+        let filter = BloomFilter::new(k + l, slice_size, k + l)?;
+        Ok(AgePartitionedBloomFilter {
+            filter,
+            k,
+            newest: 0,
+            generation_items,
+            inserted_in_generation: 0,
+        })
+    }
+
+    /// Inserts an item into the newest `k` slices, ageing the slices first when the generation is full.
+    pub fn insert(&mut self, item: &str) -> bool {
+        self.insert_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    /// Inserts a binary key into the newest `k` slices.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        if self.generation_items > 0 && self.inserted_in_generation >= self.generation_items {
+            self.shift();
+        }
+        let positions = self.filter.positions(item);
+        let mut newly_set = false;
+        for age in 0..self.k {
+            let slice = self.slice(age);
+            newly_set |= self.filter.levels[slice].insert_positions(&positions[slice..=slice]);
+        }
+        self.inserted_in_generation += 1;
+        newly_set
+    }
+
+    /// Checks whether the item was (probably) inserted within the window.
+    pub fn query(&self, item: &str) -> bool {
+        self.query_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    /// Checks whether the binary key was (probably) inserted within the window.
+    pub fn query_bytes(&self, item: &[u8]) -> bool {
+        self.age_of_bytes(item).is_some()
+    }
+
+    /// Returns how many generations ago the item was inserted, if it is still remembered.
+    ///
+    /// The age is the position of the newest run of `k` matching slices, so
+    /// an item inserted again reports its latest generation.
+    pub fn age_of(&self, item: &str) -> Option<usize> {
+        self.age_of_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    fn age_of_bytes(&self, item: &[u8]) -> Option<usize> {
+        let positions = self.filter.positions(item);
+        let mut run = 0;
+        for age in 0..self.num_slices() {
+            let slice = self.slice(age);
+            if self.filter.levels[slice].contains_positions(&positions[slice..=slice]) {
+                run += 1;
+                if run == self.k {
+                    return Some(age + 1 - self.k);
+                }
+            } else {
+                run = 0;
+            }
+        }
+        None
+    }
+
+    /// Estimates the false-positive rate of `query` from the slices' current fill.
+    ///
+    /// A query matches when some run of `k` consecutive slices all have the
+    /// probed bit set; this sums that probability over every run, an upper
+    /// bound that is tight while the rate is small.
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let fills: Vec<f64> = (0..self.num_slices())
+            .map(|age| self.filter.levels()[self.slice(age)].fill_ratio())
+            .collect();
+        fills
+            .windows(self.k)
+            .map(|run| run.iter().product::<f64>())
+            .sum::<f64>()
+            .min(1.0)
+    }
+
+    /// Ages every slice by one generation, clearing the oldest to become the newest.
+    pub fn shift(&mut self) {
+        let slices = self.num_slices();
+        self.newest = (self.newest + slices - 1) % slices;
+        self.filter.levels[self.newest].clear();
+        self.inserted_in_generation = 0;
+    }
+
+    /// Returns the number of slices, `k + l`.
+    pub fn num_slices(&self) -> usize {
+        self.filter.levels().len()
+    }
+
+    /// Returns the number of inserts per generation (0 when ageing is driven by `shift`).
+    pub fn generation_items(&self) -> usize {
+        self.generation_items
+    }
+
+    /// Returns the underlying filter, one level per slice in physical order.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+
+    /// Saves the filter, including its generation state, as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving AgePartitionedBloomFilter to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a filter saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading AgePartitionedBloomFilter from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let apbf: Self = serde_json::from_reader(reader)?;
+        apbf.validate()?;
+        Ok(apbf)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), BloomFilterError> {
+        self.filter.validate()?;
+        let slices = self.num_slices();
+        if self.k == 0 || self.k > slices || self.newest >= slices || self.filter.num_hash_functions() != slices {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "age-partitioned filter with k={} and newest slice {} does not fit {} slices and {} hash functions",
+                self.k,
+                self.newest,
+                slices,
+                self.filter.num_hash_functions()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Physical index of the slice `age` generations older than the newest.
+    fn slice(&self, age: usize) -> usize {
+        (self.newest + age) % self.num_slices()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_items_age_out_after_l_generations() {
+        let mut apbf = AgePartitionedBloomFilter::new(4, 2, 2000, 10).unwrap();
+        let generation = |g: usize| (0..10).map(move |i| format!("gen{}-{}", g, i));
+        for g in 0..3 {
+            generation(g).for_each(|item| {
+                apbf.insert(&item);
+            });
+        }
+        // Generation 2 is current; 0 and 1 are within the l = 2 guaranteed generations.
+        assert!(generation(0).all(|item| apbf.query(&item)));
+        assert_eq!(apbf.age_of("gen0-0"), Some(2));
+        assert_eq!(apbf.age_of("gen2-0"), Some(0));
+
+        generation(3).for_each(|item| {
+            apbf.insert(&item);
+        });
+        assert!(generation(0).all(|item| !apbf.query(&item)));
+        assert!(generation(1).all(|item| apbf.query(&item)));
+
+        let path = std::env::temp_dir().join("test_bloom_age_partitioned.json");
+        let path = path.to_str().unwrap();
+        apbf.save_to_file(path).unwrap();
+        let mut loaded = AgePartitionedBloomFilter::load_from_file(path).unwrap();
+        assert_eq!(loaded.age_of("gen3-9"), Some(0));
+        loaded.shift();
+        loaded.shift();
+        assert!(!loaded.query("gen1-0"));
+        assert_eq!(loaded.age_of("gen3-9"), Some(2));
+        std::fs::remove_file(path).unwrap();
+    }
+}
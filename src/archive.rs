@@ -0,0 +1,295 @@
+// src/archive.rs
+
+use rkyv::rancor;
+use rkyv::util::AlignedVec;
+use std::fs::File;
+use std::io::Write;
+use tracing::{error, info};
+
+use crate::bloom_filter::{
+    key_fingerprint, keyed_positions, u64_positions, BloomFilter, BloomFilterError, HashFunction, MAX_HASH_FUNCTIONS,
+};
+use crate::hashing::HashAlgorithm;
+
+/// The archived layout: hash configuration plus every level's bits packed 64
+/// to a word, level after level.
+#[derive(rkyv::Archive, rkyv::Serialize)]
+struct FilterImage {
+    array_size: u64,
+    multipliers: Vec<u64>,
+    seed: u64,
+    hash_algorithm: u8,
+    key_fingerprint: Option<u64>,
+    num_levels: u64,
+    words: Vec<u64>,
+}
+
+const ALGORITHMS: [HashAlgorithm; 4] = [
+    HashAlgorithm::Polynomial,
+    HashAlgorithm::Fnv1a,
+    HashAlgorithm::Xxh3,
+    HashAlgorithm::Murmur3,
+];
+
+impl BloomFilter {
+    /// Encodes the filter in the rkyv archived format, for querying in place with `ArchivedBloomFilter`.
+    ///
+    /// Like `freeze`, only the bits and hash configuration are kept (no labels,
+    /// metadata or TTLs), and levels whose TTL has already elapsed are stored
+    /// empty. The layout has no room for key normalization, so filters that
+    /// normalize keys cannot be archived.
+    pub fn to_archived(&self) -> Result<AlignedVec, BloomFilterError> {
+        if !self.normalization().is_identity() {
+            error!("Cannot archive a filter that normalizes keys ({})", self.normalization());
+            return Err(BloomFilterError::InvalidArchive(format!(
+                "the archived format cannot record key normalization ({})",
+                self.normalization()
+            )));
+        }
+        let words_per_level = self.array_size().div_ceil(64);
+        let now = crate::bloom_filter::unix_now();
+        let mut words = vec![0u64; words_per_level * self.levels().len()];
+        for (index, level) in self.levels().iter().enumerate() {
+            if level.is_expired_at(now) {
+                continue;
+            }
+            let base = index * words_per_level;
+            for bit in level.bit_array.iter_ones() {
+                words[base + bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        let image = FilterImage {
+            array_size: self.array_size() as u64,
+            multipliers: self.hash_functions().iter().map(|hf| hf.multiplier as u64).collect(),
+            seed: self.seed(),
+            hash_algorithm: ALGORITHMS.iter().position(|&a| a == self.hash_algorithm()).unwrap_or(0) as u8,
+            key_fingerprint: self.key_fingerprint,
+            num_levels: self.levels().len() as u64,
+            words,
+        };
+        rkyv::to_bytes::<rancor::Error>(&image).map_err(encoding_error)
+    }
+
+    /// Writes the archived format to a file.
+    pub fn save_archived(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving archived BloomFilter to file: {}", filepath);
+        let bytes = self.to_archived()?;
+        let mut file = File::create(filepath)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Reads an archived filter file into a buffer aligned for `ArchivedBloomFilter::from_bytes`.
+///
+/// A memory-mapped file is page-aligned and can be passed to `from_bytes`
+/// directly instead.
+pub fn read_archived(filepath: &str) -> Result<AlignedVec, BloomFilterError> {
+    info!("Reading archived BloomFilter from file: {}", filepath);
+    let mut bytes = AlignedVec::new();
+    bytes.extend_from_reader(&mut File::open(filepath)?)?;
+    Ok(bytes)
+}
+
+/// A query-only view of a filter in the rkyv archived format.
+///
+/// Opening validates the buffer and then reads the bits where they lie, so
+/// the only allocation is the filter's handful of hash functions; load time
+/// does not grow with the filter's size.
+pub struct ArchivedBloomFilter<'a> {
+    image: &'a ArchivedFilterImage,
+    hash_functions: Vec<HashFunction>,
+    hash_algorithm: HashAlgorithm,
+    hash_key: Option<[u8; 16]>,
+    array_size: usize,
+    words_per_level: usize,
+    num_levels: usize,
+}
+
+impl<'a> ArchivedBloomFilter<'a> {
+    /// Validates `bytes` (which must be 16-byte aligned, as `AlignedVec` is) and opens it for querying.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, BloomFilterError> {
+        let filter = Self::open(bytes, None)?;
+        if filter.image.key_fingerprint.is_some() {
+            error!("Archived filter uses keyed hashing, but no hash key was supplied");
+            return Err(BloomFilterError::MissingHashKey);
+        }
+        Ok(filter)
+    }
+
+    /// Like `from_bytes`, for a keyed-hashing filter whose key is never part of the archive.
+    pub fn from_bytes_keyed(bytes: &'a [u8], hash_key: [u8; 16]) -> Result<Self, BloomFilterError> {
+        let filter = Self::open(bytes, Some(hash_key))?;
+        if filter.image.key_fingerprint.as_ref().map(|f| f.to_native()) != Some(key_fingerprint(&hash_key)) {
+            error!("Hash key does not match the archived filter's key fingerprint");
+            return Err(BloomFilterError::HashKeyMismatch);
+        }
+        Ok(filter)
+    }
+
+    fn open(bytes: &'a [u8], hash_key: Option<[u8; 16]>) -> Result<Self, BloomFilterError> {
+        let image = rkyv::access::<ArchivedFilterImage, rancor::Error>(bytes).map_err(encoding_error)?;
+        let invalid = |message: String| Err(BloomFilterError::InvalidArchive(message));
+
+        let array_size = image.array_size.to_native();
+        let Ok(array_size) = usize::try_from(array_size) else {
+            return invalid(format!("array size {} is too large", array_size));
+        };
+        if array_size == 0 {
+            return Err(BloomFilterError::ZeroArraySize);
+        }
+        let num_hashes = image.multipliers.len();
+        if num_hashes == 0 {
+            return Err(BloomFilterError::ZeroHashFunctions);
+        }
+        if num_hashes > MAX_HASH_FUNCTIONS {
+            return Err(BloomFilterError::InvalidHashFunctions {
+                requested: num_hashes,
+                available: MAX_HASH_FUNCTIONS,
+            });
+        }
+        let Some(&hash_algorithm) = ALGORITHMS.get(image.hash_algorithm as usize) else {
+            return invalid(format!("unknown hash algorithm {}", image.hash_algorithm));
+        };
+        if !hash_algorithm.is_available() {
+            return Err(BloomFilterError::UnsupportedHashAlgorithm(hash_algorithm));
+        }
+        let words_per_level = array_size.div_ceil(64);
+        let num_levels = image.num_levels.to_native() as usize;
+        if num_levels == 0 {
+            return Err(BloomFilterError::ZeroLevels);
+        }
+        if num_levels.checked_mul(words_per_level) != Some(image.words.len()) {
+            return invalid(format!(
+                "{} words cannot hold {} levels of {} bits",
+                image.words.len(),
+                num_levels,
+                array_size
+            ));
+        }
+
+        let seed = image.seed.to_native();
+        Ok(ArchivedBloomFilter {
+            image,
+            hash_functions: image
+                .multipliers
+                .iter()
+                .map(|multiplier| HashFunction::with_seed(multiplier.to_native() as usize, seed))
+                .collect(),
+            hash_algorithm,
+            hash_key,
+            array_size,
+            words_per_level,
+            num_levels,
+        })
+    }
+
+    /// Queries an item across the specified number of levels.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.query_level(item, num_levels_to_search).is_some()
+    }
+
+    /// Returns the index of the first level (of the first `num_levels_to_search`) that contains the item.
+    pub fn query_level(&self, item: &str, num_levels_to_search: usize) -> Option<usize> {
+        self.first_match(&self.positions(item.as_bytes()), num_levels_to_search)
+    }
+
+    /// Queries a binary key across the specified number of levels.
+    pub fn query_bytes(&self, item: &[u8], num_levels_to_search: usize) -> bool {
+        self.first_match(&self.positions(item), num_levels_to_search).is_some()
+    }
+
+    /// Queries an integer key inserted with `BloomFilter::insert_u64`.
+    pub fn query_u64(&self, item: u64, num_levels_to_search: usize) -> bool {
+        let positions = match &self.hash_key {
+            Some(hash_key) => keyed_positions(hash_key, self.hash_functions.len(), self.array_size, &item.to_le_bytes()),
+            None => u64_positions(&self.hash_functions, self.array_size, item),
+        };
+        self.first_match(&positions, num_levels_to_search).is_some()
+    }
+
+    fn positions(&self, key: &[u8]) -> Vec<usize> {
+        match &self.hash_key {
+            Some(hash_key) => keyed_positions(hash_key, self.hash_functions.len(), self.array_size, key),
+            None => self.hash_algorithm.positions(&self.hash_functions, self.array_size, key),
+        }
+    }
+
+    fn first_match(&self, positions: &[usize], num_levels_to_search: usize) -> Option<usize> {
+        (0..num_levels_to_search.min(self.num_levels)).find(|&level| {
+            let words = &self.image.words[level * self.words_per_level..(level + 1) * self.words_per_level];
+            positions
+                .iter()
+                .all(|&bit| words[bit / 64].to_native() & (1 << (bit % 64)) != 0)
+        })
+    }
+
+    /// Returns the number of levels.
+    pub fn num_levels(&self) -> usize {
+        self.num_levels
+    }
+
+    /// Returns the number of bits in each level.
+    pub fn array_size(&self) -> usize {
+        self.array_size
+    }
+}
+
+fn encoding_error(e: rancor::Error) -> BloomFilterError {
+    BloomFilterError::Encoding {
+        format: "rkyv",
+        message: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archived_matches_source() {
+        let mut bf = BloomFilter::builder()
+            .levels(2)
+            .array_size(1000)
+            .hash_algorithm(HashAlgorithm::Fnv1a)
+            .build()
+            .unwrap();
+        for i in 0..100 {
+            bf.insert(&format!("item-{}", i));
+        }
+        bf.insert_u64(7);
+
+        let path = std::env::temp_dir().join("test_bloom_archived.rkyv");
+        let path = path.to_str().unwrap();
+        bf.save_archived(path).unwrap();
+        let bytes = read_archived(path).unwrap();
+        let archived = ArchivedBloomFilter::from_bytes(&bytes).unwrap();
+        for i in 0..500 {
+            let item = format!("item-{}", i);
+            assert_eq!(archived.query_level(&item, 2), bf.query_level(&item, 2));
+        }
+        assert!(archived.query_u64(7, 2));
+        assert_eq!(archived.num_levels(), 2);
+        assert_eq!(archived.array_size(), 1000);
+
+        let mut truncated = AlignedVec::<16>::new();
+        truncated.extend_from_slice(&bytes[..bytes.len() - 8]);
+        assert!(ArchivedBloomFilter::from_bytes(&truncated).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_archived_keyed_filter() {
+        let key = [9u8; 16];
+        let mut bf = BloomFilter::builder().array_size(100).hash_key(key).build().unwrap();
+        bf.insert("secret");
+        let bytes = bf.to_archived().unwrap();
+        assert!(matches!(
+            ArchivedBloomFilter::from_bytes(&bytes),
+            Err(BloomFilterError::MissingHashKey)
+        ));
+        assert!(ArchivedBloomFilter::from_bytes_keyed(&bytes, [1u8; 16]).is_err());
+        assert!(ArchivedBloomFilter::from_bytes_keyed(&bytes, key).unwrap().query("secret", 1));
+    }
+}
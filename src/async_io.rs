@@ -0,0 +1,121 @@
+// src/async_io.rs
+
+use futures_util::{Stream, StreamExt};
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, info};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+impl BloomFilter {
+    /// Saves the Bloom filter to a file in JSON format without blocking the async runtime.
+    pub async fn save_to_file_async(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving BloomFilter to file (async): {}", filepath);
+        let bytes = serde_json::to_vec_pretty(self)?;
+        tokio::fs::write(filepath, bytes).await?;
+        Ok(())
+    }
+
+    /// Loads a Bloom filter from a JSON file without blocking the async runtime.
+    pub async fn load_from_file_async(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading BloomFilter from file (async): {}", filepath);
+        let bytes = tokio::fs::read(filepath).await?;
+        let bloom_filter: Self = serde_json::from_slice(&bytes)?;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Inserts every item produced by an async stream, returning the number of items inserted.
+    pub async fn insert_from_stream<S, T>(&mut self, stream: S) -> usize
+    where
+        S: Stream<Item = T>,
+        T: AsRef<str>,
+    {
+        let mut stream = std::pin::pin!(stream);
+        let mut count = 0;
+        while let Some(item) = stream.next().await {
+            self.insert(item.as_ref());
+            count += 1;
+        }
+        info!("Inserted {} items from stream", count);
+        count
+    }
+
+    /// Inserts a live stream into a filter shared with concurrent readers.
+    ///
+    /// Items that are already available are gathered into batches of at most
+    /// `batch_size`, and the write lock is taken once per batch, so queries
+    /// interleave between batches instead of waiting for the whole stream.
+    /// The stream is not polled again until a batch is inserted, so a slow
+    /// filter slows the producer rather than buffering without bound; pair it
+    /// with `item_channel` to feed events from other tasks.
+    pub async fn insert_from_stream_shared<S, T>(filter: &RwLock<BloomFilter>, stream: S, batch_size: usize) -> usize
+    where
+        S: Stream<Item = T>,
+        T: AsRef<str>,
+    {
+        let mut batches = std::pin::pin!(stream.ready_chunks(batch_size.max(1)));
+        let mut count = 0;
+        while let Some(batch) = batches.next().await {
+            let mut filter = filter.write().await;
+            for item in &batch {
+                filter.insert(item.as_ref());
+            }
+            count += batch.len();
+            debug!("Inserted batch of {} items from stream", batch.len());
+        }
+        info!("Inserted {} items from stream", count);
+        count
+    }
+}
+
+/// Creates a bounded channel whose receiving end is a stream of items.
+///
+/// Senders wait once `capacity` items are queued, which applies backpressure
+/// to producers when insertion falls behind. The stream ends when every
+/// sender has been dropped.
+pub fn item_channel<T: Send + 'static>(capacity: usize) -> (mpsc::Sender<T>, impl Stream<Item = T>) {
+    let (sender, receiver) = mpsc::channel(capacity.max(1));
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|item| (item, receiver))
+    });
+    (sender, stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_save_load_and_stream() {
+        let mut bf = BloomFilter::new(1, 100, 3).unwrap();
+        let items = futures_util::stream::iter(vec!["alpha", "beta"]);
+        assert_eq!(bf.insert_from_stream(items).await, 2);
+
+        let path = std::env::temp_dir().join("test_bloom_async.json");
+        let path = path.to_str().unwrap();
+        bf.save_to_file_async(path).await.unwrap();
+        let loaded = BloomFilter::load_from_file_async(path).await.unwrap();
+        assert!(loaded.query("alpha", 1));
+        assert!(loaded.query("beta", 1));
+        assert!(!loaded.query("gamma", 1));
+
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shared_stream_with_backpressure() {
+        let filter = RwLock::new(BloomFilter::new(1, 1000, 3).unwrap());
+        let (sender, items) = item_channel(4);
+        let producer = tokio::spawn(async move {
+            for i in 0..100 {
+                sender.send(format!("event-{}", i)).await.unwrap();
+            }
+        });
+        assert_eq!(BloomFilter::insert_from_stream_shared(&filter, items, 8).await, 100);
+        producer.await.unwrap();
+
+        let filter = filter.read().await;
+        assert!(filter.query("event-0", 1));
+        assert!(filter.query("event-99", 1));
+    }
+}
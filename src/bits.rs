@@ -0,0 +1,546 @@
+// src/bits.rs
+
+use serde::{Deserialize, Serialize};
+use std::io;
+
+#[cfg(feature = "roaring")]
+use roaring::RoaringBitmap;
+
+#[cfg(feature = "mmap")]
+use memmap2::MmapMut;
+#[cfg(feature = "mmap")]
+use std::{fs::OpenOptions, path::{Path, PathBuf}};
+
+/// Where a level's bits are kept.
+///
+/// `BloomLevel` is generic over its storage, so a new persistence strategy
+/// only has to read and write bits; hashing, item counts and queries stay
+/// in the level. Indices past `len` read as unset and are ignored on write.
+pub trait BitStorage {
+    /// Returns the number of bits.
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no bits.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads a bit.
+    fn get(&self, index: usize) -> bool;
+
+    /// Sets a bit, returning `true` if it was previously unset.
+    fn set(&mut self, index: usize) -> bool;
+
+    /// Sets several bits, returning how many were previously unset.
+    ///
+    /// Storages with a per-write cost can override this to batch the writes.
+    fn set_many(&mut self, indices: &[usize]) -> usize {
+        indices.iter().filter(|&&index| self.set(index)).count()
+    }
+
+    /// Makes every write so far durable; a no-op for storage that only lives in memory.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Returns the number of set bits.
+    fn count_ones(&self) -> usize {
+        (0..self.len()).filter(|&index| self.get(index)).count()
+    }
+}
+
+/// Plain in-memory bits, one `bool` each.
+impl BitStorage for Vec<bool> {
+    fn len(&self) -> usize {
+        <[bool]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> bool {
+        <[bool]>::get(self, index).copied().unwrap_or(false)
+    }
+
+    fn set(&mut self, index: usize) -> bool {
+        <[bool]>::get_mut(self, index).is_some_and(|bit| !std::mem::replace(bit, true))
+    }
+
+    fn count_ones(&self) -> usize {
+        self.iter().filter(|&&bit| bit).count()
+    }
+}
+
+/// The storage of the filter's own levels: dense, sparse or memory-mapped.
+impl BitStorage for LevelBits {
+    fn len(&self) -> usize {
+        LevelBits::len(self)
+    }
+
+    fn get(&self, index: usize) -> bool {
+        LevelBits::get(self, index)
+    }
+
+    fn set(&mut self, index: usize) -> bool {
+        LevelBits::set(self, index)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(mapped) => mapped.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    fn count_ones(&self) -> usize {
+        LevelBits::count_ones(self)
+    }
+}
+
+/// Storage for the bits of a single level.
+///
+/// `Dense` serializes its bits packed LSB first as `{ "len", "bits" }`, with
+/// `bits` in base64 for human-readable formats such as JSON and as raw bytes
+/// otherwise; the original array of booleans still loads. `Sparse` (behind
+/// the `roaring` feature) keeps only the set positions in a roaring bitmap
+/// and serializes as `{ "len", "ones" }`. `Mapped` (behind the `mmap`
+/// feature) is a cold level whose packed bits live in a memory-mapped file;
+/// it serializes like `Dense`, so saved files never depend on the mapping and
+/// load back as in-memory levels.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LevelBits {
+    Dense(#[serde(with = "packed_bits")] Vec<bool>),
+    #[cfg(feature = "roaring")]
+    Sparse(SparseBits),
+    #[cfg(feature = "mmap")]
+    #[serde(skip_deserializing)]
+    Mapped(MappedBits),
+}
+
+impl LevelBits {
+    pub(crate) fn dense(len: usize) -> Self {
+        LevelBits::Dense(vec![false; len])
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            LevelBits::Dense(bits) => bits.len(),
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => sparse.len,
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(mapped) => mapped.len,
+        }
+    }
+
+    /// Reads a bit; indices past the end read as unset.
+    pub(crate) fn get(&self, index: usize) -> bool {
+        if index >= self.len() {
+            return false;
+        }
+        match self {
+            LevelBits::Dense(bits) => bits[index],
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => sparse.ones.contains(index as u32),
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(mapped) => mapped.get(index),
+        }
+    }
+
+    /// Sets a bit, returning `true` if it was previously unset. Indices past the end are ignored.
+    pub(crate) fn set(&mut self, index: usize) -> bool {
+        if index >= self.len() {
+            return false;
+        }
+        match self {
+            LevelBits::Dense(bits) => !std::mem::replace(&mut bits[index], true),
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => sparse.ones.insert(index as u32),
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(mapped) => mapped.set(index),
+        }
+    }
+
+    /// Clears a bit, returning `true` if it was previously set. Indices past the end are ignored.
+    pub(crate) fn unset(&mut self, index: usize) -> bool {
+        if index >= self.len() {
+            return false;
+        }
+        match self {
+            LevelBits::Dense(bits) => std::mem::replace(&mut bits[index], false),
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => sparse.ones.remove(index as u32),
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(mapped) => mapped.unset(index),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            LevelBits::Dense(bits) => bits.iter_mut().for_each(|bit| *bit = false),
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => sparse.ones.clear(),
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(mapped) => mapped.map.fill(0),
+        }
+    }
+
+    pub(crate) fn count_ones(&self) -> usize {
+        match self {
+            LevelBits::Dense(bits) => bits.iter().filter(|&&bit| bit).count(),
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => sparse.ones.len() as usize,
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(mapped) => mapped.map.iter().map(|byte| byte.count_ones() as usize).sum(),
+        }
+    }
+
+    /// Bytes of heap memory holding the bits.
+    pub(crate) fn heap_bytes(&self) -> usize {
+        match self {
+            LevelBits::Dense(bits) => bits.capacity() * std::mem::size_of::<bool>(),
+            // Roaring containers take about as much memory as their serialized form.
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => sparse.ones.serialized_size(),
+            // Mapped pages belong to the page cache, which can evict them.
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(_) => 0,
+        }
+    }
+
+    /// Number of dense bits that fit in `bytes` of heap memory.
+    pub(crate) fn dense_len_for(bytes: usize) -> usize {
+        bytes / std::mem::size_of::<bool>()
+    }
+
+    /// Iterates over the positions of set bits in ascending order.
+    pub(crate) fn iter_ones(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self {
+            LevelBits::Dense(bits) => Box::new(bits.iter().enumerate().filter(|(_, &bit)| bit).map(|(i, _)| i)),
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => Box::new(sparse.ones.iter().map(|i| i as usize)),
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(mapped) => Box::new(mapped.iter_ones()),
+        }
+    }
+
+    /// Returns false if a deserialized sparse level claims bits beyond its length.
+    pub(crate) fn is_consistent(&self) -> bool {
+        match self {
+            LevelBits::Dense(_) => true,
+            #[cfg(feature = "mmap")]
+            LevelBits::Mapped(_) => true,
+            #[cfg(feature = "roaring")]
+            LevelBits::Sparse(sparse) => {
+                sparse.len <= SparseBits::MAX_LEN && sparse.ones.max().is_none_or(|max| (max as usize) < sparse.len)
+            }
+        }
+    }
+
+    /// Sets every bit that is set in `other`.
+    pub(crate) fn union_with(&mut self, other: &LevelBits) {
+        match (self, other) {
+            (LevelBits::Dense(bits), LevelBits::Dense(other)) => {
+                for (bit, &other) in bits.iter_mut().zip(other) {
+                    *bit |= other;
+                }
+            }
+            #[cfg(feature = "roaring")]
+            (LevelBits::Sparse(sparse), LevelBits::Sparse(other)) => sparse.ones |= &other.ones,
+            #[allow(unreachable_patterns)]
+            (this, other) => {
+                for index in other.iter_ones() {
+                    this.set(index);
+                }
+            }
+        }
+    }
+
+    /// Clears every bit that is not set in `other`.
+    pub(crate) fn intersect_with(&mut self, other: &LevelBits) {
+        match (self, other) {
+            (LevelBits::Dense(bits), LevelBits::Dense(other)) => {
+                for (bit, &other) in bits.iter_mut().zip(other) {
+                    *bit &= other;
+                }
+            }
+            #[cfg(feature = "roaring")]
+            (LevelBits::Sparse(sparse), LevelBits::Sparse(other)) => sparse.ones &= &other.ones,
+            #[allow(unreachable_patterns)]
+            (this, other) => {
+                let cleared: Vec<usize> = this.iter_ones().filter(|&index| !other.get(index)).collect();
+                for index in cleared {
+                    this.unset(index);
+                }
+            }
+        }
+    }
+}
+
+/// Packed bits of a cold level, mapped from a file on disk.
+///
+/// Bit `i` is stored in byte `i / 8` at bit `i % 8` (LSB first), the same
+/// layout as `BloomLevel::to_bytes`. Writes go straight to the mapping and
+/// reach the file when the OS writes the pages back.
+#[cfg(feature = "mmap")]
+pub struct MappedBits {
+    len: usize,
+    path: PathBuf,
+    map: MmapMut,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedBits {
+    /// Writes `bits` to `path` (replacing any existing file) and maps it.
+    pub(crate) fn create(path: &Path, bits: &LevelBits) -> io::Result<Self> {
+        let len = bits.len();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(len.div_ceil(8) as u64)?;
+        // SAFETY: the file was just created for this level and is only accessed
+        // through this mapping; another process modifying it is outside the
+        // contract, as with any other data file.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        for i in bits.iter_ones() {
+            map[i / 8] |= 1 << (i % 8);
+        }
+        Ok(MappedBits {
+            len,
+            path: path.to_path_buf(),
+            map,
+        })
+    }
+
+    /// Returns the file backing this level.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes modified pages back to the file.
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+
+    fn get(&self, index: usize) -> bool {
+        debug_assert!(index < self.len, "bit index {} out of range for {} bits", index, self.len);
+        self.map[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    fn set(&mut self, index: usize) -> bool {
+        let was_set = self.get(index);
+        self.map[index / 8] |= 1 << (index % 8);
+        !was_set
+    }
+
+    fn unset(&mut self, index: usize) -> bool {
+        let was_set = self.get(index);
+        self.map[index / 8] &= !(1 << (index % 8));
+        was_set
+    }
+
+    fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.map.iter().enumerate().flat_map(|(byte_index, &byte)| {
+            (0..8)
+                .filter(move |bit| byte & (1 << bit) != 0)
+                .map(move |bit| byte_index * 8 + bit)
+        })
+    }
+}
+
+/// A level's bits in a memory-mapped file; `flush` writes dirty pages back.
+#[cfg(feature = "mmap")]
+impl BitStorage for MappedBits {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> bool {
+        index < self.len && MappedBits::get(self, index)
+    }
+
+    fn set(&mut self, index: usize) -> bool {
+        index < self.len && MappedBits::set(self, index)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        MappedBits::flush(self)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl std::fmt::Debug for MappedBits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedBits")
+            .field("len", &self.len)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl PartialEq for MappedBits {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.map[..] == other.map[..]
+    }
+}
+
+/// Serializes like a dense level, so a saved cold level loads back into memory.
+#[cfg(feature = "mmap")]
+impl Serialize for MappedBits {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        packed_bits::serialize_packed(self.len, &self.map, serializer)
+    }
+}
+
+/// Serializes dense bits as `{ "len", "bits" }`, accepting the legacy array of booleans too.
+pub(crate) mod packed_bits {
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use serde::de::{self, MapAccess, SeqAccess, Visitor};
+    use serde::ser::SerializeMap;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(bits: &[bool], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut packed = vec![0u8; bits.len().div_ceil(8)];
+        for (i, _) in bits.iter().enumerate().filter(|(_, &bit)| bit) {
+            packed[i / 8] |= 1 << (i % 8);
+        }
+        serialize_packed(bits.len(), &packed, serializer)
+    }
+
+    pub(crate) fn serialize_packed<S: Serializer>(len: usize, packed: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let human_readable = serializer.is_human_readable();
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("len", &len)?;
+        if human_readable {
+            map.serialize_entry("bits", &BASE64.encode(packed))?;
+        } else {
+            map.serialize_entry("bits", &Bytes(packed))?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<bool>, D::Error> {
+        deserializer.deserialize_any(DenseVisitor)
+    }
+
+    /// Serializes a byte slice with `serialize_bytes` rather than as a sequence.
+    struct Bytes<'a>(&'a [u8]);
+
+    impl serde::Serialize for Bytes<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    struct DenseVisitor;
+
+    impl<'de> Visitor<'de> for DenseVisitor {
+        type Value = Vec<bool>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("packed bits or an array of booleans")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bits = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(bit) = seq.next_element()? {
+                bits.push(bit);
+            }
+            Ok(bits)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let (mut len, mut packed) = (None, None);
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    "len" => len = Some(map.next_value::<usize>()?),
+                    "bits" => packed = Some(map.next_value::<PackedBytes>()?.0),
+                    other => return Err(de::Error::unknown_field(other, &["len", "bits"])),
+                }
+            }
+            let len = len.ok_or_else(|| de::Error::missing_field("len"))?;
+            let packed = packed.ok_or_else(|| de::Error::missing_field("bits"))?;
+            if packed.len() != len.div_ceil(8) {
+                return Err(de::Error::invalid_length(packed.len(), &"ceil(len / 8) bytes"));
+            }
+            Ok((0..len).map(|i| packed[i / 8] & (1 << (i % 8)) != 0).collect())
+        }
+    }
+
+    /// Packed bytes given as base64 text or as raw bytes.
+    struct PackedBytes(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for PackedBytes {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(PackedBytesVisitor)
+        }
+    }
+
+    struct PackedBytesVisitor;
+
+    impl<'de> Visitor<'de> for PackedBytesVisitor {
+        type Value = PackedBytes;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("base64 text or bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            BASE64.decode(value).map(PackedBytes).map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+            Ok(PackedBytes(value.to_vec()))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(PackedBytes(bytes))
+        }
+    }
+}
+
+/// Set positions of a sparsely populated level.
+#[cfg(feature = "roaring")]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SparseBits {
+    len: usize,
+    #[serde(with = "roaring_positions")]
+    ones: RoaringBitmap,
+}
+
+#[cfg(feature = "roaring")]
+impl SparseBits {
+    /// Largest level a roaring bitmap can index (`u32` positions).
+    pub(crate) const MAX_LEN: usize = u32::MAX as usize + 1;
+
+    pub(crate) fn new(len: usize) -> Self {
+        SparseBits {
+            len,
+            ones: RoaringBitmap::new(),
+        }
+    }
+}
+
+/// Serializes a roaring bitmap as the list of its set positions.
+#[cfg(feature = "roaring")]
+mod roaring_positions {
+    use roaring::RoaringBitmap;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(ones: &RoaringBitmap, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(ones.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RoaringBitmap, D::Error> {
+        let positions = Vec::<u32>::deserialize(deserializer)?;
+        Ok(positions.into_iter().collect())
+    }
+}
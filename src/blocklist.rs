@@ -0,0 +1,167 @@
+// src/blocklist.rs
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError, RebuildParams};
+use crate::hashing::HashAlgorithm;
+
+// Domain blocklists.
+//
+// Lists come in several dialects: bare domains, hosts files
+// ("0.0.0.0 ads.example.com"), adblock rules ("||ads.example.com^") and
+// full URLs. `canonicalize` reduces any of them, and any URL being looked
+// up, to a lowercase host name, so the filter only ever sees one spelling.
+// A blocked domain also blocks its subdomains: a lookup checks the host and
+// then each parent domain.
+
+/// Host names that hosts files map to themselves and that are never blocked.
+const HOSTS_FILE_NAMES: [&str; 4] = ["localhost", "localhost.localdomain", "broadcasthost", "local"];
+
+/// Reduces a blocklist entry or a URL to its lowercase host name.
+///
+/// Returns `None` for comments, blank lines, hosts-file boilerplate and
+/// anything that is not a host name or IP address.
+pub fn canonicalize(entry: &str) -> Option<String> {
+    let entry = entry.split(" #").next().unwrap_or_default().trim();
+    if entry.is_empty() || entry.starts_with('#') || entry.starts_with('!') {
+        return None;
+    }
+    let mut tokens = entry.split_whitespace();
+    let first = tokens.next()?;
+    let token = match tokens.next() {
+        // Hosts file: the address the name is redirected to, then the name.
+        Some(name) if first.parse::<IpAddr>().is_ok() && name.parse::<IpAddr>().is_err() => name,
+        Some(_) => return None,
+        None => first,
+    };
+    let token = token.strip_prefix("||").unwrap_or(token);
+    let token = token.split('^').next().unwrap_or_default();
+    let token = token.split_once("://").map_or(token, |(_, rest)| rest);
+    let authority = token.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = match authority.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    let host = host.strip_prefix("*.").unwrap_or(host).trim_end_matches('.').to_lowercase();
+
+    if host.parse::<IpAddr>().is_ok() {
+        return Some(host);
+    }
+    let valid = !host.is_empty()
+        && !HOSTS_FILE_NAMES.contains(&host.as_str())
+        && host.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        });
+    valid.then_some(host)
+}
+
+/// Reads blocklist files in any of the supported dialects, returning their distinct hosts, sorted.
+///
+/// Also returns the number of non-blank, non-comment lines that were not
+/// recognized as a host.
+pub fn read_lists(paths: &[String]) -> Result<(Vec<String>, usize), BloomFilterError> {
+    let mut hosts = BTreeSet::new();
+    let mut skipped = 0;
+    for path in paths {
+        info!("Reading blocklist {}", path);
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            match canonicalize(&line) {
+                Some(host) => {
+                    hosts.insert(host);
+                }
+                None if is_content(&line) => skipped += 1,
+                None => {}
+            }
+        }
+    }
+    info!("Read {} distinct hosts, skipped {} entries", hosts.len(), skipped);
+    Ok((hosts.into_iter().collect(), skipped))
+}
+
+/// Builds a filter holding `hosts` at the target false-positive rate.
+///
+/// Host names share long suffixes, which the default polynomial hash
+/// spreads poorly, so the filter uses FNV-1a.
+pub fn build(hosts: &[String], false_positive_rate: f64) -> Result<BloomFilter, BloomFilterError> {
+    let sizing = RebuildParams::for_capacity(hosts.len().max(1), false_positive_rate);
+    let mut filter = BloomFilter::builder()
+        .array_size(sizing.array_size.unwrap_or(1))
+        .hash_functions(sizing.num_hash_functions.unwrap_or(1))
+        .hash_algorithm(HashAlgorithm::Fnv1a)
+        .build()?;
+    for host in hosts {
+        filter.insert(host);
+    }
+    Ok(filter)
+}
+
+/// Returns the host of `target` and the blocked domain it falls under, if any.
+///
+/// `target` may be a URL or a bare host; the host itself is checked first,
+/// then each parent domain. IP addresses only match exactly.
+pub fn lookup(filter: &BloomFilter, target: &str) -> Result<(String, Option<String>), BloomFilterError> {
+    let host = canonicalize(target).ok_or_else(|| BloomFilterError::InvalidHost(target.to_string()))?;
+    let levels = filter.levels().len();
+    let blocked_by = if host.parse::<IpAddr>().is_ok() {
+        filter.query(&host, levels).then(|| host.clone())
+    } else {
+        parent_domains(&host)
+            .find(|domain| filter.query(domain, levels))
+            .map(str::to_string)
+    };
+    Ok((host, blocked_by))
+}
+
+/// The host followed by each of its parent domains: `a.b.c`, `b.c`, `c`.
+fn parent_domains(host: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(host).chain(host.match_indices('.').map(move |(dot, _)| &host[dot + 1..]))
+}
+
+fn is_content(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && !line.starts_with('#') && !line.starts_with('!')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lists_in_every_dialect_block_subdomains() {
+        let cases = [
+            ("ads.example.com", Some("ads.example.com")),
+            ("0.0.0.0 Tracker.Example.NET # analytics", Some("tracker.example.net")),
+            ("127.0.0.1 localhost", None),
+            ("||cdn.bad.org^$third-party", Some("cdn.bad.org")),
+            ("https://user@Evil.com:8443/path?q=1", Some("evil.com")),
+            ("*.wild.io.", Some("wild.io")),
+            ("http://[2001:db8::1]:80/", Some("2001:db8::1")),
+            ("# comment", None),
+            ("not a host", None),
+        ];
+        for (entry, expected) in cases {
+            assert_eq!(canonicalize(entry).as_deref(), expected, "{}", entry);
+        }
+
+        let path = std::env::temp_dir().join("test_bloom_blocklist.txt");
+        std::fs::write(&path, "! adblock\n||ads.example.com^\n0.0.0.0 evil.com\n10.0.0.1\nbad line here\n").unwrap();
+        let (hosts, skipped) = read_lists(&[path.to_str().unwrap().to_string()]).unwrap();
+        assert_eq!(hosts, ["10.0.0.1", "ads.example.com", "evil.com"]);
+        assert_eq!(skipped, 1);
+        std::fs::remove_file(&path).unwrap();
+
+        let filter = build(&hosts, 0.0001).unwrap();
+        let blocked = |target: &str| lookup(&filter, target).unwrap().1;
+        assert_eq!(blocked("https://x.ads.example.com/banner.png").as_deref(), Some("ads.example.com"));
+        assert_eq!(blocked("EVIL.com").as_deref(), Some("evil.com"));
+        assert_eq!(blocked("example.com"), None);
+        assert_eq!(blocked("http://10.0.0.1:8080/"), Some("10.0.0.1".to_string()));
+        assert!(lookup(&filter, "# nothing").is_err());
+    }
+}
@@ -1,11 +1,37 @@
 // src/bloom_filter.rs
 
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher13;
+use siphasher::sip128::SipHasher24;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufReader, BufWriter};
-use log::{info, error};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::ops::{Bound, Range, RangeBounds};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{field, info, error, instrument, warn, Span};
 use thiserror::Error;
 
+use crate::bits::{BitStorage, LevelBits};
+use crate::builder::BloomFilterBuilder;
+use crate::hashing::{double_hash, HashAlgorithm};
+use crate::normalize::Normalization;
+use crate::observer::Observer;
+use crate::saturation::{CapacityLimit, SaturationAlert};
+use crate::stats::Counters;
+#[cfg(feature = "roaring")]
+use crate::bits::SparseBits;
+#[cfg(feature = "mmap")]
+use crate::bits::MappedBits;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+use crate::math;
+
 /// Custom error type for BloomFilter operations.
 #[derive(Error, Debug)]
 pub enum BloomFilterError {
@@ -17,168 +43,2200 @@ pub enum BloomFilterError {
 
     #[error("Invalid number of hash functions. Requested: {requested}, Available: {available}")]
     InvalidHashFunctions { requested: usize, available: usize },
+
+    #[error("Delta does not match filter: {0}")]
+    DeltaMismatch(String),
+
+    #[error("Invalid bit array encoding: {0}")]
+    InvalidBitArray(String),
+
+    #[error("Invalid RedisBloom dump: {0}")]
+    RedisDump(String),
+
+    #[error("Filter must have at least one level")]
+    ZeroLevels,
+
+    #[error("Bit array size must be greater than zero")]
+    ZeroArraySize,
+
+    #[error("Filter must have at least one hash function")]
+    ZeroHashFunctions,
+
+    #[error("Sharded filter must have at least one shard")]
+    ZeroShards,
+
+    #[error("Level {level} has {actual} bits, expected {expected}")]
+    LevelSizeMismatch { level: usize, expected: usize, actual: usize },
+
+    #[error("Level index {index} is out of range (filter has {levels} levels)")]
+    InvalidLevel { index: usize, levels: usize },
+
+    #[error("Level label already in use: {0}")]
+    DuplicateLevelLabel(String),
+
+    #[error("Invalid level range {start}..{end} (filter has {levels} levels)")]
+    InvalidLevelRange { start: usize, end: usize, levels: usize },
+
+    #[error("Filter uses keyed hashing; load it with its hash key")]
+    MissingHashKey,
+
+    #[error("Hash key does not match the key the filter was built with")]
+    HashKeyMismatch,
+
+    #[error("Hash algorithm {0:?} is not enabled in this build")]
+    UnsupportedHashAlgorithm(HashAlgorithm),
+
+    #[error("{format} encoding error: {message}")]
+    Encoding { format: &'static str, message: String },
+
+    #[error("Invalid protobuf filter: {0}")]
+    InvalidProto(String),
+
+    #[error("Invalid archived filter: {0}")]
+    InvalidArchive(String),
+
+    #[error("File is corrupted: checksum {actual:08x} does not match stored {expected:08x}")]
+    CorruptedFile { expected: u32, actual: u32 },
+
+    #[error("Decryption failed: wrong key or corrupted data")]
+    DecryptionFailed,
+
+    #[error("Signature does not verify with the trusted public key")]
+    InvalidSignature,
+
+    #[error("Filters are not compatible: {0}")]
+    IncompatibleFilters(String),
+
+    #[error("Invalid filter name: {0}")]
+    InvalidFilterName(String),
+
+    #[error("No filter named {0}")]
+    FilterNotFound(String),
+
+    #[error("A filter named {0} already exists")]
+    FilterExists(String),
+
+    #[error("Filter {0} uses keyed hashing, and its key cannot be stored")]
+    KeyedFilterNotStorable(String),
+
+    #[error("HyperLogLog precision must be between {min} and {max}, got {requested}")]
+    InvalidPrecision { requested: u8, min: u8, max: u8 },
+
+    #[error("Cannot build Bloomier filter: {0}")]
+    BloomierConstruction(String),
+
+    #[error("Not a URL or host name: {0}")]
+    InvalidHost(String),
+
+    #[error("Not a breached-password filter: {0}")]
+    InvalidPasswordFilter(String),
+
+    #[error("Invalid k-mer filter: {0}")]
+    InvalidKmer(String),
+
+    #[error("Invalid n-gram filter: {0}")]
+    InvalidNgram(String),
+
+    #[error("Invalid prefix lengths: {0}")]
+    InvalidPrefixLengths(String),
+
+    #[error("Storage backend error: {0}")]
+    Storage(String),
+
+    #[error("Invalid journal: {0}")]
+    InvalidJournal(String),
+
+    #[error("Filter is saturated: {0}")]
+    Saturated(SaturationAlert),
+
+    #[error("Daemon error: {0}")]
+    Daemon(String),
+}
+
+/// Multipliers for the first polynomial hash functions, one per function.
+///
+/// Further functions continue with the following primes (see `hash_multipliers`).
+const HASH_MULTIPLIERS: [usize; 10] = [31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
+
+/// Upper bound on hash functions per item; beyond this the false-positive
+/// rate is already far below anything measurable.
+pub const MAX_HASH_FUNCTIONS: usize = 256;
+
+/// Parameters for `BloomFilter::rebuild`; `None` keeps the current filter's value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RebuildParams {
+    pub num_levels: Option<usize>,
+    pub array_size: Option<usize>,
+    pub num_hash_functions: Option<usize>,
+}
+
+impl RebuildParams {
+    /// Sizes the bit array and hash count for `expected_items` at the target false-positive rate.
+    ///
+    /// The hash count is clamped to `MAX_HASH_FUNCTIONS`.
+    pub fn for_capacity(expected_items: usize, false_positive_rate: f64) -> Self {
+        let array_size = math::optimal_bits(expected_items, false_positive_rate);
+        let num_hash_functions = math::optimal_hashes(array_size, expected_items).min(MAX_HASH_FUNCTIONS);
+        RebuildParams {
+            num_levels: None,
+            array_size: Some(array_size),
+            num_hash_functions: Some(num_hash_functions),
+        }
+    }
+}
+
+/// Controls which levels an insert writes to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum InsertMode {
+    /// Every insert is written to every level.
+    #[default]
+    AllLevels,
+    /// Inserts go only to the active level, which advances to the next level
+    /// once it holds `max_items` items or reaches `max_fill_ratio` set bits.
+    /// After the last level the active level wraps around to the first,
+    /// clearing it, so the levels form generations of a ring.
+    ActiveLevel {
+        max_items: Option<usize>,
+        max_fill_ratio: Option<f64>,
+    },
 }
 
 /// Represents a Bloom Filter with multiple levels.
 #[derive(Serialize, Deserialize)]
 pub struct BloomFilter {
-    levels: Vec<BloomLevel>,
-    hash_functions: Vec<HashFunction>,
-    array_size: usize,
+    pub(crate) levels: Vec<BloomLevel>,
+    pub(crate) hash_functions: Vec<HashFunction>,
+    pub(crate) array_size: usize,
+    #[serde(default)]
+    pub(crate) insert_mode: InsertMode,
+    #[serde(default)]
+    pub(crate) active_level: usize,
+    #[serde(default)]
+    pub(crate) hash_algorithm: HashAlgorithm,
+    /// Identifies the SipHash key without revealing it; the key itself is never saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) key_fingerprint: Option<u64>,
+    #[serde(skip)]
+    pub(crate) hash_key: Option<[u8; 16]>,
+    #[serde(default, skip_serializing_if = "Normalization::is_identity")]
+    pub(crate) normalization: Normalization,
+    #[serde(skip)]
+    pub(crate) observers: Vec<Arc<dyn Observer>>,
+    #[serde(skip)]
+    pub(crate) counters: Counters,
+    #[serde(skip)]
+    pub(crate) capacity: CapacityLimit,
 }
 
 impl BloomFilter {
     /// Creates a new BloomFilter with the specified number of levels, array size, and hash functions.
+    ///
+    /// `num_hash_functions` must be between 1 and `MAX_HASH_FUNCTIONS` (256);
+    /// more fails with `InvalidHashFunctions`.
     pub fn new(num_levels: usize, array_size: usize, num_hash_functions: usize) -> Result<Self, BloomFilterError> {
+        Self::with_levels(num_levels, array_size, num_hash_functions, 0, || Ok(BloomLevel::new(array_size)))
+    }
+
+    /// Returns a builder for configuring a filter beyond what `new` exposes.
+    pub fn builder() -> BloomFilterBuilder {
+        BloomFilterBuilder::default()
+    }
+
+    /// Validates the parameters, creates the hash functions and builds each level with `make_level`.
+    pub(crate) fn with_levels<F>(
+        num_levels: usize,
+        array_size: usize,
+        num_hash_functions: usize,
+        seed: u64,
+        mut make_level: F,
+    ) -> Result<Self, BloomFilterError>
+    where
+        F: FnMut() -> Result<BloomLevel, BloomFilterError>,
+    {
         info!(
             "Creating BloomFilter: levels={}, array_size={}, hash_functions={}",
             num_levels, array_size, num_hash_functions
         );
+        if num_levels == 0 {
+            error!("Requested zero levels");
+            return Err(BloomFilterError::ZeroLevels);
+        }
+        if array_size == 0 {
+            error!("Requested a zero-sized bit array");
+            return Err(BloomFilterError::ZeroArraySize);
+        }
+        if num_hash_functions == 0 {
+            error!("Requested zero hash functions");
+            return Err(BloomFilterError::ZeroHashFunctions);
+        }
+
         // Create the hash functions
-        let multipliers = vec![31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
-        if num_hash_functions > multipliers.len() {
+        if num_hash_functions > MAX_HASH_FUNCTIONS {
             error!(
-                "Requested hash functions ({}) exceed available ({})",
-                num_hash_functions,
-                multipliers.len()
+                "Requested hash functions ({}) exceed maximum ({})",
+                num_hash_functions, MAX_HASH_FUNCTIONS
             );
             return Err(BloomFilterError::InvalidHashFunctions {
                 requested: num_hash_functions,
-                available: multipliers.len(),
+                available: MAX_HASH_FUNCTIONS,
             });
         }
-        let hash_functions: Vec<HashFunction> = multipliers[..num_hash_functions]
-            .iter()
-            .map(|&multiplier| HashFunction::new(multiplier))
+        let hash_functions: Vec<HashFunction> = hash_multipliers(num_hash_functions)
+            .into_iter()
+            .map(|multiplier| HashFunction::with_seed(multiplier, seed))
             .collect();
 
         // Create levels
         let levels = (0..num_levels)
-            .map(|_| BloomLevel::new(array_size))
-            .collect();
+            .map(|_| make_level())
+            .collect::<Result<_, _>>()?;
 
         Ok(BloomFilter {
             levels,
             hash_functions,
             array_size,
+            insert_mode: InsertMode::AllLevels,
+            active_level: 0,
+            hash_algorithm: HashAlgorithm::Polynomial,
+            key_fingerprint: None,
+            hash_key: None,
+            normalization: Normalization::default(),
+            observers: Vec::new(),
+            counters: Counters::default(),
+            capacity: CapacityLimit::default(),
         })
     }
 
-    /// Inserts an item into all levels of the Bloom filter.
-    pub fn insert(&mut self, item: &str) {
-        info!("Inserting item: {}", item);
-        for level in &mut self.levels {
-            level.insert(item, &self.hash_functions, self.array_size);
+    /// Creates a single-level filter whose total memory stays within `budget_bytes`.
+    ///
+    /// The bit array takes whatever the budget leaves after the fixed
+    /// overhead, and the hash count is the optimum for that size and
+    /// `expected_items`. Dense levels store one bit per byte, so the array
+    /// has roughly `budget_bytes` bits; check the outcome with `memory_usage`
+    /// and `estimated_false_positive_rate`.
+    pub fn with_memory_budget(budget_bytes: usize, expected_items: usize) -> Result<Self, BloomFilterError> {
+        let overhead = std::mem::size_of::<BloomFilter>() + std::mem::size_of::<BloomLevel>();
+        let available = budget_bytes.saturating_sub(overhead);
+        let num_hash_functions =
+            math::optimal_hashes(LevelBits::dense_len_for(available), expected_items).min(MAX_HASH_FUNCTIONS);
+        let array_size = LevelBits::dense_len_for(
+            available.saturating_sub(num_hash_functions * std::mem::size_of::<HashFunction>()),
+        );
+        info!(
+            "Sizing BloomFilter for a {}-byte budget and {} items: array_size={}, hash_functions={}",
+            budget_bytes, expected_items, array_size, num_hash_functions
+        );
+        Self::new(1, array_size, num_hash_functions)
+    }
+
+    /// Creates a filter whose levels are roaring bitmaps instead of dense bit arrays.
+    ///
+    /// Memory use is proportional to the number of set bits rather than to
+    /// `array_size`, which suits very large, sparsely populated filters. The
+    /// insert/query API and the `MAX_HASH_FUNCTIONS` cap are as in `new`.
+    #[cfg(feature = "roaring")]
+    pub fn new_sparse(num_levels: usize, array_size: usize, num_hash_functions: usize) -> Result<Self, BloomFilterError> {
+        Self::with_levels(num_levels, array_size, num_hash_functions, 0, || BloomLevel::new_sparse(array_size))
+    }
+
+    /// Sets how inserts are distributed across levels.
+    pub fn set_insert_mode(&mut self, mode: InsertMode) {
+        info!("Setting insert mode: {:?}", mode);
+        self.insert_mode = mode;
+    }
+
+    /// Returns the current insert mode.
+    pub fn insert_mode(&self) -> InsertMode {
+        self.insert_mode
+    }
+
+    /// Returns the index of the level that receives inserts in `InsertMode::ActiveLevel`.
+    pub fn active_level(&self) -> usize {
+        self.active_level
+    }
+
+    /// Moves the active level to the next one (wrapping around) and clears it.
+    ///
+    /// Returns the new active level index.
+    pub fn advance_level(&mut self) -> usize {
+        debug_assert!(!self.levels.is_empty(), "a filter always has at least one level");
+        self.active_level = (self.active_level + 1).checked_rem(self.levels.len()).unwrap_or(0);
+        info!("Advancing active level to {}", self.active_level);
+        if let Some(level) = self.levels.get_mut(self.active_level) {
+            level.clear();
+            if level.ttl.is_some() {
+                level.created_at = Some(unix_now());
+            }
         }
+        self.active_level
     }
 
-    /// Queries an item across the specified number of levels.
-    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
-        info!("Querying item: {} across {} levels", item, num_levels_to_search);
-        let levels_to_search = std::cmp::min(num_levels_to_search, self.levels.len());
-        for i in 0..levels_to_search {
-            if self.levels[i].query(item, &self.hash_functions, self.array_size) {
-                return true;
+    /// Builds a correctly sized replacement filter from the original items.
+    ///
+    /// Parameters left as `None` in `params` keep this filter's values, and the
+    /// insert mode, seed, hash algorithm and hash key carry over. When the level count is unchanged, each level's
+    /// label, metadata and TTL are copied to the matching new level.
+    pub fn rebuild<I, S>(&self, source_items: I, params: RebuildParams) -> Result<Self, BloomFilterError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let num_levels = params.num_levels.unwrap_or(self.levels.len());
+        let array_size = params.array_size.unwrap_or(self.array_size);
+        let num_hash_functions = params.num_hash_functions.unwrap_or(self.hash_functions.len());
+        info!(
+            "Rebuilding BloomFilter: levels={}, array_size={}, hash_functions={}",
+            num_levels, array_size, num_hash_functions
+        );
+        let mut rebuilt = Self::with_levels(num_levels, array_size, num_hash_functions, self.seed(), || {
+            Ok(BloomLevel::new(array_size))
+        })?;
+        rebuilt.insert_mode = self.insert_mode;
+        rebuilt.hash_algorithm = self.hash_algorithm;
+        rebuilt.key_fingerprint = self.key_fingerprint;
+        rebuilt.hash_key = self.hash_key;
+        rebuilt.normalization = self.normalization;
+        if num_levels == self.levels.len() {
+            for (new_level, old_level) in rebuilt.levels.iter_mut().zip(&self.levels) {
+                new_level.label = old_level.label.clone();
+                new_level.metadata = old_level.metadata.clone();
+                new_level.ttl = old_level.ttl;
+                new_level.created_at = old_level.ttl.map(|_| unix_now());
             }
         }
-        false
+        for item in source_items {
+            rebuilt.insert(item.as_ref());
+        }
+        Ok(rebuilt)
     }
 
-    /// Saves the Bloom filter to a file in JSON format.
-    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
-        info!("Saving BloomFilter to file: {}", filepath);
-        let file = File::create(filepath)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, &self)?;
+    /// Builds a filter from existing levels, e.g. ones decoded with `BloomLevel::from_bytes`.
+    ///
+    /// All levels must share the same size; hash functions are created exactly as in `new`,
+    /// so `num_hash_functions` is capped at `MAX_HASH_FUNCTIONS` as there.
+    pub fn from_levels(levels: Vec<BloomLevel>, num_hash_functions: usize) -> Result<Self, BloomFilterError> {
+        let array_size = levels.first().map_or(0, BloomLevel::len);
+        if let Some(level) = levels.iter().find(|level| level.len() != array_size) {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "level has {} bits, expected {}",
+                level.len(),
+                array_size
+            )));
+        }
+        let mut bloom_filter = Self::new(levels.len(), array_size, num_hash_functions)?;
+        bloom_filter.levels = levels;
+        Ok(bloom_filter)
+    }
+
+    /// Returns the levels of the filter in index order.
+    pub fn levels(&self) -> &[BloomLevel] {
+        &self.levels
+    }
+
+    /// Returns the number of bits in each level.
+    pub fn array_size(&self) -> usize {
+        self.array_size
+    }
+
+    /// Returns the hash functions applied to each item.
+    pub fn hash_functions(&self) -> &[HashFunction] {
+        &self.hash_functions
+    }
+
+    /// Returns the seed perturbing every hash (0 for an unseeded filter).
+    pub fn seed(&self) -> u64 {
+        self.hash_functions.first().map_or(0, HashFunction::seed)
+    }
+
+    /// Returns the number of hash functions applied per item.
+    pub fn num_hash_functions(&self) -> usize {
+        self.hash_functions.len()
+    }
+
+    /// Estimates the false-positive rate of a query across all levels from their current fill.
+    ///
+    /// A query matches if any unexpired level matches, so the rate is
+    /// `1 - prod(1 - fill_i^k)`.
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let now = unix_now();
+        let k = self.hash_functions.len();
+        1.0 - self
+            .levels
+            .iter()
+            .filter(|level| !level.is_expired_at(now))
+            .map(|level| 1.0 - math::estimated_false_positive_rate(level.fill_ratio(), k))
+            .product::<f64>()
+    }
+
+    /// Measures the false-positive rate by querying items known never to have been inserted.
+    ///
+    /// Each item is normalized and checked across all levels, like a `query`
+    /// with every level, and the share that match is returned (0 for no
+    /// items). Compare it with `estimated_false_positive_rate` to validate the
+    /// theory against the data. The probes are not counted as queries and are
+    /// not reported to observers.
+    pub fn measure_false_positive_rate<I>(&self, negatives: I) -> f64
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let positions = negatives.into_iter().map(|item| self.positions(self.normalize(item.as_ref()).as_bytes()));
+        self.measure_positions(positions)
+    }
+
+    /// Like `measure_false_positive_rate`, for binary keys, which are hashed as given.
+    pub fn measure_false_positive_rate_bytes<I>(&self, negatives: I) -> f64
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        self.measure_positions(negatives.into_iter().map(|item| self.positions(item.as_ref())))
+    }
+
+    fn measure_positions(&self, negatives: impl Iterator<Item = Vec<usize>>) -> f64 {
+        let now = unix_now();
+        let (mut probes, mut matches) = (0usize, 0usize);
+        for positions in negatives {
+            probes += 1;
+            let mut live = self.levels.iter().filter(|level| !level.is_expired_at(now));
+            matches += usize::from(live.any(|level| level.contains_positions(&positions)));
+        }
+        info!("Measured {} false positives in {} probes", matches, probes);
+        if probes == 0 {
+            0.0
+        } else {
+            matches as f64 / probes as f64
+        }
+    }
+
+    /// Returns the index of the level with the given label.
+    pub fn level_index(&self, label: &str) -> Option<usize> {
+        self.levels.iter().position(|level| level.label() == Some(label))
+    }
+
+    /// Returns the level with the given label.
+    pub fn level_by_name(&self, label: &str) -> Option<&BloomLevel> {
+        self.level_index(label).map(|index| &self.levels[index])
+    }
+
+    /// Sets (or clears, with `None`) the label of a level. Labels must be unique.
+    pub fn set_level_label(&mut self, index: usize, label: Option<String>) -> Result<(), BloomFilterError> {
+        self.check_level(index)?;
+        if let Some(label) = &label {
+            if self.level_index(label).is_some_and(|existing| existing != index) {
+                return Err(BloomFilterError::DuplicateLevelLabel(label.clone()));
+            }
+        }
+        self.levels[index].label = label;
         Ok(())
     }
 
-    /// Loads a Bloom filter from a JSON file.
-    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
-        info!("Loading BloomFilter from file: {}", filepath);
-        let file = File::open(filepath)?;
-        let reader = BufReader::new(file);
-        let bloom_filter = serde_json::from_reader(reader)?;
-        Ok(bloom_filter)
+    /// Attaches a metadata entry (e.g. a date range or source dataset) to a level.
+    pub fn set_level_metadata(&mut self, index: usize, key: &str, value: &str) -> Result<(), BloomFilterError> {
+        self.check_level(index)?;
+        self.levels[index].metadata.insert(key.to_string(), value.to_string());
+        Ok(())
     }
-}
 
-/// Represents a single level within the Bloom filter.
-#[derive(Serialize, Deserialize)]
-pub struct BloomLevel {
-    bit_array: Vec<bool>,
-}
+    /// Sets (or removes, with `None`) the time-to-live of a level.
+    ///
+    /// The level's creation timestamp is reset to now, so the TTL counts from
+    /// this call. Once it elapses the level is ignored by queries and cleared by
+    /// `expire` (which inserts also run first).
+    pub fn set_level_ttl(&mut self, index: usize, ttl: Option<Duration>) -> Result<(), BloomFilterError> {
+        self.check_level(index)?;
+        let level = &mut self.levels[index];
+        level.ttl = ttl.map(|ttl| ttl.as_secs());
+        level.created_at = ttl.map(|_| unix_now());
+        Ok(())
+    }
 
-impl BloomLevel {
-    /// Creates a new BloomLevel with the specified array size.
-    pub fn new(array_size: usize) -> Self {
-        BloomLevel {
-            bit_array: vec![false; array_size],
+    /// Clears every level whose TTL has elapsed and restarts its clock.
+    ///
+    /// Returns the number of levels cleared. Clearing cannot be expressed as a
+    /// delta, so take a fresh snapshot afterwards when using `save_delta`.
+    pub fn expire(&mut self) -> usize {
+        self.expire_at(SystemTime::now())
+    }
+
+    /// Like `expire`, but evaluates TTLs against the given time.
+    pub fn expire_at(&mut self, now: SystemTime) -> usize {
+        let now = unix_secs(now);
+        let mut cleared = 0;
+        for (index, level) in self.levels.iter_mut().enumerate() {
+            if level.is_expired_at(now) {
+                info!("Level {} expired, clearing", index);
+                level.clear();
+                level.created_at = Some(now);
+                cleared += 1;
+            }
         }
+        cleared
     }
 
-    /// Inserts an item into the BloomLevel using the provided hash functions.
-    pub fn insert(&mut self, item: &str, hash_functions: &[HashFunction], array_size: usize) {
-        for hf in hash_functions {
-            let hash = hf.hash(item) % array_size;
-            self.bit_array[hash] = true;
+    /// Moves a level's bits into a memory-mapped file at `path`, freeing the RAM they used.
+    ///
+    /// Cold levels are queried and inserted into like any other, with the OS
+    /// paging their bits in on demand, which suits large historical levels that
+    /// are rarely hit. The file is replaced if it exists. Saving the filter still
+    /// writes the cold bits in full, so a loaded filter starts with every level
+    /// in memory.
+    #[cfg(feature = "mmap")]
+    pub fn make_level_cold(&mut self, index: usize, path: &str) -> Result<(), BloomFilterError> {
+        self.make_level_hot(index)?;
+        info!("Moving level {} to {}", index, path);
+        let level = &mut self.levels[index];
+        level.bit_array = LevelBits::Mapped(MappedBits::create(Path::new(path), &level.bit_array)?);
+        Ok(())
+    }
+
+    /// Reads a cold level's bits back into memory. The backing file is left in place.
+    #[cfg(feature = "mmap")]
+    pub fn make_level_hot(&mut self, index: usize) -> Result<(), BloomFilterError> {
+        self.check_level(index)?;
+        let level = &mut self.levels[index];
+        if let LevelBits::Mapped(mapped) = &level.bit_array {
+            info!("Moving level {} from {} into memory", index, mapped.path().display());
+            let mut bits = LevelBits::dense(level.bit_array.len());
+            bits.union_with(&level.bit_array);
+            level.bit_array = bits;
         }
+        Ok(())
     }
 
-    /// Queries an item in the BloomLevel using the provided hash functions.
-    pub fn query(&self, item: &str, hash_functions: &[HashFunction], array_size: usize) -> bool {
-        for hf in hash_functions {
-            let hash = hf.hash(item) % array_size;
-            if !self.bit_array[hash] {
-                return false;
+    /// Writes pending changes to cold levels back to their files.
+    #[cfg(feature = "mmap")]
+    pub fn flush_cold_levels(&self) -> Result<(), BloomFilterError> {
+        for level in &self.levels {
+            if let LevelBits::Mapped(mapped) = &level.bit_array {
+                mapped.flush()?;
             }
         }
-        true
+        Ok(())
     }
-}
 
-/// Represents a single hash function used in the Bloom filter.
-#[derive(Serialize, Deserialize)]
-pub struct HashFunction {
-    multiplier: usize,
-}
+    fn check_level(&self, index: usize) -> Result<(), BloomFilterError> {
+        if index >= self.levels.len() {
+            return Err(BloomFilterError::InvalidLevel {
+                index,
+                levels: self.levels.len(),
+            });
+        }
+        Ok(())
+    }
 
-impl HashFunction {
-    /// Creates a new HashFunction with the specified multiplier.
-    pub fn new(multiplier: usize) -> Self {
-        HashFunction { multiplier }
+    /// Inserts an item into all levels of the Bloom filter.
+    ///
+    /// Returns `true` if any bit was newly set, meaning the item was definitely
+    /// not present before; `false` means it may already have been inserted.
+    #[instrument(level = "debug", skip_all, fields(item_hash = item_hash(item.as_bytes()), levels = field::Empty))]
+    pub fn insert(&mut self, item: &str) -> bool {
+        info!("Inserting {}-byte item", item.len());
+        let key = self.normalize(item);
+        let newly_set = self.insert_positions(&self.positions(key.as_bytes()));
+        self.notify(|observer| observer.on_insert(key.as_bytes(), newly_set));
+        newly_set
     }
 
-    /// Computes the hash of a string.
-    pub fn hash(&self, s: &str) -> usize {
-        s.bytes()
-            .fold(0, |hash, b| hash.wrapping_mul(self.multiplier).wrapping_add(b as usize))
+    /// Inserts a binary key (hash, UUID, serialized record) without converting it to a string.
+    ///
+    /// A `&str` inserted with `insert` and its UTF-8 bytes inserted here are the same key.
+    #[instrument(level = "debug", name = "insert", skip_all, fields(item_hash = item_hash(item), levels = field::Empty))]
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        info!("Inserting {}-byte key", item.len());
+        let newly_set = self.insert_positions(&self.positions(item));
+        self.notify(|observer| observer.on_insert(item, newly_set));
+        newly_set
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Inserts an integer key, hashing it directly instead of formatting it.
+    ///
+    /// Integer keys use their own index derivation, so `insert_u64(42)` and
+    /// `insert("42")` are different keys; query with `query_u64`.
+    #[instrument(
+        level = "debug",
+        name = "insert",
+        skip_all,
+        fields(item_hash = item_hash(&item.to_le_bytes()), levels = field::Empty)
+    )]
+    pub fn insert_u64(&mut self, item: u64) -> bool {
+        info!("Inserting integer key");
+        let newly_set = self.insert_positions(&self.integer_positions(item));
+        self.notify(|observer| observer.on_insert(&item.to_le_bytes(), newly_set));
+        newly_set
+    }
 
-    #[test]
-    fn test_insert_and_query() {
-        let mut bf = BloomFilter::new(1, 100, 3).unwrap();
-        bf.insert("test");
-        assert!(bf.query("test", 1));
-        assert!(!bf.query("nonexistent", 1));
+    fn insert_positions(&mut self, positions: &[usize]) -> bool {
+        let bounded = self.capacity != CapacityLimit::default();
+        let saturated = bounded && self.saturation().is_some();
+        let newly_set = self.insert_positions_unbounded(positions);
+        if bounded && !saturated {
+            if let Some(alert) = self.saturation() {
+                warn!("Filter reached its capacity limit: {}; accuracy degrades with further inserts", alert);
+            }
+        }
+        newly_set
     }
 
-    #[test]
-    fn test_save_and_load() {
-        let mut bf = BloomFilter::new(1, 100, 3).unwrap();
-        bf.insert("test");
-        bf.save_to_file("test_bloom.json").unwrap();
+    fn insert_positions_unbounded(&mut self, positions: &[usize]) -> bool {
+        self.counters.inserts.fetch_add(1, Ordering::Relaxed);
+        if self.levels.iter().any(|level| level.ttl.is_some()) {
+            self.expire();
+        }
+        let levels_written = match self.insert_mode {
+            InsertMode::AllLevels => self.levels.len(),
+            InsertMode::ActiveLevel { .. } => 1,
+        };
+        Span::current().record("levels", levels_written);
+        match self.insert_mode {
+            InsertMode::AllLevels => {
+                let mut newly_set = false;
+                for level in &mut self.levels {
+                    newly_set |= level.insert_positions(positions);
+                }
+                newly_set
+            }
+            InsertMode::ActiveLevel {
+                max_items,
+                max_fill_ratio,
+            } => {
+                debug_assert!(self.active_level < self.levels.len(), "active level out of range");
+                let Some(level) = self.levels.get_mut(self.active_level) else {
+                    error!("Active level {} is out of range; item not inserted", self.active_level);
+                    return false;
+                };
+                let newly_set = level.insert_positions(positions);
+                let full_by_items = max_items.is_some_and(|max| level.item_count >= max);
+                let full_by_fill = max_fill_ratio.is_some_and(|max| level.fill_ratio() >= max);
+                if full_by_items || full_by_fill {
+                    self.advance_level();
+                }
+                newly_set
+            }
+        }
+    }
 
-        let loaded_bf = BloomFilter::load_from_file("test_bloom.json").unwrap();
-        assert!(loaded_bf.query("test", 1));
-        assert!(!loaded_bf.query("nonexistent", 1));
+    /// Computes the bit positions probed for a key.
+    pub(crate) fn positions(&self, key: &[u8]) -> Vec<usize> {
+        match &self.hash_key {
+            Some(hash_key) => keyed_positions(hash_key, self.hash_functions.len(), self.array_size, key),
+            None => self.hash_algorithm.positions(&self.hash_functions, self.array_size, key),
+        }
+    }
 
-        // Clean up test file
-        std::fs::remove_file("test_bloom.json").unwrap();
+    /// Applies the filter's key normalization to a string key.
+    pub(crate) fn normalize<'a>(&self, item: &'a str) -> Cow<'a, str> {
+        self.normalization.apply(item)
+    }
+
+    /// Returns how string keys are rewritten before hashing.
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    /// Computes the bit positions probed for an integer key.
+    fn integer_positions(&self, item: u64) -> Vec<usize> {
+        match &self.hash_key {
+            Some(hash_key) => keyed_positions(hash_key, self.hash_functions.len(), self.array_size, &item.to_le_bytes()),
+            None => u64_positions(&self.hash_functions, self.array_size, item),
+        }
+    }
+
+    /// Sets the algorithm used to hash string and byte keys.
+    pub(crate) fn set_hash_algorithm(&mut self, algorithm: HashAlgorithm) -> Result<(), BloomFilterError> {
+        if !algorithm.is_available() {
+            error!("Hash algorithm {:?} is not enabled", algorithm);
+            return Err(BloomFilterError::UnsupportedHashAlgorithm(algorithm));
+        }
+        self.hash_algorithm = algorithm;
+        Ok(())
+    }
+
+    /// Returns the algorithm used to hash string and byte keys.
+    ///
+    /// A keyed filter hashes with SipHash regardless, and integer keys always
+    /// use their own splitmix derivation.
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Switches the filter to SipHash keyed with `hash_key` and records the key's fingerprint.
+    pub(crate) fn set_hash_key(&mut self, hash_key: [u8; 16]) {
+        self.key_fingerprint = Some(key_fingerprint(&hash_key));
+        self.hash_key = Some(hash_key);
+    }
+
+    /// Returns the SipHash key, if the filter uses keyed hashing.
+    pub fn hash_key(&self) -> Option<&[u8; 16]> {
+        self.hash_key.as_ref()
+    }
+
+    /// Streams delimiter-separated items from a reader and inserts each one.
+    ///
+    /// Items are read one at a time, so the input never has to fit in memory.
+    /// Empty items are skipped, and when splitting on `b'\n'` a trailing `\r`
+    /// is stripped so CRLF files behave like LF files. Returns the number of
+    /// items inserted.
+    pub fn insert_from_reader<R: Read>(&mut self, reader: R, delimiter: u8) -> Result<usize, BloomFilterError> {
+        info!("Inserting items from reader (delimiter={:?})", delimiter as char);
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        let mut count = 0;
+        loop {
+            buf.clear();
+            if reader.read_until(delimiter, &mut buf)? == 0 {
+                break;
+            }
+            if buf.last() == Some(&delimiter) {
+                buf.pop();
+            }
+            if delimiter == b'\n' && buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+            if buf.is_empty() {
+                continue;
+            }
+            let item = std::str::from_utf8(&buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.insert(item);
+            count += 1;
+        }
+        info!("Inserted {} items from reader", count);
+        Ok(count)
+    }
+
+    /// Queries an item across the specified number of levels.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.query_level(item, num_levels_to_search).is_some()
+    }
+
+    /// Returns the index of the first level (of the first `num_levels_to_search`) that contains the item.
+    #[instrument(
+        level = "debug",
+        name = "query",
+        skip_all,
+        fields(item_hash = item_hash(item.as_bytes()), levels = num_levels_to_search, levels_probed = field::Empty)
+    )]
+    pub fn query_level(&self, item: &str, num_levels_to_search: usize) -> Option<usize> {
+        info!("Querying {}-byte item across {} levels", item.len(), num_levels_to_search);
+        let key = self.normalize(item);
+        let level = self.first_match(&self.positions(key.as_bytes()), 0..num_levels_to_search);
+        self.notify(|observer| observer.on_query(key.as_bytes(), level.is_some()));
+        level
+    }
+
+    /// Queries a binary key across the specified number of levels.
+    #[instrument(
+        level = "debug",
+        name = "query",
+        skip_all,
+        fields(item_hash = item_hash(item), levels = num_levels_to_search, levels_probed = field::Empty)
+    )]
+    pub fn query_bytes(&self, item: &[u8], num_levels_to_search: usize) -> bool {
+        info!("Querying {}-byte key across {} levels", item.len(), num_levels_to_search);
+        let hit = self.first_match(&self.positions(item), 0..num_levels_to_search).is_some();
+        self.notify(|observer| observer.on_query(item, hit));
+        hit
+    }
+
+    /// Queries an integer key inserted with `insert_u64` across the specified number of levels.
+    #[instrument(
+        level = "debug",
+        name = "query",
+        skip_all,
+        fields(
+            item_hash = item_hash(&item.to_le_bytes()),
+            levels = num_levels_to_search,
+            levels_probed = field::Empty
+        )
+    )]
+    pub fn query_u64(&self, item: u64, num_levels_to_search: usize) -> bool {
+        info!("Querying integer key across {} levels", num_levels_to_search);
+        let hit = self.first_match(&self.integer_positions(item), 0..num_levels_to_search).is_some();
+        self.notify(|observer| observer.on_query(&item.to_le_bytes(), hit));
+        hit
+    }
+
+    /// Queries an item across an arbitrary range of levels, e.g. `2..5` or `3..`.
+    ///
+    /// Bounds past the last level are clamped; an empty range never matches.
+    #[instrument(
+        level = "debug",
+        name = "query",
+        skip_all,
+        fields(item_hash = item_hash(item.as_bytes()), levels_probed = field::Empty)
+    )]
+    pub fn query_range(&self, item: &str, levels: impl RangeBounds<usize>) -> bool {
+        let range = self.resolve_range(levels);
+        info!("Querying {}-byte item across levels {}..{}", item.len(), range.start, range.end);
+        let key = self.normalize(item);
+        let hit = self.first_match(&self.positions(key.as_bytes()), range).is_some();
+        self.notify(|observer| observer.on_query(key.as_bytes(), hit));
+        hit
+    }
+
+    /// Like `query_range`, for a binary key.
+    pub(crate) fn query_bytes_range(&self, item: &[u8], levels: impl RangeBounds<usize>) -> bool {
+        let range = self.resolve_range(levels);
+        self.first_match(&self.positions(item), range).is_some()
+    }
+
+    /// Merges the levels in `range` into a single level by OR-ing their bits.
+    ///
+    /// The merged level takes the place of the first level in the range and
+    /// keeps its label, metadata and TTL; item counts are summed. Later levels
+    /// shift down. Because the level layout changes, delta tracking stops and
+    /// a new snapshot is needed before the next `save_delta`.
+    pub fn compact_levels(&mut self, levels: impl RangeBounds<usize>) -> Result<(), BloomFilterError> {
+        let range = self.resolve_range(levels);
+        if range.start >= range.end || range.end > self.levels.len() {
+            return Err(BloomFilterError::InvalidLevelRange {
+                start: range.start,
+                end: range.end,
+                levels: self.levels.len(),
+            });
+        }
+        info!("Compacting levels {}..{}", range.start, range.end);
+        let merged: Vec<BloomLevel> = self.levels.drain(range.start + 1..range.end).collect();
+        let target = &mut self.levels[range.start];
+        for level in &merged {
+            target.bit_array.union_with(&level.bit_array);
+            target.item_count += level.item_count;
+        }
+        target.ones = None;
+
+        let removed = merged.len();
+        if self.active_level >= range.end {
+            self.active_level -= removed;
+        } else if self.active_level > range.start {
+            self.active_level = range.start;
+        }
+        for level in &mut self.levels {
+            level.dirty = None;
+        }
+        Ok(())
+    }
+
+    /// Checks that `other` hashes every item to the same bit positions as this filter.
+    ///
+    /// Compatible filters have the same array size, hash functions, seed,
+    /// algorithm, hash key and key normalization, so their bits can be
+    /// compared or combined.
+    /// The number of levels may differ.
+    pub fn check_compatible(&self, other: &BloomFilter) -> Result<(), BloomFilterError> {
+        let mismatch = if self.array_size != other.array_size {
+            Some(format!("array sizes differ ({} vs {})", self.array_size, other.array_size))
+        } else if self.hash_functions.len() != other.hash_functions.len() {
+            Some(format!(
+                "hash function counts differ ({} vs {})",
+                self.hash_functions.len(),
+                other.hash_functions.len()
+            ))
+        } else if self
+            .hash_functions
+            .iter()
+            .zip(&other.hash_functions)
+            .any(|(a, b)| a.multiplier != b.multiplier || a.seed != b.seed)
+        {
+            Some("hash functions or seeds differ".to_string())
+        } else if self.hash_algorithm != other.hash_algorithm {
+            Some(format!(
+                "hash algorithms differ ({:?} vs {:?})",
+                self.hash_algorithm, other.hash_algorithm
+            ))
+        } else if self.key_fingerprint != other.key_fingerprint {
+            Some("hash keys differ".to_string())
+        } else if self.normalization != other.normalization {
+            Some(format!(
+                "key normalizations differ ({} vs {})",
+                self.normalization, other.normalization
+            ))
+        } else {
+            None
+        };
+        match mismatch {
+            Some(reason) => {
+                error!("Incompatible filters: {}", reason);
+                Err(BloomFilterError::IncompatibleFilters(reason))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Merges `other` into this filter, level by level, so it matches every item either filter matched.
+    ///
+    /// Both filters must pass `check_compatible` and have the same number of
+    /// levels. Labels, metadata and TTLs of this filter are kept; item counts
+    /// are summed, which over-counts items present in both. As with
+    /// `compact_levels`, delta tracking stops.
+    pub fn union_with(&mut self, other: &BloomFilter) -> Result<(), BloomFilterError> {
+        self.check_same_levels(other)?;
+        info!("Merging BloomFilter with {} levels", other.levels.len());
+        for (level, other_level) in self.levels.iter_mut().zip(&other.levels) {
+            level.bit_array.union_with(&other_level.bit_array);
+            level.item_count += other_level.item_count;
+            level.ones = None;
+            level.dirty = None;
+        }
+        Ok(())
+    }
+
+    /// Keeps only the bits also set in `other`, level by level, so it matches items both filters matched.
+    ///
+    /// The requirements are those of `union_with`. The result can match
+    /// items that were in neither filter alone (their bits may be set by
+    /// different items in each), so it is an approximation of the true
+    /// intersection with a higher false-positive rate than a filter built from
+    /// it. Item counts become the smaller of the two, an upper bound.
+    pub fn intersect_with(&mut self, other: &BloomFilter) -> Result<(), BloomFilterError> {
+        self.check_same_levels(other)?;
+        info!("Intersecting BloomFilter with {} levels", other.levels.len());
+        for (level, other_level) in self.levels.iter_mut().zip(&other.levels) {
+            level.bit_array.intersect_with(&other_level.bit_array);
+            level.item_count = level.item_count.min(other_level.item_count);
+            level.ones = None;
+            level.dirty = None;
+        }
+        Ok(())
+    }
+
+    /// Checks that `other` passes `check_compatible` and has as many levels.
+    pub(crate) fn check_same_levels(&self, other: &BloomFilter) -> Result<(), BloomFilterError> {
+        self.check_compatible(other)?;
+        if self.levels.len() != other.levels.len() {
+            return Err(BloomFilterError::IncompatibleFilters(format!(
+                "level counts differ ({} vs {})",
+                self.levels.len(),
+                other.levels.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Converts range bounds into a concrete `start..end` over level indices.
+    fn resolve_range(&self, levels: impl RangeBounds<usize>) -> Range<usize> {
+        let start = match levels.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match levels.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.levels.len(),
+        };
+        start..end
+    }
+
+    /// Returns the first level in `range` (clamped to the existing levels) with all `positions` set.
+    fn first_match(&self, positions: &[usize], range: Range<usize>) -> Option<usize> {
+        let end = std::cmp::min(range.end, self.levels.len());
+        let now = unix_now();
+        let mut probed = 0;
+        let found = (range.start..end).find(|&i| {
+            let level = &self.levels[i];
+            probed += 1;
+            !level.is_expired_at(now) && level.contains_positions(positions)
+        });
+        Span::current().record("levels_probed", probed);
+        self.counters.queries.fetch_add(1, Ordering::Relaxed);
+        if let Some(level) = found {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            self.levels[level].hits.fetch_add(1, Ordering::Relaxed);
+        }
+        found
+    }
+
+    /// Saves the Bloom filter to a file in JSON format.
+    #[instrument(level = "info", name = "save", skip(self), fields(duration_ms = field::Empty))]
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving BloomFilter to file: {}", filepath);
+        let start = Instant::now();
+        let file = File::create(filepath)?;
+        let result = self.save_to_writer(BufWriter::new(file));
+        record_duration(start);
+        if result.is_ok() {
+            self.notify(|observer| observer.on_save(filepath));
+        }
+        result
+    }
+
+    /// Writes the filter as JSON to any writer: a socket, an in-memory buffer, a compressor.
+    ///
+    /// The writer is not buffered here; wrap unbuffered sinks in a `BufWriter`.
+    pub fn save_to_writer<W: Write>(&self, writer: W) -> Result<(), BloomFilterError> {
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a Bloom filter from a JSON file.
+    #[instrument(level = "info", name = "load", fields(duration_ms = field::Empty))]
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading BloomFilter from file: {}", filepath);
+        let start = Instant::now();
+        let file = File::open(filepath)?;
+        let result = Self::load_from_reader(BufReader::new(file));
+        record_duration(start);
+        result
+    }
+
+    /// Reads and validates a filter written by `save_to_writer` (or `save_to_file`).
+    ///
+    /// The reader is not buffered here; wrap unbuffered sources in a `BufReader`.
+    pub fn load_from_reader<R: Read>(reader: R) -> Result<Self, BloomFilterError> {
+        let bloom_filter: Self = serde_json::from_reader(reader)?;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Loads a keyed-hashing filter saved with `save_to_file`, supplying the key that was left out of the file.
+    pub fn load_keyed_from_file(filepath: &str, hash_key: [u8; 16]) -> Result<Self, BloomFilterError> {
+        info!("Loading keyed BloomFilter from file: {}", filepath);
+        let file = File::open(filepath)?;
+        Self::load_keyed_from_reader(BufReader::new(file), hash_key)
+    }
+
+    /// Like `load_from_reader`, for a keyed-hashing filter.
+    pub fn load_keyed_from_reader<R: Read>(reader: R, hash_key: [u8; 16]) -> Result<Self, BloomFilterError> {
+        let mut bloom_filter: Self = serde_json::from_reader(reader)?;
+        bloom_filter.restore_hash_key(hash_key)?;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Supplies the key of a deserialized keyed filter after checking it against the stored fingerprint.
+    pub(crate) fn restore_hash_key(&mut self, hash_key: [u8; 16]) -> Result<(), BloomFilterError> {
+        if self.key_fingerprint != Some(key_fingerprint(&hash_key)) {
+            error!("Hash key does not match the filter's key fingerprint");
+            return Err(BloomFilterError::HashKeyMismatch);
+        }
+        self.hash_key = Some(hash_key);
+        Ok(())
+    }
+
+    /// Checks the structural invariants that insert and query rely on.
+    ///
+    /// Deserialized filters are untrusted, so every loader runs this before
+    /// handing the filter out.
+    pub(crate) fn validate(&self) -> Result<(), BloomFilterError> {
+        if self.levels.is_empty() {
+            return Err(BloomFilterError::ZeroLevels);
+        }
+        if self.array_size == 0 {
+            return Err(BloomFilterError::ZeroArraySize);
+        }
+        if self.hash_functions.is_empty() {
+            return Err(BloomFilterError::ZeroHashFunctions);
+        }
+        if self.hash_functions.len() > MAX_HASH_FUNCTIONS {
+            return Err(BloomFilterError::InvalidHashFunctions {
+                requested: self.hash_functions.len(),
+                available: MAX_HASH_FUNCTIONS,
+            });
+        }
+        if !self.hash_algorithm.is_available() {
+            return Err(BloomFilterError::UnsupportedHashAlgorithm(self.hash_algorithm));
+        }
+        if self.key_fingerprint.is_some() && self.hash_key.is_none() {
+            return Err(BloomFilterError::MissingHashKey);
+        }
+        if self.active_level >= self.levels.len() {
+            return Err(BloomFilterError::InvalidLevel {
+                index: self.active_level,
+                levels: self.levels.len(),
+            });
+        }
+        for (level, bloom_level) in self.levels.iter().enumerate() {
+            if !bloom_level.bit_array.is_consistent() {
+                return Err(BloomFilterError::InvalidBitArray(format!(
+                    "level {} has set bits beyond its length",
+                    level
+                )));
+            }
+            if bloom_level.len() != self.array_size {
+                error!(
+                    "Level {} has {} bits, expected {}",
+                    level,
+                    bloom_level.len(),
+                    self.array_size
+                );
+                return Err(BloomFilterError::LevelSizeMismatch {
+                    level,
+                    expected: self.array_size,
+                    actual: bloom_level.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves a full snapshot and starts tracking newly set bits for `save_delta`.
+    pub fn save_snapshot(&mut self, filepath: &str) -> Result<(), BloomFilterError> {
+        self.save_to_file(filepath)?;
+        for level in &mut self.levels {
+            level.dirty = Some(Vec::new());
+        }
+        Ok(())
+    }
+
+    /// Saves only the bit positions set since the last snapshot or delta.
+    ///
+    /// Deltas only ever set bits, so they can be applied in any order. Fails if
+    /// `save_snapshot` has not been called on this instance.
+    pub fn save_delta(&mut self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving BloomFilter delta to file: {}", filepath);
+        let mut levels = Vec::with_capacity(self.levels.len());
+        for level in &self.levels {
+            match &level.dirty {
+                Some(dirty) => levels.push(dirty.clone()),
+                None => {
+                    return Err(BloomFilterError::DeltaMismatch(
+                        "no snapshot has been saved to diff against".to_string(),
+                    ))
+                }
+            }
+        }
+        let delta = FilterDelta {
+            array_size: self.array_size,
+            levels,
+        };
+        let file = File::create(filepath)?;
+        serde_json::to_writer(BufWriter::new(file), &delta)?;
+        for level in &mut self.levels {
+            level.dirty = Some(Vec::new());
+        }
+        Ok(())
+    }
+
+    /// Loads a snapshot and applies the given delta files on top of it.
+    pub fn load_with_deltas(snapshot: &str, deltas: &[&str]) -> Result<Self, BloomFilterError> {
+        let mut bloom_filter = Self::load_from_file(snapshot)?;
+        for path in deltas {
+            info!("Applying BloomFilter delta from file: {}", path);
+            let reader = BufReader::new(File::open(path)?);
+            let delta: FilterDelta = serde_json::from_reader(reader)?;
+            bloom_filter.apply_delta(&delta)?;
+        }
+        Ok(bloom_filter)
+    }
+
+    fn apply_delta(&mut self, delta: &FilterDelta) -> Result<(), BloomFilterError> {
+        if delta.array_size != self.array_size || delta.levels.len() != self.levels.len() {
+            return Err(BloomFilterError::DeltaMismatch(format!(
+                "delta has {} levels of size {}, filter has {} levels of size {}",
+                delta.levels.len(),
+                delta.array_size,
+                self.levels.len(),
+                self.array_size
+            )));
+        }
+        for (level, positions) in self.levels.iter_mut().zip(&delta.levels) {
+            if let Some(&bad) = positions.iter().find(|&&p| p >= self.array_size) {
+                return Err(BloomFilterError::DeltaMismatch(format!(
+                    "bit position {} out of range for array size {}",
+                    bad, self.array_size
+                )));
+            }
+            for &position in positions {
+                level.bit_array.set(position);
+            }
+            level.ones = None;
+        }
+        Ok(())
+    }
+}
+
+/// Stable 64-bit hash identifying an item in trace spans without recording the item itself.
+fn item_hash(item: &[u8]) -> u64 {
+    SipHasher13::new().hash(item)
+}
+
+/// Records the time since `start` as the current span's `duration_ms` field.
+pub(crate) fn record_duration(start: Instant) {
+    Span::current().record("duration_ms", start.elapsed().as_millis() as u64);
+}
+
+/// Shows the configuration and per-level fill instead of the raw bit arrays.
+///
+/// The SipHash key is never printed; only whether one is set.
+impl fmt::Debug for BloomFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fill: Vec<f64> = self.levels.iter().map(BloomLevel::fill_ratio).collect();
+        f.debug_struct("BloomFilter")
+            .field("levels", &self.levels.len())
+            .field("array_size", &self.array_size)
+            .field("hash_functions", &self.hash_functions.len())
+            .field("hash_algorithm", &self.hash_algorithm)
+            .field("seed", &self.seed())
+            .field("keyed", &self.hash_key.is_some())
+            .field("insert_mode", &self.insert_mode)
+            .field("active_level", &self.active_level)
+            .field("fill", &fill)
+            .field("estimated_fpr", &self.estimated_false_positive_rate())
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
+
+/// Same as the `Display` output of `stats()`.
+impl fmt::Display for BloomFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.stats().fmt(f)
+    }
+}
+
+/// Seconds since the Unix epoch for `time` (zero for times before it).
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+pub(crate) fn unix_now() -> u64 {
+    unix_secs(SystemTime::now())
+}
+
+/// Bit positions newly set in each level since the previous save.
+#[derive(Serialize, Deserialize)]
+struct FilterDelta {
+    array_size: usize,
+    levels: Vec<Vec<usize>>,
+}
+
+/// Represents a single level within the Bloom filter.
+///
+/// A level is generic over the `BitStorage` holding its bits. Filters keep
+/// their levels in the built-in storage, which can be dense, sparse or
+/// memory-mapped; other storages work with the level's own insert and query.
+#[derive(Serialize, Deserialize)]
+pub struct BloomLevel<S = LevelBits> {
+    pub(crate) bit_array: S,
+    /// Optional user-facing name, unique within a filter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) label: Option<String>,
+    /// Arbitrary user metadata such as the date range or source dataset.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) metadata: BTreeMap<String, String>,
+    /// Unix time (seconds) the level was created or last cleared, when it has a TTL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) created_at: Option<u64>,
+    /// Time-to-live in seconds, counted from `created_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) ttl: Option<u64>,
+    /// Number of inserts that set at least one new bit in this level.
+    #[serde(default)]
+    pub(crate) item_count: usize,
+    /// Cached number of set bits; `None` until first needed after a load.
+    #[serde(skip)]
+    ones: Option<usize>,
+    /// Positions set since the last snapshot/delta; `None` when not tracking.
+    #[serde(skip)]
+    dirty: Option<Vec<usize>>,
+    /// Queries this level answered first, since the filter was loaded or its counters reset.
+    #[serde(skip)]
+    pub(crate) hits: AtomicU64,
+}
+
+impl<S: BitStorage> BloomLevel<S> {
+    /// Creates a level over the given storage, keeping any bits it already holds.
+    pub fn with_storage(bit_array: S) -> Self {
+        BloomLevel {
+            bit_array,
+            label: None,
+            metadata: BTreeMap::new(),
+            created_at: None,
+            ttl: None,
+            item_count: 0,
+            ones: None,
+            dirty: None,
+            hits: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the label of this level, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the metadata attached to this level.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Returns the Unix time (seconds) the level was created or last cleared, if tracked.
+    pub fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+
+    /// Returns the level's time-to-live, if any.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl.map(Duration::from_secs)
+    }
+
+    /// Returns true if the level has a TTL that has elapsed at `now` (Unix seconds).
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        match (self.created_at, self.ttl) {
+            (Some(created_at), Some(ttl)) => now >= created_at.saturating_add(ttl),
+            _ => false,
+        }
+    }
+
+    /// Returns the number of inserts that set at least one new bit in this level.
+    pub fn item_count(&self) -> usize {
+        self.item_count
+    }
+
+    /// Returns the number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.ones
+            .unwrap_or_else(|| self.bit_array.count_ones())
+    }
+
+    /// Returns the fraction of bits that are set.
+    pub fn fill_ratio(&self) -> f64 {
+        self.count_ones() as f64 / self.bit_array.len() as f64
+    }
+
+    /// Returns the number of bits in this level.
+    pub fn len(&self) -> usize {
+        self.bit_array.len()
+    }
+
+    /// Returns true if this level has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.bit_array.len() == 0
+    }
+
+    /// Inserts an item into the BloomLevel using the provided hash functions.
+    ///
+    /// Returns `true` if any bit was newly set. Positions past the end of the
+    /// level, from an `array_size` larger than its length, are skipped.
+    pub fn insert(&mut self, item: &str, hash_functions: &[HashFunction], array_size: usize) -> bool {
+        self.insert_positions(&key_positions(hash_functions, array_size, item.as_bytes()))
+    }
+
+    /// Queries an item in the BloomLevel using the provided hash functions.
+    ///
+    /// Positions past the end of the level count as set, so a mismatched
+    /// `array_size` gives false positives, never false negatives.
+    pub fn query(&self, item: &str, hash_functions: &[HashFunction], array_size: usize) -> bool {
+        self.contains_positions(&key_positions(hash_functions, array_size, item.as_bytes()))
+    }
+
+    /// Sets the given bit positions, returning `true` if any was newly set.
+    pub(crate) fn insert_positions(&mut self, positions: &[usize]) -> bool {
+        let mut ones = self.count_ones();
+        let mut newly_set = false;
+        for &position in positions {
+            if self.bit_array.set(position) {
+                ones += 1;
+                newly_set = true;
+                if let Some(dirty) = &mut self.dirty {
+                    dirty.push(position);
+                }
+            }
+        }
+        self.ones = Some(ones);
+        if newly_set {
+            self.item_count += 1;
+        }
+        newly_set
+    }
+
+    /// Returns true if every given bit position is set.
+    ///
+    /// A position past the end of the level cannot rule the item out, so it
+    /// counts as set: a filter whose state disagrees with its parameters
+    /// answers with false positives rather than false negatives or a panic.
+    pub(crate) fn contains_positions(&self, positions: &[usize]) -> bool {
+        positions
+            .iter()
+            .all(|&position| position >= self.len() || self.bit_array.get(position))
+    }
+
+    /// Makes the level's bits durable, for storage that persists them.
+    pub fn flush(&mut self) -> Result<(), BloomFilterError> {
+        Ok(self.bit_array.flush()?)
+    }
+}
+
+impl BloomLevel {
+    /// Creates a new BloomLevel with the specified array size.
+    pub fn new(array_size: usize) -> Self {
+        Self::with_storage(LevelBits::dense(array_size))
+    }
+
+    /// Creates a level backed by a roaring bitmap, using memory proportional to the set bits.
+    #[cfg(feature = "roaring")]
+    pub fn new_sparse(array_size: usize) -> Result<Self, BloomFilterError> {
+        if array_size > SparseBits::MAX_LEN {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "sparse levels hold at most {} bits, requested {}",
+                SparseBits::MAX_LEN,
+                array_size
+            )));
+        }
+        Ok(Self::with_storage(LevelBits::Sparse(SparseBits::new(array_size))))
+    }
+
+    /// Resets every bit in this level.
+    pub fn clear(&mut self) {
+        self.bit_array.clear();
+        self.item_count = 0;
+        self.ones = Some(0);
+    }
+
+    /// Returns the file backing this level if it is cold (see `BloomFilter::make_level_cold`).
+    #[cfg(feature = "mmap")]
+    pub fn cold_path(&self) -> Option<&Path> {
+        match &self.bit_array {
+            LevelBits::Mapped(mapped) => Some(mapped.path()),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes of memory this level occupies, including its bits, label and metadata.
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<BloomLevel>()
+            + self.bit_array.heap_bytes()
+            + self.label.as_ref().map_or(0, String::capacity)
+            + self
+                .metadata
+                .iter()
+                .map(|(key, value)| key.capacity() + value.capacity())
+                .sum::<usize>()
+            + self
+                .dirty
+                .as_ref()
+                .map_or(0, |dirty| dirty.capacity() * std::mem::size_of::<usize>())
+    }
+
+    /// Packs the bit array into bytes, bit `i` stored in byte `i / 8` at bit `i % 8` (LSB first).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; self.bit_array.len().div_ceil(8)];
+        for i in self.bit_array.iter_ones() {
+            bytes[i / 8] |= 1 << (i % 8);
+        }
+        bytes
+    }
+
+    /// Unpacks a level of `array_size` bits from the layout produced by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8], array_size: usize) -> Result<Self, BloomFilterError> {
+        if bytes.len() != array_size.div_ceil(8) {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "{} bytes cannot hold exactly {} bits",
+                bytes.len(),
+                array_size
+            )));
+        }
+        let mut level = Self::new(array_size);
+        for i in (0..array_size).filter(|&i| bytes[i / 8] & (1 << (i % 8)) != 0) {
+            level.bit_array.set(i);
+        }
+        Ok(level)
+    }
+
+    /// Encodes the packed bit array as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        self.to_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Decodes a level from the hex produced by `to_hex`.
+    pub fn from_hex(hex: &str, array_size: usize) -> Result<Self, BloomFilterError> {
+        if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+            return Err(BloomFilterError::InvalidBitArray("malformed hex string".to_string()));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|e| BloomFilterError::InvalidBitArray(e.to_string()))?;
+        Self::from_bytes(&bytes, array_size)
+    }
+
+    /// Encodes the packed bit array as standard (padded) base64.
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.to_bytes())
+    }
+
+    /// Decodes a level from the base64 produced by `to_base64`.
+    pub fn from_base64(encoded: &str, array_size: usize) -> Result<Self, BloomFilterError> {
+        let bytes = BASE64
+            .decode(encoded)
+            .map_err(|e| BloomFilterError::InvalidBitArray(e.to_string()))?;
+        Self::from_bytes(&bytes, array_size)
+    }
+
+    /// Overwrites the bits starting at byte `offset` with packed `bytes` (the layout of `to_bytes`).
+    ///
+    /// Newly set bits are recorded for `save_delta`; cleared bits cannot be.
+    pub(crate) fn overwrite_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        let end = self.len().min((offset + bytes.len()) * 8);
+        for position in offset * 8..end {
+            let local = position - offset * 8;
+            if bytes[local / 8] & (1 << (local % 8)) != 0 {
+                if self.bit_array.set(position) {
+                    if let Some(dirty) = &mut self.dirty {
+                        dirty.push(position);
+                    }
+                }
+            } else {
+                self.bit_array.unset(position);
+            }
+        }
+        self.ones = None;
+    }
+
+    /// Clears one bit, keeping the cached count of set bits current.
+    pub(crate) fn clear_position(&mut self, position: usize) {
+        if self.bit_array.unset(position) {
+            if let Some(ones) = &mut self.ones {
+                *ones -= 1;
+            }
+        }
+    }
+}
+
+/// Returns `count` distinct prime multipliers: the fixed table, then successive primes.
+///
+/// Keeping the table as the prefix means filters with up to ten functions hash
+/// exactly as before.
+fn hash_multipliers(count: usize) -> Vec<usize> {
+    let mut multipliers: Vec<usize> = HASH_MULTIPLIERS.iter().copied().take(count).collect();
+    let mut candidate = HASH_MULTIPLIERS[HASH_MULTIPLIERS.len() - 1];
+    while multipliers.len() < count {
+        candidate += 2;
+        if (3..).step_by(2).take_while(|d| d * d <= candidate).all(|d| !candidate.is_multiple_of(d)) {
+            multipliers.push(candidate);
+        }
+    }
+    multipliers
+}
+
+/// Computes the bit position of a key under each hash function; an empty array has none.
+pub(crate) fn key_positions(hash_functions: &[HashFunction], array_size: usize, key: &[u8]) -> Vec<usize> {
+    hash_functions
+        .iter()
+        .filter_map(|hf| hf.hash_u64(key).checked_rem(array_size as u64).map(|position| position as usize))
+        .collect()
+}
+
+/// Computes one bit position per hash function for an integer key.
+///
+/// Two splitmix64 outputs are combined by double hashing (`h1 + i * h2`), which
+/// avoids formatting the integer; only the functions' count and seed are used.
+pub(crate) fn u64_positions(hash_functions: &[HashFunction], array_size: usize, value: u64) -> Vec<usize> {
+    let seed = hash_functions.first().map_or(0, |hf| hf.seed);
+    let h1 = splitmix64(value ^ seed);
+    double_hash(h1, splitmix64(h1), hash_functions.len(), array_size)
+}
+
+/// Computes `num_hashes` bit positions with SipHash-2-4 under a secret key.
+///
+/// One 128-bit SipHash supplies the two base hashes for double hashing, so
+/// without the key an attacker cannot predict which bits an input sets.
+pub(crate) fn keyed_positions(hash_key: &[u8; 16], num_hashes: usize, array_size: usize, key: &[u8]) -> Vec<usize> {
+    let hash = SipHasher24::new_with_key(hash_key).hash(key);
+    double_hash(hash.h1, hash.h2, num_hashes, array_size)
+}
+
+/// Hashes a fixed message under `hash_key`, identifying the key without storing it.
+pub(crate) fn key_fingerprint(hash_key: &[u8; 16]) -> u64 {
+    SipHasher24::new_with_key(hash_key).hash(b"bloom-filter key fingerprint").h1
+}
+
+/// The splitmix64 finalizer: a cheap, well-mixed bijection on `u64`.
+pub(crate) fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Represents a single hash function used in the Bloom filter.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HashFunction {
+    pub(crate) multiplier: usize,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    seed: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl HashFunction {
+    /// Creates a new HashFunction with the specified multiplier.
+    pub fn new(multiplier: usize) -> Self {
+        Self::with_seed(multiplier, 0)
+    }
+
+    /// Creates a HashFunction whose output is perturbed by `seed`.
+    ///
+    /// Seed 0 is the unseeded hash, so filters saved before seeds existed load unchanged.
+    pub fn with_seed(multiplier: usize, seed: u64) -> Self {
+        HashFunction { multiplier, seed }
+    }
+
+    /// Returns the seed mixed into this function's output.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Computes the hash of a string.
+    pub fn hash(&self, s: &str) -> usize {
+        self.hash_bytes(s.as_bytes())
+    }
+
+    /// Computes the hash of a byte slice.
+    ///
+    /// A non-zero seed is mixed into the polynomial value before it is reduced
+    /// to a bit position, so keys that collide under one seed are unrelated
+    /// under another.
+    pub fn hash_bytes(&self, bytes: &[u8]) -> usize {
+        self.hash_u64(bytes) as usize
+    }
+
+    /// The hash in full: the fold wraps at 64 bits on every target, so bit
+    /// positions do not depend on the platform's pointer width.
+    pub(crate) fn hash_u64(&self, bytes: &[u8]) -> u64 {
+        let multiplier = self.multiplier as u64;
+        let hash = bytes.iter().fold(0, |hash: u64, &b| hash.wrapping_mul(multiplier).wrapping_add(u64::from(b)));
+        if self.seed == 0 {
+            hash
+        } else {
+            splitmix64(hash ^ self.seed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_query() {
+        let mut bf = BloomFilter::new(1, 100, 3).unwrap();
+        assert!(bf.insert("test"));
+        assert!(!bf.insert("test"));
+        assert!(bf.query("test", 1));
+        assert!(!bf.query("nonexistent", 1));
+    }
+
+    #[test]
+    fn test_byte_keys() {
+        let mut bf = BloomFilter::new(1, 1000, 3).unwrap();
+        let uuid = [0x9f, 0x00, 0xff, 0x10, 0x80, 0x7f, 0x00, 0x01];
+        assert!(bf.insert_bytes(&uuid));
+        assert!(!bf.insert_bytes(&uuid));
+        assert!(bf.query_bytes(&uuid, 1));
+        assert!(!bf.query_bytes(&uuid[..7], 1));
+
+        bf.insert("text");
+        assert!(bf.query_bytes(b"text", 1));
+    }
+
+    #[test]
+    fn test_integer_keys() {
+        let mut bf = BloomFilter::new(2, 10_000, 4).unwrap();
+        assert!(bf.insert_u64(0));
+        for id in 1..500u64 {
+            bf.insert_u64(id * 7919);
+        }
+        assert!((0..500u64).all(|id| bf.query_u64(id * 7919, 2)));
+        let false_positives = (1..=1000u64).filter(|&id| bf.query_u64(id * 7919 + 1, 2)).count();
+        assert!(false_positives < 50);
+        assert!(!bf.query("7919", 2));
+    }
+
+    #[test]
+    fn test_seeded_filters_are_independent() {
+        let build = |seed| BloomFilter::builder().array_size(1000).hash_functions(3).seed(seed).build().unwrap();
+        let (mut a, mut b, mut c) = (build(1), build(1), build(2));
+        for i in 0..100 {
+            let item = format!("item-{}", i);
+            a.insert(&item);
+            b.insert(&item);
+            c.insert(&item);
+        }
+        let probes: Vec<String> = (0..2000).map(|i| format!("probe-{}", i)).collect();
+        let hits = |bf: &BloomFilter| probes.iter().filter(|p| bf.query(p, 1)).cloned().collect::<Vec<_>>();
+        assert_eq!(hits(&a), hits(&b));
+        assert_ne!(hits(&a), hits(&c));
+        assert!(c.query("item-7", 1));
+        assert_eq!(c.seed(), 2);
+
+        let filepath = std::env::temp_dir().join("test_seeded_filter.json");
+        let filepath = filepath.to_str().unwrap();
+        c.save_to_file(filepath).unwrap();
+        let loaded = BloomFilter::load_from_file(filepath).unwrap();
+        assert_eq!(loaded.seed(), 2);
+        assert_eq!(hits(&loaded), hits(&c));
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[test]
+    fn test_keyed_hashing() {
+        let hash_key = *b"0123456789abcdef";
+        let mut bf = BloomFilter::builder().array_size(1000).hash_key(hash_key).build().unwrap();
+        bf.insert("secret");
+        bf.insert_u64(7);
+        assert!(bf.query("secret", 1));
+        assert!(bf.query_u64(7, 1));
+        assert_ne!(bf.positions(b"secret"), key_positions(bf.hash_functions(), 1000, b"secret"));
+
+        let filepath = std::env::temp_dir().join("test_keyed_filter.json");
+        let filepath = filepath.to_str().unwrap();
+        bf.save_to_file(filepath).unwrap();
+        assert!(!std::fs::read_to_string(filepath).unwrap().contains("0123456789abcdef"));
+        assert!(matches!(BloomFilter::load_from_file(filepath), Err(BloomFilterError::MissingHashKey)));
+        assert!(matches!(
+            BloomFilter::load_keyed_from_file(filepath, *b"fedcba9876543210"),
+            Err(BloomFilterError::HashKeyMismatch)
+        ));
+        let loaded = BloomFilter::load_keyed_from_file(filepath, hash_key).unwrap();
+        assert!(loaded.query("secret", 1));
+        assert!(loaded.freeze().query_u64(7, 1));
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[test]
+    fn test_many_hash_functions() {
+        assert_eq!(hash_multipliers(13)[9..], [71, 73, 79, 83]);
+
+        let mut bf = BloomFilter::new(1, 10_000, 20).unwrap();
+        assert_eq!(bf.num_hash_functions(), 20);
+        bf.insert("needle");
+        assert!(bf.query("needle", 1));
+        assert!(!bf.query("haystack", 1));
+        let distinct: std::collections::BTreeSet<usize> = bf.positions(b"needle").into_iter().collect();
+        assert_eq!(bf.levels()[0].count_ones(), distinct.len());
+    }
+
+    #[test]
+    fn test_debug_and_display_summarize() {
+        let mut bf = BloomFilter::builder().levels(2).array_size(100).hash_key([7; 16]).build().unwrap();
+        bf.set_level_label(0, Some("recent".to_string())).unwrap();
+        assert_eq!(bf.estimated_false_positive_rate(), 0.0);
+        bf.insert("a");
+
+        let debug = format!("{:?}", bf);
+        assert!(debug.starts_with("BloomFilter { levels: 2, array_size: 100, hash_functions: 3,"));
+        assert!(debug.contains("keyed: true"));
+        assert!(!debug.contains("[7, 7"));
+
+        let display = bf.to_string();
+        assert_eq!(display.lines().count(), 3);
+        assert!(display.contains("level 1 \"recent\": 3/100 bits set (3.0%), 1 items"));
+    }
+
+    #[test]
+    fn test_query_level() {
+        let mut bf = BloomFilter::new(3, 100, 3).unwrap();
+        bf.levels[1].insert("test", &bf.hash_functions, bf.array_size);
+        bf.levels[2].insert("test", &bf.hash_functions, bf.array_size);
+        assert_eq!(bf.query_level("test", 3), Some(1));
+        assert_eq!(bf.query_level("test", 1), None);
+        assert_eq!(bf.query_level("nonexistent", 3), None);
+    }
+
+    #[test]
+    fn test_named_levels_round_trip() {
+        let mut bf = BloomFilter::new(3, 100, 3).unwrap();
+        bf.set_level_label(1, Some("2024-q1".to_string())).unwrap();
+        bf.set_level_metadata(1, "source", "crawl").unwrap();
+        assert!(matches!(
+            bf.set_level_label(2, Some("2024-q1".to_string())),
+            Err(BloomFilterError::DuplicateLevelLabel(_))
+        ));
+        assert!(matches!(bf.set_level_metadata(3, "k", "v"), Err(BloomFilterError::InvalidLevel { index: 3, levels: 3 })));
+
+        let path = std::env::temp_dir().join("test_bloom_labels.json");
+        let path = path.to_str().unwrap();
+        bf.save_to_file(path).unwrap();
+        let loaded = BloomFilter::load_from_file(path).unwrap();
+        assert_eq!(loaded.level_index("2024-q1"), Some(1));
+        let level = loaded.level_by_name("2024-q1").unwrap();
+        assert_eq!(level.metadata().get("source").map(String::as_str), Some("crawl"));
+        assert_eq!(loaded.levels()[0].label(), None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_level_ttl_expiry() {
+        let mut bf = BloomFilter::new(2, 100, 3).unwrap();
+        bf.insert("test");
+        bf.set_level_ttl(0, Some(Duration::from_secs(60))).unwrap();
+        assert_eq!(bf.query_level("test", 2), Some(0));
+
+        // A level whose TTL has already elapsed is skipped by queries.
+        bf.levels[0].created_at = Some(unix_now() - 120);
+        assert_eq!(bf.query_level("test", 2), Some(1));
+
+        assert_eq!(bf.expire_at(SystemTime::now()), 1);
+        assert_eq!(bf.expire(), 0);
+        assert!(!bf.query_range("test", 0..1));
+        assert!(bf.query_range("test", 1..2));
+        assert!(bf.levels()[0].created_at().is_some());
+    }
+
+    #[test]
+    fn test_active_level_advances_by_item_count() {
+        let mut bf = BloomFilter::new(3, 1000, 3).unwrap();
+        bf.set_insert_mode(InsertMode::ActiveLevel {
+            max_items: Some(2),
+            max_fill_ratio: None,
+        });
+        for item in ["a", "b", "c", "d", "e"] {
+            bf.insert(item);
+        }
+        assert_eq!(bf.active_level(), 2);
+        assert_eq!(bf.query_level("a", 3), Some(0));
+        assert_eq!(bf.query_level("c", 3), Some(1));
+        assert_eq!(bf.query_level("e", 3), Some(2));
+
+        // Filling the last level wraps around and clears the oldest generation.
+        bf.insert("f");
+        assert_eq!(bf.active_level(), 0);
+        assert!(!bf.query("a", 3));
+        assert_eq!(bf.levels()[0].item_count(), 0);
+    }
+
+    #[test]
+    fn test_active_level_advances_by_fill_ratio() {
+        let mut bf = BloomFilter::new(2, 10, 3).unwrap();
+        bf.set_insert_mode(InsertMode::ActiveLevel {
+            max_items: None,
+            max_fill_ratio: Some(0.1),
+        });
+        bf.insert("test");
+        assert_eq!(bf.active_level(), 1);
+        assert!(bf.levels()[0].fill_ratio() >= 0.1);
+    }
+
+    #[test]
+    fn test_compact_levels() {
+        let mut bf = BloomFilter::new(4, 100, 3).unwrap();
+        bf.set_level_label(0, Some("oldest".to_string())).unwrap();
+        bf.set_level_label(3, Some("newest".to_string())).unwrap();
+        for (level, item) in ["a", "b", "c", "d"].iter().enumerate() {
+            bf.levels[level].insert(item, &bf.hash_functions, bf.array_size);
+        }
+        bf.active_level = 3;
+
+        bf.compact_levels(0..3).unwrap();
+        assert_eq!(bf.levels().len(), 2);
+        assert_eq!(bf.active_level(), 1);
+        assert_eq!(bf.level_index("oldest"), Some(0));
+        assert_eq!(bf.level_index("newest"), Some(1));
+        for item in ["a", "b", "c"] {
+            assert_eq!(bf.query_level(item, 2), Some(0));
+        }
+        assert_eq!(bf.query_level("d", 2), Some(1));
+        assert_eq!(bf.levels()[0].item_count(), 3);
+
+        assert!(matches!(
+            bf.compact_levels(1..5),
+            Err(BloomFilterError::InvalidLevelRange { start: 1, end: 5, levels: 2 })
+        ));
+        assert!(bf.compact_levels(1..1).is_err());
+    }
+
+    #[test]
+    fn test_rebuild_resizes_and_keeps_configuration() {
+        let items: Vec<String> = (0..200).map(|i| format!("item-{}", i)).collect();
+        let mut bf = BloomFilter::new(2, 64, 3).unwrap();
+        bf.set_level_label(1, Some("recent".to_string())).unwrap();
+        for item in &items {
+            bf.insert(item);
+        }
+        assert!(bf.levels()[0].fill_ratio() > 0.9);
+
+        let rebuilt = bf.rebuild(&items, RebuildParams::for_capacity(200, 0.01)).unwrap();
+        assert_eq!(rebuilt.array_size(), math::optimal_bits(200, 0.01));
+        assert_eq!(rebuilt.num_hash_functions(), 7);
+        assert_eq!(rebuilt.level_index("recent"), Some(1));
+        assert!(items.iter().all(|item| rebuilt.query(item, 2)));
+        assert!(rebuilt.levels()[0].fill_ratio() < 0.6);
+
+        let only_levels = RebuildParams {
+            num_levels: Some(1),
+            ..RebuildParams::default()
+        };
+        let rebuilt = bf.rebuild(&items, only_levels).unwrap();
+        assert_eq!((rebuilt.levels().len(), rebuilt.array_size(), rebuilt.num_hash_functions()), (1, 64, 3));
+    }
+
+    #[cfg(feature = "roaring")]
+    #[test]
+    fn test_sparse_backend() {
+        let mut bf = BloomFilter::new_sparse(2, 1 << 32, 3).unwrap();
+        assert!(bf.insert("test"));
+        assert!(!bf.insert("test"));
+        assert!(bf.query("test", 2));
+        assert!(!bf.query("nonexistent", 2));
+        assert_eq!(bf.levels()[0].count_ones(), 3);
+        assert!(BloomFilter::new_sparse(1, (1 << 32) + 1, 3).is_err());
+
+        let path = std::env::temp_dir().join("test_bloom_sparse.json");
+        let path = path.to_str().unwrap();
+        bf.save_to_file(path).unwrap();
+        let loaded = BloomFilter::load_from_file(path).unwrap();
+        assert!(loaded.query("test", 2));
+        assert_eq!(loaded.levels()[0].bit_array, bf.levels()[0].bit_array);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_cold_levels() {
+        let mut bf = BloomFilter::new(2, 1000, 3).unwrap();
+        bf.set_insert_mode(InsertMode::ActiveLevel {
+            max_items: None,
+            max_fill_ratio: None,
+        });
+        bf.insert("old");
+        bf.advance_level();
+        bf.insert("new");
+
+        let cold = std::env::temp_dir().join("test_bloom_cold.bits");
+        let cold = cold.to_str().unwrap();
+        let ones = bf.levels()[0].count_ones();
+        bf.make_level_cold(0, cold).unwrap();
+        assert_eq!(bf.levels()[0].cold_path(), Some(Path::new(cold)));
+        assert_eq!(std::fs::metadata(cold).unwrap().len(), 125);
+        assert!(bf.levels()[0].memory_usage() < bf.levels()[1].memory_usage());
+        assert_eq!(bf.query_level("old", 2), Some(0));
+        assert_eq!(bf.query_level("new", 2), Some(1));
+        assert_eq!(bf.levels()[0].count_ones(), ones);
+
+        bf.set_insert_mode(InsertMode::AllLevels);
+        bf.insert("both");
+        bf.flush_cold_levels().unwrap();
+        let mut buffer = Vec::new();
+        bf.save_to_writer(&mut buffer).unwrap();
+        let loaded = BloomFilter::load_from_reader(buffer.as_slice()).unwrap();
+        assert!(loaded.levels()[0].cold_path().is_none());
+        assert_eq!(loaded.query_level("both", 2), Some(0));
+
+        bf.make_level_hot(0).unwrap();
+        assert!(bf.levels()[0].cold_path().is_none());
+        assert_eq!(bf.levels()[0].bit_array, loaded.levels()[0].bit_array);
+        assert!(bf.make_level_cold(2, cold).is_err());
+        std::fs::remove_file(cold).unwrap();
+    }
+
+    #[test]
+    fn test_query_range() {
+        let mut bf = BloomFilter::new(4, 100, 3).unwrap();
+        bf.levels[2].insert("test", &bf.hash_functions, bf.array_size);
+        assert!(bf.query_range("test", 2..3));
+        assert!(bf.query_range("test", 1..=2));
+        assert!(bf.query_range("test", 2..));
+        assert!(bf.query_range("test", ..10));
+        assert!(!bf.query_range("test", 0..2));
+        assert!(!bf.query_range("test", 3..));
+        assert!(!bf.query_range("test", 5..9));
+    }
+
+    #[test]
+    fn test_new_rejects_zero_parameters() {
+        assert!(matches!(BloomFilter::new(0, 100, 3), Err(BloomFilterError::ZeroLevels)));
+        assert!(matches!(BloomFilter::new(1, 0, 3), Err(BloomFilterError::ZeroArraySize)));
+        assert!(matches!(BloomFilter::new(1, 100, 0), Err(BloomFilterError::ZeroHashFunctions)));
+        assert!(matches!(
+            BloomFilter::new(1, 100, MAX_HASH_FUNCTIONS + 1),
+            Err(BloomFilterError::InvalidHashFunctions { requested: 257, available: MAX_HASH_FUNCTIONS })
+        ));
+        assert!(BloomFilter::from_levels(Vec::new(), 3).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut bf = BloomFilter::new(1, 100, 3).unwrap();
+        bf.insert("test");
+        bf.save_to_file("test_bloom.json").unwrap();
+
+        let loaded_bf = BloomFilter::load_from_file("test_bloom.json").unwrap();
+        assert!(loaded_bf.query("test", 1));
+        assert!(!loaded_bf.query("nonexistent", 1));
+
+        // Clean up test file
+        std::fs::remove_file("test_bloom.json").unwrap();
+    }
+
+    #[test]
+    fn test_json_packs_bits_and_reads_legacy_arrays() {
+        let mut bf = BloomFilter::new(1, 1000, 3).unwrap();
+        bf.insert("test");
+        let mut json = Vec::new();
+        bf.save_to_writer(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains(&format!(r#""bits": "{}""#, bf.levels()[0].to_base64())));
+        assert!(json.len() < 1000);
+        assert!(BloomFilter::load_from_reader(json.as_bytes()).unwrap().query("test", 1));
+
+        let legacy = r#"{"levels":[{"bit_array":[false,true,false]}],"hash_functions":[{"multiplier":31}],"array_size":3}"#;
+        let loaded = BloomFilter::load_from_reader(legacy.as_bytes()).unwrap();
+        assert_eq!(loaded.levels()[0].to_bytes(), [0b010]);
+        let bad = r#"{"levels":[{"bit_array":{"len":9,"bits":"AA=="}}],"hash_functions":[{"multiplier":31}],"array_size":9}"#;
+        assert!(BloomFilter::load_from_reader(bad.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_measure_false_positive_rate() {
+        let mut bf = BloomFilter::new(1, 2000, 3).unwrap();
+        for i in 0..200 {
+            bf.insert(&format!("in-{}", i));
+        }
+        let measured = bf.measure_false_positive_rate((0..5000).map(|i| format!("out-{}", i)));
+        let estimated = bf.estimated_false_positive_rate();
+        assert!((measured - estimated).abs() < 0.02, "measured {} vs estimated {}", measured, estimated);
+        assert_eq!(bf.measure_false_positive_rate(["in-1", "in-2"]), 1.0);
+        assert_eq!(bf.measure_false_positive_rate(Vec::<String>::new()), 0.0);
+        assert_eq!(bf.measure_false_positive_rate_bytes([b"in-1"]), 1.0);
+        // Probing is a diagnostic: it leaves the query counters alone.
+        assert_eq!(bf.counts().queries, 0);
+
+        let normalization = "trim,lowercase".parse().unwrap();
+        let mut normalized = BloomFilter::builder().array_size(2000).normalization(normalization).build().unwrap();
+        normalized.insert("in-1");
+        assert_eq!(normalized.measure_false_positive_rate([" IN-1 "]), 1.0);
+    }
+
+    #[test]
+    fn test_union_with() {
+        let mut a = BloomFilter::new(2, 500, 3).unwrap();
+        let mut b = BloomFilter::new(2, 500, 3).unwrap();
+        a.insert("apple");
+        b.insert("banana");
+        a.union_with(&b).unwrap();
+        assert!(a.query("apple", 2));
+        assert!(a.query("banana", 2));
+        assert_eq!(a.levels()[0].item_count(), 2);
+
+        let one_level = BloomFilter::new(1, 500, 3).unwrap();
+        assert!(matches!(a.union_with(&one_level), Err(BloomFilterError::IncompatibleFilters(_))));
+        let other_size = BloomFilter::new(2, 400, 3).unwrap();
+        assert!(a.union_with(&other_size).is_err());
+    }
+
+    #[test]
+    fn test_writer_and_reader_round_trip() {
+        let mut bf = BloomFilter::new(2, 100, 3).unwrap();
+        bf.insert("in-memory");
+        let mut buffer = Vec::new();
+        bf.save_to_writer(&mut buffer).unwrap();
+
+        let loaded = BloomFilter::load_from_reader(buffer.as_slice()).unwrap();
+        assert!(loaded.query("in-memory", 2));
+        assert!(!loaded.query("elsewhere", 2));
+        assert!(BloomFilter::load_from_reader(&buffer[..buffer.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_and_deltas() {
+        let dir = std::env::temp_dir();
+        let snapshot = dir.join("test_bloom_snapshot.json");
+        let delta1 = dir.join("test_bloom_delta1.json");
+        let delta2 = dir.join("test_bloom_delta2.json");
+        let (snapshot, delta1, delta2) = (
+            snapshot.to_str().unwrap(),
+            delta1.to_str().unwrap(),
+            delta2.to_str().unwrap(),
+        );
+
+        let mut bf = BloomFilter::new(2, 100, 3).unwrap();
+        assert!(bf.save_delta(delta1).is_err());
+        bf.insert("alpha");
+        bf.save_snapshot(snapshot).unwrap();
+        bf.insert("beta");
+        bf.save_delta(delta1).unwrap();
+        bf.insert("gamma");
+        bf.save_delta(delta2).unwrap();
+
+        let loaded = BloomFilter::load_with_deltas(snapshot, &[delta2, delta1]).unwrap();
+        for item in ["alpha", "beta", "gamma"] {
+            assert!(loaded.query(item, 2));
+        }
+        let partial = BloomFilter::load_with_deltas(snapshot, &[delta1]).unwrap();
+        assert!(partial.query("beta", 2));
+        assert!(!partial.query("gamma", 2));
+
+        for path in [snapshot, delta1, delta2] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_level_byte_encodings() {
+        let mut bf = BloomFilter::new(2, 21, 3).unwrap();
+        bf.insert("test");
+        let level = &bf.levels()[0];
+
+        let bytes = level.to_bytes();
+        assert_eq!(bytes.len(), 3);
+        let from_bytes = BloomLevel::from_bytes(&bytes, 21).unwrap();
+        let from_hex = BloomLevel::from_hex(&level.to_hex(), 21).unwrap();
+        let from_base64 = BloomLevel::from_base64(&level.to_base64(), 21).unwrap();
+        for decoded in [&from_bytes, &from_hex, &from_base64] {
+            assert_eq!(decoded.bit_array, level.bit_array);
+        }
+        assert!(BloomLevel::from_bytes(&bytes, 25).is_err());
+        assert!(BloomLevel::from_hex("zz", 8).is_err());
+
+        let rebuilt = BloomFilter::from_levels(vec![from_bytes, from_hex], 3).unwrap();
+        assert!(rebuilt.query("test", 2));
+        assert!(!rebuilt.query("nonexistent", 2));
+    }
+
+    #[test]
+    fn test_load_rejects_malformed_filters() {
+        let cases = [
+            (r#"{"levels":[],"hash_functions":[{"multiplier":31}],"array_size":2}"#, "levels"),
+            (r#"{"levels":[{"bit_array":[]}],"hash_functions":[{"multiplier":31}],"array_size":0}"#, "size"),
+            (r#"{"levels":[{"bit_array":[false,true]}],"hash_functions":[],"array_size":2}"#, "hash"),
+            (r#"{"levels":[{"bit_array":[false]}],"hash_functions":[{"multiplier":31}],"array_size":2}"#, "mismatch"),
+        ];
+        let path = std::env::temp_dir().join("test_bloom_malformed.json");
+        let path = path.to_str().unwrap();
+        for (json, case) in cases {
+            std::fs::write(path, json).unwrap();
+            let err = BloomFilter::load_from_file(path).err().unwrap();
+            let matched = match case {
+                "levels" => matches!(err, BloomFilterError::ZeroLevels),
+                "size" => matches!(err, BloomFilterError::ZeroArraySize),
+                "hash" => matches!(err, BloomFilterError::ZeroHashFunctions),
+                _ => matches!(err, BloomFilterError::LevelSizeMismatch { level: 0, expected: 2, actual: 1 }),
+            };
+            assert!(matched, "unexpected error for {}: {}", case, err);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_from_reader() {
+        let mut bf = BloomFilter::new(1, 100, 3).unwrap();
+        let input = "alpha\r\nbeta\n\ngamma";
+        let count = bf.insert_from_reader(input.as_bytes(), b'\n').unwrap();
+        assert_eq!(count, 3);
+        assert!(bf.query("alpha", 1));
+        assert!(bf.query("beta", 1));
+        assert!(bf.query("gamma", 1));
+
+        let count = bf.insert_from_reader("x,y".as_bytes(), b',').unwrap();
+        assert_eq!(count, 2);
+        assert!(bf.query("y", 1));
+    }
+
+    #[test]
+    fn test_mismatched_array_size_does_not_panic() {
+        let bf = BloomFilter::new(1, 1000, 4).unwrap();
+        let mut level = BloomLevel::new(10);
+        // Positions past the end are skipped on insert and cannot rule an item out.
+        level.insert("alpha", bf.hash_functions(), 1000);
+        assert!(level.count_ones() <= 4);
+        assert!(level.query("alpha", bf.hash_functions(), 1000));
+        // An empty array probes nothing.
+        assert!(!level.insert("beta", bf.hash_functions(), 0));
+        assert!(level.query("beta", bf.hash_functions(), 0));
     }
 }
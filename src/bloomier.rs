@@ -0,0 +1,204 @@
+// src/bloomier.rs
+
+use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher13;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter};
+use tracing::{debug, info};
+
+use crate::bloom_filter::{splitmix64, BloomFilterError};
+
+/// Seeds tried before construction gives up; each fails with probability well under 1%.
+const MAX_ATTEMPTS: u64 = 64;
+
+/// A static map from keys to small values (a Bloomier filter).
+///
+/// Built once from `(key, value)` pairs, it returns every key's value exactly.
+/// The keys themselves are not stored: each key hashes to three table cells,
+/// one per segment, whose XOR holds the value and an 8-bit fingerprint of the
+/// key. A key outside the construction set is rejected unless its
+/// fingerprint happens to match, so `get` wrongly returns a value for about
+/// 1 in 256 unknown keys. The table needs about 1.23 cells of two bytes per
+/// key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BloomierFilter {
+    seed: u64,
+    segment_length: usize,
+    /// Value in the low byte, fingerprint in the high byte.
+    table: Vec<u16>,
+}
+
+impl BloomierFilter {
+    /// Builds the filter from `(key, value)` pairs.
+    ///
+    /// A key repeated with the same value is stored once; a key given two
+    /// different values is an error.
+    pub fn build<I, K>(pairs: I) -> Result<Self, BloomFilterError>
+    where
+        I: IntoIterator<Item = (K, u8)>,
+        K: AsRef<[u8]>,
+    {
+        let mut values: HashMap<Vec<u8>, u8> = HashMap::new();
+        for (key, value) in pairs {
+            let key = key.as_ref();
+            match values.insert(key.to_vec(), value) {
+                Some(previous) if previous != value => {
+                    return Err(BloomFilterError::BloomierConstruction(format!(
+                        "key {} maps to both {} and {}",
+                        String::from_utf8_lossy(key),
+                        previous,
+                        value
+                    )));
+                }
+                _ => {}
+            }
+        }
+        // Sorted, so the same pairs always build the same table.
+        let mut entries: Vec<(Vec<u8>, u8)> = values.into_iter().collect();
+        entries.sort_unstable();
+        let segment_length = (32 + entries.len() * 123 / 100).div_ceil(3);
+        info!("Building BloomierFilter: keys={}, segment_length={}", entries.len(), segment_length);
+
+        for attempt in 0..MAX_ATTEMPTS {
+            let seed = splitmix64(attempt);
+            if let Some(table) = Self::try_build(&entries, seed, segment_length) {
+                return Ok(BloomierFilter {
+                    seed,
+                    segment_length,
+                    table,
+                });
+            }
+            debug!("Bloomier construction attempt {} failed", attempt);
+        }
+        Err(BloomFilterError::BloomierConstruction(format!("no table found after {} attempts", MAX_ATTEMPTS)))
+    }
+
+    /// Peels the key hypergraph and assigns cells, or returns `None` if the graph has a cycle.
+    fn try_build(entries: &[(Vec<u8>, u8)], seed: u64, segment_length: usize) -> Option<Vec<u16>> {
+        let cells = 3 * segment_length;
+        let edges: Vec<([usize; 3], u16)> = entries
+            .iter()
+            .map(|(key, value)| {
+                let (positions, fingerprint) = locate(seed, segment_length, key);
+                (positions, (fingerprint as u16) << 8 | *value as u16)
+            })
+            .collect();
+
+        // For each cell, how many keys touch it and the XOR of their indices:
+        // a cell touched once names its only key.
+        let mut counts = vec![0u32; cells];
+        let mut xors = vec![0usize; cells];
+        for (index, (positions, _)) in edges.iter().enumerate() {
+            for &cell in positions {
+                counts[cell] += 1;
+                xors[cell] ^= index;
+            }
+        }
+        let mut queue: Vec<usize> = (0..cells).filter(|&cell| counts[cell] == 1).collect();
+        let mut order = Vec::with_capacity(edges.len());
+        while let Some(cell) = queue.pop() {
+            if counts[cell] != 1 {
+                continue;
+            }
+            let index = xors[cell];
+            order.push((index, cell));
+            for &other in &edges[index].0 {
+                counts[other] -= 1;
+                xors[other] ^= index;
+                if counts[other] == 1 {
+                    queue.push(other);
+                }
+            }
+        }
+        if order.len() != edges.len() {
+            return None;
+        }
+
+        // Assign in reverse peel order: each key's free cell is still zero and
+        // is never written again, so setting it makes the key's three cells
+        // XOR to its entry.
+        let mut table = vec![0u16; cells];
+        for &(index, cell) in order.iter().rev() {
+            let (positions, entry) = edges[index];
+            table[cell] = positions.iter().fold(entry, |acc, &p| acc ^ table[p]);
+        }
+        Some(table)
+    }
+
+    /// Returns the key's value, or `None` if the key was not in the construction set.
+    ///
+    /// Unknown keys return an arbitrary value with probability about 1/256.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<u8> {
+        let (positions, fingerprint) = locate(self.seed, self.segment_length, key.as_ref());
+        let entry = positions.iter().fold(0, |acc, &p| acc ^ self.table[p]);
+        ((entry >> 8) as u8 == fingerprint).then_some(entry as u8)
+    }
+
+    /// Returns the number of bytes used by the table.
+    pub fn memory_usage(&self) -> usize {
+        self.table.len() * std::mem::size_of::<u16>()
+    }
+
+    /// Saves the filter as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving BloomierFilter to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a filter saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading BloomierFilter from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let bloomier: Self = serde_json::from_reader(reader)?;
+        if bloomier.segment_length == 0 || bloomier.table.len() != 3 * bloomier.segment_length {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "Bloomier table has {} cells, expected 3 segments of {}",
+                bloomier.table.len(),
+                bloomier.segment_length
+            )));
+        }
+        Ok(bloomier)
+    }
+}
+
+/// Hashes a key to one cell in each segment and an 8-bit fingerprint.
+fn locate(seed: u64, segment_length: usize, key: &[u8]) -> ([usize; 3], u8) {
+    let mut hasher = SipHasher13::new_with_keys(seed, !seed);
+    hasher.write(key);
+    let hash = hasher.finish();
+    let positions = [0, 1, 2].map(|segment| {
+        let h = splitmix64(hash.wrapping_add(segment as u64 + 1));
+        segment * segment_length + (h % segment_length as u64) as usize
+    });
+    (positions, (hash >> 56) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloomier_returns_construction_values() {
+        let pairs: Vec<(String, u8)> = (0..2000).map(|i| (format!("key-{}", i), (i % 7) as u8)).collect();
+        let bloomier = BloomierFilter::build(pairs.iter().map(|(k, v)| (k, *v))).unwrap();
+        assert!(pairs.iter().all(|(key, value)| bloomier.get(key) == Some(*value)));
+        assert!(bloomier.memory_usage() < 2000 * 3);
+
+        let unknown = (0..10_000).filter(|i| bloomier.get(format!("other-{}", i)).is_some()).count();
+        assert!(unknown < 100, "{} unknown keys matched", unknown);
+
+        assert!(BloomierFilter::build([("a", 1), ("a", 1), ("b", 2)]).is_ok());
+        assert!(BloomierFilter::build([("a", 1), ("a", 2)]).is_err());
+        assert_eq!(BloomierFilter::build(Vec::<(&str, u8)>::new()).unwrap().get("a"), None);
+
+        let path = std::env::temp_dir().join("test_bloom_bloomier.json");
+        let path = path.to_str().unwrap();
+        bloomier.save_to_file(path).unwrap();
+        assert_eq!(BloomierFilter::load_from_file(path).unwrap(), bloomier);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+// src/builder.rs
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError, BloomLevel, InsertMode};
+use crate::counting::CounterWidth;
+use crate::hashing::HashAlgorithm;
+use crate::normalize::Normalization;
+use crate::saturation::CapacityLimit;
+
+/// Configures and creates a `BloomFilter`.
+///
+/// Starts from one level, three hash functions, seed 0 and
+/// `InsertMode::AllLevels`; the array size must be set.
+#[derive(Clone)]
+pub struct BloomFilterBuilder {
+    num_levels: usize,
+    array_size: usize,
+    num_hash_functions: usize,
+    seed: u64,
+    hash_algorithm: HashAlgorithm,
+    hash_key: Option<[u8; 16]>,
+    insert_mode: InsertMode,
+    pub(crate) normalization: Normalization,
+    pub(crate) counter_width: CounterWidth,
+    capacity: CapacityLimit,
+    #[cfg(feature = "roaring")]
+    sparse: bool,
+}
+
+impl Default for BloomFilterBuilder {
+    fn default() -> Self {
+        BloomFilterBuilder {
+            num_levels: 1,
+            array_size: 0,
+            num_hash_functions: 3,
+            seed: 0,
+            hash_algorithm: HashAlgorithm::Polynomial,
+            hash_key: None,
+            insert_mode: InsertMode::AllLevels,
+            normalization: Normalization::default(),
+            counter_width: CounterWidth::default(),
+            capacity: CapacityLimit::default(),
+            #[cfg(feature = "roaring")]
+            sparse: false,
+        }
+    }
+}
+
+impl BloomFilterBuilder {
+    /// Sets the number of levels.
+    pub fn levels(mut self, num_levels: usize) -> Self {
+        self.num_levels = num_levels;
+        self
+    }
+
+    /// Sets the number of bits in each level.
+    pub fn array_size(mut self, array_size: usize) -> Self {
+        self.array_size = array_size;
+        self
+    }
+
+    /// Sets the number of hash functions applied per item, at most `MAX_HASH_FUNCTIONS` (256).
+    pub fn hash_functions(mut self, num_hash_functions: usize) -> Self {
+        self.num_hash_functions = num_hash_functions;
+        self
+    }
+
+    /// Sets the seed perturbing every hash.
+    ///
+    /// Filters with different seeds have independent false positives over the
+    /// same data; the same seed always reproduces the same filter. The seed is
+    /// stored in saved files.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the algorithm used to hash string and byte keys.
+    ///
+    /// `build` fails if the algorithm's feature is not enabled.
+    pub fn hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = algorithm;
+        self
+    }
+
+    /// Hashes items with SipHash-2-4 under a secret 128-bit key.
+    ///
+    /// Without the key, an attacker who knows the filter's parameters cannot
+    /// craft inputs that collide or force false positives. The key is never
+    /// written to saved files; reload them with `BloomFilter::load_keyed_from_file`.
+    /// A hash key takes precedence over the hash algorithm.
+    pub fn hash_key(mut self, hash_key: [u8; 16]) -> Self {
+        self.hash_key = Some(hash_key);
+        self
+    }
+
+    /// Sets how inserts are distributed across levels.
+    pub fn insert_mode(mut self, mode: InsertMode) -> Self {
+        self.insert_mode = mode;
+        self
+    }
+
+    /// Sets how string keys are rewritten before hashing, on insert and query alike.
+    ///
+    /// The normalization is saved with the filter and must be chosen before
+    /// any item is inserted; filters with different normalizations cannot be
+    /// merged or compared.
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Sets the counter width used by `build_counting`; `build` ignores it.
+    pub fn counter_width(mut self, width: CounterWidth) -> Self {
+        self.counter_width = width;
+        self
+    }
+
+    /// Makes `try_insert` refuse items once the levels being inserted into hold `max_items`.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.capacity.max_items = Some(max_items);
+        self
+    }
+
+    /// Makes `try_insert` refuse items once the estimated false-positive rate exceeds `rate`.
+    pub fn max_false_positive_rate(mut self, rate: f64) -> Self {
+        self.capacity.max_false_positive_rate = Some(rate);
+        self
+    }
+
+    /// Stores levels as roaring bitmaps, as `BloomFilter::new_sparse` does.
+    #[cfg(feature = "roaring")]
+    pub fn sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Validates the configuration and creates the filter.
+    pub fn build(self) -> Result<BloomFilter, BloomFilterError> {
+        let array_size = self.array_size;
+        #[cfg(feature = "roaring")]
+        let mut filter = if self.sparse {
+            BloomFilter::with_levels(self.num_levels, array_size, self.num_hash_functions, self.seed, || {
+                BloomLevel::new_sparse(array_size)
+            })?
+        } else {
+            BloomFilter::with_levels(self.num_levels, array_size, self.num_hash_functions, self.seed, || {
+                Ok(BloomLevel::new(array_size))
+            })?
+        };
+        #[cfg(not(feature = "roaring"))]
+        let mut filter = BloomFilter::with_levels(self.num_levels, array_size, self.num_hash_functions, self.seed, || {
+            Ok(BloomLevel::new(array_size))
+        })?;
+        filter.set_insert_mode(self.insert_mode);
+        filter.normalization = self.normalization;
+        filter.capacity = self.capacity;
+        filter.set_hash_algorithm(self.hash_algorithm)?;
+        if let Some(hash_key) = self.hash_key {
+            filter.set_hash_key(hash_key);
+        }
+        Ok(filter)
+    }
+}
@@ -0,0 +1,169 @@
+// src/chain.rs
+
+use serde::Serialize;
+use std::fmt;
+use tracing::info;
+
+use crate::bloom_filter::BloomFilter;
+
+/// What a chain stage decides when an item matches its filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Allow => "allow",
+            Action::Deny => "deny",
+        })
+    }
+}
+
+/// The outcome of running an item through a `FilterChain`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Decision<'a> {
+    pub action: Action,
+    /// Name of the stage whose filter matched, or `None` if the chain's default applied.
+    pub stage: Option<&'a str>,
+}
+
+impl Decision<'_> {
+    /// Returns true if the item was allowed.
+    pub fn is_allowed(&self) -> bool {
+        self.action == Action::Allow
+    }
+}
+
+struct Stage {
+    name: String,
+    filter: BloomFilter,
+    action: Action,
+}
+
+/// An ordered list of filters with allow/deny semantics.
+///
+/// Each stage queries its filter across all levels; the first stage whose
+/// filter matches decides, and items no stage matches get the default action.
+/// A blocklist with exceptions, for instance, is an allow stage for the
+/// allowlist followed by a deny stage for the blocklist, defaulting to allow.
+pub struct FilterChain {
+    stages: Vec<Stage>,
+    default: Action,
+}
+
+impl FilterChain {
+    /// Creates an empty chain that applies `default` to every item.
+    pub fn new(default: Action) -> Self {
+        FilterChain {
+            stages: Vec::new(),
+            default,
+        }
+    }
+
+    /// Appends a stage that allows the items its filter matches.
+    pub fn allow(mut self, name: &str, filter: BloomFilter) -> Self {
+        self.push(name, filter, Action::Allow);
+        self
+    }
+
+    /// Appends a stage that denies the items its filter matches.
+    pub fn deny(mut self, name: &str, filter: BloomFilter) -> Self {
+        self.push(name, filter, Action::Deny);
+        self
+    }
+
+    /// Appends a stage, evaluated after every existing one.
+    pub fn push(&mut self, name: &str, filter: BloomFilter, action: Action) {
+        info!("Adding {} stage {} to filter chain", action, name);
+        self.stages.push(Stage {
+            name: name.to_string(),
+            filter,
+            action,
+        });
+    }
+
+    /// Runs an item through the stages in order.
+    pub fn evaluate(&self, item: &str) -> Decision<'_> {
+        self.decide(|filter| filter.query(item, filter.levels().len()))
+    }
+
+    /// Runs a binary key through the stages in order.
+    pub fn evaluate_bytes(&self, item: &[u8]) -> Decision<'_> {
+        self.decide(|filter| filter.query_bytes(item, filter.levels().len()))
+    }
+
+    /// Returns true if the chain allows the item.
+    pub fn is_allowed(&self, item: &str) -> bool {
+        self.evaluate(item).is_allowed()
+    }
+
+    fn decide(&self, matches: impl Fn(&BloomFilter) -> bool) -> Decision<'_> {
+        match self.stages.iter().find(|stage| matches(&stage.filter)) {
+            Some(stage) => Decision {
+                action: stage.action,
+                stage: Some(&stage.name),
+            },
+            None => Decision {
+                action: self.default,
+                stage: None,
+            },
+        }
+    }
+
+    /// Returns the action applied when no stage matches.
+    pub fn default_action(&self) -> Action {
+        self.default
+    }
+
+    /// Returns the stage names in evaluation order.
+    pub fn stage_names(&self) -> impl Iterator<Item = &str> {
+        self.stages.iter().map(|stage| stage.name.as_str())
+    }
+
+    /// Returns the filter of the named stage, e.g. to add items to a blocklist.
+    pub fn filter_mut(&mut self, name: &str) -> Option<&mut BloomFilter> {
+        self.stages
+            .iter_mut()
+            .find(|stage| stage.name == name)
+            .map(|stage| &mut stage.filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter_of(items: &[&str]) -> BloomFilter {
+        let mut filter = BloomFilter::new(1, 1000, 3).unwrap();
+        for item in items {
+            filter.insert(item);
+        }
+        filter
+    }
+
+    #[test]
+    fn test_allowlist_overrides_blocklist() {
+        let mut chain = FilterChain::new(Action::Allow)
+            .allow("allowlist", filter_of(&["partner.example"]))
+            .deny("blocklist", filter_of(&["spam.example", "partner.example"]));
+
+        let decision = chain.evaluate("partner.example");
+        assert_eq!(decision.action, Action::Allow);
+        assert_eq!(decision.stage, Some("allowlist"));
+        let decision = chain.evaluate("spam.example");
+        assert_eq!(decision.action, Action::Deny);
+        assert_eq!(decision.stage, Some("blocklist"));
+        let decision = chain.evaluate("news.example");
+        assert!(decision.is_allowed());
+        assert_eq!(decision.stage, None);
+
+        chain.filter_mut("blocklist").unwrap().insert("news.example");
+        assert!(!chain.is_allowed("news.example"));
+        assert!(chain.filter_mut("missing").is_none());
+        assert_eq!(chain.stage_names().collect::<Vec<_>>(), ["allowlist", "blocklist"]);
+    }
+}
@@ -0,0 +1,183 @@
+// src/chunked.rs
+
+use std::io::{self, Read, Write};
+use tracing::{error, info};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// Magic bytes opening a chunked stream.
+const CHUNKED_MAGIC: &[u8; 4] = b"BLMC";
+/// Current version of the chunked format.
+const CHUNKED_VERSION: u8 = 1;
+
+impl BloomFilter {
+    /// Encodes the filter in the binary format and passes it to `f` in chunks of `chunk_size` bytes.
+    ///
+    /// `f` receives each chunk's index and bytes; only the last chunk may be
+    /// shorter. The encoding is produced as it is consumed, so memory stays
+    /// bounded by the chunk size however large the filter is. Chunk boundaries
+    /// depend only on the filter and `chunk_size`, so an interrupted upload can
+    /// be resumed by re-running this and skipping the chunks already sent.
+    pub fn for_each_chunk<F>(&self, chunk_size: usize, f: F) -> Result<usize, BloomFilterError>
+    where
+        F: FnMut(usize, &[u8]) -> io::Result<()>,
+    {
+        let mut chunker = Chunker {
+            buffer: Vec::with_capacity(chunk_size.max(1)),
+            chunk_size: chunk_size.max(1),
+            index: 0,
+            f,
+        };
+        self.write_binary(&mut chunker)?;
+        chunker.emit()?;
+        Ok(chunker.index)
+    }
+
+    /// Streams the filter to a writer as a sequence of checksummed chunks.
+    ///
+    /// Layout: magic `BLMC`, version and chunk size, then for each chunk its
+    /// length, the CRC-32 of its bytes and the bytes themselves, ending with
+    /// a zero-length chunk. The chunks concatenate to the binary format.
+    /// Returns the number of data chunks written.
+    pub fn save_chunked<W: Write>(&self, mut writer: W, chunk_size: usize) -> Result<usize, BloomFilterError> {
+        let chunk_size = u32::try_from(chunk_size.max(1)).unwrap_or(u32::MAX);
+        info!("Saving BloomFilter in chunks of {} bytes", chunk_size);
+        writer.write_all(CHUNKED_MAGIC)?;
+        writer.write_all(&[CHUNKED_VERSION])?;
+        writer.write_all(&chunk_size.to_le_bytes())?;
+        let chunks = self.for_each_chunk(chunk_size as usize, |_, chunk| write_frame(&mut writer, chunk))?;
+        write_frame(&mut writer, &[])?;
+        writer.flush()?;
+        Ok(chunks)
+    }
+
+    /// Reads and validates a filter written by `save_chunked`, checking every chunk's checksum.
+    pub fn load_chunked<R: Read>(mut reader: R) -> Result<Self, BloomFilterError> {
+        let mut header = [0u8; 9];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != CHUNKED_MAGIC {
+            return Err(chunked_error("missing magic bytes"));
+        }
+        if header[4] != CHUNKED_VERSION {
+            return Err(chunked_error(format!("unsupported version {}", header[4])));
+        }
+        let chunk_size = u32::from_le_bytes(header[5..].try_into().unwrap()) as usize;
+
+        let mut binary = Vec::new();
+        loop {
+            let mut frame = [0u8; 8];
+            reader.read_exact(&mut frame)?;
+            let len = u32::from_le_bytes(frame[..4].try_into().unwrap()) as usize;
+            let expected = u32::from_le_bytes(frame[4..].try_into().unwrap());
+            if len > chunk_size {
+                return Err(chunked_error(format!("chunk of {} bytes exceeds chunk size {}", len, chunk_size)));
+            }
+            let start = binary.len();
+            // Read only what arrives rather than allocating the claimed length up front.
+            if reader.by_ref().take(len as u64).read_to_end(&mut binary)? != len {
+                return Err(chunked_error(format!("chunk at offset {} is truncated", start)));
+            }
+            let actual = crc32fast::hash(&binary[start..]);
+            if actual != expected {
+                error!("Chunk at offset {} failed its checksum", start);
+                return Err(BloomFilterError::CorruptedFile { expected, actual });
+            }
+            if len == 0 {
+                break;
+            }
+        }
+        Self::from_binary(&binary)
+    }
+}
+
+fn write_frame<W: Write>(writer: &mut W, chunk: &[u8]) -> io::Result<()> {
+    writer.write_all(&(chunk.len() as u32).to_le_bytes())?;
+    writer.write_all(&crc32fast::hash(chunk).to_le_bytes())?;
+    writer.write_all(chunk)
+}
+
+fn chunked_error(message: impl Into<String>) -> BloomFilterError {
+    BloomFilterError::Encoding {
+        format: "chunked",
+        message: message.into(),
+    }
+}
+
+/// Collects written bytes into chunks and hands each full chunk to `f`.
+struct Chunker<F> {
+    buffer: Vec<u8>,
+    chunk_size: usize,
+    index: usize,
+    f: F,
+}
+
+impl<F: FnMut(usize, &[u8]) -> io::Result<()>> Chunker<F> {
+    fn emit(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            (self.f)(self.index, &self.buffer)?;
+            self.index += 1;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<F: FnMut(usize, &[u8]) -> io::Result<()>> Write for Chunker<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let take = buf.len().min(self.chunk_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..take]);
+        if self.buffer.len() == self.chunk_size {
+            self.emit()?;
+        }
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunked_round_trip() {
+        // Over a megabit per level, so the packed bits span several blocks.
+        let mut bf = BloomFilter::new(2, 1_100_000, 3).unwrap();
+        let items: Vec<String> = (0..1000).map(|i| format!("item-{}", i)).collect();
+        for item in &items {
+            bf.insert(item);
+        }
+
+        let mut chunks = Vec::new();
+        let count = bf
+            .for_each_chunk(100_000, |index, chunk| {
+                chunks.push((index, chunk.len()));
+                Ok(())
+            })
+            .unwrap();
+        let binary = bf.to_binary();
+        assert_eq!(count, binary.len().div_ceil(100_000));
+        assert!(chunks.iter().enumerate().all(|(i, &(index, _))| i == index));
+        assert_eq!(chunks.iter().map(|&(_, len)| len).sum::<usize>(), binary.len());
+
+        let mut buffer = Vec::new();
+        assert_eq!(bf.save_chunked(&mut buffer, 100_000).unwrap(), count);
+        let loaded = BloomFilter::load_chunked(buffer.as_slice()).unwrap();
+        assert!(items.iter().all(|item| loaded.query(item, 2)));
+        assert_eq!(loaded.to_binary(), binary);
+
+        let middle = buffer.len() / 2;
+        buffer[middle] ^= 1;
+        assert!(matches!(
+            BloomFilter::load_chunked(buffer.as_slice()),
+            Err(BloomFilterError::CorruptedFile { .. })
+        ));
+        assert!(BloomFilter::load_chunked(&buffer[..middle]).is_err());
+        // A frame claiming 4 GiB fails on the missing bytes instead of allocating them.
+        let mut huge = b"BLMC\x01\xff\xff\xff\xff\xff\xff\xff\xff\0\0\0\0".to_vec();
+        huge.extend_from_slice(&[0; 16]);
+        assert!(BloomFilter::load_chunked(huge.as_slice()).is_err());
+    }
+}
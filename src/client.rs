@@ -0,0 +1,350 @@
+// src/client.rs
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+use tracing::{debug, info, warn};
+
+use crate::bloom_filter::BloomFilterError;
+use crate::stats::FilterStats;
+
+/// Idle connections kept for reuse when no limit is set with `max_idle`.
+const DEFAULT_MAX_IDLE: usize = 4;
+
+/// An async client for the daemon started by `bloom daemon` (see `daemon::Daemon`).
+///
+/// Requests go over the daemon's line protocol: one command per line, one
+/// JSON response per line. Connections are pooled and reused, and a request
+/// whose connection fails is retried on a fresh one, so a daemon restart is
+/// survived; errors the daemon reports are returned without retrying. Inserts
+/// are idempotent, so a retried insert cannot add an item twice, though its
+/// `new` flag may then read false. Clones share the pool.
+#[derive(Clone)]
+pub struct DaemonClient {
+    pool: Arc<Pool>,
+    filter: String,
+    retries: usize,
+    retry_delay: Duration,
+}
+
+/// The result of a query: whether the item may be present, and where it matched.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct QueryResult {
+    pub present: bool,
+    /// 1-based number of the first matching level.
+    pub level: Option<usize>,
+    pub label: Option<String>,
+}
+
+struct Pool {
+    path: PathBuf,
+    idle: Mutex<Vec<Connection>>,
+    max_idle: usize,
+}
+
+/// One open socket and the filter its commands currently act on.
+struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    active: String,
+}
+
+/// A daemon response line: `ok`, then either the outcome's fields or `error`.
+#[derive(Deserialize)]
+struct Response {
+    ok: bool,
+    error: Option<String>,
+    #[serde(flatten)]
+    outcome: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct Inserted {
+    new: bool,
+}
+
+#[derive(Deserialize)]
+struct Stats {
+    stats: FilterStats,
+}
+
+#[derive(Deserialize)]
+struct Filters {
+    active: Option<String>,
+    names: Vec<String>,
+}
+
+impl DaemonClient {
+    /// Connects to the daemon listening on `socket_path`, acting on its default filter.
+    pub async fn connect(socket_path: impl AsRef<Path>) -> Result<Self, BloomFilterError> {
+        let path = socket_path.as_ref().to_path_buf();
+        info!("Connecting to daemon at {}", path.display());
+        let mut connection = Connection::open(&path, String::new()).await?;
+        let filters: Filters = connection.request(&["filters".to_string()]).await?.remove(0)?;
+        connection.active =
+            filters.active.ok_or_else(|| BloomFilterError::Daemon("daemon has no active filter".to_string()))?;
+        let filter = connection.active.clone();
+        Ok(DaemonClient {
+            pool: Arc::new(Pool {
+                path,
+                idle: Mutex::new(vec![connection]),
+                max_idle: DEFAULT_MAX_IDLE,
+            }),
+            filter,
+            retries: 2,
+            retry_delay: Duration::from_millis(100),
+        })
+    }
+
+    /// Returns a client for the filter called `name`, sharing this client's connections.
+    pub fn with_filter(&self, name: &str) -> Self {
+        DaemonClient {
+            filter: name.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Retries a request up to `retries` more times when its connection fails,
+    /// waiting `delay` times the attempt number before each; 2 and 100 ms by default.
+    pub fn with_retries(mut self, retries: usize, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Keeps at most `max_idle` connections open between requests; 4 by default.
+    ///
+    /// Only takes effect on a freshly connected client, before it is cloned.
+    pub fn max_idle(mut self, max_idle: usize) -> Self {
+        if let Some(pool) = Arc::get_mut(&mut self.pool) {
+            pool.max_idle = max_idle;
+        }
+        self
+    }
+
+    /// The name of the filter this client acts on.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// Inserts an item, returning true if it set at least one new bit.
+    pub async fn insert(&self, item: &str) -> Result<bool, BloomFilterError> {
+        let inserted: Inserted = self.send(vec![command("insert", item)?]).await?.remove(0)?;
+        Ok(inserted.new)
+    }
+
+    /// Inserts every item over one connection, returning how many set a new bit.
+    pub async fn insert_batch<I, T>(&self, items: I) -> Result<usize, BloomFilterError>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let lines = items.into_iter().map(|item| command("insert", item.as_ref())).collect::<Result<_, _>>()?;
+        let results: Vec<Result<Inserted, _>> = self.send(lines).await?;
+        results.into_iter().try_fold(0, |new, inserted| Ok(new + usize::from(inserted?.new)))
+    }
+
+    /// Queries an item across the first `levels` levels, or all of them.
+    pub async fn query(&self, item: &str, levels: Option<usize>) -> Result<QueryResult, BloomFilterError> {
+        self.send(vec![query_command(item, levels)?]).await?.remove(0)
+    }
+
+    /// Queries every item over one connection, returning one result per item in order.
+    pub async fn query_batch<I, T>(
+        &self,
+        items: I,
+        levels: Option<usize>,
+    ) -> Result<Vec<QueryResult>, BloomFilterError>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let lines = items.into_iter().map(|item| query_command(item.as_ref(), levels)).collect::<Result<_, _>>()?;
+        self.send(lines).await?.into_iter().collect()
+    }
+
+    /// Returns the filter's parameters, per-level fill and operation counts.
+    pub async fn stats(&self) -> Result<FilterStats, BloomFilterError> {
+        let stats: Stats = self.send(vec!["stats".to_string()]).await?.remove(0)?;
+        Ok(stats.stats)
+    }
+
+    /// Lists the filters the daemon holds.
+    pub async fn filters(&self) -> Result<Vec<String>, BloomFilterError> {
+        let filters: Filters = self.send(vec!["filters".to_string()]).await?.remove(0)?;
+        Ok(filters.names)
+    }
+
+    /// Creates the filter `to` as a copy of `from`; the daemon has no command to build a filter from scratch.
+    pub async fn copy(&self, from: &str, to: &str) -> Result<(), BloomFilterError> {
+        let line = format!("copy {} {}", quote(from)?, quote(to)?);
+        self.send::<serde_json::Value>(vec![line]).await?.remove(0)?;
+        Ok(())
+    }
+
+    /// Asks the daemon to save its filters and stop.
+    pub async fn shutdown(&self) -> Result<(), BloomFilterError> {
+        info!("Shutting down daemon at {}", self.pool.path.display());
+        let mut connection = self.pool.checkout(&self.filter).await?;
+        connection.request::<serde_json::Value>(&["shutdown".to_string()]).await?.remove(0)?;
+        Ok(())
+    }
+
+    /// Sends `lines` on a pooled connection switched to this client's filter,
+    /// retrying on a fresh connection if the socket fails.
+    ///
+    /// The outer error is a failed connection or a malformed response; each
+    /// inner result is one command's outcome as the daemon reported it.
+    async fn send<T: DeserializeOwned>(
+        &self,
+        lines: Vec<String>,
+    ) -> Result<Vec<Result<T, BloomFilterError>>, BloomFilterError> {
+        let mut attempt = 0;
+        loop {
+            let result = async {
+                let mut connection = self.pool.checkout(&self.filter).await?;
+                let results = connection.request(&lines).await?;
+                self.pool.checkin(connection);
+                Ok(results)
+            }
+            .await;
+            match result {
+                Err(BloomFilterError::IoError(e)) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!("Daemon request failed ({}), retrying ({}/{})", e, attempt, self.retries);
+                    tokio::time::sleep(self.retry_delay * attempt as u32).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Pool {
+    /// Takes an idle connection, or opens one, and switches it to `filter`.
+    async fn checkout(&self, filter: &str) -> Result<Connection, BloomFilterError> {
+        let idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner).pop();
+        let mut connection = match idle {
+            Some(connection) => connection,
+            None => Connection::open(&self.path, String::new()).await?,
+        };
+        if connection.active != filter {
+            connection.request::<serde_json::Value>(&[command("use", filter)?]).await?.remove(0)?;
+            connection.active = filter.to_string();
+        }
+        Ok(connection)
+    }
+
+    fn checkin(&self, connection: Connection) {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        if idle.len() < self.max_idle {
+            idle.push(connection);
+        }
+    }
+}
+
+impl Connection {
+    async fn open(path: &Path, active: String) -> Result<Self, BloomFilterError> {
+        debug!("Opening daemon connection to {}", path.display());
+        let (reader, writer) = UnixStream::connect(path).await?.into_split();
+        Ok(Connection {
+            reader: BufReader::new(reader),
+            writer,
+            active,
+        })
+    }
+
+    /// Writes every line, then reads one response per line.
+    async fn request<T: DeserializeOwned>(
+        &mut self,
+        lines: &[String],
+    ) -> Result<Vec<Result<T, BloomFilterError>>, BloomFilterError> {
+        let mut batch = lines.join("\n");
+        batch.push('\n');
+        self.writer.write_all(batch.as_bytes()).await?;
+        let mut results = Vec::with_capacity(lines.len());
+        let mut line = String::new();
+        for _ in lines {
+            line.clear();
+            if self.reader.read_line(&mut line).await? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "daemon closed the connection").into());
+            }
+            let response: Response = serde_json::from_str(&line)?;
+            results.push(match response.ok {
+                true => Ok(serde_json::from_value(response.outcome)?),
+                false => Err(BloomFilterError::Daemon(response.error.unwrap_or_default())),
+            });
+        }
+        Ok(results)
+    }
+}
+
+/// Formats `name argument`, quoting the argument so the daemon reads it as one token.
+fn command(name: &str, argument: &str) -> Result<String, BloomFilterError> {
+    Ok(format!("{} {}", name, quote(argument)?))
+}
+
+fn query_command(item: &str, levels: Option<usize>) -> Result<String, BloomFilterError> {
+    let line = command("query", item)?;
+    Ok(match levels {
+        Some(levels) => format!("{} --levels {}", line, levels),
+        None => line,
+    })
+}
+
+/// Quotes an argument, rejecting what the line protocol cannot carry: quotes,
+/// line breaks and the option names `--levels` and `--format`.
+fn quote(argument: &str) -> Result<String, BloomFilterError> {
+    if argument.contains(['"', '\n', '\r']) || argument == "--levels" || argument == "--format" {
+        return Err(BloomFilterError::Daemon(format!("{:?} cannot be sent to the daemon", argument)));
+    }
+    Ok(format!("\"{}\"", argument))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "cli")]
+    #[tokio::test]
+    async fn test_client_talks_to_daemon() {
+        use super::*;
+        use crate::bloom_filter::BloomFilter;
+        use crate::daemon::Daemon;
+        use crate::store::FilterStore;
+
+        let mut store = FilterStore::in_memory();
+        store.create("default", BloomFilter::new(1, 1000, 3).unwrap()).unwrap();
+        let path = std::env::temp_dir().join("test_bloom_client.sock");
+        let daemon = Daemon::bind(path.to_str().unwrap(), store, "default").unwrap();
+        let handle = std::thread::spawn(move || daemon.run());
+
+        let client = DaemonClient::connect(&path).await.unwrap().with_retries(0, Duration::ZERO);
+        assert_eq!(client.filter(), "default");
+        assert!(client.insert("hello world").await.unwrap());
+        assert_eq!(client.insert_batch(["a", "b", "hello world"]).await.unwrap(), 2);
+        assert!(client.query("hello world", None).await.unwrap().present);
+        let results = client.query_batch(["a", "missing"], Some(1)).await.unwrap();
+        assert_eq!(results.iter().map(|r| r.present).collect::<Vec<_>>(), [true, false]);
+        assert!(client.query("\"quoted\"", None).await.is_err());
+
+        client.copy("default", "other").await.unwrap();
+        let other = client.with_filter("other");
+        other.insert("only-other").await.unwrap();
+        assert!(!client.query("only-other", None).await.unwrap().present);
+        assert_eq!(other.stats().await.unwrap().counts.inserts, 1);
+        assert_eq!(client.filters().await.unwrap(), ["default", "other"]);
+        assert!(matches!(
+            client.with_filter("nope").stats().await,
+            Err(BloomFilterError::Daemon(_))
+        ));
+
+        client.shutdown().await.unwrap();
+        handle.join().unwrap().unwrap();
+        assert!(client.stats().await.is_err());
+    }
+}
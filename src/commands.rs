@@ -0,0 +1,993 @@
+// src/commands.rs
+
+use flate2::read::MultiGzDecoder;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::blocklist;
+use crate::bloom_filter::{splitmix64, BloomFilter, BloomFilterError, RebuildParams, MAX_HASH_FUNCTIONS};
+use crate::filter::Filter;
+use crate::format::FileFormat;
+use crate::hashing::HashAlgorithm;
+use crate::kmer::{KmerMode, SequenceReader};
+use crate::math;
+use crate::minhash::MinHash;
+use crate::ngram::NgramMode;
+use crate::passwords::{BreachedPasswords, CorpusFormat, PasswordDigest};
+use crate::progress;
+use crate::repl::Outcome;
+use crate::source::{self, FileFollower, FileSource, ItemSource};
+use crate::store::FilterStore;
+use crate::top_k::TopK;
+use crate::tuning::{self, TuningParams};
+use crate::variant::{AnyFilter, FilterVariant, VariantParams};
+use crate::visualize::Shading;
+
+/// Parameters for `bench`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchParams {
+    pub items: usize,
+    /// Target false-positive rate; sizes the filter when `array_size` is `None`.
+    pub false_positive_rate: f64,
+    /// `None` picks the optimal size for `items` at `false_positive_rate`.
+    pub array_size: Option<usize>,
+    /// `None` picks the optimal count for the array size.
+    pub num_hash_functions: Option<usize>,
+    /// Seeds the random item generator, so runs can be repeated exactly.
+    pub seed: u64,
+}
+
+/// Inserts `items` random items, then queries as many different random items.
+///
+/// Reports insert and query throughput and compares the measured
+/// false-positive rate (the share of never-inserted items that match) with
+/// the theoretical rate for the filter's parameters.
+pub fn bench(params: &BenchParams) -> Result<Outcome, String> {
+    info!("Benchmarking: {:?}", params);
+    check_false_positive_rate(params.false_positive_rate)?;
+    let array_size = params
+        .array_size
+        .unwrap_or_else(|| math::optimal_bits(params.items, params.false_positive_rate));
+    let num_hash_functions = params
+        .num_hash_functions
+        .unwrap_or_else(|| math::optimal_hashes(array_size, params.items).min(MAX_HASH_FUNCTIONS));
+    let mut filter = BloomFilter::new(1, array_size, num_hash_functions)
+        .map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+    // Inserted and probe items get different prefixes, so the sets are disjoint.
+    let mut state = params.seed;
+    let mut random_items = |prefix: char| -> Vec<String> {
+        (0..params.items)
+            .map(|_| {
+                state = splitmix64(state);
+                format!("{}{:016x}", prefix, state)
+            })
+            .collect()
+    };
+    let items = random_items('i');
+    let probes = random_items('p');
+
+    let start = Instant::now();
+    for item in &items {
+        filter.insert(item);
+    }
+    let insert_seconds = start.elapsed().as_secs_f64();
+
+    let start = Instant::now();
+    let false_positives = probes.iter().filter(|probe| filter.query(probe, 1)).count();
+    let query_seconds = start.elapsed().as_secs_f64();
+
+    let per_second = |seconds: f64| if seconds > 0.0 { params.items as f64 / seconds } else { f64::INFINITY };
+    Ok(Outcome::Bench {
+        items: params.items,
+        array_size,
+        hash_functions: num_hash_functions,
+        inserts_per_second: per_second(insert_seconds),
+        queries_per_second: per_second(query_seconds),
+        false_positives,
+        measured_false_positive_rate: false_positives as f64 / params.items.max(1) as f64,
+        theoretical_false_positive_rate: math::false_positive_rate(array_size, params.items, num_hash_functions),
+    })
+}
+
+/// Recommends array sizes and hash counts for the expected items within the given limits.
+pub fn tune(params: &TuningParams) -> Result<Outcome, String> {
+    info!("Tuning: {:?}", params);
+    check_false_positive_rate(params.target_false_positive_rate)?;
+    if params.expected_items == 0 {
+        return Err("Expected items must be greater than zero".to_string());
+    }
+    let candidates = tuning::recommend(params);
+    if candidates.is_empty() {
+        return Err("No configuration fits the memory budget".to_string());
+    }
+    Ok(Outcome::Tune {
+        items: params.expected_items,
+        target_false_positive_rate: params.target_false_positive_rate,
+        candidates,
+    })
+}
+
+/// Parameters for `generate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerateParams {
+    /// Newline-separated items, one per line.
+    pub input: String,
+    pub output: String,
+    pub false_positive_rate: f64,
+    /// `None` picks the format from the output file's extension.
+    pub format: Option<FileFormat>,
+}
+
+/// Builds a filter sized for the input at the target false-positive rate and saves it.
+///
+/// The input is read twice: once to count the items, which fixes the optimal
+/// array size and hash count, and once to insert them. Duplicate lines are
+/// counted every time, so a filter for input with repeats errs on the large side.
+pub fn generate(params: &GenerateParams) -> Result<Outcome, String> {
+    info!("Generating: {:?}", params);
+    check_false_positive_rate(params.false_positive_rate)?;
+    let mut source =
+        FileSource::open(&params.input).map_err(|e| format!("Failed to read {}: {}", params.input, e))?;
+    let items = source.remaining().unwrap_or(0);
+
+    let sizing = RebuildParams::for_capacity(items, params.false_positive_rate);
+    let mut filter = BloomFilter::new(
+        1,
+        sizing.array_size.unwrap_or(1),
+        sizing.num_hash_functions.unwrap_or(1),
+    )
+    .map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+    let bar = progress::items_bar(Some(items as u64), "Inserting");
+    progress::for_each_item(&mut source, &bar, |item| {
+        filter.insert(item);
+    })
+    .map_err(|e| format!("Failed to insert from {}: {}", params.input, e))?;
+
+    let format = params.format.unwrap_or_else(|| FileFormat::for_path(&params.output));
+    progress::save(&filter, &params.output, format).map_err(|e| format!("Failed to save BloomFilter: {}", e))?;
+    Ok(Outcome::Generate {
+        path: params.output.clone(),
+        format: format.to_string(),
+        items,
+        target_false_positive_rate: params.false_positive_rate,
+        stats: filter.stats(),
+    })
+}
+
+/// Loads two saved filters and reports their compatibility and overlap.
+///
+/// Incompatible filters are a normal result, not an error: the outcome says
+/// why they differ.
+pub fn compare(first: &str, second: &str) -> Result<Outcome, String> {
+    let (a, b) = (load(first)?, load(second)?);
+    let (comparison, reason) = match a.compare(&b) {
+        Ok(comparison) => (Some(comparison), None),
+        Err(BloomFilterError::IncompatibleFilters(reason)) => (None, Some(reason)),
+        Err(e) => return Err(format!("Failed to compare filters: {}", e)),
+    };
+    Ok(Outcome::Compare {
+        first: first.to_string(),
+        second: second.to_string(),
+        compatible: comparison.is_some(),
+        reason,
+        comparison,
+    })
+}
+
+/// Loads two saved filters and reports which bits differ between them.
+pub fn diff(first: &str, second: &str) -> Result<Outcome, String> {
+    let (a, b) = (load(first)?, load(second)?);
+    let diff = a.diff(&b).map_err(|e| format!("Failed to diff filters: {}", e))?;
+    Ok(Outcome::Diff {
+        first: first.to_string(),
+        second: second.to_string(),
+        diff,
+    })
+}
+
+pub fn visualize(path: &str, width: usize, ascii: bool) -> Result<Outcome, String> {
+    if width == 0 {
+        return Err("Width must be at least 1".to_string());
+    }
+    let shading = if ascii { Shading::Ascii } else { Shading::Unicode };
+    Ok(Outcome::Visualize {
+        path: path.to_string(),
+        rendering: load(path)?.visualize_with(width, shading),
+    })
+}
+
+/// Estimates the Jaccard similarity of two item sources from their MinHash signatures.
+///
+/// Each source is read once, in constant memory, so corpora far larger than
+/// memory can be compared.
+pub fn similarity(first: &str, second: &str, num_permutations: usize) -> Result<Outcome, String> {
+    let signature = |spec: &str| -> Result<(MinHash, usize), String> {
+        let mut minhash = MinHash::new(num_permutations).map_err(|e| format!("Error creating MinHash: {}", e))?;
+        let mut input = source::open(spec).map_err(|e| format!("Failed to open {}: {}", spec, e))?;
+        let bar = progress::items_bar(input.remaining().map(|n| n as u64), "Hashing");
+        let items = progress::for_each_item(input.as_mut(), &bar, |item| minhash.insert(item))
+            .map_err(|e| format!("Failed to read {}: {}", spec, e))?;
+        Ok((minhash, items))
+    };
+    let ((a, items_first), (b, items_second)) = (signature(first)?, signature(second)?);
+    Ok(Outcome::Similarity {
+        first: first.to_string(),
+        second: second.to_string(),
+        items_first,
+        items_second,
+        permutations: num_permutations,
+        jaccard: a.jaccard(&b).map_err(|e| e.to_string())?,
+    })
+}
+
+/// Reports the `k` most frequent items of a source with approximate counts.
+///
+/// Counts come from a Count-Min sketch whose error is at most `epsilon`
+/// times the number of items, so memory stays fixed however many distinct
+/// items the source has.
+pub fn top_k(spec: &str, k: usize, epsilon: f64) -> Result<Outcome, String> {
+    let mut top_k = TopK::new(k, epsilon, 0.001).map_err(|e| format!("Error creating top-k tracker: {}", e))?;
+    let mut input = source::open(spec).map_err(|e| format!("Failed to open {}: {}", spec, e))?;
+    let bar = progress::items_bar(input.remaining().map(|n| n as u64), "Counting");
+    let items = progress::for_each_item(input.as_mut(), &bar, |item| top_k.insert(item))
+        .map_err(|e| format!("Failed to read {}: {}", spec, e))?;
+    Ok(Outcome::TopK {
+        source: spec.to_string(),
+        items,
+        top: top_k.top(),
+    })
+}
+
+/// Creates an empty filter of the given variant and saves it, tagged, to `path`.
+pub fn create_variant(path: &str, variant: FilterVariant, params: &VariantParams) -> Result<Outcome, String> {
+    let any = AnyFilter::new(variant, params).map_err(|e| format!("Error creating {} filter: {}", variant, e))?;
+    any.save_to_file(path)
+        .map_err(|e| format!("Failed to save {} filter: {}", variant, e))?;
+    Ok(Outcome::CreateVariant {
+        path: path.to_string(),
+        variant: variant.to_string(),
+    })
+}
+
+/// Inserts items into a filter saved with `create_variant`, whatever its variant, and saves it back.
+pub fn add_items(path: &str, items: &[String]) -> Result<Outcome, String> {
+    let mut any = load_any(path)?;
+    let new = items.iter().filter(|item| any.insert(item.as_bytes())).count();
+    any.save_to_file(path)
+        .map_err(|e| format!("Failed to save {} filter: {}", any.variant(), e))?;
+    Ok(Outcome::Add {
+        path: path.to_string(),
+        variant: any.variant().to_string(),
+        items: items.len(),
+        new,
+    })
+}
+
+/// Reports which items a filter saved with `create_variant` may contain, whatever its variant.
+pub fn contains_items(path: &str, items: &[String]) -> Result<Outcome, String> {
+    let any = load_any(path)?;
+    let (present, absent) = items.iter().cloned().partition(|item| any.contains(item.as_bytes()));
+    Ok(Outcome::Contains {
+        path: path.to_string(),
+        variant: any.variant().to_string(),
+        present,
+        absent,
+        estimated_false_positive_rate: any.estimated_fpr(),
+    })
+}
+
+/// Serves the store's filters over a Unix socket until a client sends `shutdown`.
+///
+/// Without `store_dir` the filters live only in memory. `name` is created
+/// with the given sizing if the store does not have it yet.
+#[cfg(unix)]
+pub fn daemon(
+    socket: &str,
+    store_dir: Option<&str>,
+    name: &str,
+    levels: Option<usize>,
+    array_size: Option<usize>,
+    hash_functions: Option<usize>,
+) -> Result<Outcome, String> {
+    let mut store = match store_dir {
+        Some(dir) => FilterStore::open(dir).map_err(|e| format!("Failed to open store {}: {}", dir, e))?,
+        None => FilterStore::in_memory(),
+    };
+    if !store.contains(name) {
+        let filter = new_filter(name, levels, array_size, hash_functions)?;
+        store.create(name, filter).map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+    }
+    let daemon = crate::daemon::Daemon::bind(socket, store, name)
+        .map_err(|e| format!("Failed to listen on {}: {}", socket, e))?;
+    daemon.run().map_err(|e| format!("Daemon failed: {}", e))?;
+    Ok(Outcome::Shutdown)
+}
+
+/// Parameters for `follow`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FollowParams {
+    /// File to watch for new lines.
+    pub input: String,
+    /// Filter file to insert into; created from the sizing below if missing.
+    pub filter: String,
+    pub levels: Option<usize>,
+    pub array_size: Option<usize>,
+    pub num_hash_functions: Option<usize>,
+    /// Insert the lines already in `input` before following it.
+    pub from_start: bool,
+    /// How often to save the filter while new lines keep arriving.
+    pub save_interval: Duration,
+    /// How long to wait for a new line before checking again.
+    pub poll_interval: Duration,
+    /// Stop after this long without a new line; `None` follows until killed.
+    pub idle_timeout: Option<Duration>,
+}
+
+/// Inserts lines appended to a file as they appear, saving the filter periodically.
+///
+/// The filter is saved at most every `save_interval` while there is
+/// something new to save, and once more on stopping, so killing the process
+/// loses at most one interval of lines. Saves go through a temporary file
+/// and a rename, so a reader never sees a half-written filter.
+pub fn follow(params: &FollowParams) -> Result<Outcome, String> {
+    info!("Following: {:?}", params);
+    let exists = fs::metadata(&params.filter).is_ok();
+    let (mut filter, format) = if exists {
+        progress::load(&params.filter).map_err(|e| format!("Failed to load {}: {}", params.filter, e))?
+    } else {
+        let filter = new_filter(&params.filter, params.levels, params.array_size, params.num_hash_functions)?;
+        (filter, FileFormat::for_path(&params.filter))
+    };
+    let mut follower = FileFollower::open(&params.input, params.from_start)
+        .map_err(|e| format!("Failed to open {}: {}", params.input, e))?;
+
+    let save = |filter: &BloomFilter| -> Result<(), String> {
+        let tmp_path = format!("{}.tmp", params.filter);
+        filter
+            .save_as(&tmp_path, format)
+            .and_then(|()| Ok(fs::rename(&tmp_path, &params.filter)?))
+            .map_err(|e| format!("Failed to save {}: {}", params.filter, e))
+    };
+    let (mut items, mut new, mut saves) = (0, 0, 0);
+    let mut unsaved = !exists;
+    let mut last_save = Instant::now();
+    let mut last_line = Instant::now();
+    let mut batch = Vec::new();
+    loop {
+        batch.clear();
+        let read = follower
+            .read_new(&mut batch)
+            .map_err(|e| format!("Failed to read {}: {}", params.input, e))?;
+        if read > 0 {
+            items += read;
+            new += batch.iter().filter(|item| filter.insert(item)).count();
+            unsaved = true;
+            last_line = Instant::now();
+        }
+        if unsaved && last_save.elapsed() >= params.save_interval {
+            save(&filter)?;
+            saves += 1;
+            unsaved = false;
+            last_save = Instant::now();
+        }
+        if params.idle_timeout.is_some_and(|timeout| last_line.elapsed() >= timeout) {
+            break;
+        }
+        if read == 0 {
+            std::thread::sleep(params.poll_interval);
+        }
+    }
+    if unsaved {
+        save(&filter)?;
+        saves += 1;
+    }
+    Ok(Outcome::Follow {
+        input: params.input.clone(),
+        path: params.filter.clone(),
+        items,
+        new,
+        saves,
+    })
+}
+
+/// Parameters for `spellcheck`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpellcheckParams {
+    /// Word list, one word per line, or a filter saved from one.
+    pub dict: String,
+    /// Text to check.
+    pub input: String,
+    /// Target false-positive rate when building from a word list: the
+    /// share of misspellings that slip through as known words.
+    pub false_positive_rate: f64,
+    /// Where to save a filter built from a word list, so later runs can load it.
+    pub save: Option<String>,
+}
+
+/// A word of the checked text that is not in the dictionary.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Misspelling {
+    pub word: String,
+    /// 1-based line number.
+    pub line: usize,
+}
+
+/// Flags the words of a text that are not in a dictionary filter.
+///
+/// A word list is inserted lowercased into a filter sized for its length
+/// at `false_positive_rate`; a saved filter is used as is. Words are runs
+/// of letters and inner apostrophes, compared lowercased. A flagged word is
+/// certainly not in the dictionary; a misspelling goes unflagged with
+/// probability about `false_positive_rate`.
+pub fn spellcheck(params: &SpellcheckParams) -> Result<Outcome, String> {
+    info!("Spellchecking: {:?}", params);
+    check_false_positive_rate(params.false_positive_rate)?;
+    let bytes = fs::read(&params.dict).map_err(|e| format!("Failed to read {}: {}", params.dict, e))?;
+    let dictionary = if FileFormat::detect(&bytes).is_some() {
+        let (filter, _) = BloomFilter::load_auto_from_reader(bytes.as_slice())
+            .map_err(|e| format!("Failed to load {}: {}", params.dict, e))?;
+        filter
+    } else {
+        let text = String::from_utf8_lossy(&bytes);
+        let words: Vec<String> = text
+            .lines()
+            .map(|line| line.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let sizing = RebuildParams::for_capacity(words.len().max(1), params.false_positive_rate);
+        // Dictionary words are short and alike, which the default polynomial hash spreads poorly.
+        let mut filter = BloomFilter::builder()
+            .array_size(sizing.array_size.unwrap_or(1))
+            .hash_functions(sizing.num_hash_functions.unwrap_or(1))
+            .hash_algorithm(HashAlgorithm::Fnv1a)
+            .build()
+            .map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+        words.iter().for_each(|word| {
+            filter.insert(word);
+        });
+        if let Some(path) = &params.save {
+            progress::save(&filter, path, FileFormat::for_path(path))
+                .map_err(|e| format!("Failed to save BloomFilter: {}", e))?;
+        }
+        filter
+    };
+
+    let text = fs::read_to_string(&params.input).map_err(|e| format!("Failed to read {}: {}", params.input, e))?;
+    let levels = dictionary.levels().len();
+    let mut words = 0;
+    let mut misspelled = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        for word in line
+            .split(|c: char| !c.is_alphabetic() && c != '\'')
+            .map(|word| word.trim_matches('\''))
+            .filter(|word| !word.is_empty())
+        {
+            words += 1;
+            let word = word.to_lowercase();
+            if !dictionary.query(&word, levels) {
+                misspelled.push(Misspelling { word, line: number + 1 });
+            }
+        }
+    }
+    Ok(Outcome::Spellcheck {
+        dict: params.dict.clone(),
+        path: params.input.clone(),
+        words,
+        misspelled,
+        estimated_false_positive_rate: dictionary.estimated_false_positive_rate(),
+    })
+}
+
+/// Reads blocklists in any supported dialect and saves a filter of their hosts.
+pub fn build_blocklist(
+    output: &str,
+    lists: &[String],
+    false_positive_rate: f64,
+    format: Option<FileFormat>,
+) -> Result<Outcome, String> {
+    check_false_positive_rate(false_positive_rate)?;
+    let (hosts, skipped) = blocklist::read_lists(lists).map_err(|e| format!("Failed to read blocklists: {}", e))?;
+    let filter =
+        blocklist::build(&hosts, false_positive_rate).map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+    let format = format.unwrap_or_else(|| FileFormat::for_path(output));
+    progress::save(&filter, output, format).map_err(|e| format!("Failed to save BloomFilter: {}", e))?;
+    Ok(Outcome::BlocklistBuild {
+        path: output.to_string(),
+        format: format.to_string(),
+        lists: lists.len(),
+        hosts: hosts.len(),
+        skipped,
+        stats: filter.stats(),
+    })
+}
+
+/// One target looked up by `check_blocklist`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BlocklistLookup {
+    pub target: String,
+    /// `None` if the target is not a URL or host name.
+    pub host: Option<String>,
+    /// The blocked domain the host falls under, if any.
+    pub blocked_by: Option<String>,
+}
+
+/// Looks up URLs or hosts in a filter saved by `build_blocklist`; with no targets, reads them from stdin.
+pub fn check_blocklist(path: &str, targets: &[String]) -> Result<Outcome, String> {
+    let filter = load(path)?;
+    let mut lookups = Vec::new();
+    let mut check = |target: &str| {
+        let (host, blocked_by) = match blocklist::lookup(&filter, target) {
+            Ok((host, blocked_by)) => (Some(host), blocked_by),
+            Err(_) => (None, None),
+        };
+        lookups.push(BlocklistLookup {
+            target: target.to_string(),
+            host,
+            blocked_by,
+        });
+    };
+    if targets.is_empty() {
+        let mut stdin = source::open("-").map_err(|e| format!("Failed to read stdin: {}", e))?;
+        source::for_each_item(stdin.as_mut(), 1024, |target| check(target))
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+    } else {
+        targets.iter().for_each(|target| check(target));
+    }
+    Ok(Outcome::BlocklistCheck {
+        path: path.to_string(),
+        blocked: lookups.iter().filter(|lookup| lookup.blocked_by.is_some()).count(),
+        lookups,
+    })
+}
+
+/// Parameters for `build_password_filter`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasswordCorpusParams {
+    /// Breached passwords, one per line, or their hex digests.
+    pub corpus: String,
+    pub output: String,
+    /// Whether the corpus lines are hex digests rather than passwords.
+    pub hashed: bool,
+    pub digest: PasswordDigest,
+    pub false_positive_rate: f64,
+    /// `None` picks the format from the output file's extension.
+    pub format: Option<FileFormat>,
+}
+
+/// Builds a breached-password filter from a corpus and saves it.
+///
+/// Like `generate`, the corpus is read once to count its lines and once to
+/// insert them. Lines that are not valid entries (such as digests of the
+/// wrong length) are skipped and counted.
+pub fn build_password_filter(params: &PasswordCorpusParams) -> Result<Outcome, String> {
+    info!("Building password filter from {}", params.corpus);
+    check_false_positive_rate(params.false_positive_rate)?;
+    let mut source =
+        FileSource::open(&params.corpus).map_err(|e| format!("Failed to read {}: {}", params.corpus, e))?;
+    let lines = source.remaining().unwrap_or(0);
+    let mut passwords = BreachedPasswords::new(lines, params.false_positive_rate, params.digest)
+        .map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+    let format = match params.hashed {
+        true => CorpusFormat::Hex(params.digest),
+        false => CorpusFormat::Plain(params.digest),
+    };
+    let mut skipped = 0;
+    let bar = progress::items_bar(Some(lines as u64), "Inserting");
+    progress::for_each_item(&mut source, &bar, |entry| {
+        skipped += usize::from(!passwords.insert_entry(entry, format));
+    })
+    .map_err(|e| format!("Failed to insert from {}: {}", params.corpus, e))?;
+
+    let output_format = params.format.unwrap_or_else(|| FileFormat::for_path(&params.output));
+    progress::save(passwords.filter(), &params.output, output_format)
+        .map_err(|e| format!("Failed to save BloomFilter: {}", e))?;
+    Ok(Outcome::PasswordBuild {
+        path: params.output.clone(),
+        format: output_format.to_string(),
+        digest: params.digest.to_string(),
+        entries: lines - skipped,
+        skipped,
+        stats: passwords.filter().stats(),
+    })
+}
+
+/// Checks candidate passwords against a filter saved by `build_password_filter`.
+///
+/// Candidates are hashed here and never echoed back: the outcome only says,
+/// by position, which of them were found.
+pub fn check_passwords(path: &str, candidates: &[String]) -> Result<Outcome, String> {
+    let passwords = BreachedPasswords::from_filter(load(path)?).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+    let found: Vec<bool> = candidates.iter().map(|candidate| passwords.contains(candidate)).collect();
+    Ok(Outcome::PasswordCheck {
+        path: path.to_string(),
+        breached: found.iter().filter(|&&found| found).count(),
+        found,
+    })
+}
+
+/// Builds a filter of the k-mers in FASTA/FASTQ files, sized for their length, and saves it.
+///
+/// The files are read once to count the k-mers and once to insert them;
+/// files ending in `.gz` are decompressed on the fly.
+pub fn build_kmers(
+    output: &str,
+    inputs: &[String],
+    mode: &KmerMode,
+    false_positive_rate: f64,
+    format: Option<FileFormat>,
+) -> Result<Outcome, String> {
+    check_false_positive_rate(false_positive_rate)?;
+    let mut expected = 0;
+    for input in inputs {
+        for record in open_sequences(input)? {
+            let record = record.map_err(|e| format!("Failed to read {}: {}", input, e))?;
+            expected += mode.max_kmers(record.sequence.len());
+        }
+    }
+    let sizing = RebuildParams::for_capacity(expected.max(1), false_positive_rate);
+    let mut filter = BloomFilter::new(1, sizing.array_size.unwrap_or(1), sizing.num_hash_functions.unwrap_or(1))
+        .map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+    mode.prepare(&mut filter).map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+
+    let (mut sequences, mut kmers) = (0, 0);
+    for input in inputs {
+        let (records, inserted) = filter
+            .insert_kmers_from(open_sequences(input)?, mode)
+            .map_err(|e| format!("Failed to insert from {}: {}", input, e))?;
+        sequences += records;
+        kmers += inserted;
+    }
+    let format = format.unwrap_or_else(|| FileFormat::for_path(output));
+    progress::save(&filter, output, format).map_err(|e| format!("Failed to save BloomFilter: {}", e))?;
+    Ok(Outcome::KmerBuild {
+        path: output.to_string(),
+        format: format.to_string(),
+        k: mode.k(),
+        canonical: mode.canonical(),
+        sequences,
+        kmers,
+        stats: filter.stats(),
+    })
+}
+
+/// How many of one sequence's k-mers a k-mer filter contains.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct KmerHits {
+    pub id: String,
+    pub kmers: usize,
+    pub present: usize,
+}
+
+/// Reports, for each sequence in a FASTA/FASTQ file, how many of its k-mers a filter saved by `build_kmers` contains.
+pub fn query_kmers(path: &str, input: &str) -> Result<Outcome, String> {
+    let filter = load(path)?;
+    let mode = KmerMode::from_filter(&filter).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+    let mut records = Vec::new();
+    for record in open_sequences(input)? {
+        let record = record.map_err(|e| format!("Failed to read {}: {}", input, e))?;
+        let (kmers, present) = mode.query(&filter, &record.sequence);
+        records.push(KmerHits {
+            id: record.id,
+            kmers,
+            present,
+        });
+    }
+    Ok(Outcome::KmerQuery {
+        path: path.to_string(),
+        k: mode.k(),
+        records,
+    })
+}
+
+/// Builds a filter of the n-grams of whole documents, sized for them, and saves it.
+///
+/// Each file is one item, so word n-grams run across line breaks.
+pub fn build_ngrams(
+    output: &str,
+    inputs: &[String],
+    mode: &NgramMode,
+    false_positive_rate: f64,
+    format: Option<FileFormat>,
+) -> Result<Outcome, String> {
+    check_false_positive_rate(false_positive_rate)?;
+    let documents = read_documents(inputs)?;
+    let expected = documents.iter().map(|document| mode.ngrams(document).len()).sum::<usize>();
+    let sizing = RebuildParams::for_capacity(expected.max(1), false_positive_rate);
+    let mut filter = BloomFilter::builder()
+        .array_size(sizing.array_size.unwrap_or(1))
+        .hash_functions(sizing.num_hash_functions.unwrap_or(1))
+        .hash_algorithm(HashAlgorithm::Fnv1a)
+        .build()
+        .map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+    mode.prepare(&mut filter).map_err(|e| format!("Error creating BloomFilter: {}", e))?;
+    let ngrams = documents.iter().map(|document| filter.insert_ngrams(document, mode)).sum();
+
+    let format = format.unwrap_or_else(|| FileFormat::for_path(output));
+    progress::save(&filter, output, format).map_err(|e| format!("Failed to save BloomFilter: {}", e))?;
+    Ok(Outcome::NgramBuild {
+        path: output.to_string(),
+        format: format.to_string(),
+        n: mode.n(),
+        unit: mode.unit().to_string(),
+        documents: documents.len(),
+        ngrams,
+        stats: filter.stats(),
+    })
+}
+
+/// How much of one document an n-gram filter contains.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NgramContainment {
+    pub path: String,
+    pub ngrams: usize,
+    pub present: usize,
+    /// The share of the document's n-grams found, from 0.0 to 1.0.
+    pub fraction: f64,
+}
+
+/// Reports, for each document, the fraction of its n-grams a filter saved by `build_ngrams` contains.
+pub fn query_ngrams(path: &str, inputs: &[String]) -> Result<Outcome, String> {
+    let filter = load(path)?;
+    let mode = NgramMode::from_filter(&filter).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+    let documents = read_documents(inputs)?
+        .iter()
+        .zip(inputs)
+        .map(|(document, input)| {
+            let (ngrams, present) = mode.query(&filter, document);
+            NgramContainment {
+                path: input.clone(),
+                ngrams,
+                present,
+                fraction: filter.query_ngrams(document, &mode),
+            }
+        })
+        .collect();
+    Ok(Outcome::NgramQuery {
+        path: path.to_string(),
+        n: mode.n(),
+        unit: mode.unit().to_string(),
+        documents,
+    })
+}
+
+fn read_documents(paths: &[String]) -> Result<Vec<String>, String> {
+    paths
+        .iter()
+        .map(|path| fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e)))
+        .collect()
+}
+
+fn open_sequences(path: &str) -> Result<SequenceReader<Box<dyn BufRead>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let reader: Box<dyn BufRead> = if path.ends_with(".gz") {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+    Ok(SequenceReader::new(reader))
+}
+
+/// Unions saved filters into one and saves it, e.g. to combine shards built in parallel.
+///
+/// Every input must be compatible with the first and have as many levels;
+/// the merged filter keeps the first input's labels and metadata.
+pub fn merge(output: &str, inputs: &[String], format: Option<FileFormat>) -> Result<Outcome, String> {
+    let (first, rest) = inputs.split_first().ok_or("merge needs at least one input filter")?;
+    let mut merged = load(first)?;
+    for path in rest {
+        merged
+            .union_with(&load(path)?)
+            .map_err(|e| format!("Failed to merge {}: {}", path, e))?;
+    }
+
+    let format = format.unwrap_or_else(|| FileFormat::for_path(output));
+    progress::save(&merged, output, format).map_err(|e| format!("Failed to save BloomFilter: {}", e))?;
+    Ok(Outcome::Merge {
+        path: output.to_string(),
+        format: format.to_string(),
+        inputs: inputs.len(),
+        stats: merged.stats(),
+    })
+}
+
+/// Lists the filters in the store directory `dir`.
+pub fn list_filters(dir: &str) -> Result<Outcome, String> {
+    let names = open_store(dir)?
+        .list()
+        .map_err(|e| format!("Failed to list filters: {}", e))?;
+    Ok(Outcome::Filters { active: None, names })
+}
+
+/// Copies a filter within the store directory `dir`.
+pub fn copy_filter(dir: &str, from: &str, to: &str) -> Result<Outcome, String> {
+    let mut store = open_store(dir)?;
+    store
+        .copy(from, to)
+        .and_then(|()| store.flush())
+        .map_err(|e| format!("Failed to copy filter: {}", e))?;
+    Ok(Outcome::Copy {
+        from: from.to_string(),
+        to: to.to_string(),
+    })
+}
+
+/// Renames a filter within the store directory `dir`.
+pub fn rename_filter(dir: &str, from: &str, to: &str) -> Result<Outcome, String> {
+    open_store(dir)?
+        .rename(from, to)
+        .map_err(|e| format!("Failed to rename filter: {}", e))?;
+    Ok(Outcome::Rename {
+        from: from.to_string(),
+        to: to.to_string(),
+    })
+}
+
+fn open_store(dir: &str) -> Result<FilterStore, String> {
+    FilterStore::open(dir).map_err(|e| format!("Failed to open store {}: {}", dir, e))
+}
+
+fn load(path: &str) -> Result<BloomFilter, String> {
+    progress::load(path)
+        .map(|(filter, _)| filter)
+        .map_err(|e| format!("Failed to load {}: {}", path, e))
+}
+
+/// Creates the filter `what` names from CLI sizing, which must all be given.
+fn new_filter(
+    what: &str,
+    levels: Option<usize>,
+    array_size: Option<usize>,
+    hash_functions: Option<usize>,
+) -> Result<BloomFilter, String> {
+    let (Some(levels), Some(array_size), Some(hash_functions)) = (levels, array_size, hash_functions) else {
+        return Err(format!(
+            "'{}' does not exist; pass --levels, --array-size and --hash-functions to create it",
+            what
+        ));
+    };
+    BloomFilter::new(levels, array_size, hash_functions).map_err(|e| format!("Error creating BloomFilter: {}", e))
+}
+
+fn load_any(path: &str) -> Result<AnyFilter, String> {
+    AnyFilter::load_from_file(path).map_err(|e| format!("Failed to load {}: {}", path, e))
+}
+
+fn check_false_positive_rate(false_positive_rate: f64) -> Result<(), String> {
+    if false_positive_rate > 0.0 && false_positive_rate < 1.0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "False-positive rate must be between 0 and 1 (exclusive), got {}",
+            false_positive_rate
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_measures_close_to_theory() {
+        let params = BenchParams {
+            items: 2000,
+            false_positive_rate: 0.05,
+            array_size: None,
+            num_hash_functions: None,
+            seed: 1,
+        };
+        match bench(&params).unwrap() {
+            Outcome::Bench {
+                measured_false_positive_rate,
+                theoretical_false_positive_rate,
+                ..
+            } => {
+                assert!(measured_false_positive_rate < theoretical_false_positive_rate * 3.0);
+                assert!(theoretical_false_positive_rate > 0.01 && theoretical_false_positive_rate < 0.1);
+            }
+            other => panic!("unexpected outcome {:?}", other),
+        }
+        assert!(bench(&BenchParams { array_size: Some(0), ..params }).is_err());
+    }
+
+    #[test]
+    fn test_generate_sizes_for_input() {
+        let input = std::env::temp_dir().join("test_generate_input.txt");
+        let output = std::env::temp_dir().join("test_generate_output.bin");
+        let words: Vec<String> = (0..500).map(|i| format!("word{}", i)).collect();
+        std::fs::write(&input, words.join("\n")).unwrap();
+        let params = GenerateParams {
+            input: input.to_str().unwrap().to_string(),
+            output: output.to_str().unwrap().to_string(),
+            false_positive_rate: 0.001,
+            format: None,
+        };
+
+        match generate(&params).unwrap() {
+            Outcome::Generate { items, format, stats, .. } => {
+                assert_eq!(items, 500);
+                assert_eq!(format, "binary");
+                assert_eq!(stats.array_size, math::optimal_bits(500, 0.001));
+                assert!(stats.estimated_false_positive_rate < 0.002);
+            }
+            other => panic!("unexpected outcome {:?}", other),
+        }
+        let (loaded, _) = BloomFilter::load_auto(&params.output).unwrap();
+        assert!(words.iter().all(|word| loaded.query(word, 1)));
+        assert!(generate(&GenerateParams { false_positive_rate: 1.5, ..params.clone() }).is_err());
+
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_follow_inserts_appended_lines() {
+        let input = std::env::temp_dir().join("test_follow_input.log");
+        let output = std::env::temp_dir().join("test_follow_output.bin");
+        std::fs::write(&input, "before\n").unwrap();
+        let params = FollowParams {
+            input: input.to_str().unwrap().to_string(),
+            filter: output.to_str().unwrap().to_string(),
+            levels: Some(1),
+            array_size: Some(1000),
+            num_hash_functions: Some(3),
+            from_start: false,
+            save_interval: Duration::ZERO,
+            poll_interval: Duration::from_millis(10),
+            idle_timeout: Some(Duration::from_millis(300)),
+        };
+
+        let path = input.clone();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+            std::io::Write::write_all(&mut file, b"alpha\nbeta\n").unwrap();
+        });
+        match follow(&params).unwrap() {
+            Outcome::Follow { items, new, saves, .. } => assert!(items == 2 && new == 2 && saves >= 1),
+            other => panic!("unexpected outcome {:?}", other),
+        }
+        writer.join().unwrap();
+        let (loaded, _) = BloomFilter::load_auto(&params.filter).unwrap();
+        assert!(loaded.query("alpha", 1) && loaded.query("beta", 1));
+        assert!(!loaded.query("before", 1));
+
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_spellcheck_flags_unknown_words() {
+        let dict = std::env::temp_dir().join("test_spellcheck_words.txt");
+        let input = std::env::temp_dir().join("test_spellcheck_input.txt");
+        let saved = std::env::temp_dir().join("test_spellcheck_dict.bin");
+        std::fs::write(&dict, "the\nquick\nbrown\nfox\ndon't\n").unwrap();
+        std::fs::write(&input, "The quikc brown fox.\n'Don't' 42 teh\n").unwrap();
+        let params = SpellcheckParams {
+            dict: dict.to_str().unwrap().to_string(),
+            input: input.to_str().unwrap().to_string(),
+            false_positive_rate: 0.001,
+            save: Some(saved.to_str().unwrap().to_string()),
+        };
+        let expected = vec![
+            Misspelling { word: "quikc".to_string(), line: 1 },
+            Misspelling { word: "teh".to_string(), line: 2 },
+        ];
+        for params in [params.clone(), SpellcheckParams { dict: params.save.clone().unwrap(), save: None, ..params }] {
+            match spellcheck(&params).unwrap() {
+                Outcome::Spellcheck { words, misspelled, .. } => {
+                    assert_eq!(words, 6);
+                    assert_eq!(misspelled, expected);
+                }
+                other => panic!("unexpected outcome {:?}", other),
+            }
+        }
+
+        for path in [dict, input, saved] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,243 @@
+// src/compare.rs
+
+use serde::Serialize;
+use std::fmt;
+use tracing::info;
+
+use crate::bloom_filter::{unix_now, BloomFilter, BloomFilterError};
+use crate::math;
+
+/// Bit-level similarity of two compatible filters.
+///
+/// Each filter is taken as the union of its unexpired levels, i.e. the set of
+/// items a query across all levels would report. Item counts are estimated
+/// from the number of set bits, so they include the effect of collisions.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FilterComparison {
+    pub bits_set_first: usize,
+    pub bits_set_second: usize,
+    pub bits_set_both: usize,
+    pub bits_set_either: usize,
+    /// Share of set bits that are set in both filters (1 when neither has any).
+    pub bit_overlap: f64,
+    pub estimated_items_first: f64,
+    pub estimated_items_second: f64,
+    pub estimated_intersection: f64,
+    /// Estimated intersection over estimated union of the two item sets.
+    pub estimated_jaccard: f64,
+}
+
+/// Bits set in one filter but not the other, level by level.
+///
+/// Two builds of the same filter from the same items diff to zero; bits
+/// only in one of them point to items inserted only there (or, for bits only
+/// in the older filter, to items the newer build dropped).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FilterDiff {
+    pub levels: Vec<LevelDiff>,
+    /// Totals over all levels.
+    pub only_self: usize,
+    pub only_other: usize,
+    pub shared: usize,
+}
+
+/// Bit differences within one pair of matching levels.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LevelDiff {
+    pub level: usize,
+    pub only_self: usize,
+    pub only_other: usize,
+    pub shared: usize,
+}
+
+impl FilterDiff {
+    /// Returns true if both filters have exactly the same bits set.
+    pub fn is_identical(&self) -> bool {
+        self.only_self == 0 && self.only_other == 0
+    }
+}
+
+impl BloomFilter {
+    /// Counts, level by level, the bits set only in this filter, only in `other`, and in both.
+    ///
+    /// Unlike `compare`, levels are diffed separately and expired levels are
+    /// included, so the result reflects the saved bits exactly. Fails with
+    /// `IncompatibleFilters` unless `check_compatible` passes and the level
+    /// counts match.
+    pub fn diff(&self, other: &BloomFilter) -> Result<FilterDiff, BloomFilterError> {
+        self.check_same_levels(other)?;
+        info!("Diffing BloomFilters of {} levels", self.levels.len());
+        let levels: Vec<LevelDiff> = self
+            .levels
+            .iter()
+            .zip(&other.levels)
+            .enumerate()
+            .map(|(level, (a, b))| {
+                let mut diff = LevelDiff {
+                    level,
+                    only_self: 0,
+                    only_other: 0,
+                    shared: 0,
+                };
+                for index in 0..self.array_size {
+                    match (a.bit_array.get(index), b.bit_array.get(index)) {
+                        (true, true) => diff.shared += 1,
+                        (true, false) => diff.only_self += 1,
+                        (false, true) => diff.only_other += 1,
+                        (false, false) => {}
+                    }
+                }
+                diff
+            })
+            .collect();
+        Ok(FilterDiff {
+            only_self: levels.iter().map(|level| level.only_self).sum(),
+            only_other: levels.iter().map(|level| level.only_other).sum(),
+            shared: levels.iter().map(|level| level.shared).sum(),
+            levels,
+        })
+    }
+
+    /// Compares the bits of two filters and estimates how much their item sets overlap.
+    ///
+    /// Fails with `IncompatibleFilters` unless `check_compatible` passes.
+    pub fn compare(&self, other: &BloomFilter) -> Result<FilterComparison, BloomFilterError> {
+        self.check_compatible(other)?;
+        info!("Comparing BloomFilters of {} bits", self.array_size);
+        let first = self.combined_bits();
+        let second = other.combined_bits();
+        let count = |f: fn(bool, bool) -> bool| first.iter().zip(&second).filter(|(&a, &b)| f(a, b)).count();
+        let bits_set_first = count(|a, _| a);
+        let bits_set_second = count(|_, b| b);
+        let bits_set_both = count(|a, b| a && b);
+        let bits_set_either = count(|a, b| a || b);
+
+        let (m, k) = (self.array_size, self.hash_functions.len());
+        let estimated_items_first = math::estimated_items(m, k, bits_set_first);
+        let estimated_items_second = math::estimated_items(m, k, bits_set_second);
+        let estimated_union = math::estimated_items(m, k, bits_set_either);
+        let estimated_intersection = (estimated_items_first + estimated_items_second - estimated_union).max(0.0);
+        Ok(FilterComparison {
+            bits_set_first,
+            bits_set_second,
+            bits_set_both,
+            bits_set_either,
+            bit_overlap: ratio(bits_set_both as f64, bits_set_either as f64),
+            estimated_items_first,
+            estimated_items_second,
+            estimated_intersection,
+            estimated_jaccard: ratio(estimated_intersection, estimated_union),
+        })
+    }
+
+    /// ORs the unexpired levels into one bit array.
+    fn combined_bits(&self) -> Vec<bool> {
+        let now = unix_now();
+        let mut bits = vec![false; self.array_size];
+        for level in self.levels.iter().filter(|level| !level.is_expired_at(now)) {
+            for index in level.bit_array.iter_ones() {
+                bits[index] = true;
+            }
+        }
+        bits
+    }
+}
+
+/// `part / whole`, treating two empty sets as identical.
+fn ratio(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 && whole.is_finite() {
+        part / whole
+    } else {
+        1.0
+    }
+}
+
+impl fmt::Display for FilterComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bits set: {} and {}, {} in both, {} in either (bit overlap {:.2}%)\n\
+             estimated items: {:.0} and {:.0}, intersection {:.0}, Jaccard similarity {:.4}",
+            self.bits_set_first,
+            self.bits_set_second,
+            self.bits_set_both,
+            self.bits_set_either,
+            self.bit_overlap * 100.0,
+            self.estimated_items_first,
+            self.estimated_items_second,
+            self.estimated_intersection,
+            self.estimated_jaccard
+        )
+    }
+}
+
+impl fmt::Display for FilterDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bits only in the first, {} only in the second, {} shared",
+            self.only_self, self.only_other, self.shared
+        )?;
+        if self.levels.len() > 1 {
+            for level in &self.levels {
+                write!(
+                    f,
+                    "\n  level {}: {} only in the first, {} only in the second, {} shared",
+                    level.level + 1,
+                    level.only_self,
+                    level.only_other,
+                    level.shared
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_estimates_overlap() {
+        let mut a = BloomFilter::new(2, 20_000, 4).unwrap();
+        let mut b = BloomFilter::new(1, 20_000, 4).unwrap();
+        for i in 0..1000 {
+            a.insert(&format!("item{}", i));
+        }
+        for i in 500..1500 {
+            b.insert(&format!("item{}", i));
+        }
+
+        let comparison = a.compare(&b).unwrap();
+        assert!(comparison.bits_set_both <= comparison.bits_set_first.min(comparison.bits_set_second));
+        assert!((comparison.estimated_items_first - 1000.0).abs() < 50.0);
+        assert!((comparison.estimated_intersection - 500.0).abs() < 75.0);
+        assert!((comparison.estimated_jaccard - 1.0 / 3.0).abs() < 0.05);
+        assert_eq!(a.compare(&a).unwrap().bit_overlap, 1.0);
+
+        let other_size = BloomFilter::new(1, 10_000, 4).unwrap();
+        assert!(matches!(a.compare(&other_size), Err(BloomFilterError::IncompatibleFilters(_))));
+        let seeded = BloomFilter::builder().array_size(20_000).hash_functions(4).seed(3).build().unwrap();
+        assert!(a.check_compatible(&seeded).is_err());
+    }
+
+    #[test]
+    fn test_diff_counts_bits_per_level() {
+        let mut a = BloomFilter::new(2, 1000, 3).unwrap();
+        let mut b = BloomFilter::new(2, 1000, 3).unwrap();
+        a.insert("shared");
+        b.insert("shared");
+        assert!(a.diff(&b).unwrap().is_identical());
+
+        let positions = b.positions(b"nightly");
+        b.levels[1].insert_positions(&positions);
+        let diff = a.diff(&b).unwrap();
+        assert!(!diff.is_identical());
+        assert_eq!((diff.levels[0].only_other, diff.levels[0].shared), (0, a.levels[0].count_ones()));
+        assert_eq!(diff.levels[1].only_other, b.levels[1].count_ones() - a.levels[1].count_ones());
+        assert_eq!((diff.only_self, diff.only_other), (0, diff.levels[1].only_other));
+        assert_eq!(b.diff(&a).unwrap().only_self, diff.only_other);
+        assert!(a.diff(&BloomFilter::new(1, 1000, 3).unwrap()).is_err());
+    }
+}
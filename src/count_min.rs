@@ -0,0 +1,228 @@
+// src/count_min.rs
+
+use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher13;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter};
+use tracing::{error, info};
+
+use crate::bloom_filter::{splitmix64, BloomFilterError};
+use crate::hashing::double_hash;
+
+/// A Count-Min sketch estimating how often each item has been seen.
+///
+/// `depth` rows of `width` counters; each item increments one counter per
+/// row and its estimate is the smallest of them. Estimates never undercount,
+/// and overcount by at most `e / width` of the total with probability
+/// `1 - e^-depth` (see `for_error`). With conservative update, an increment
+/// only raises counters up to the item's new estimate, which leaves far less
+/// overcount on skewed streams. Conservative sketches can still be merged;
+/// the result never undercounts but loses part of that advantage.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    conservative: bool,
+    /// Row-major counters, `depth * width` of them.
+    counters: Vec<u64>,
+    total: u64,
+}
+
+impl CountMinSketch {
+    /// Creates a sketch with `depth` rows of `width` counters.
+    pub fn new(width: usize, depth: usize) -> Result<Self, BloomFilterError> {
+        info!("Creating CountMinSketch: width={}, depth={}", width, depth);
+        if width == 0 {
+            error!("Requested a zero-width sketch");
+            return Err(BloomFilterError::ZeroArraySize);
+        }
+        if depth == 0 {
+            error!("Requested a zero-depth sketch");
+            return Err(BloomFilterError::ZeroHashFunctions);
+        }
+        Ok(CountMinSketch {
+            width,
+            depth,
+            seed: 0,
+            conservative: false,
+            counters: vec![0; width * depth],
+            total: 0,
+        })
+    }
+
+    /// Creates a sketch whose estimates exceed the true count by at most
+    /// `epsilon` times the total, except with probability `delta`.
+    ///
+    /// `width = ceil(e / epsilon)` and `depth = ceil(ln(1 / delta))`.
+    pub fn for_error(epsilon: f64, delta: f64) -> Result<Self, BloomFilterError> {
+        let width = if epsilon > 0.0 { (std::f64::consts::E / epsilon).ceil() as usize } else { 0 };
+        let depth = if delta > 0.0 && delta < 1.0 { (1.0 / delta).ln().ceil().max(1.0) as usize } else { 0 };
+        Self::new(width, depth)
+    }
+
+    /// Enables or disables conservative update for later increments.
+    pub fn set_conservative_update(&mut self, conservative: bool) {
+        self.conservative = conservative;
+    }
+
+    /// Sets the seed perturbing every hash, before anything is counted; only sketches with the same seed can be merged.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Counts one occurrence of an item.
+    pub fn increment(&mut self, item: &str) {
+        self.add_bytes(item.as_bytes(), 1);
+    }
+
+    /// Counts `count` occurrences of an item.
+    pub fn add(&mut self, item: &str, count: u64) {
+        self.add_bytes(item.as_bytes(), count);
+    }
+
+    /// Counts `count` occurrences of a binary key.
+    pub fn add_bytes(&mut self, item: &[u8], count: u64) {
+        let cells = self.cells(item);
+        if self.conservative {
+            let target = self.estimate_cells(&cells).saturating_add(count);
+            for cell in cells {
+                self.counters[cell] = self.counters[cell].max(target);
+            }
+        } else {
+            for cell in cells {
+                self.counters[cell] = self.counters[cell].saturating_add(count);
+            }
+        }
+        self.total = self.total.saturating_add(count);
+    }
+
+    /// Estimates how often an item was counted; never less than the true count.
+    pub fn estimate(&self, item: &str) -> u64 {
+        self.estimate_bytes(item.as_bytes())
+    }
+
+    /// Estimates how often a binary key was counted.
+    pub fn estimate_bytes(&self, item: &[u8]) -> u64 {
+        self.estimate_cells(&self.cells(item))
+    }
+
+    /// Returns the sum of all counts added.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the number of counters per row.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Adds another sketch's counts to this one, as if both streams had been counted here.
+    pub fn merge(&mut self, other: &CountMinSketch) -> Result<(), BloomFilterError> {
+        if self.width != other.width || self.depth != other.depth || self.seed != other.seed {
+            return Err(BloomFilterError::IncompatibleFilters(format!(
+                "{}x{} sketch with seed {} vs {}x{} with seed {}",
+                self.depth, self.width, self.seed, other.depth, other.width, other.seed
+            )));
+        }
+        for (counter, theirs) in self.counters.iter_mut().zip(&other.counters) {
+            *counter = counter.saturating_add(*theirs);
+        }
+        self.total = self.total.saturating_add(other.total);
+        Ok(())
+    }
+
+    /// Resets every counter to zero.
+    pub fn clear(&mut self) {
+        self.counters.iter_mut().for_each(|counter| *counter = 0);
+        self.total = 0;
+    }
+
+    /// Saves the sketch as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving CountMinSketch to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a sketch saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading CountMinSketch from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let sketch: Self = serde_json::from_reader(reader)?;
+        if sketch.width == 0 || sketch.depth == 0 || Some(sketch.counters.len()) != sketch.width.checked_mul(sketch.depth) {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "sketch has {} counters, expected {} rows of {}",
+                sketch.counters.len(),
+                sketch.depth,
+                sketch.width
+            )));
+        }
+        Ok(sketch)
+    }
+
+    /// Index of the item's counter in each row.
+    fn cells(&self, item: &[u8]) -> Vec<usize> {
+        let mut hasher = SipHasher13::new_with_keys(self.seed, 0x434d_5348_4b45_5443);
+        hasher.write(item);
+        let h1 = hasher.finish();
+        double_hash(h1, splitmix64(h1), self.depth, self.width)
+            .into_iter()
+            .enumerate()
+            .map(|(row, column)| row * self.width + column)
+            .collect()
+    }
+
+    fn estimate_cells(&self, cells: &[usize]) -> u64 {
+        cells.iter().map(|&cell| self.counters[cell]).min().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_bound_true_counts() {
+        let mut plain = CountMinSketch::for_error(0.01, 0.01).unwrap();
+        assert_eq!((plain.width(), plain.depth()), (272, 5));
+        let mut conservative = plain.clone();
+        conservative.set_conservative_update(true);
+        for i in 0..2000u64 {
+            let item = format!("item-{}", i % 100);
+            plain.increment(&item);
+            conservative.add(&item, 1);
+        }
+        plain.add("heavy", 500);
+        conservative.add("heavy", 500);
+
+        for i in 0..100 {
+            let item = format!("item-{}", i);
+            let (p, c) = (plain.estimate(&item), conservative.estimate(&item));
+            assert!(c >= 20 && c <= p, "item {}: plain {}, conservative {}", i, p, c);
+            assert!(p <= 20 + (0.01 * plain.total() as f64) as u64);
+        }
+        assert!(plain.estimate("heavy") >= 500);
+
+        let mut merged = plain.clone();
+        merged.merge(&plain).unwrap();
+        assert_eq!(merged.total(), 2 * plain.total());
+        assert!(merged.estimate("heavy") >= 1000);
+        assert!(merged.merge(&CountMinSketch::new(10, 5).unwrap()).is_err());
+
+        let path = std::env::temp_dir().join("test_bloom_count_min.json");
+        let path = path.to_str().unwrap();
+        conservative.save_to_file(path).unwrap();
+        assert_eq!(CountMinSketch::load_from_file(path).unwrap(), conservative);
+        std::fs::remove_file(path).unwrap();
+    }
+}
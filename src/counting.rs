@@ -0,0 +1,287 @@
+// src/counting.rs
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use tracing::{info, warn};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::builder::BloomFilterBuilder;
+
+/// Bits per counter in a `CountingBloomFilter`.
+///
+/// Wider counters take more memory but saturate later. A saturated counter
+/// sticks at its maximum and is never decremented, so removals cannot clear
+/// its bit: the filter keeps answering correctly for present items but may
+/// hold on to removed ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CounterWidth {
+    /// Counts up to 3.
+    Two,
+    /// Counts up to 15; enough for almost all workloads.
+    #[default]
+    Four,
+    /// Counts up to 255.
+    Eight,
+}
+
+impl CounterWidth {
+    /// Returns the number of bits per counter.
+    pub fn bits(self) -> usize {
+        match self {
+            CounterWidth::Two => 2,
+            CounterWidth::Four => 4,
+            CounterWidth::Eight => 8,
+        }
+    }
+
+    /// Returns the largest value a counter can hold.
+    pub fn max_count(self) -> u8 {
+        ((1u16 << self.bits()) - 1) as u8
+    }
+
+    fn per_word(self) -> usize {
+        64 / self.bits()
+    }
+}
+
+/// A Bloom filter that supports removal by keeping a small counter per bit.
+///
+/// Each position's counter is incremented on insert and decremented on
+/// remove, and the bit is cleared when its counter reaches zero. Counters
+/// saturate instead of overflowing; each increment lost to saturation is
+/// counted in `saturation_events`.
+#[derive(Serialize, Deserialize)]
+pub struct CountingBloomFilter {
+    filter: BloomFilter,
+    width: CounterWidth,
+    counters: Vec<u64>,
+    #[serde(default)]
+    saturation_events: u64,
+}
+
+impl CountingBloomFilter {
+    /// Creates a counting filter with `array_size` counters of the given width.
+    pub fn new(array_size: usize, num_hash_functions: usize, width: CounterWidth) -> Result<Self, BloomFilterError> {
+        BloomFilter::builder()
+            .array_size(array_size)
+            .hash_functions(num_hash_functions)
+            .counter_width(width)
+            .build_counting()
+    }
+
+    pub(crate) fn from_filter(filter: BloomFilter, width: CounterWidth) -> Self {
+        info!("Creating CountingBloomFilter: array_size={}, width={:?}", filter.array_size(), width);
+        let counters = vec![0; filter.array_size().div_ceil(width.per_word())];
+        CountingBloomFilter {
+            filter,
+            width,
+            counters,
+            saturation_events: 0,
+        }
+    }
+
+    /// Inserts an item, returning `true` if any bit was newly set.
+    pub fn insert(&mut self, item: &str) -> bool {
+        self.insert_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    /// Inserts a binary key, returning `true` if any bit was newly set.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        let positions = self.filter.positions(item);
+        let max = self.width.max_count();
+        for &position in &positions {
+            let count = self.counter(position);
+            if count == max {
+                self.saturation_events += 1;
+            } else {
+                self.set_counter(position, count + 1);
+                if count + 1 == max {
+                    warn!("Counter {} saturated", position);
+                }
+            }
+        }
+        self.filter.levels[0].insert_positions(&positions)
+    }
+
+    /// Removes an item, returning `false` (and changing nothing) if it is not present.
+    ///
+    /// Removing an item that was never inserted but matches as a false
+    /// positive decrements other items' counters and can make them vanish,
+    /// so only remove items known to be in the filter.
+    pub fn remove(&mut self, item: &str) -> bool {
+        self.remove_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    /// Removes a binary key, returning `false` (and changing nothing) if it is not present.
+    pub fn remove_bytes(&mut self, item: &[u8]) -> bool {
+        let positions = self.filter.positions(item);
+        if !self.filter.levels[0].contains_positions(&positions) {
+            return false;
+        }
+        let max = self.width.max_count();
+        for &position in &positions {
+            let count = self.counter(position);
+            // A position probed twice by one item may already be at zero.
+            if count == max || count == 0 {
+                continue;
+            }
+            self.set_counter(position, count - 1);
+            if count == 1 {
+                self.filter.levels[0].clear_position(position);
+            }
+        }
+        let level = &mut self.filter.levels[0];
+        level.item_count = level.item_count.saturating_sub(1);
+        true
+    }
+
+    /// Checks whether the item may be present.
+    pub fn query(&self, item: &str) -> bool {
+        self.query_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    /// Checks whether the binary key may be present.
+    pub fn query_bytes(&self, item: &[u8]) -> bool {
+        self.filter.query_bytes(item, 1)
+    }
+
+    /// Returns an upper bound on how many times the item was inserted: its smallest counter.
+    pub fn count(&self, item: &str) -> u8 {
+        let positions = self.filter.positions(self.filter.normalize(item).as_bytes());
+        positions.iter().map(|&position| self.counter(position)).min().unwrap_or(0)
+    }
+
+    /// Returns the number of increments lost because a counter was already saturated.
+    pub fn saturation_events(&self) -> u64 {
+        self.saturation_events
+    }
+
+    /// Returns the number of counters stuck at their maximum.
+    pub fn saturated_counters(&self) -> usize {
+        let max = self.width.max_count();
+        (0..self.filter.array_size()).filter(|&position| self.counter(position) == max).count()
+    }
+
+    /// Returns the counter width.
+    pub fn counter_width(&self) -> CounterWidth {
+        self.width
+    }
+
+    /// Returns the number of bytes used by the counters.
+    pub fn counter_bytes(&self) -> usize {
+        self.counters.len() * std::mem::size_of::<u64>()
+    }
+
+    /// Returns the underlying filter, whose bits are set exactly where counters are non-zero.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+
+    /// Saves the counting filter, including its counters, as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving CountingBloomFilter to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a counting filter saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading CountingBloomFilter from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let counting: Self = serde_json::from_reader(reader)?;
+        counting.validate()?;
+        Ok(counting)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), BloomFilterError> {
+        self.filter.validate()?;
+        let expected = self.filter.array_size().div_ceil(self.width.per_word());
+        if self.filter.levels().len() != 1 || self.counters.len() != expected {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "counting filter needs one level and {} counter words, found {} levels and {} words",
+                expected,
+                self.filter.levels().len(),
+                self.counters.len()
+            )));
+        }
+        Ok(())
+    }
+
+    fn counter(&self, position: usize) -> u8 {
+        let (word, shift) = self.locate(position);
+        ((self.counters[word] >> shift) & self.width.max_count() as u64) as u8
+    }
+
+    fn set_counter(&mut self, position: usize, count: u8) {
+        let (word, shift) = self.locate(position);
+        let mask = (self.width.max_count() as u64) << shift;
+        self.counters[word] = (self.counters[word] & !mask) | ((count as u64) << shift);
+    }
+
+    fn locate(&self, position: usize) -> (usize, usize) {
+        let per_word = self.width.per_word();
+        (position / per_word, (position % per_word) * self.width.bits())
+    }
+}
+
+impl BloomFilterBuilder {
+    /// Creates a counting filter from the configuration, with one level.
+    ///
+    /// The level count and insert mode are ignored; the counter width is set
+    /// with `counter_width`.
+    pub fn build_counting(self) -> Result<CountingBloomFilter, BloomFilterError> {
+        let width = self.counter_width;
+        let filter = self.levels(1).build()?;
+        Ok(CountingBloomFilter::from_filter(filter, width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_widths_saturate_and_remove() {
+        let mut two = CountingBloomFilter::new(1000, 3, CounterWidth::Two).unwrap();
+        for _ in 0..5 {
+            two.insert("hot");
+        }
+        assert_eq!(two.count("hot"), 3);
+        assert!(two.saturation_events() >= 6);
+        assert!(two.saturated_counters() >= 1);
+        // Saturated counters never drop, so the item cannot be removed.
+        for _ in 0..5 {
+            two.remove("hot");
+        }
+        assert!(two.query("hot"));
+
+        let mut eight = BloomFilter::builder()
+            .array_size(1000)
+            .counter_width(CounterWidth::Eight)
+            .build_counting()
+            .unwrap();
+        assert!(eight.counter_bytes() > two.counter_bytes());
+        eight.insert("alpha");
+        eight.insert("alpha");
+        eight.insert("beta");
+        assert_eq!(eight.count("alpha"), 2);
+        assert!(eight.remove("alpha"));
+        assert!(eight.query("alpha"));
+        assert!(eight.remove("alpha"));
+        assert!(!eight.query("alpha"));
+        assert!(eight.query("beta"));
+        assert!(!eight.remove("gamma"));
+        assert_eq!(eight.saturation_events(), 0);
+
+        let path = std::env::temp_dir().join("test_bloom_counting.json");
+        let path = path.to_str().unwrap();
+        eight.save_to_file(path).unwrap();
+        let mut loaded = CountingBloomFilter::load_from_file(path).unwrap();
+        assert_eq!(loaded.counter_width(), CounterWidth::Eight);
+        assert!(loaded.remove("beta"));
+        assert!(!loaded.query("beta"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+// src/daemon.rs
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use tracing::{error, info};
+
+use crate::bloom_filter::BloomFilterError;
+use crate::repl::{self, Command, Outcome};
+use crate::store::FilterStore;
+
+/// Serves a store's filters over a Unix socket, keeping them loaded between requests.
+///
+/// Clients send REPL commands (`insert`, `query`, `use`, ...) one per line
+/// and get one single-line JSON response per command, as `--json` prints
+/// them. Each connection starts on the default filter and can switch with
+/// `use` without affecting other clients. `exit` closes the connection;
+/// `shutdown` saves the store and stops the daemon.
+pub struct Daemon {
+    listener: UnixListener,
+    path: PathBuf,
+    store: Arc<Mutex<FilterStore>>,
+    default_filter: String,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl Daemon {
+    /// Listens on `socket_path`, replacing a stale socket file left by a daemon that is no longer running.
+    pub fn bind(socket_path: &str, store: FilterStore, default_filter: &str) -> Result<Self, BloomFilterError> {
+        info!("Binding daemon to {}", socket_path);
+        if !store.contains(default_filter) {
+            return Err(BloomFilterError::FilterNotFound(default_filter.to_string()));
+        }
+        let path = Path::new(socket_path);
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                error!("Another daemon is listening on {}", socket_path);
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is in use", socket_path)).into());
+            }
+            std::fs::remove_file(path)?;
+        }
+        Ok(Daemon {
+            listener: UnixListener::bind(path)?,
+            path: path.to_path_buf(),
+            store: Arc::new(Mutex::new(store)),
+            default_filter: default_filter.to_string(),
+            shutdown: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Accepts clients until one sends `shutdown`, then saves the store and removes the socket.
+    pub fn run(self) -> Result<(), BloomFilterError> {
+        info!("Daemon listening on {}", self.path.display());
+        for stream in self.listener.incoming() {
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("Failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            let client = Client {
+                store: Arc::clone(&self.store),
+                active: self.default_filter.clone(),
+                shutdown: Arc::clone(&self.shutdown),
+                path: self.path.clone(),
+            };
+            thread::spawn(move || {
+                if let Err(e) = client.serve(stream) {
+                    error!("Connection failed: {}", e);
+                }
+            });
+        }
+        std::fs::remove_file(&self.path)?;
+        let result = lock(&self.store).flush();
+        result
+    }
+}
+
+/// One connection's state: the shared store and the filter this client acts on.
+struct Client {
+    store: Arc<Mutex<FilterStore>>,
+    active: String,
+    shutdown: Arc<AtomicBool>,
+    path: PathBuf,
+}
+
+impl Client {
+    fn serve(mut self, stream: UnixStream) -> io::Result<()> {
+        info!("Client connected");
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let result = if line.trim() == "shutdown" {
+                self.shutdown.store(true, Ordering::SeqCst);
+                // Wake the accept loop so it sees the flag.
+                let _ = UnixStream::connect(&self.path);
+                Ok(Outcome::Shutdown)
+            } else {
+                match line.parse::<Command>() {
+                    Ok(command) => match repl::execute_in(&mut lock(&self.store), &mut self.active, command) {
+                        Some(result) => result,
+                        None => break,
+                    },
+                    Err(e) => Err(e),
+                }
+            };
+            writeln!(writer, "{}", repl::render(&result, true))?;
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+        info!("Client disconnected");
+        Ok(())
+    }
+}
+
+// A command that panics has either finished with the filter or left some of
+// an item's bits set, so a poisoned store is still safe to serve.
+fn lock(store: &Mutex<FilterStore>) -> MutexGuard<'_, FilterStore> {
+    store.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bloom_filter::BloomFilter;
+
+    #[test]
+    fn test_clients_share_resident_filters() {
+        let mut store = FilterStore::in_memory();
+        store.create("default", BloomFilter::new(1, 1000, 3).unwrap()).unwrap();
+        store.create("other", BloomFilter::new(1, 1000, 3).unwrap()).unwrap();
+        let path = std::env::temp_dir().join("test_bloom_daemon.sock");
+        let path = path.to_str().unwrap();
+        let daemon = Daemon::bind(path, store, "default").unwrap();
+        let handle = thread::spawn(move || daemon.run());
+
+        let request = |lines: &str| -> Vec<String> {
+            let mut stream = UnixStream::connect(path).unwrap();
+            stream.write_all(lines.as_bytes()).unwrap();
+            stream.shutdown(std::net::Shutdown::Write).unwrap();
+            BufReader::new(stream).lines().map(Result::unwrap).collect()
+        };
+        let first = request("insert alpha\nuse other\nquery alpha\nbogus\n");
+        assert!(first[0].contains(r#""command":"insert""#));
+        assert!(first[2].contains(r#""present":false"#));
+        assert!(first[3].contains(r#""ok":false"#));
+        // A new connection starts on the default filter, where alpha is resident.
+        let second = request("query alpha\nshutdown\n");
+        assert!(second[0].contains(r#""present":true"#));
+        assert_eq!(second[1], r#"{"ok":true,"command":"shutdown"}"#);
+
+        handle.join().unwrap().unwrap();
+        assert!(!Path::new(path).exists());
+    }
+}
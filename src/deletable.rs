@@ -0,0 +1,198 @@
+// src/deletable.rs
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::builder::BloomFilterBuilder;
+
+/// A deletable Bloom filter (DlBF): removal without counters, at one bit per region.
+///
+/// The bit array is split into regions, and each region has a collision bit
+/// that is set once an insert finds one of its bits already set. Bits in
+/// collision-free regions belong to a single item, so removing an item clears
+/// those of its bits; bits in collided regions are left alone. An item can be
+/// removed when at least one of its bits lies in a collision-free region,
+/// which for a lightly loaded filter with enough regions is almost always.
+/// Removal never causes false negatives for other items.
+#[derive(Serialize, Deserialize)]
+pub struct DeletableBloomFilter {
+    filter: BloomFilter,
+    #[serde(with = "crate::bits::packed_bits")]
+    collisions: Vec<bool>,
+}
+
+impl DeletableBloomFilter {
+    /// Creates a deletable filter whose `array_size` bits are split into `num_regions` regions.
+    pub fn new(array_size: usize, num_hash_functions: usize, num_regions: usize) -> Result<Self, BloomFilterError> {
+        BloomFilter::builder()
+            .array_size(array_size)
+            .hash_functions(num_hash_functions)
+            .build_deletable(num_regions)
+    }
+
+    /// Inserts an item, returning `true` if any bit was newly set.
+    pub fn insert(&mut self, item: &str) -> bool {
+        self.insert_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    /// Inserts a binary key, returning `true` if any bit was newly set.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        let positions = self.filter.positions(item);
+        for &position in &positions {
+            if self.filter.levels[0].bit_array.get(position) {
+                let region = self.region(position);
+                self.collisions[region] = true;
+            }
+        }
+        self.filter.levels[0].insert_positions(&positions)
+    }
+
+    /// Removes an item, returning whether it was removed.
+    ///
+    /// Returns `false` if the item is not present, or if all of its bits lie
+    /// in collided regions so it cannot be removed safely. Removing a false
+    /// positive clears bits of the items it collides with, so only remove
+    /// items known to be in the filter.
+    pub fn remove(&mut self, item: &str) -> bool {
+        self.remove_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    /// Removes a binary key, returning whether it was removed.
+    pub fn remove_bytes(&mut self, item: &[u8]) -> bool {
+        let positions = self.filter.positions(item);
+        let level = &self.filter.levels[0];
+        if !level.contains_positions(&positions) {
+            return false;
+        }
+        let deletable: Vec<usize> = positions
+            .into_iter()
+            .filter(|&position| !self.collisions[self.region(position)])
+            .collect();
+        if deletable.is_empty() {
+            info!("Item cannot be removed: all its bits are in collided regions");
+            return false;
+        }
+        let level = &mut self.filter.levels[0];
+        for position in deletable {
+            level.clear_position(position);
+        }
+        level.item_count = level.item_count.saturating_sub(1);
+        true
+    }
+
+    /// Checks whether the item may be present.
+    pub fn query(&self, item: &str) -> bool {
+        self.query_bytes(self.filter.normalize(item).as_bytes())
+    }
+
+    /// Checks whether the binary key may be present.
+    pub fn query_bytes(&self, item: &[u8]) -> bool {
+        self.filter.query_bytes(item, 1)
+    }
+
+    /// Returns the number of regions.
+    pub fn num_regions(&self) -> usize {
+        self.collisions.len()
+    }
+
+    /// Returns the number of regions where a collision has been recorded.
+    pub fn collided_regions(&self) -> usize {
+        self.collisions.iter().filter(|&&collided| collided).count()
+    }
+
+    /// Returns the underlying filter.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+
+    /// Saves the deletable filter, including its collision bits, as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving DeletableBloomFilter to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a deletable filter saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading DeletableBloomFilter from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let deletable: Self = serde_json::from_reader(reader)?;
+        deletable.validate()?;
+        Ok(deletable)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), BloomFilterError> {
+        self.filter.validate()?;
+        let regions = self.collisions.len();
+        if self.filter.levels().len() != 1 || regions == 0 || regions > self.filter.array_size() {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "deletable filter needs one level and 1 to {} regions, found {} levels and {} regions",
+                self.filter.array_size(),
+                self.filter.levels().len(),
+                regions
+            )));
+        }
+        Ok(())
+    }
+
+    fn region(&self, position: usize) -> usize {
+        position / self.filter.array_size().div_ceil(self.collisions.len())
+    }
+}
+
+impl BloomFilterBuilder {
+    /// Creates a deletable filter from the configuration, with one level split into `num_regions` regions.
+    ///
+    /// The level count and insert mode are ignored. More regions make more
+    /// items removable at the cost of one bit each; the region count is
+    /// clamped to the array size.
+    pub fn build_deletable(self, num_regions: usize) -> Result<DeletableBloomFilter, BloomFilterError> {
+        let filter = self.levels(1).build()?;
+        let num_regions = num_regions.clamp(1, filter.array_size());
+        info!("Creating DeletableBloomFilter: array_size={}, regions={}", filter.array_size(), num_regions);
+        Ok(DeletableBloomFilter {
+            filter,
+            collisions: vec![false; num_regions],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removal_spares_collided_regions() {
+        let mut dlbf = DeletableBloomFilter::new(10_000, 3, 1000).unwrap();
+        let items: Vec<String> = (0..200).map(|i| format!("item-{}", i)).collect();
+        for item in &items {
+            dlbf.insert(item);
+        }
+        assert!(dlbf.collided_regions() > 0 && dlbf.collided_regions() < 1000);
+
+        let removed: Vec<&String> = items.iter().step_by(2).filter(|item| dlbf.remove(item)).collect();
+        assert!(removed.len() > 80, "only {} of 100 items were removable", removed.len());
+        assert!(removed.iter().all(|item| !dlbf.query(item)));
+        // Removal never clears a bit another item relies on.
+        assert!(items.iter().skip(1).step_by(2).all(|item| dlbf.query(item)));
+        assert!(!dlbf.remove("never-inserted"));
+
+        // With a single region, the first collision makes nothing removable.
+        let mut single = DeletableBloomFilter::new(1000, 3, 1).unwrap();
+        single.insert("alpha");
+        single.insert("alpha");
+        assert!(!single.remove("alpha"));
+
+        let path = std::env::temp_dir().join("test_bloom_deletable.json");
+        let path = path.to_str().unwrap();
+        dlbf.save_to_file(path).unwrap();
+        let loaded = DeletableBloomFilter::load_from_file(path).unwrap();
+        assert_eq!(loaded.collided_regions(), dlbf.collided_regions());
+        assert!(items.iter().skip(1).step_by(2).all(|item| loaded.query(item)));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+// src/embedded.rs
+
+use core::fmt;
+
+use crate::bloom_filter::splitmix64;
+use crate::hashing::fnv1a;
+
+/// Identifies the byte export; the `1` is the layout version.
+const MAGIC: [u8; 4] = *b"BFE1";
+const HEADER_LEN: usize = 16;
+
+/// Why an embedded filter could not be exported or restored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmbeddedError {
+    /// The output buffer holds fewer bytes than `byte_len`.
+    BufferTooSmall { needed: usize, available: usize },
+    /// The bytes do not start with the embedded filter magic.
+    BadMagic,
+    /// The bytes are for a filter of different dimensions.
+    WrongDimensions { words: u32, hash_functions: u32 },
+    /// The bytes are shorter or longer than `byte_len`.
+    WrongLength { expected: usize, actual: usize },
+}
+
+impl fmt::Display for EmbeddedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbeddedError::BufferTooSmall { needed, available } => {
+                write!(f, "buffer holds {} bytes, {} needed", available, needed)
+            }
+            EmbeddedError::BadMagic => f.write_str("not an embedded Bloom filter"),
+            EmbeddedError::WrongDimensions { words, hash_functions } => {
+                write!(f, "bytes are for {} words and {} hash functions", words, hash_functions)
+            }
+            EmbeddedError::WrongLength { expected, actual } => write!(f, "expected {} bytes, got {}", expected, actual),
+        }
+    }
+}
+
+impl core::error::Error for EmbeddedError {}
+
+/// A Bloom filter of `N` 64-bit words (`64 * N` bits) and `K` hash functions in a fixed array.
+///
+/// Built for targets without a heap: the filter uses only `core`, never
+/// allocates, and has no serde or file I/O. The bits are packed, so a 1 KiB
+/// filter is `EmbeddedBloomFilter<128, K>`. Keys are hashed with FNV-1a and
+/// double hashing, like a `BloomFilter` of `64 * N` bits built with
+/// `HashAlgorithm::Fnv1a`.
+///
+/// `write_bytes` exports a 16-byte header (the magic `BFE1`, then `N`, `K`
+/// and the item count as little-endian `u32`s) followed by the words as
+/// little-endian `u64`s, `byte_len` bytes in all; `from_bytes` reads it back.
+/// Where the bytes are kept (flash, EEPROM, a host file) is up to the caller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmbeddedBloomFilter<const N: usize, const K: usize> {
+    words: [u64; N],
+    item_count: u32,
+}
+
+impl<const N: usize, const K: usize> EmbeddedBloomFilter<N, K> {
+    /// Fails the build for a filter with no bits or no hash functions.
+    const VALID: () = assert!(
+        N > 0 && K > 0 && N <= u32::MAX as usize / 64 && K <= u32::MAX as usize,
+        "an EmbeddedBloomFilter needs at least one word and one hash function"
+    );
+
+    /// Number of bits in the filter.
+    pub const BITS: usize = 64 * N;
+
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        EmbeddedBloomFilter {
+            words: [0; N],
+            item_count: 0,
+        }
+    }
+
+    /// Inserts an item, returning `true` if any bit was newly set.
+    pub fn insert(&mut self, item: &[u8]) -> bool {
+        let mut newly_set = false;
+        for position in Self::positions(item) {
+            let (word, mask) = (position / 64, 1 << (position % 64));
+            newly_set |= self.words[word] & mask == 0;
+            self.words[word] |= mask;
+        }
+        if newly_set {
+            self.item_count = self.item_count.saturating_add(1);
+        }
+        newly_set
+    }
+
+    /// Checks whether an item is (probably) present.
+    pub fn query(&self, item: &[u8]) -> bool {
+        Self::positions(item)
+            .iter()
+            .all(|&position| self.words[position / 64] & (1 << (position % 64)) != 0)
+    }
+
+    /// Clears every bit.
+    pub fn clear(&mut self) {
+        self.words = [0; N];
+        self.item_count = 0;
+    }
+
+    /// Returns the number of inserts that set at least one new bit.
+    pub fn item_count(&self) -> u32 {
+        self.item_count
+    }
+
+    /// Returns the number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns the number of bytes `write_bytes` produces.
+    pub const fn byte_len() -> usize {
+        HEADER_LEN + 8 * N
+    }
+
+    /// Writes the filter into `out`, returning the number of bytes written.
+    pub fn write_bytes(&self, out: &mut [u8]) -> Result<usize, EmbeddedError> {
+        let len = Self::byte_len();
+        let Some(out) = out.get_mut(..len) else {
+            return Err(EmbeddedError::BufferTooSmall {
+                needed: len,
+                available: out.len(),
+            });
+        };
+        out[..4].copy_from_slice(&MAGIC);
+        out[4..8].copy_from_slice(&(N as u32).to_le_bytes());
+        out[8..12].copy_from_slice(&(K as u32).to_le_bytes());
+        out[12..16].copy_from_slice(&self.item_count.to_le_bytes());
+        for (chunk, word) in out[HEADER_LEN..].chunks_exact_mut(8).zip(&self.words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        Ok(len)
+    }
+
+    /// Restores a filter written by `write_bytes` with the same `N` and `K`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EmbeddedError> {
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+        };
+        if bytes.len() < HEADER_LEN || bytes[..4] != MAGIC {
+            return Err(EmbeddedError::BadMagic);
+        }
+        let (words, hash_functions) = (u32_at(4), u32_at(8));
+        if words as usize != N || hash_functions as usize != K {
+            return Err(EmbeddedError::WrongDimensions { words, hash_functions });
+        }
+        if bytes.len() != Self::byte_len() {
+            return Err(EmbeddedError::WrongLength {
+                expected: Self::byte_len(),
+                actual: bytes.len(),
+            });
+        }
+        let mut filter = Self::new();
+        filter.item_count = u32_at(12);
+        for (word, chunk) in filter.words.iter_mut().zip(bytes[HEADER_LEN..].chunks_exact(8)) {
+            *word = u64::from_le_bytes(chunk.try_into().expect("chunks are 8 bytes"));
+        }
+        Ok(filter)
+    }
+
+    /// The `K` bit positions of a key, as `HashAlgorithm::Fnv1a` computes them.
+    fn positions(item: &[u8]) -> [usize; K] {
+        let h1 = fnv1a(item, 0);
+        let h2 = splitmix64(h1) | 1;
+        core::array::from_fn(|i| (h1.wrapping_add((i as u64).wrapping_mul(h2)) % Self::BITS as u64) as usize)
+    }
+}
+
+impl<const N: usize, const K: usize> Default for EmbeddedBloomFilter<N, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bloom_filter::BloomFilter;
+    use crate::hashing::HashAlgorithm;
+
+    #[test]
+    fn test_round_trips_through_host_bytes() {
+        let mut filter = EmbeddedBloomFilter::<16, 3>::new();
+        assert!(filter.insert(b"sensor-1"));
+        assert!(!filter.insert(b"sensor-1"));
+        filter.insert(b"sensor-2");
+        assert!(filter.query(b"sensor-2") && !filter.query(b"sensor-3"));
+
+        let mut buffer = [0u8; 256];
+        let len = filter.write_bytes(&mut buffer).unwrap();
+        assert_eq!((len, &buffer[..4]), (EmbeddedBloomFilter::<16, 3>::byte_len(), &b"BFE1"[..]));
+        let restored = EmbeddedBloomFilter::<16, 3>::from_bytes(&buffer[..len]).unwrap();
+        assert_eq!(restored, filter);
+        assert_eq!(restored.item_count(), 2);
+
+        assert!(matches!(filter.write_bytes(&mut [0; 64]), Err(EmbeddedError::BufferTooSmall { needed: 144, .. })));
+        assert!(matches!(
+            EmbeddedBloomFilter::<16, 4>::from_bytes(&buffer[..len]),
+            Err(EmbeddedError::WrongDimensions { .. })
+        ));
+        assert_eq!(
+            EmbeddedBloomFilter::<16, 3>::from_bytes(&buffer[..len - 1]),
+            Err(EmbeddedError::WrongLength {
+                expected: 144,
+                actual: 143
+            })
+        );
+        assert_eq!(EmbeddedBloomFilter::<16, 3>::from_bytes(b"nope"), Err(EmbeddedError::BadMagic));
+
+        // The same keys set the same bits as a host-side FNV-1a filter.
+        let mut host = BloomFilter::builder()
+            .array_size(1024)
+            .hash_functions(3)
+            .hash_algorithm(HashAlgorithm::Fnv1a)
+            .build()
+            .unwrap();
+        host.insert_bytes(b"sensor-1");
+        host.insert_bytes(b"sensor-2");
+        let bit = |i: usize| filter.words[i / 64] & (1 << (i % 64)) != 0;
+        assert!((0..1024).all(|i| host.levels[0].bit_array.get(i) == bit(i)));
+    }
+}
@@ -0,0 +1,162 @@
+// src/encryption.rs
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::fs::{self, File};
+use std::io::Write;
+use tracing::{error, info};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// Magic bytes opening an encrypted filter file.
+const ENCRYPTED_MAGIC: &[u8; 4] = b"BLME";
+/// Current version of the encrypted format.
+const ENCRYPTED_VERSION: u8 = 1;
+/// Bytes of the ChaCha20-Poly1305 nonce following the header.
+const NONCE_LEN: usize = 12;
+
+impl BloomFilter {
+    /// Encrypts the filter with ChaCha20-Poly1305 under a caller-supplied 256-bit key.
+    ///
+    /// Layout: magic `BLME`, version, a random 96-bit nonce, then the sealed
+    /// payload (the binary format, preceded by the filter's hash key when it
+    /// uses keyed hashing). The header is authenticated along with the
+    /// payload, so any tampering fails decryption. Since the payload is
+    /// encrypted, a keyed filter's hash key travels with it, unlike in the
+    /// plain formats.
+    pub fn to_encrypted(&self, key: &[u8; 32]) -> Result<Vec<u8>, BloomFilterError> {
+        let mut plaintext = Vec::new();
+        match self.hash_key() {
+            Some(hash_key) => {
+                plaintext.push(1);
+                plaintext.extend_from_slice(hash_key);
+            }
+            None => plaintext.push(0),
+        }
+        plaintext.extend_from_slice(&self.to_binary());
+
+        let mut out = Vec::with_capacity(ENCRYPTED_MAGIC.len() + 1 + NONCE_LEN + plaintext.len() + 16);
+        out.extend_from_slice(ENCRYPTED_MAGIC);
+        out.push(ENCRYPTED_VERSION);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = ChaCha20Poly1305::new(Key::from_slice(key))
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &out,
+                },
+            )
+            .map_err(|_| encryption_error("encryption failed"))?;
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    /// Decrypts and validates a filter produced by `to_encrypted`.
+    pub fn from_encrypted(bytes: &[u8], key: &[u8; 32]) -> Result<Self, BloomFilterError> {
+        let header_len = ENCRYPTED_MAGIC.len() + 1;
+        if bytes.len() < header_len + NONCE_LEN || &bytes[..ENCRYPTED_MAGIC.len()] != ENCRYPTED_MAGIC {
+            return Err(encryption_error("not an encrypted filter"));
+        }
+        let version = bytes[ENCRYPTED_MAGIC.len()];
+        if version != ENCRYPTED_VERSION {
+            return Err(encryption_error(format!("unsupported version {}", version)));
+        }
+        let (header, rest) = bytes.split_at(header_len);
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: sealed,
+                    aad: header,
+                },
+            )
+            .map_err(|_| {
+                error!("Failed to decrypt filter: wrong key or corrupted data");
+                BloomFilterError::DecryptionFailed
+            })?;
+
+        let (hash_key, binary) = match plaintext.split_first() {
+            Some((0, binary)) => (None, binary),
+            Some((1, rest)) if rest.len() >= 16 => {
+                let (hash_key, binary) = rest.split_at(16);
+                (Some(hash_key.try_into().unwrap()), binary)
+            }
+            _ => return Err(encryption_error("malformed payload")),
+        };
+        let mut bloom_filter = Self::from_binary_unvalidated(binary)?;
+        if let Some(hash_key) = hash_key {
+            bloom_filter.restore_hash_key(hash_key)?;
+        }
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Encrypts the filter (see `to_encrypted`) and writes it to a file.
+    pub fn save_encrypted(&self, filepath: &str, key: &[u8; 32]) -> Result<(), BloomFilterError> {
+        info!("Saving encrypted BloomFilter to file: {}", filepath);
+        let bytes = self.to_encrypted(key)?;
+        let mut file = File::create(filepath)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Loads and decrypts a filter saved with `save_encrypted`.
+    pub fn load_encrypted(filepath: &str, key: &[u8; 32]) -> Result<Self, BloomFilterError> {
+        info!("Loading encrypted BloomFilter from file: {}", filepath);
+        Self::from_encrypted(&fs::read(filepath)?, key)
+    }
+}
+
+fn encryption_error(message: impl Into<String>) -> BloomFilterError {
+    BloomFilterError::Encoding {
+        format: "encrypted",
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        let key = [7u8; 32];
+        let mut bf = BloomFilter::builder().levels(2).array_size(200).build().unwrap();
+        bf.insert("customer-1");
+
+        let path = std::env::temp_dir().join("test_bloom_encrypted.bin");
+        let path = path.to_str().unwrap();
+        bf.save_encrypted(path, &key).unwrap();
+        let bytes = fs::read(path).unwrap();
+        assert!(!bytes.windows(4).any(|window| window == b"BLMB"));
+        let loaded = BloomFilter::load_encrypted(path, &key).unwrap();
+        assert!(loaded.query("customer-1", 2));
+        assert!(!loaded.query("customer-2", 2));
+        std::fs::remove_file(path).unwrap();
+
+        assert!(matches!(
+            BloomFilter::from_encrypted(&bytes, &[8u8; 32]),
+            Err(BloomFilterError::DecryptionFailed)
+        ));
+        let mut tampered = bytes.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            BloomFilter::from_encrypted(&tampered, &key),
+            Err(BloomFilterError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn test_encrypted_keeps_hash_key() {
+        let hash_key = [3u8; 16];
+        let mut bf = BloomFilter::builder().array_size(100).hash_key(hash_key).build().unwrap();
+        bf.insert("secret");
+        let loaded = BloomFilter::from_encrypted(&bf.to_encrypted(&[1u8; 32]).unwrap(), &[1u8; 32]).unwrap();
+        assert_eq!(loaded.hash_key(), Some(&hash_key));
+        assert!(loaded.query("secret", 1));
+    }
+}
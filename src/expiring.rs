@@ -0,0 +1,252 @@
+// src/expiring.rs
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::sliding::unix_millis;
+
+/// A Bloom filter where every item carries its own time-to-live.
+///
+/// Items are grouped by when they expire: each insert rounds its expiry up
+/// to a multiple of the `granularity` and goes into the bucket (one level)
+/// holding that expiry time. Once a bucket's time has passed, queries ignore
+/// it and `sweep` clears it for reuse, so each item is forgotten between its
+/// TTL and its TTL plus one granularity, without resetting anything else.
+///
+/// When every bucket is taken by a different expiry, an item joins the
+/// bucket expiring soonest after it, or extends the latest bucket if none
+/// does. Items are then kept longer than asked, never shorter, so a live
+/// item is never reported absent. Size `num_buckets` to the longest TTL
+/// divided by the granularity to avoid that.
+#[derive(Serialize, Deserialize)]
+pub struct ExpiringBloomFilter {
+    filter: BloomFilter,
+    granularity_ms: u64,
+    default_ttl_ms: u64,
+    /// Unix milliseconds each bucket expires at; `None` for an empty bucket.
+    expires_at_ms: Vec<Option<u64>>,
+}
+
+impl ExpiringBloomFilter {
+    /// Creates a filter of `num_buckets` buckets whose expiry times are `granularity` apart.
+    ///
+    /// `default_ttl` applies to `insert`; `insert_with_ttl` sets one per item.
+    pub fn new(
+        default_ttl: Duration,
+        granularity: Duration,
+        num_buckets: usize,
+        array_size: usize,
+        num_hash_functions: usize,
+    ) -> Result<Self, BloomFilterError> {
+        info!(
+            "Creating ExpiringBloomFilter: default_ttl={:?}, granularity={:?}, buckets={}",
+            default_ttl, granularity, num_buckets
+        );
+        let filter = BloomFilter::new(num_buckets, array_size, num_hash_functions)?;
+        Ok(ExpiringBloomFilter {
+            filter,
+            granularity_ms: (granularity.as_millis() as u64).max(1),
+            default_ttl_ms: default_ttl.as_millis() as u64,
+            expires_at_ms: vec![None; num_buckets],
+        })
+    }
+
+    /// Inserts an item that expires after the default TTL.
+    pub fn insert(&mut self, item: &str) -> bool {
+        self.insert_with_ttl_at(item, self.default_ttl(), SystemTime::now())
+    }
+
+    /// Inserts an item that expires after `ttl`.
+    pub fn insert_with_ttl(&mut self, item: &str, ttl: Duration) -> bool {
+        self.insert_with_ttl_at(item, ttl, SystemTime::now())
+    }
+
+    /// Like `insert_with_ttl`, evaluated at time `now`.
+    pub fn insert_with_ttl_at(&mut self, item: &str, ttl: Duration, now: SystemTime) -> bool {
+        self.insert_bytes_with_ttl_at(self.filter.normalize(item).as_bytes(), ttl, now)
+    }
+
+    /// Inserts a binary key that expires after the default TTL.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        self.insert_bytes_with_ttl_at(item, self.default_ttl(), SystemTime::now())
+    }
+
+    /// Inserts a binary key that expires after `ttl`.
+    pub fn insert_bytes_with_ttl(&mut self, item: &[u8], ttl: Duration) -> bool {
+        self.insert_bytes_with_ttl_at(item, ttl, SystemTime::now())
+    }
+
+    fn insert_bytes_with_ttl_at(&mut self, item: &[u8], ttl: Duration, now: SystemTime) -> bool {
+        let now_ms = unix_millis(now);
+        let expires_at = (now_ms.saturating_add(ttl.as_millis() as u64))
+            .div_ceil(self.granularity_ms)
+            .saturating_mul(self.granularity_ms);
+        let bucket = self.bucket_for(expires_at, now_ms);
+        let positions = self.filter.positions(item);
+        self.filter.levels[bucket].insert_positions(&positions)
+    }
+
+    /// Picks the bucket for an item expiring at `expires_at`, claiming or extending one if needed.
+    fn bucket_for(&mut self, expires_at: u64, now_ms: u64) -> usize {
+        let live = |expiry: &Option<u64>| expiry.filter(|&expiry| expiry > now_ms);
+        if let Some(bucket) = self.expires_at_ms.iter().position(|expiry| live(expiry) == Some(expires_at)) {
+            return bucket;
+        }
+        if let Some(bucket) = self.expires_at_ms.iter().position(|expiry| live(expiry).is_none()) {
+            self.filter.levels[bucket].clear();
+            self.expires_at_ms[bucket] = Some(expires_at);
+            return bucket;
+        }
+        // Every bucket is live: keep the item longer rather than lose it early.
+        let later = (0..self.expires_at_ms.len())
+            .filter(|&bucket| self.expires_at_ms[bucket] > Some(expires_at))
+            .min_by_key(|&bucket| self.expires_at_ms[bucket]);
+        later.unwrap_or_else(|| {
+            let latest = (0..self.expires_at_ms.len())
+                .max_by_key(|&bucket| self.expires_at_ms[bucket])
+                .expect("a filter has at least one bucket");
+            warn!("All {} buckets are live; extending one to hold a later expiry", self.expires_at_ms.len());
+            self.expires_at_ms[latest] = Some(expires_at);
+            latest
+        })
+    }
+
+    /// Checks whether the item was (probably) inserted and has not expired.
+    pub fn query(&self, item: &str) -> bool {
+        self.query_at(item, SystemTime::now())
+    }
+
+    /// Like `query`, evaluated at time `now`.
+    pub fn query_at(&self, item: &str, now: SystemTime) -> bool {
+        self.query_bytes_at(self.filter.normalize(item).as_bytes(), now)
+    }
+
+    /// Checks whether the binary key was (probably) inserted and has not expired.
+    pub fn query_bytes(&self, item: &[u8]) -> bool {
+        self.query_bytes_at(item, SystemTime::now())
+    }
+
+    fn query_bytes_at(&self, item: &[u8], now: SystemTime) -> bool {
+        let now_ms = unix_millis(now);
+        let positions = self.filter.positions(item);
+        self.expires_at_ms.iter().enumerate().any(|(bucket, expiry)| {
+            expiry.is_some_and(|expiry| expiry > now_ms) && self.filter.levels[bucket].contains_positions(&positions)
+        })
+    }
+
+    /// Clears every expired bucket, returning how many were cleared. Call it from a timer.
+    pub fn sweep(&mut self) -> usize {
+        self.sweep_at(SystemTime::now())
+    }
+
+    /// Like `sweep`, evaluated at time `now`.
+    pub fn sweep_at(&mut self, now: SystemTime) -> usize {
+        let now_ms = unix_millis(now);
+        let mut swept = 0;
+        for (bucket, expiry) in self.expires_at_ms.iter_mut().enumerate() {
+            if expiry.is_some_and(|expiry| expiry <= now_ms) {
+                self.filter.levels[bucket].clear();
+                *expiry = None;
+                swept += 1;
+            }
+        }
+        if swept > 0 {
+            info!("Swept {} expired buckets", swept);
+        }
+        swept
+    }
+
+    /// Returns the number of buckets holding items, including expired ones not yet swept.
+    pub fn occupied_buckets(&self) -> usize {
+        self.expires_at_ms.iter().filter(|expiry| expiry.is_some()).count()
+    }
+
+    /// Returns the TTL `insert` uses.
+    pub fn default_ttl(&self) -> Duration {
+        Duration::from_millis(self.default_ttl_ms)
+    }
+
+    /// Returns how far apart bucket expiry times are.
+    pub fn granularity(&self) -> Duration {
+        Duration::from_millis(self.granularity_ms)
+    }
+
+    /// Returns the underlying filter, one level per bucket.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+
+    /// Saves the filter, including each bucket's expiry time, as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving ExpiringBloomFilter to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a filter saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading ExpiringBloomFilter from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let expiring: Self = serde_json::from_reader(reader)?;
+        expiring.filter.validate()?;
+        if expiring.expires_at_ms.len() != expiring.filter.levels().len() || expiring.granularity_ms == 0 {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "expiring filter has {} expiry times for {} buckets",
+                expiring.expires_at_ms.len(),
+                expiring.filter.levels().len()
+            )));
+        }
+        Ok(expiring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_items_expire_individually() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let secs = Duration::from_secs;
+        let mut ebf = ExpiringBloomFilter::new(secs(60), secs(10), 3, 1000, 3).unwrap();
+
+        ebf.insert_with_ttl_at("short", secs(15), start);
+        ebf.insert_with_ttl_at("long", secs(100), start);
+        ebf.insert_with_ttl_at("also-short", secs(12), at(3));
+        assert_eq!(ebf.occupied_buckets(), 2);
+        assert!(ebf.query_at("short", at(15)) && ebf.query_at("also-short", at(19)));
+        // Expiry is rounded up to the next 10 seconds.
+        assert!(!ebf.query_at("short", at(20)) && !ebf.query_at("also-short", at(20)));
+        assert!(ebf.query_at("long", at(20)));
+        assert_eq!(ebf.sweep_at(at(20)), 1);
+        assert_eq!(ebf.occupied_buckets(), 1);
+
+        // With every bucket live, an item is kept longer rather than dropped early.
+        ebf.insert_with_ttl_at("medium", secs(30), at(20));
+        ebf.insert_with_ttl_at("brief", secs(5), at(20));
+        ebf.insert_with_ttl_at("tiny", secs(1), at(20));
+        assert_eq!(ebf.occupied_buckets(), 3);
+        ebf.insert_with_ttl_at("joins-medium", secs(15), at(20));
+        assert!(ebf.query_at("joins-medium", at(45)));
+        assert!(ebf.query_at("tiny", at(29)) && ebf.query_at("medium", at(49)));
+        assert!(!ebf.query_at("brief", at(30)) && !ebf.query_at("medium", at(50)));
+        ebf.insert_with_ttl_at("longest", secs(500), at(20));
+        assert!(ebf.query_at("longest", at(510)) && ebf.query_at("long", at(510)));
+
+        let path = std::env::temp_dir().join("test_bloom_expiring.json");
+        let path = path.to_str().unwrap();
+        ebf.save_to_file(path).unwrap();
+        let mut loaded = ExpiringBloomFilter::load_from_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.sweep_at(at(520)), 3);
+        assert!(!loaded.query_at("longest", at(520)));
+        assert_eq!((loaded.default_ttl(), loaded.granularity()), (secs(60), secs(10)));
+    }
+}
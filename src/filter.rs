@@ -0,0 +1,165 @@
+// src/filter.rs
+
+use crate::age_partitioned::AgePartitionedBloomFilter;
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::counting::CountingBloomFilter;
+use crate::deletable::DeletableBloomFilter;
+use crate::expiring::ExpiringBloomFilter;
+use crate::sliding::SlidingBloomFilter;
+
+/// The operations every filter variant supports, so applications can choose
+/// an implementation at runtime behind `Box<dyn Filter>`.
+///
+/// Keys are bytes and bypass key normalization, like the variants' own
+/// `*_bytes` methods: `insert(b"x")` here and a variant's `insert("x")` are
+/// the same key only on a filter without normalization. Multi-level filters
+/// insert according to their insert mode and `contains` searches every level.
+pub trait Filter {
+    /// Inserts a key, returning `true` if any bit was newly set.
+    fn insert(&mut self, item: &[u8]) -> bool;
+
+    /// Checks whether the key may be present.
+    fn contains(&self, item: &[u8]) -> bool;
+
+    /// Estimates the current false-positive rate of `contains`.
+    fn estimated_fpr(&self) -> f64;
+
+    /// Encodes the filter in the JSON format its `save_to_file` writes.
+    fn serialize(&self) -> Result<Vec<u8>, BloomFilterError>;
+}
+
+impl Filter for BloomFilter {
+    fn insert(&mut self, item: &[u8]) -> bool {
+        self.insert_bytes(item)
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.query_bytes(item, self.levels().len())
+    }
+
+    fn estimated_fpr(&self) -> f64 {
+        self.estimated_false_positive_rate()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BloomFilterError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+impl Filter for CountingBloomFilter {
+    fn insert(&mut self, item: &[u8]) -> bool {
+        self.insert_bytes(item)
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.query_bytes(item)
+    }
+
+    fn estimated_fpr(&self) -> f64 {
+        self.filter().estimated_false_positive_rate()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BloomFilterError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+impl Filter for DeletableBloomFilter {
+    fn insert(&mut self, item: &[u8]) -> bool {
+        self.insert_bytes(item)
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.query_bytes(item)
+    }
+
+    fn estimated_fpr(&self) -> f64 {
+        self.filter().estimated_false_positive_rate()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BloomFilterError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+impl Filter for AgePartitionedBloomFilter {
+    fn insert(&mut self, item: &[u8]) -> bool {
+        self.insert_bytes(item)
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.query_bytes(item)
+    }
+
+    fn estimated_fpr(&self) -> f64 {
+        self.estimated_false_positive_rate()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BloomFilterError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+impl Filter for SlidingBloomFilter {
+    fn insert(&mut self, item: &[u8]) -> bool {
+        self.insert_bytes(item)
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.query_bytes(item)
+    }
+
+    fn estimated_fpr(&self) -> f64 {
+        self.filter().estimated_false_positive_rate()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BloomFilterError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+impl Filter for ExpiringBloomFilter {
+    fn insert(&mut self, item: &[u8]) -> bool {
+        self.insert_bytes(item)
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.query_bytes(item)
+    }
+
+    fn estimated_fpr(&self) -> f64 {
+        self.filter().estimated_false_positive_rate()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BloomFilterError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counting::CounterWidth;
+    use std::time::Duration;
+
+    #[test]
+    fn test_variants_behind_dyn_filter() {
+        let mut filters: Vec<Box<dyn Filter>> = vec![
+            Box::new(BloomFilter::new(2, 1000, 3).unwrap()),
+            Box::new(CountingBloomFilter::new(1000, 3, CounterWidth::Four).unwrap()),
+            Box::new(DeletableBloomFilter::new(1000, 3, 100).unwrap()),
+            Box::new(AgePartitionedBloomFilter::new(3, 2, 1000, 100).unwrap()),
+            Box::new(SlidingBloomFilter::new(Duration::from_secs(60), 3, 1000, 3).unwrap()),
+            Box::new(ExpiringBloomFilter::new(Duration::from_secs(60), Duration::from_secs(10), 3, 1000, 3).unwrap()),
+        ];
+        for filter in &mut filters {
+            assert_eq!(filter.estimated_fpr(), 0.0);
+            assert!(filter.insert(b"alpha"));
+            filter.insert("beta".as_bytes());
+            assert!(filter.contains(b"alpha") && filter.contains(b"beta"));
+            assert!(!filter.contains(b"gamma"));
+            assert!(filter.estimated_fpr() > 0.0 && filter.estimated_fpr() < 0.01);
+            assert!(!filter.serialize().unwrap().is_empty());
+        }
+    }
+}
@@ -0,0 +1,588 @@
+// src/format.rs
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::str::FromStr;
+use std::time::Instant;
+use tracing::{error, field, info, instrument, Span};
+
+use crate::bloom_filter::{record_duration, BloomFilter, BloomFilterError, BloomLevel, HashFunction, InsertMode};
+use crate::hashing::HashAlgorithm;
+use crate::normalize::Normalization;
+use crate::saturation::CapacityLimit;
+use crate::stats::Counters;
+
+/// Magic bytes opening the binary format.
+const BINARY_MAGIC: &[u8; 4] = b"BLMB";
+/// Current version of the binary format; version 1 had no checksum and
+/// version 2 no key normalization.
+const BINARY_VERSION: u8 = 3;
+/// Bytes of the CRC-32 trailer closing a version 2 or later binary file.
+const CHECKSUM_LEN: usize = 4;
+/// Magic bytes opening every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Most bytes a compressed file may inflate to, so a decompression bomb fails instead of exhausting memory.
+const MAX_DECOMPRESSED_LEN: u64 = 1 << 30;
+
+/// On-disk encodings understood by `save_as` and `load_auto`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Pretty-printed JSON, as written by `save_to_file`.
+    Json,
+    /// Compact binary: a fixed header followed by each level's packed bits.
+    Binary,
+    /// The binary format, gzip-compressed.
+    Compressed,
+    /// MessagePack (feature `msgpack`).
+    MessagePack,
+}
+
+impl FileFormat {
+    /// Every format, in the order offered to users.
+    pub const ALL: [FileFormat; 4] = [
+        FileFormat::Json,
+        FileFormat::Binary,
+        FileFormat::Compressed,
+        FileFormat::MessagePack,
+    ];
+
+    /// Returns the format's lowercase name, as accepted by `from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            FileFormat::Json => "json",
+            FileFormat::Binary => "binary",
+            FileFormat::Compressed => "compressed",
+            FileFormat::MessagePack => "msgpack",
+        }
+    }
+
+    /// Returns false if the format's implementation was not compiled in.
+    pub fn is_available(self) -> bool {
+        self != FileFormat::MessagePack || cfg!(feature = "msgpack")
+    }
+
+    /// Picks a save format from the file extension, defaulting to JSON.
+    pub fn for_path(path: &str) -> FileFormat {
+        match path.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("bin") => FileFormat::Binary,
+            Some("gz") => FileFormat::Compressed,
+            Some("msgpack" | "mp") => FileFormat::MessagePack,
+            _ => FileFormat::Json,
+        }
+    }
+
+    /// Guesses the format of a saved filter from its first bytes.
+    pub fn detect(bytes: &[u8]) -> Option<FileFormat> {
+        if bytes.starts_with(BINARY_MAGIC) {
+            return Some(FileFormat::Binary);
+        }
+        if bytes.starts_with(&GZIP_MAGIC) {
+            return Some(FileFormat::Compressed);
+        }
+        match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => Some(FileFormat::Json),
+            // fixmap, map16 and map32 markers
+            Some(0x80..=0x8f | 0xde | 0xdf) => Some(FileFormat::MessagePack),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FileFormat::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown format '{}' (expected json, binary, compressed or msgpack)", s))
+    }
+}
+
+impl BloomFilter {
+    /// Saves the filter to a file in the given format.
+    #[instrument(level = "info", name = "save", skip(self), fields(%format, duration_ms = field::Empty))]
+    pub fn save_as(&self, filepath: &str, format: FileFormat) -> Result<(), BloomFilterError> {
+        info!("Saving BloomFilter to file: {} ({})", filepath, format);
+        let start = Instant::now();
+        let result = self.save_to_writer_as(BufWriter::new(File::create(filepath)?), format);
+        record_duration(start);
+        if result.is_ok() {
+            self.notify(|observer| observer.on_save(filepath));
+        }
+        result
+    }
+
+    /// Writes the filter to any writer in the given format.
+    pub fn save_to_writer_as<W: Write>(&self, mut writer: W, format: FileFormat) -> Result<(), BloomFilterError> {
+        match format {
+            FileFormat::Json => self.save_to_writer(writer),
+            FileFormat::Binary => {
+                self.write_binary(&mut writer)?;
+                writer.flush()?;
+                Ok(())
+            }
+            FileFormat::Compressed => {
+                let mut encoder = GzEncoder::new(writer, Compression::default());
+                self.write_binary(&mut encoder)?;
+                encoder.finish()?.flush()?;
+                Ok(())
+            }
+            #[cfg(feature = "msgpack")]
+            FileFormat::MessagePack => self.write_msgpack(writer),
+            #[allow(unreachable_patterns)]
+            unavailable => Err(unsupported(unavailable)),
+        }
+    }
+
+    /// Loads a filter saved in any supported format, detecting the format from the file contents.
+    #[instrument(level = "info", name = "load", fields(format = field::Empty, duration_ms = field::Empty))]
+    pub fn load_auto(filepath: &str) -> Result<(Self, FileFormat), BloomFilterError> {
+        info!("Loading BloomFilter from file: {} (detecting format)", filepath);
+        let start = Instant::now();
+        let result = Self::load_auto_from_reader(File::open(filepath)?);
+        if let Ok((_, format)) = &result {
+            Span::current().record("format", format.name());
+        }
+        record_duration(start);
+        result
+    }
+
+    /// Reads a filter in any supported format from a reader, detecting the format from its contents.
+    ///
+    /// Compressed input that inflates past 1 GiB is rejected.
+    pub fn load_auto_from_reader<R: Read>(mut reader: R) -> Result<(Self, FileFormat), BloomFilterError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let format = FileFormat::detect(&bytes).ok_or_else(|| {
+            error!("Unrecognized file format");
+            BloomFilterError::Encoding {
+                format: "unknown",
+                message: "unrecognized file format".to_string(),
+            }
+        })?;
+        let bloom_filter = match format {
+            FileFormat::Json => Self::load_from_reader(bytes.as_slice())?,
+            FileFormat::Binary => Self::from_binary(&bytes)?,
+            FileFormat::Compressed => {
+                let mut binary = Vec::new();
+                GzDecoder::new(bytes.as_slice()).take(MAX_DECOMPRESSED_LEN + 1).read_to_end(&mut binary)?;
+                if binary.len() as u64 > MAX_DECOMPRESSED_LEN {
+                    error!("Compressed file inflates past {} bytes", MAX_DECOMPRESSED_LEN);
+                    return Err(BloomFilterError::Encoding {
+                        format: "compressed",
+                        message: format!("decompressed size exceeds {} bytes", MAX_DECOMPRESSED_LEN),
+                    });
+                }
+                Self::from_binary(&binary)?
+            }
+            #[cfg(feature = "msgpack")]
+            FileFormat::MessagePack => Self::read_msgpack(bytes.as_slice())?,
+            #[allow(unreachable_patterns)]
+            unavailable => return Err(unsupported(unavailable)),
+        };
+        Ok((bloom_filter, format))
+    }
+
+    /// Encodes the filter in the binary format.
+    ///
+    /// All integers are little-endian. Layout (version 3):
+    /// magic `BLMB`, version, array size, level count, active level, hash
+    /// algorithm, seed, hash count and multipliers, insert mode, key
+    /// fingerprint, key normalization; then per level its label, metadata,
+    /// creation time, TTL, item count and `ceil(array_size / 8)` bytes of
+    /// LSB-first packed bits; finally a CRC-32 of everything before it.
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_binary(&mut out).expect("writing to a Vec cannot fail");
+        out
+    }
+
+    /// Streams the binary format to a writer, holding at most one level's
+    /// header and a small block of packed bits in memory at a time.
+    pub(crate) fn write_binary<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = ChecksumWriter {
+            inner: writer,
+            hasher: crc32fast::Hasher::new(),
+        };
+        let mut out = Vec::new();
+        out.extend_from_slice(BINARY_MAGIC);
+        out.push(BINARY_VERSION);
+        put_u64(&mut out, self.array_size as u64);
+        put_u32(&mut out, self.levels.len() as u32);
+        put_u32(&mut out, self.active_level as u32);
+        out.push(algorithm_code(self.hash_algorithm));
+        put_u64(&mut out, self.seed());
+        put_u32(&mut out, self.hash_functions.len() as u32);
+        for hash_function in &self.hash_functions {
+            put_u64(&mut out, hash_function.multiplier as u64);
+        }
+        match self.insert_mode {
+            InsertMode::AllLevels => out.push(0),
+            InsertMode::ActiveLevel {
+                max_items,
+                max_fill_ratio,
+            } => {
+                out.push(1);
+                put_opt_u64(&mut out, max_items.map(|max| max as u64));
+                put_opt_u64(&mut out, max_fill_ratio.map(f64::to_bits));
+            }
+        }
+        put_opt_u64(&mut out, self.key_fingerprint);
+        out.push(self.normalization.to_code());
+        writer.write_all(&out)?;
+        for level in &self.levels {
+            out.clear();
+            match &level.label {
+                Some(label) => {
+                    out.push(1);
+                    put_str(&mut out, label);
+                }
+                None => out.push(0),
+            }
+            put_u32(&mut out, level.metadata.len() as u32);
+            for (key, value) in &level.metadata {
+                put_str(&mut out, key);
+                put_str(&mut out, value);
+            }
+            put_opt_u64(&mut out, level.created_at);
+            put_opt_u64(&mut out, level.ttl);
+            put_u64(&mut out, level.item_count as u64);
+            writer.write_all(&out)?;
+            write_packed_bits(level, &mut writer)?;
+        }
+        let checksum = writer.hasher.finalize();
+        writer.inner.write_all(&checksum.to_le_bytes())
+    }
+
+    /// Decodes and validates a filter produced by `to_binary`.
+    ///
+    /// The checksum is verified before anything else is read, so a partially
+    /// written or bit-rotted file fails with `CorruptedFile`. Version 1 files,
+    /// which predate the checksum, still load.
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, BloomFilterError> {
+        let bloom_filter = Self::from_binary_unvalidated(bytes)?;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Decodes a binary filter without validating it, so a caller can restore a hash key first.
+    pub(crate) fn from_binary_unvalidated(bytes: &[u8]) -> Result<Self, BloomFilterError> {
+        let mut input = BinaryReader { bytes };
+        if input.take(BINARY_MAGIC.len())? != BINARY_MAGIC {
+            return Err(binary_error("missing magic bytes"));
+        }
+        let version = input.u8()?;
+        match version {
+            1 => {}
+            2 | BINARY_VERSION => {
+                input.bytes = verify_checksum(bytes)?
+                    .get(BINARY_MAGIC.len() + 1..)
+                    .ok_or_else(|| binary_error("unexpected end of data"))?;
+            }
+            _ => return Err(binary_error(format!("unsupported version {}", version))),
+        }
+        let array_size = usize::try_from(input.u64()?).map_err(|_| binary_error("array size too large"))?;
+        let num_levels = input.u32()? as usize;
+        let active_level = input.u32()? as usize;
+        let hash_algorithm = algorithm_from_code(input.u8()?)?;
+        let seed = input.u64()?;
+        let num_hash_functions = input.u32()? as usize;
+        let mut hash_functions = Vec::new();
+        for _ in 0..num_hash_functions {
+            hash_functions.push(HashFunction::with_seed(input.u64()? as usize, seed));
+        }
+        let insert_mode = match input.u8()? {
+            0 => InsertMode::AllLevels,
+            1 => InsertMode::ActiveLevel {
+                max_items: input.opt_u64()?.map(|max| max as usize),
+                max_fill_ratio: input.opt_u64()?.map(f64::from_bits),
+            },
+            tag => return Err(binary_error(format!("unknown insert mode {}", tag))),
+        };
+        let key_fingerprint = input.opt_u64()?;
+        let normalization = match version {
+            BINARY_VERSION => {
+                let code = input.u8()?;
+                Normalization::from_code(code).ok_or_else(|| binary_error(format!("unknown key normalization {}", code)))?
+            }
+            _ => Normalization::default(),
+        };
+        let mut levels = Vec::new();
+        for _ in 0..num_levels {
+            let label = match input.u8()? {
+                0 => None,
+                _ => Some(input.string()?),
+            };
+            let mut metadata = std::collections::BTreeMap::new();
+            for _ in 0..input.u32()? {
+                let key = input.string()?;
+                metadata.insert(key, input.string()?);
+            }
+            let created_at = input.opt_u64()?;
+            let ttl = input.opt_u64()?;
+            let item_count = input.u64()? as usize;
+            let mut level = BloomLevel::from_bytes(input.take(array_size.div_ceil(8))?, array_size)?;
+            level.label = label;
+            level.metadata = metadata;
+            level.created_at = created_at;
+            level.ttl = ttl;
+            level.item_count = item_count;
+            levels.push(level);
+        }
+        if !input.bytes.is_empty() {
+            return Err(binary_error(format!("{} trailing bytes", input.bytes.len())));
+        }
+        Ok(BloomFilter {
+            levels,
+            hash_functions,
+            array_size,
+            insert_mode,
+            active_level,
+            hash_algorithm,
+            key_fingerprint,
+            hash_key: None,
+            normalization,
+            observers: Vec::new(),
+            counters: Counters::default(),
+            capacity: CapacityLimit::default(),
+        })
+    }
+}
+
+/// Bytes of packed bits buffered at a time while streaming a level.
+const PACKED_BLOCK_LEN: usize = 64 * 1024;
+
+/// Writes a level's bits in the layout of `BloomLevel::to_bytes`, one block at a time.
+fn write_packed_bits<W: Write>(level: &BloomLevel, writer: &mut W) -> io::Result<()> {
+    let total = level.len().div_ceil(8);
+    let mut block = vec![0u8; PACKED_BLOCK_LEN.min(total)];
+    let mut block_start = 0;
+    for bit in level.bit_array.iter_ones() {
+        while bit / 8 >= block_start + block.len() {
+            writer.write_all(&block)?;
+            block_start += block.len();
+            block.truncate(PACKED_BLOCK_LEN.min(total - block_start));
+            block.fill(0);
+        }
+        block[bit / 8 - block_start] |= 1 << (bit % 8);
+    }
+    while block_start < total {
+        writer.write_all(&block)?;
+        block_start += block.len();
+        block.truncate(PACKED_BLOCK_LEN.min(total - block_start));
+        block.fill(0);
+    }
+    Ok(())
+}
+
+/// Passes writes through while computing their CRC-32.
+struct ChecksumWriter<W> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Checks the CRC-32 trailer of a binary file, returning the data it covers.
+fn verify_checksum(bytes: &[u8]) -> Result<&[u8], BloomFilterError> {
+    let Some(split) = bytes.len().checked_sub(CHECKSUM_LEN) else {
+        return Err(binary_error("unexpected end of data"));
+    };
+    let (data, trailer) = bytes.split_at(split);
+    let expected = u32::from_le_bytes(trailer.try_into().unwrap());
+    let actual = crc32fast::hash(data);
+    if actual != expected {
+        error!("Binary filter checksum mismatch: stored {:08x}, computed {:08x}", expected, actual);
+        return Err(BloomFilterError::CorruptedFile { expected, actual });
+    }
+    Ok(data)
+}
+
+fn unsupported(format: FileFormat) -> BloomFilterError {
+    BloomFilterError::Encoding {
+        format: format.name(),
+        message: "support for this format is not enabled in this build".to_string(),
+    }
+}
+
+fn binary_error(message: impl Into<String>) -> BloomFilterError {
+    BloomFilterError::Encoding {
+        format: "binary",
+        message: message.into(),
+    }
+}
+
+pub(crate) fn algorithm_code(algorithm: HashAlgorithm) -> u8 {
+    match algorithm {
+        HashAlgorithm::Polynomial => 0,
+        HashAlgorithm::Fnv1a => 1,
+        HashAlgorithm::Xxh3 => 2,
+        HashAlgorithm::Murmur3 => 3,
+    }
+}
+
+pub(crate) fn algorithm_from_code(code: u8) -> Result<HashAlgorithm, BloomFilterError> {
+    let algorithm = match code {
+        0 => HashAlgorithm::Polynomial,
+        1 => HashAlgorithm::Fnv1a,
+        2 => HashAlgorithm::Xxh3,
+        3 => HashAlgorithm::Murmur3,
+        _ => return Err(binary_error(format!("unknown hash algorithm {}", code))),
+    };
+    if !algorithm.is_available() {
+        error!("Hash algorithm {:?} is not enabled", algorithm);
+        return Err(BloomFilterError::UnsupportedHashAlgorithm(algorithm));
+    }
+    Ok(algorithm)
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_opt_u64(out: &mut Vec<u8>, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            put_u64(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    put_u32(out, value.len() as u32);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Cursor over binary input; every read fails cleanly on truncated data.
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BinaryReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BloomFilterError> {
+        if self.bytes.len() < len {
+            return Err(binary_error("unexpected end of data"));
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, BloomFilterError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, BloomFilterError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, BloomFilterError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn opt_u64(&mut self) -> Result<Option<u64>, BloomFilterError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => self.u64().map(Some),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, BloomFilterError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| binary_error("string is not valid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_round_trip_with_detection() {
+        let mut bf = BloomFilter::builder().levels(2).array_size(300).seed(3).build().unwrap();
+        bf.set_level_label(1, Some("archive".to_string())).unwrap();
+        bf.set_level_metadata(1, "range", "2024").unwrap();
+        bf.insert("alpha");
+
+        for format in FileFormat::ALL.into_iter().filter(|f| f.is_available()) {
+            let filepath = std::env::temp_dir().join(format!("test_format.{}", format));
+            let filepath = filepath.to_str().unwrap();
+            bf.save_as(filepath, format).unwrap();
+            let (loaded, detected) = BloomFilter::load_auto(filepath).unwrap();
+            assert_eq!(detected, format);
+            assert!(loaded.query("alpha", 2));
+            assert!(!loaded.query("beta", 2));
+            assert_eq!(loaded.seed(), 3);
+            assert_eq!(loaded.level_index("archive"), Some(1));
+            std::fs::remove_file(filepath).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_binary_rejects_truncated_input() {
+        let bf = BloomFilter::new(1, 100, 3).unwrap();
+        let binary = bf.to_binary();
+        assert!(BloomFilter::from_binary(&binary).is_ok());
+        assert!(BloomFilter::from_binary(&binary[..binary.len() - 1]).is_err());
+        assert_eq!("Compressed".parse::<FileFormat>(), Ok(FileFormat::Compressed));
+        assert!("xml".parse::<FileFormat>().is_err());
+    }
+
+    #[test]
+    fn test_binary_checksum_detects_corruption() {
+        let mut bf = BloomFilter::new(1, 100, 3).unwrap();
+        bf.insert("alpha");
+        let mut binary = bf.to_binary();
+        let last_bit_byte = binary.len() - CHECKSUM_LEN - 1;
+        binary[last_bit_byte] ^= 0x10;
+        assert!(matches!(
+            BloomFilter::from_binary(&binary),
+            Err(BloomFilterError::CorruptedFile { .. })
+        ));
+        assert!(matches!(
+            BloomFilter::from_binary(&bf.to_binary()[..40]),
+            Err(BloomFilterError::CorruptedFile { .. })
+        ));
+
+        // Versions 1 and 2 lack the key normalization byte, which follows the
+        // 60-byte header of a filter with three hash functions and no hash key.
+        let mut legacy = bf.to_binary();
+        legacy.truncate(legacy.len() - CHECKSUM_LEN);
+        legacy.remove(60);
+        legacy[BINARY_MAGIC.len()] = 2;
+        let checksum = crc32fast::hash(&legacy);
+        let mut version2 = legacy.clone();
+        version2.extend_from_slice(&checksum.to_le_bytes());
+        assert!(BloomFilter::from_binary(&version2).unwrap().query("alpha", 1));
+        // Version 1 files carry no checksum.
+        legacy[BINARY_MAGIC.len()] = 1;
+        assert!(BloomFilter::from_binary(&legacy).unwrap().query("alpha", 1));
+    }
+}
@@ -0,0 +1,144 @@
+// src/frozen.rs
+
+use std::sync::Arc;
+use tracing::info;
+
+use crate::bloom_filter::{keyed_positions, u64_positions, BloomFilter, HashFunction};
+use crate::hashing::HashAlgorithm;
+use crate::normalize::Normalization;
+
+/// An immutable, query-only snapshot of a `BloomFilter`.
+///
+/// Bits are packed 64 to a word in one contiguous buffer and the hash
+/// configuration is fixed at freeze time. The data lives behind an `Arc`, so
+/// cloning is a reference-count bump and the filter can be shared freely
+/// across threads on hot read paths.
+#[derive(Clone)]
+pub struct FrozenBloomFilter {
+    inner: Arc<FrozenInner>,
+}
+
+struct FrozenInner {
+    hash_functions: Vec<HashFunction>,
+    hash_algorithm: HashAlgorithm,
+    hash_key: Option<[u8; 16]>,
+    normalization: Normalization,
+    array_size: usize,
+    words_per_level: usize,
+    num_levels: usize,
+    words: Box<[u64]>,
+}
+
+impl BloomFilter {
+    /// Produces a compact, immutable copy of the filter for read-heavy use.
+    ///
+    /// Levels whose TTL has already elapsed are frozen as empty.
+    pub fn freeze(&self) -> FrozenBloomFilter {
+        let array_size = self.array_size();
+        let words_per_level = array_size.div_ceil(64);
+        let num_levels = self.levels().len();
+        info!("Freezing BloomFilter: levels={}, array_size={}", num_levels, array_size);
+        let now = crate::bloom_filter::unix_now();
+        let mut words = vec![0u64; words_per_level * num_levels].into_boxed_slice();
+        for (index, level) in self.levels().iter().enumerate() {
+            if level.is_expired_at(now) {
+                continue;
+            }
+            let base = index * words_per_level;
+            for bit in level.bit_array.iter_ones() {
+                words[base + bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        FrozenBloomFilter {
+            inner: Arc::new(FrozenInner {
+                hash_functions: self.hash_functions().to_vec(),
+                hash_algorithm: self.hash_algorithm(),
+                hash_key: self.hash_key().copied(),
+                normalization: self.normalization(),
+                array_size,
+                words_per_level,
+                num_levels,
+                words,
+            }),
+        }
+    }
+}
+
+impl FrozenBloomFilter {
+    /// Queries an item across the specified number of levels.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.query_level(item, num_levels_to_search).is_some()
+    }
+
+    /// Returns the index of the first level (of the first `num_levels_to_search`) that contains the item.
+    pub fn query_level(&self, item: &str, num_levels_to_search: usize) -> Option<usize> {
+        let item = self.inner.normalization.apply(item);
+        self.first_match(&self.positions(item.as_bytes()), num_levels_to_search)
+    }
+
+    /// Queries a binary key across the specified number of levels.
+    pub fn query_bytes(&self, item: &[u8], num_levels_to_search: usize) -> bool {
+        self.first_match(&self.positions(item), num_levels_to_search).is_some()
+    }
+
+    /// Queries an integer key inserted with `BloomFilter::insert_u64`.
+    pub fn query_u64(&self, item: u64, num_levels_to_search: usize) -> bool {
+        let inner = &*self.inner;
+        let positions = match &inner.hash_key {
+            Some(hash_key) => keyed_positions(hash_key, inner.hash_functions.len(), inner.array_size, &item.to_le_bytes()),
+            None => u64_positions(&inner.hash_functions, inner.array_size, item),
+        };
+        self.first_match(&positions, num_levels_to_search).is_some()
+    }
+
+    fn positions(&self, key: &[u8]) -> Vec<usize> {
+        let inner = &*self.inner;
+        match &inner.hash_key {
+            Some(hash_key) => keyed_positions(hash_key, inner.hash_functions.len(), inner.array_size, key),
+            None => inner.hash_algorithm.positions(&inner.hash_functions, inner.array_size, key),
+        }
+    }
+
+    fn first_match(&self, positions: &[usize], num_levels_to_search: usize) -> Option<usize> {
+        let inner = &*self.inner;
+        (0..num_levels_to_search.min(inner.num_levels)).find(|&level| {
+            let words = &inner.words[level * inner.words_per_level..(level + 1) * inner.words_per_level];
+            positions.iter().all(|&bit| words[bit / 64] & (1 << (bit % 64)) != 0)
+        })
+    }
+
+    /// Returns the number of levels.
+    pub fn num_levels(&self) -> usize {
+        self.inner.num_levels
+    }
+
+    /// Returns the number of bits in each level.
+    pub fn array_size(&self) -> usize {
+        self.inner.array_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frozen_matches_source() {
+        let mut bf = BloomFilter::new(2, 1000, 3).unwrap();
+        let items: Vec<String> = (0..100).map(|i| format!("item-{}", i)).collect();
+        for item in &items {
+            bf.insert(item);
+        }
+        let frozen = bf.freeze();
+        let shared = frozen.clone();
+        let handle = std::thread::spawn(move || shared.query("item-42", 2));
+        assert!(handle.join().unwrap());
+
+        for i in 0..500 {
+            let item = format!("item-{}", i);
+            assert_eq!(frozen.query_level(&item, 2), bf.query_level(&item, 2));
+        }
+        assert_eq!(frozen.num_levels(), 2);
+        assert_eq!(frozen.array_size(), 1000);
+    }
+}
@@ -0,0 +1,151 @@
+// src/hashing.rs
+
+use serde::{Deserialize, Serialize};
+
+use crate::bloom_filter::{key_positions, HashFunction};
+
+/// The hash that maps items to bit positions, recorded in saved filters.
+///
+/// `Polynomial` is the original multiplier hash and stays the default so
+/// existing files load unchanged. The other algorithms hash each item once to
+/// 128 bits and derive every position from the two halves by double hashing
+/// (`h1 + i * h2`), which spreads short keys far better. `Xxh3` and `Murmur3`
+/// are only available when the `xxhash` and `murmur3` features are enabled;
+/// deserializing one that is not fails, so no filter can hold it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "StoredHashAlgorithm")]
+pub enum HashAlgorithm {
+    /// Polynomial rolling hash with one multiplier per hash function.
+    #[default]
+    Polynomial,
+    /// 64-bit FNV-1a, with the second half derived by a splitmix64 finalizer.
+    Fnv1a,
+    /// XXH3 128-bit (feature `xxhash`).
+    Xxh3,
+    /// MurmurHash3 x64 128-bit (feature `murmur3`).
+    Murmur3,
+}
+
+/// The serialized names of `HashAlgorithm`, read before checking the algorithm is compiled in.
+#[derive(Deserialize)]
+enum StoredHashAlgorithm {
+    Polynomial,
+    Fnv1a,
+    Xxh3,
+    Murmur3,
+}
+
+impl TryFrom<StoredHashAlgorithm> for HashAlgorithm {
+    type Error = String;
+
+    fn try_from(stored: StoredHashAlgorithm) -> Result<Self, Self::Error> {
+        let algorithm = match stored {
+            StoredHashAlgorithm::Polynomial => HashAlgorithm::Polynomial,
+            StoredHashAlgorithm::Fnv1a => HashAlgorithm::Fnv1a,
+            StoredHashAlgorithm::Xxh3 => HashAlgorithm::Xxh3,
+            StoredHashAlgorithm::Murmur3 => HashAlgorithm::Murmur3,
+        };
+        if !algorithm.is_available() {
+            return Err(format!("hash algorithm {:?} is not enabled in this build", algorithm));
+        }
+        Ok(algorithm)
+    }
+}
+
+impl HashAlgorithm {
+    /// Returns false if the algorithm's implementation was not compiled in.
+    pub fn is_available(self) -> bool {
+        match self {
+            HashAlgorithm::Polynomial | HashAlgorithm::Fnv1a => true,
+            HashAlgorithm::Xxh3 => cfg!(feature = "xxhash"),
+            HashAlgorithm::Murmur3 => cfg!(feature = "murmur3"),
+        }
+    }
+
+    /// Computes one bit position per hash function for `key`.
+    ///
+    /// An unavailable algorithm never reaches this point: `set_hash_algorithm`,
+    /// every binary decoder and deserialization reject it with an error, and
+    /// `validate` checks it again on load.
+    pub(crate) fn positions(self, hash_functions: &[HashFunction], array_size: usize, key: &[u8]) -> Vec<usize> {
+        let seed = hash_functions.first().map_or(0, HashFunction::seed);
+        let (h1, h2) = match self {
+            HashAlgorithm::Polynomial => return key_positions(hash_functions, array_size, key),
+            HashAlgorithm::Fnv1a => {
+                let h1 = fnv1a(key, seed);
+                (h1, crate::bloom_filter::splitmix64(h1))
+            }
+            #[cfg(feature = "xxhash")]
+            HashAlgorithm::Xxh3 => split(xxhash_rust::xxh3::xxh3_128_with_seed(key, seed)),
+            #[cfg(feature = "murmur3")]
+            HashAlgorithm::Murmur3 => split(fastmurmur3::murmur3_x64_128(key, seed)),
+            #[allow(unreachable_patterns)]
+            unavailable => unreachable!("hash algorithm {:?} was rejected when the filter was built", unavailable),
+        };
+        double_hash(h1, h2, hash_functions.len(), array_size)
+    }
+}
+
+/// Derives `num_hashes` positions from two base hashes as `h1 + i * h2`.
+///
+/// `h2` is forced odd so the probe sequence never collapses onto one bit.
+/// An empty array has no positions.
+pub(crate) fn double_hash(h1: u64, h2: u64, num_hashes: usize, array_size: usize) -> Vec<usize> {
+    let h2 = h2 | 1;
+    (0..num_hashes as u64)
+        .filter_map(|i| h1.wrapping_add(i.wrapping_mul(h2)).checked_rem(array_size as u64))
+        .map(|position| position as usize)
+        .collect()
+}
+
+/// 64-bit FNV-1a, with the seed folded into the offset basis.
+pub(crate) fn fnv1a(key: &[u8], seed: u64) -> u64 {
+    key.iter().fold(0xcbf2_9ce4_8422_2325 ^ seed, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(any(feature = "xxhash", feature = "murmur3"))]
+fn split(hash: u128) -> (u64, u64) {
+    (hash as u64, (hash >> 64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+    #[test]
+    fn test_algorithms_round_trip() {
+        let algorithms = [
+            HashAlgorithm::Polynomial,
+            HashAlgorithm::Fnv1a,
+            HashAlgorithm::Xxh3,
+            HashAlgorithm::Murmur3,
+        ];
+        for algorithm in algorithms.into_iter().filter(|a| a.is_available()) {
+            let mut bf = BloomFilter::builder().array_size(2000).hash_algorithm(algorithm).build().unwrap();
+            for i in 0..100 {
+                bf.insert(&format!("k{}", i));
+            }
+            assert!((0..100).all(|i| bf.query(&format!("k{}", i), 1)));
+
+            let filepath = std::env::temp_dir().join(format!("test_hash_algorithm_{:?}.json", algorithm));
+            let filepath = filepath.to_str().unwrap();
+            bf.save_to_file(filepath).unwrap();
+            let loaded = BloomFilter::load_from_file(filepath).unwrap();
+            assert_eq!(loaded.hash_algorithm(), algorithm);
+            assert!(loaded.query("k42", 1));
+            std::fs::remove_file(filepath).unwrap();
+        }
+        for algorithm in algorithms.into_iter().filter(|a| !a.is_available()) {
+            let json = format!(r#"{{"levels":[],"hash_functions":[],"hash_algorithm":"{:?}"}}"#, algorithm);
+            let error = BloomFilter::load_from_reader(json.as_bytes()).err().unwrap();
+            assert!(error.to_string().contains("not enabled in this build"), "{}", error);
+            assert!(matches!(
+                crate::format::algorithm_from_code(crate::format::algorithm_code(algorithm)),
+                Err(BloomFilterError::UnsupportedHashAlgorithm(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// src/hyperloglog.rs
+
+use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher13;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter};
+use tracing::{error, info};
+
+use crate::bloom_filter::BloomFilterError;
+
+/// Smallest supported precision (16 registers).
+pub const MIN_PRECISION: u8 = 4;
+/// Largest supported precision (65536 registers).
+pub const MAX_PRECISION: u8 = 16;
+
+const HLL_KEY: u64 = 0x4859_5045_524c_4c47;
+
+/// A HyperLogLog estimating how many distinct items have been inserted.
+///
+/// `2^precision` one-byte registers record the longest run of leading zeros
+/// seen among the hashes routed to them. The standard error of `cardinality`
+/// is about `1.04 / sqrt(2^precision)`: 1.6% at the default 12. Like filters,
+/// sketches with the same precision and seed can be combined with
+/// `union_with`, giving exactly the sketch of the combined stream.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HyperLogLog {
+    precision: u8,
+    #[serde(default)]
+    seed: u64,
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new(12).expect("12 is a valid precision")
+    }
+}
+
+impl HyperLogLog {
+    /// Creates a sketch with `2^precision` registers.
+    pub fn new(precision: u8) -> Result<Self, BloomFilterError> {
+        info!("Creating HyperLogLog: precision={}", precision);
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            error!("Requested unsupported precision {}", precision);
+            return Err(BloomFilterError::InvalidPrecision {
+                requested: precision,
+                min: MIN_PRECISION,
+                max: MAX_PRECISION,
+            });
+        }
+        Ok(HyperLogLog {
+            precision,
+            seed: 0,
+            registers: vec![0; 1 << precision],
+        })
+    }
+
+    /// Creates the smallest sketch whose standard error is at most `standard_error`.
+    ///
+    /// Errors below what the largest precision gives (0.4%) are an error.
+    pub fn for_error(standard_error: f64) -> Result<Self, BloomFilterError> {
+        let registers = (1.04 / standard_error).powi(2);
+        let precision = if registers.is_finite() && registers > 0.0 {
+            registers.log2().ceil().clamp(MIN_PRECISION as f64, u8::MAX as f64) as u8
+        } else {
+            u8::MAX
+        };
+        Self::new(precision)
+    }
+
+    /// Sets the seed perturbing every hash, before anything is inserted.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Records an item.
+    pub fn insert(&mut self, item: &str) {
+        self.insert_bytes(item.as_bytes());
+    }
+
+    /// Records a binary key.
+    pub fn insert_bytes(&mut self, item: &[u8]) {
+        let mut hasher = SipHasher13::new_with_keys(self.seed, HLL_KEY);
+        hasher.write(item);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - self.precision)) as usize;
+        // The remaining bits, with a sentinel so an all-zero remainder has a bounded rank.
+        let rest = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        let register = &mut self.registers[index];
+        *register = (*register).max(rank);
+    }
+
+    /// Estimates the number of distinct items inserted.
+    pub fn cardinality(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|&rank| (-(rank as f64)).exp2()).sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        // Linear counting is more accurate while many registers are still empty.
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+
+    /// Returns the expected relative standard error of `cardinality`.
+    pub fn standard_error(&self) -> f64 {
+        1.04 / (self.registers.len() as f64).sqrt()
+    }
+
+    /// Returns the precision: the base-2 logarithm of the register count.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Merges another sketch into this one, as if its items had been inserted here.
+    pub fn union_with(&mut self, other: &HyperLogLog) -> Result<(), BloomFilterError> {
+        if self.precision != other.precision || self.seed != other.seed {
+            return Err(BloomFilterError::IncompatibleFilters(format!(
+                "precision {} with seed {} vs precision {} with seed {}",
+                self.precision, self.seed, other.precision, other.seed
+            )));
+        }
+        for (register, &theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(theirs);
+        }
+        Ok(())
+    }
+
+    /// Forgets every item.
+    pub fn clear(&mut self) {
+        self.registers.iter_mut().for_each(|register| *register = 0);
+    }
+
+    /// Saves the sketch as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving HyperLogLog to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a sketch saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading HyperLogLog from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let hll: Self = serde_json::from_reader(reader)?;
+        let expected = Self::new(hll.precision)?.registers.len();
+        if hll.registers.len() != expected {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "HyperLogLog has {} registers, expected {}",
+                hll.registers.len(),
+                expected
+            )));
+        }
+        Ok(hll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cardinality_within_error() {
+        let mut first = HyperLogLog::default();
+        let mut second = HyperLogLog::default();
+        for i in 0..60_000 {
+            first.insert(&format!("item-{}", i));
+            // Half of the second sketch's items overlap the first.
+            second.insert(&format!("item-{}", i + 30_000));
+            first.insert(&format!("item-{}", i));
+        }
+        let within = |hll: &HyperLogLog, truth: f64| (hll.cardinality() as f64 - truth).abs() / truth < 4.0 * hll.standard_error();
+        assert!(within(&first, 60_000.0), "estimated {}", first.cardinality());
+        first.union_with(&second).unwrap();
+        assert!(within(&first, 90_000.0), "estimated {}", first.cardinality());
+
+        let mut small = HyperLogLog::new(14).unwrap();
+        (0..100).for_each(|i| small.insert(&i.to_string()));
+        assert!(small.cardinality().abs_diff(100) <= 2);
+        assert!(first.union_with(&small).is_err());
+        assert!(HyperLogLog::new(20).is_err());
+        assert_eq!(HyperLogLog::for_error(0.02).unwrap().precision(), 12);
+
+        let path = std::env::temp_dir().join("test_bloom_hyperloglog.json");
+        let path = path.to_str().unwrap();
+        first.save_to_file(path).unwrap();
+        assert_eq!(HyperLogLog::load_from_file(path).unwrap(), first);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+// src/interchange.rs
+
+use std::fs::File;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+use std::io::Read;
+#[cfg(feature = "msgpack")]
+use std::io::Write;
+use std::io::{BufReader, BufWriter};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// Compact binary encodings for exchanging filters with non-Rust systems.
+///
+/// Both formats are self-describing and keep the field names of the JSON
+/// form, so a filter reads the same in any MessagePack or CBOR library.
+impl BloomFilter {
+    /// Saves the Bloom filter to a file as MessagePack.
+    #[cfg(feature = "msgpack")]
+    pub fn save_msgpack(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving BloomFilter to MessagePack file: {}", filepath);
+        self.write_msgpack(BufWriter::new(File::create(filepath)?))
+    }
+
+    #[cfg(feature = "msgpack")]
+    pub(crate) fn write_msgpack<W: Write>(&self, mut writer: W) -> Result<(), BloomFilterError> {
+        rmp_serde::encode::write_named(&mut writer, self).map_err(|e| encoding_error("MessagePack", e))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Loads a Bloom filter from a MessagePack file.
+    #[cfg(feature = "msgpack")]
+    pub fn load_msgpack(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading BloomFilter from MessagePack file: {}", filepath);
+        Self::read_msgpack(BufReader::new(File::open(filepath)?))
+    }
+
+    #[cfg(feature = "msgpack")]
+    pub(crate) fn read_msgpack<R: Read>(reader: R) -> Result<Self, BloomFilterError> {
+        let bloom_filter: Self = rmp_serde::from_read(reader).map_err(|e| encoding_error("MessagePack", e))?;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Saves the Bloom filter to a file as CBOR.
+    #[cfg(feature = "cbor")]
+    pub fn save_cbor(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving BloomFilter to CBOR file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        ciborium::ser::into_writer(self, writer).map_err(|e| encoding_error("CBOR", e))?;
+        Ok(())
+    }
+
+    /// Loads a Bloom filter from a CBOR file.
+    #[cfg(feature = "cbor")]
+    pub fn load_cbor(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading BloomFilter from CBOR file: {}", filepath);
+        Self::read_cbor(BufReader::new(File::open(filepath)?))
+    }
+
+    /// Reads and validates a Bloom filter in CBOR from a reader.
+    #[cfg(feature = "cbor")]
+    pub fn read_cbor<R: Read>(reader: R) -> Result<Self, BloomFilterError> {
+        let bloom_filter: Self = ciborium::de::from_reader(reader).map_err(|e| encoding_error("CBOR", e))?;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+}
+
+fn encoding_error(format: &'static str, error: impl std::fmt::Display) -> BloomFilterError {
+    BloomFilterError::Encoding {
+        format,
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> BloomFilter {
+        let mut bf = BloomFilter::builder().levels(2).array_size(64).seed(9).build().unwrap();
+        bf.set_level_label(1, Some("cold".to_string())).unwrap();
+        bf.insert("apple");
+        bf
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_round_trip() {
+        let filepath = std::env::temp_dir().join("test_filter.msgpack");
+        let filepath = filepath.to_str().unwrap();
+        sample().save_msgpack(filepath).unwrap();
+        let loaded = BloomFilter::load_msgpack(filepath).unwrap();
+        assert!(loaded.query("apple", 2));
+        assert_eq!(loaded.seed(), 9);
+        assert_eq!(loaded.level_index("cold"), Some(1));
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_round_trip() {
+        let filepath = std::env::temp_dir().join("test_filter.cbor");
+        let filepath = filepath.to_str().unwrap();
+        sample().save_cbor(filepath).unwrap();
+        let loaded = BloomFilter::load_cbor(filepath).unwrap();
+        assert!(loaded.query("apple", 2));
+        assert_eq!(loaded.seed(), 9);
+        assert_eq!(loaded.level_index("cold"), Some(1));
+        std::fs::remove_file(filepath).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+// src/journal.rs
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use siphasher::sip128::SipHasher24;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::observer::Observer;
+
+/// The first line of every journal.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    hashed: bool,
+}
+
+const FORMAT: &str = "bloom-journal";
+const VERSION: u32 = 1;
+
+/// An append-only record of the keys inserted into a filter.
+///
+/// A filter's bits cannot be resized or re-hashed, but its journal can be
+/// replayed into a filter of any size with `BloomFilter::replay`, here or on
+/// another machine. The file is a JSON header line followed by one base64
+/// key per line, each flushed as it is appended. A hashed journal stores a
+/// 128-bit SipHash digest of each key instead of the key, so it can be
+/// shared without revealing the items; query a filter replayed from it with
+/// `query_bytes(&Journal::hashed_key(key))`.
+///
+/// A journal is an `Observer`, so registering it with `add_observer` records
+/// every insert as the filter hashed it: string keys after normalization,
+/// integer keys as their little-endian bytes (they replay as byte keys).
+pub struct Journal {
+    writer: Mutex<BufWriter<File>>,
+    hashed: bool,
+}
+
+impl Journal {
+    /// Opens the journal at `path` for appending, creating it if it does not exist.
+    ///
+    /// An existing journal must have been created with the same `hashed` setting.
+    pub fn open(path: &str, hashed: bool) -> Result<Self, BloomFilterError> {
+        info!("Opening journal {} (hashed={})", path, hashed);
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            let header = Header {
+                format: FORMAT.to_string(),
+                version: VERSION,
+                hashed,
+            };
+            serde_json::to_writer(&mut file, &header)?;
+            file.write_all(b"\n")?;
+            file.sync_all()?;
+        } else if read_header(&mut BufReader::new(File::open(path)?))?.hashed != hashed {
+            return Err(BloomFilterError::InvalidJournal(format!(
+                "{} was created with hashed={}",
+                path, !hashed
+            )));
+        }
+        Ok(Journal {
+            writer: Mutex::new(BufWriter::new(file)),
+            hashed,
+        })
+    }
+
+    /// Appends a key, or its digest if the journal is hashed.
+    pub fn append(&self, item: &[u8]) -> Result<(), BloomFilterError> {
+        let entry = match self.hashed {
+            true => BASE64.encode(Self::hashed_key(item)),
+            false => BASE64.encode(item),
+        };
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        writeln!(writer, "{}", entry)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Returns true if the journal stores digests instead of keys.
+    pub fn is_hashed(&self) -> bool {
+        self.hashed
+    }
+
+    /// Returns the digest a hashed journal stores for a key.
+    pub fn hashed_key(item: &[u8]) -> [u8; 16] {
+        SipHasher24::new().hash(item).as_bytes()
+    }
+}
+
+impl Observer for Journal {
+    fn on_insert(&self, item: &[u8], _newly_set: bool) {
+        if let Err(e) = self.append(item) {
+            error!("Failed to append to journal: {}", e);
+        }
+    }
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> Result<Header, BloomFilterError> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let header: Header = serde_json::from_str(&line)
+        .map_err(|e| BloomFilterError::InvalidJournal(format!("unreadable header: {}", e)))?;
+    if header.format != FORMAT || header.version != VERSION {
+        return Err(BloomFilterError::InvalidJournal(format!(
+            "unsupported format {} version {}",
+            header.format, header.version
+        )));
+    }
+    Ok(header)
+}
+
+impl BloomFilter {
+    /// Inserts every key recorded in a journal into `filter` and returns it.
+    ///
+    /// `filter` is typically a fresh filter sized for the journal's contents,
+    /// with whatever parameters the rebuilt filter should have. Keys are
+    /// inserted as bytes, exactly as recorded. A torn final line, left by a
+    /// crash mid-append, is skipped with a warning.
+    pub fn replay(journal_path: &str, mut filter: BloomFilter) -> Result<Self, BloomFilterError> {
+        info!("Replaying journal {}", journal_path);
+        let mut reader = BufReader::new(File::open(journal_path)?);
+        read_header(&mut reader)?;
+        let (mut line, mut count) = (Vec::new(), 0);
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            if line.pop() != Some(b'\n') {
+                warn!("Ignoring unterminated entry at end of {}", journal_path);
+                break;
+            }
+            let item = BASE64
+                .decode(&line)
+                .map_err(|e| BloomFilterError::InvalidJournal(format!("entry {}: {}", count + 1, e)))?;
+            filter.insert_bytes(&item);
+            count += 1;
+            line.clear();
+        }
+        info!("Replayed {} keys from {}", count, journal_path);
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_journal_replays_into_a_resized_filter() {
+        let path = std::env::temp_dir().join("test_bloom_journal.log");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut small = BloomFilter::builder().array_size(64).normalization("lowercase".parse().unwrap()).build().unwrap();
+        small.add_observer(Arc::new(Journal::open(path, false).unwrap()));
+        let items: Vec<String> = (0..200).map(|i| format!("Item{}", i)).collect();
+        items.iter().for_each(|item| {
+            small.insert(item);
+        });
+        assert!(Journal::open(path, true).is_err());
+
+        let large = BloomFilter::replay(path, BloomFilter::new(1, 5000, 4).unwrap()).unwrap();
+        assert_eq!(large.levels()[0].item_count(), 200);
+        assert!(items.iter().all(|item| large.query(&item.to_lowercase(), 1)));
+        assert!(large.estimated_false_positive_rate() < small.estimated_false_positive_rate());
+
+        // Reopening appends; a torn line at the end is skipped.
+        Journal::open(path, false).unwrap().append(b"item200").unwrap();
+        OpenOptions::new().append(true).open(path).unwrap().write_all(b"aXRl").unwrap();
+        let replayed = BloomFilter::replay(path, BloomFilter::new(1, 5000, 4).unwrap()).unwrap();
+        assert!(replayed.query("item200", 1) && !replayed.query("ite", 1));
+        OpenOptions::new().append(true).open(path).unwrap().write_all(b"!\n").unwrap();
+        assert!(BloomFilter::replay(path, BloomFilter::new(1, 5000, 4).unwrap()).is_err());
+        std::fs::remove_file(path).unwrap();
+
+        let journal = Journal::open(path, true).unwrap();
+        journal.append(b"secret").unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert!(!contents.contains(&BASE64.encode(b"secret")));
+        let replayed = BloomFilter::replay(path, BloomFilter::new(1, 100, 3).unwrap()).unwrap();
+        assert!(replayed.query_bytes(&Journal::hashed_key(b"secret"), 1));
+        std::fs::remove_file(path).unwrap();
+    }
+}
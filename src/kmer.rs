@@ -0,0 +1,287 @@
+// src/kmer.rs
+
+use std::io::{self, BufRead};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// Longest k-mer that fits in a `u64` at two bits per nucleotide.
+pub const MAX_K: usize = 32;
+
+/// Level metadata keys recording how a k-mer filter was built.
+pub const K_METADATA_KEY: &str = "kmer-k";
+pub const CANONICAL_METADATA_KEY: &str = "kmer-canonical";
+
+/// How sequences are cut into k-mers.
+///
+/// Each k-mer is packed two bits per nucleotide (A=0, C=1, G=2, T=3) into a
+/// `u64` that is updated as the window slides, then inserted with
+/// `insert_u64`, so no k-mer is ever materialized as a string. Any other
+/// symbol (N, gaps) breaks the window, and k-mers spanning it are skipped.
+/// Canonical k-mers are the smaller of a k-mer and its reverse complement,
+/// so a read matches whichever strand it came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KmerMode {
+    k: usize,
+    canonical: bool,
+}
+
+impl KmerMode {
+    pub fn new(k: usize, canonical: bool) -> Result<Self, BloomFilterError> {
+        if !(1..=MAX_K).contains(&k) {
+            return Err(BloomFilterError::InvalidKmer(format!("k must be between 1 and {}, got {}", MAX_K, k)));
+        }
+        Ok(KmerMode { k, canonical })
+    }
+
+    /// Reads the mode recorded by `prepare` from a filter's metadata.
+    pub fn from_filter(filter: &BloomFilter) -> Result<Self, BloomFilterError> {
+        let metadata = filter.levels().first().map(|level| level.metadata());
+        let value = |key: &str| metadata.and_then(|metadata| metadata.get(key));
+        let k = value(K_METADATA_KEY)
+            .and_then(|k| k.parse().ok())
+            .ok_or_else(|| BloomFilterError::InvalidKmer("no k-mer length recorded".to_string()))?;
+        KmerMode::new(k, value(CANONICAL_METADATA_KEY).is_some_and(|canonical| canonical == "true"))
+    }
+
+    /// Records the mode in the filter's metadata, so `from_filter` can query it with the same k.
+    pub fn prepare(&self, filter: &mut BloomFilter) -> Result<(), BloomFilterError> {
+        filter.set_level_metadata(0, K_METADATA_KEY, &self.k.to_string())?;
+        filter.set_level_metadata(0, CANONICAL_METADATA_KEY, &self.canonical.to_string())
+    }
+
+    /// Returns the k-mer length.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns whether k-mers are folded onto their canonical strand.
+    pub fn canonical(&self) -> bool {
+        self.canonical
+    }
+
+    /// Iterates over the encoded k-mers of a sequence.
+    pub fn kmers<'a>(&self, sequence: &'a [u8]) -> Kmers<'a> {
+        let mask = if self.k == MAX_K { u64::MAX } else { (1 << (2 * self.k)) - 1 };
+        Kmers {
+            sequence: sequence.iter(),
+            mode: *self,
+            mask,
+            forward: 0,
+            reverse: 0,
+            filled: 0,
+        }
+    }
+
+    /// Inserts every k-mer of a sequence, returning how many there were.
+    pub fn insert(&self, filter: &mut BloomFilter, sequence: &[u8]) -> usize {
+        self.kmers(sequence).map(|kmer| filter.insert_u64(kmer)).count()
+    }
+
+    /// Queries every k-mer of a sequence, returning how many there were and how many matched.
+    pub fn query(&self, filter: &BloomFilter, sequence: &[u8]) -> (usize, usize) {
+        let levels = filter.levels().len();
+        self.kmers(sequence)
+            .fold((0, 0), |(total, present), kmer| (total + 1, present + usize::from(filter.query_u64(kmer, levels))))
+    }
+
+    /// Returns how many k-mers a sequence of `len` nucleotides has at most.
+    pub fn max_kmers(&self, len: usize) -> usize {
+        (len + 1).saturating_sub(self.k)
+    }
+}
+
+/// The encoded k-mers of a sequence; see `KmerMode::kmers`.
+pub struct Kmers<'a> {
+    sequence: std::slice::Iter<'a, u8>,
+    mode: KmerMode,
+    mask: u64,
+    forward: u64,
+    reverse: u64,
+    filled: usize,
+}
+
+impl Iterator for Kmers<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        for &base in self.sequence.by_ref() {
+            let Some(code) = encode(base) else {
+                self.filled = 0;
+                continue;
+            };
+            self.forward = ((self.forward << 2) | code) & self.mask;
+            self.reverse = (self.reverse >> 2) | ((3 - code) << (2 * (self.mode.k - 1)));
+            self.filled += 1;
+            if self.filled >= self.mode.k {
+                return Some(if self.mode.canonical { self.forward.min(self.reverse) } else { self.forward });
+            }
+        }
+        None
+    }
+}
+
+fn encode(base: u8) -> Option<u64> {
+    match base {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' | b'U' | b'u' => Some(3),
+        _ => None,
+    }
+}
+
+/// One named sequence from a FASTA or FASTQ file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceRecord {
+    /// The header up to the first whitespace, without `>` or `@`.
+    pub id: String,
+    pub sequence: Vec<u8>,
+}
+
+/// Reads FASTA or FASTQ records, telling the two apart by the first header.
+///
+/// FASTA sequences may span several lines; FASTQ records are the usual four
+/// lines, and their quality lines are skipped.
+pub struct SequenceReader<R> {
+    reader: R,
+    line: Vec<u8>,
+    /// The next record's header, already read while finishing the previous FASTA record.
+    pending_header: Option<Vec<u8>>,
+}
+
+impl<R: BufRead> SequenceReader<R> {
+    pub fn new(reader: R) -> Self {
+        SequenceReader {
+            reader,
+            line: Vec::new(),
+            pending_header: None,
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+            return Ok(false);
+        }
+        while matches!(self.line.last(), Some(b'\n' | b'\r')) {
+            self.line.pop();
+        }
+        Ok(true)
+    }
+
+    fn next_record(&mut self) -> io::Result<Option<SequenceRecord>> {
+        let header = match self.pending_header.take() {
+            Some(header) => header,
+            None => loop {
+                if !self.read_line()? {
+                    return Ok(None);
+                }
+                if !self.line.is_empty() {
+                    break std::mem::take(&mut self.line);
+                }
+            },
+        };
+        let id = String::from_utf8_lossy(&header[1..])
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let mut sequence = Vec::new();
+        match header[0] {
+            b'>' => {
+                while self.read_line()? {
+                    if self.line.first() == Some(&b'>') {
+                        self.pending_header = Some(std::mem::take(&mut self.line));
+                        break;
+                    }
+                    sequence.extend_from_slice(&self.line);
+                }
+            }
+            b'@' => {
+                if !self.read_line()? {
+                    return Err(invalid(&format!("FASTQ record {} has no sequence", id)));
+                }
+                sequence.extend_from_slice(&self.line);
+                // The '+' separator and the quality line.
+                if !self.read_line()? || self.line.first() != Some(&b'+') || !self.read_line()? {
+                    return Err(invalid(&format!("FASTQ record {} is truncated", id)));
+                }
+            }
+            _ => return Err(invalid("expected a FASTA '>' or FASTQ '@' header")),
+        }
+        Ok(Some(SequenceRecord { id, sequence }))
+    }
+}
+
+impl<R: BufRead> Iterator for SequenceReader<R> {
+    type Item = io::Result<SequenceRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl BloomFilter {
+    /// Inserts every k-mer of every record, returning the number of records and k-mers.
+    pub fn insert_kmers_from<R: BufRead>(
+        &mut self,
+        reader: SequenceReader<R>,
+        mode: &KmerMode,
+    ) -> Result<(usize, usize), BloomFilterError> {
+        let (mut records, mut kmers) = (0, 0);
+        for record in reader {
+            kmers += mode.insert(self, &record?.sequence);
+            records += 1;
+        }
+        info!("Inserted {} {}-mers from {} sequences", kmers, mode.k(), records);
+        Ok((records, kmers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kmers_roll_across_strands_and_formats() {
+        let forward = KmerMode::new(3, false).unwrap();
+        // ACG, CGT; the N breaks the window, then TTA.
+        let kmers: Vec<u64> = forward.kmers(b"ACGTNTTA").collect();
+        assert_eq!(kmers, [0b00_01_10, 0b01_10_11, 0b11_11_00]);
+
+        // A sequence and its reverse complement share canonical k-mers.
+        let canonical = KmerMode::new(31, true).unwrap();
+        let sequence = b"ACGTTGCATGCCATAGGCTAAGCTTCGATCGGATCCTAGG";
+        let complement: Vec<u8> = sequence
+            .iter()
+            .rev()
+            .map(|base| match base {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                _ => b'A',
+            })
+            .collect();
+        let mut filter = BloomFilter::new(1, 10_000, 3).unwrap();
+        canonical.prepare(&mut filter).unwrap();
+        assert_eq!(canonical.insert(&mut filter, sequence), 10);
+        assert_eq!(canonical.query(&filter, &complement), (10, 10));
+        assert_eq!(KmerMode::from_filter(&filter).unwrap(), canonical);
+        assert!(KmerMode::new(33, true).is_err());
+        assert!(KmerMode::new(32, false).unwrap().kmers(&[b'T'; 32]).eq([u64::MAX]));
+
+        let fasta = b">chr1 first\nACGT\nACGT\n\n>chr2\nGGGG\n";
+        let records: Vec<SequenceRecord> = SequenceReader::new(&fasta[..]).map(Result::unwrap).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].id.as_str(), &records[0].sequence[..]), ("chr1", &b"ACGTACGT"[..]));
+        let fastq = b"@read1\nACGTA\n+\nIIIII\n@read2\nTTTT\n+\nIIII\n";
+        let mut filter = BloomFilter::new(1, 1000, 3).unwrap();
+        assert_eq!(filter.insert_kmers_from(SequenceReader::new(&fastq[..]), &forward).unwrap(), (2, 5));
+        assert!(SequenceReader::new(&b"@read\nACGT\n"[..]).next().unwrap().is_err());
+    }
+}
@@ -1,5 +1,129 @@
+pub mod adaptive;
+pub mod age_partitioned;
+#[cfg(feature = "rkyv")]
+pub mod archive;
+mod bits;
+pub mod blocklist;
 pub mod bloom_filter;
+pub mod bloomier;
+pub mod builder;
+pub mod chain;
+#[cfg(all(feature = "client", unix))]
+pub mod client;
+mod chunked;
+#[cfg(feature = "cli")]
+pub mod commands;
+pub mod compare;
+pub mod count_min;
+pub mod counting;
+#[cfg(all(feature = "cli", unix))]
+pub mod daemon;
+pub mod deletable;
+pub mod embedded;
+pub mod expiring;
+#[cfg(feature = "encryption")]
+mod encryption;
+pub mod filter;
+pub mod format;
+pub mod frozen;
+pub mod hashing;
+pub mod hyperloglog;
+pub mod journal;
+pub mod kmer;
+pub mod math;
+pub mod metrics;
+pub mod minhash;
+pub mod ngram;
+pub mod observer;
+mod ops;
+pub mod normalize;
+pub mod portable;
+#[cfg(feature = "passwords")]
+pub mod passwords;
+pub mod prefix;
+#[cfg(feature = "cli")]
+pub(crate) mod progress;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+pub mod redis;
+pub mod replication;
+pub mod saturation;
+pub mod sharded;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "sled")]
+pub mod sled_backend;
+#[cfg(feature = "cli")]
+pub mod repl;
+pub mod sliding;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod source;
+pub mod static_filter;
+pub mod stats;
+pub mod store;
+pub mod sync;
+pub mod top_k;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod tuning;
+#[cfg(feature = "cli")]
 pub mod utils;
+pub mod variant;
+pub mod visualize;
+pub mod wal;
 
-pub use bloom_filter::BloomFilter;
-pub use utils::{read_string_input, read_usize_input, select_operation};
+#[cfg(feature = "async")]
+pub mod async_io;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod interchange;
+
+pub use adaptive::AdaptiveFilter;
+pub use age_partitioned::AgePartitionedBloomFilter;
+#[cfg(feature = "rkyv")]
+pub use archive::ArchivedBloomFilter;
+pub use bits::BitStorage;
+pub use bloom_filter::{BloomFilter, InsertMode, RebuildParams, MAX_HASH_FUNCTIONS};
+pub use bloomier::BloomierFilter;
+pub use builder::BloomFilterBuilder;
+pub use chain::FilterChain;
+#[cfg(all(feature = "client", unix))]
+pub use client::{DaemonClient, QueryResult};
+pub use compare::{FilterComparison, FilterDiff, LevelDiff};
+pub use count_min::CountMinSketch;
+pub use counting::{CounterWidth, CountingBloomFilter};
+pub use deletable::DeletableBloomFilter;
+pub use embedded::EmbeddedBloomFilter;
+pub use expiring::ExpiringBloomFilter;
+pub use filter::Filter;
+pub use format::FileFormat;
+pub use frozen::FrozenBloomFilter;
+pub use hashing::HashAlgorithm;
+pub use hyperloglog::HyperLogLog;
+pub use journal::Journal;
+pub use metrics::FilterMetrics;
+pub use minhash::MinHash;
+pub use normalize::{Normalization, UnicodeForm};
+pub use observer::Observer;
+pub use portable::PortableHeader;
+pub use prefix::PrefixBloomFilter;
+pub use redis::RedisBloomFilter;
+pub use saturation::{CapacityLimit, SaturationAlert, SaturationMonitor};
+pub use sharded::ShardedBloomFilter;
+#[cfg(feature = "sled")]
+pub use sled_backend::SledBloomFilter;
+pub use sliding::SlidingBloomFilter;
+pub use source::ItemSource;
+pub use static_filter::StaticBloomFilter;
+#[cfg(feature = "snapshot")]
+pub use snapshot::SnapshotBloomFilter;
+pub use stats::{FilterStats, LevelStats, MemoryUsage, OperationCounts};
+pub use store::FilterStore;
+pub use sync::{SyncDigest, SyncPatch};
+pub use top_k::TopK;
+pub use tuning::{TuningCandidate, TuningParams};
+pub use variant::{AnyFilter, FilterVariant, VariantParams};
+pub use visualize::Shading;
+pub use wal::WalBloomFilter;
+#[cfg(feature = "cli")]
+pub use utils::{read_string_input, read_usize_input};
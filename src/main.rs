@@ -1,105 +1,737 @@
 // src/main.rs
 
-use log::error;
-use std::path::Path;
+use clap::{Parser, Subcommand};
+use std::io::{self, BufRead, IsTerminal};
+use std::time::Duration;
+use tracing::error;
 
-use bloom::{BloomFilter, read_string_input, read_usize_input, select_operation};
+use bloom::commands::{self, BenchParams, FollowParams, GenerateParams, PasswordCorpusParams, SpellcheckParams};
+use bloom::repl::{self, Outcome, Session};
+use bloom::kmer::KmerMode;
+use bloom::ngram::{NgramMode, NgramUnit};
+use bloom::passwords::PasswordDigest;
+use bloom::{BloomFilter, FileFormat, FilterStore, FilterVariant, Normalization, TuningParams, VariantParams, read_usize_input};
+
+/// Interactive multi-level Bloom filter.
+#[derive(Parser)]
+#[command(name = "bloom", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Print every result as a single-line JSON object on stdout
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Directory of named filters to work with (default: one in-memory filter)
+    #[arg(long, global = true)]
+    store: Option<String>,
+
+    /// Filter in the store to start on; created if missing
+    #[arg(long, default_value = Session::DEFAULT_FILTER)]
+    name: String,
+
+    /// Number of hash functions, 1 to 256 (prompted for if omitted)
+    #[arg(long)]
+    hash_functions: Option<usize>,
+
+    /// Size of each level's bit array (prompted for if omitted)
+    #[arg(long)]
+    array_size: Option<usize>,
+
+    /// Number of levels (prompted for if omitted)
+    #[arg(long)]
+    levels: Option<usize>,
+
+    /// Key normalization for a new filter: a comma list of trim, lowercase and nfc or nfkc
+    #[arg(long, default_value = "none")]
+    normalize: Normalization,
+}
+
+/// Non-interactive commands; without one, the interactive REPL starts.
+#[derive(Subcommand)]
+enum Commands {
+    /// Manage the named filters in --store
+    Filters {
+        #[command(subcommand)]
+        action: FilterAction,
+    },
+    /// Measure insert/query throughput and the false-positive rate on random items
+    Bench {
+        /// Number of random items to insert (and to probe with)
+        #[arg(long, default_value_t = 100_000)]
+        items: usize,
+
+        /// Target false-positive rate used to size the filter
+        #[arg(long, default_value_t = 0.01)]
+        fpr: f64,
+
+        /// Size of the bit array (default: optimal for --items and --fpr)
+        #[arg(long)]
+        array_size: Option<usize>,
+
+        /// Number of hash functions (default: optimal for the array size)
+        #[arg(long)]
+        hash_functions: Option<usize>,
+
+        /// Seed for the random items
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Recommend array sizes and hash counts for an expected item count within memory and latency limits
+    Tune {
+        /// Number of items the filter must hold
+        #[arg(long)]
+        items: usize,
+
+        /// Target false-positive rate
+        #[arg(long, default_value_t = 0.01)]
+        fpr: f64,
+
+        /// Memory budget for the bit array, in bytes
+        #[arg(long)]
+        max_bytes: Option<usize>,
+
+        /// Most hash functions per operation, bounding insert and query latency
+        #[arg(long, default_value_t = bloom::MAX_HASH_FUNCTIONS)]
+        max_hash_functions: usize,
+
+        /// Also measure each candidate's false-positive rate with this many random probes
+        #[arg(long, default_value_t = 0)]
+        simulate: usize,
+
+        /// Seed for the random items
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Build a filter sized for a word list at a target false-positive rate and save it
+    Generate {
+        /// Newline-separated items to insert
+        #[arg(long)]
+        input: String,
+
+        /// Target false-positive rate
+        #[arg(long, default_value_t = 0.01)]
+        fpr: f64,
+
+        /// Where to save the filter
+        #[arg(long)]
+        out: String,
+
+        /// json, binary, compressed or msgpack (default: from the extension, else json)
+        #[arg(long)]
+        format: Option<FileFormat>,
+    },
+    /// Report whether two saved filters are compatible and how much they overlap
+    Compare {
+        first: String,
+        second: String,
+    },
+    /// Count the bits set only in one of two saved filters, level by level
+    Diff {
+        first: String,
+        second: String,
+    },
+    /// Draw each level's bits as shaded blocks to spot hash clustering
+    Visualize {
+        filter: String,
+
+        /// Number of blocks per level
+        #[arg(long, default_value_t = 64)]
+        width: usize,
+
+        /// Shade with ASCII characters instead of Unicode blocks
+        #[arg(long)]
+        ascii: bool,
+    },
+    /// Union saved filters with the same parameters into one file
+    Merge {
+        /// Where to save the merged filter
+        output: String,
+
+        /// Filters to merge
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// json, binary, compressed or msgpack (default: from the extension, else json)
+        #[arg(long)]
+        format: Option<FileFormat>,
+    },
+    /// Estimate the Jaccard similarity of two item lists from their MinHash signatures
+    Similarity {
+        /// Newline-separated items; - for stdin, tcp://host:port for a TCP stream
+        first: String,
+        second: String,
+
+        /// Number of MinHash permutations; more gives a tighter estimate
+        #[arg(long, default_value_t = 128)]
+        permutations: usize,
+    },
+    /// Report the most frequent items of a stream with approximate counts
+    TopK {
+        /// Newline-separated items; - for stdin, tcp://host:port for a TCP stream
+        source: String,
+
+        /// Number of items to report
+        #[arg(long, default_value_t = 10)]
+        k: usize,
+
+        /// Largest overcount, as a fraction of the number of items
+        #[arg(long, default_value_t = 0.0001)]
+        epsilon: f64,
+    },
+    /// Create an empty filter of any variant and save it, tagged with its variant
+    Create {
+        /// Where to save the filter
+        out: String,
+
+        /// standard, counting, deletable, age-partitioned or sliding
+        #[arg(long, default_value_t = FilterVariant::Standard)]
+        variant: FilterVariant,
+
+        /// Size of each level's bit array
+        #[arg(long, default_value_t = 10_000)]
+        array_size: usize,
+
+        /// Number of hash functions
+        #[arg(long, default_value_t = 3)]
+        hash_functions: usize,
+
+        /// Levels (standard), extra generations (age-partitioned) or buckets (sliding)
+        #[arg(long, default_value_t = 1)]
+        levels: usize,
+
+        /// Seconds a sliding filter remembers items
+        #[arg(long, default_value_t = 3600)]
+        window_secs: u64,
+    },
+    /// Insert items into a filter saved by 'create', whatever its variant
+    Add {
+        path: String,
+
+        #[arg(required = true)]
+        items: Vec<String>,
+    },
+    /// Check items against a filter saved by 'create', whatever its variant
+    Contains {
+        path: String,
+
+        #[arg(required = true)]
+        items: Vec<String>,
+    },
+    /// Insert each new line of a file as it is written, like tail -f, saving the filter periodically
+    ///
+    /// The filter is created with --levels, --array-size and --hash-functions if missing.
+    Follow {
+        /// File to watch
+        input: String,
+
+        /// Filter file to keep current
+        #[arg(long)]
+        filter: String,
+
+        /// Also insert the lines already in the file
+        #[arg(long)]
+        from_start: bool,
+
+        /// Seconds between saves while new lines arrive
+        #[arg(long, default_value_t = 30)]
+        save_secs: u64,
+
+        /// Milliseconds to wait for new lines before checking again
+        #[arg(long, default_value_t = 250)]
+        poll_ms: u64,
+
+        /// Stop after this many seconds without a new line (default: follow until killed)
+        #[arg(long)]
+        idle_secs: Option<u64>,
+    },
+    /// Flag the words of a text that are not in a dictionary
+    Spellcheck {
+        /// Text to check
+        input: String,
+
+        /// Word list, one word per line, or a filter saved with --save
+        #[arg(long)]
+        dict: String,
+
+        /// Target false-positive rate when building from a word list
+        #[arg(long, default_value_t = 0.001)]
+        fpr: f64,
+
+        /// Save the filter built from the word list here, for reuse as --dict
+        #[arg(long)]
+        save: Option<String>,
+    },
+    /// Build a filter from URL/domain blocklists and look up URLs or hosts in it
+    Blocklist {
+        #[command(subcommand)]
+        action: BlocklistAction,
+    },
+    /// Build a filter of breached passwords and check candidates against it offline
+    Passwords {
+        #[command(subcommand)]
+        action: PasswordAction,
+    },
+    /// Build a filter of DNA k-mers from FASTA/FASTQ files and match sequences against it
+    Kmers {
+        #[command(subcommand)]
+        action: KmerAction,
+    },
+    /// Build a filter of documents' character or word n-grams and measure how much of other documents it contains
+    Ngrams {
+        #[command(subcommand)]
+        action: NgramAction,
+    },
+    /// Keep the store's filters in memory and answer REPL commands sent over a Unix socket
+    ///
+    /// Starts on --name, created with --levels, --array-size and --hash-functions if missing.
+    #[cfg(unix)]
+    Daemon {
+        /// Path of the socket to listen on, e.g. /run/bloom.sock
+        #[arg(long)]
+        socket: String,
+    },
+    /// Start a full-screen dashboard instead of the REPL: per-level fill, rates, estimated FPR and a command line
+    ///
+    /// Takes the same --store, --name and filter options as the REPL.
+    #[cfg(feature = "tui")]
+    Tui,
+}
+
+#[derive(Subcommand)]
+enum BlocklistAction {
+    /// Read domain lists, hosts files, adblock rules or URLs and save a filter of their hosts
+    Build {
+        /// Where to save the filter
+        #[arg(long)]
+        out: String,
+
+        /// Blocklist files
+        #[arg(required = true)]
+        lists: Vec<String>,
+
+        /// Target false-positive rate: the share of unlisted hosts reported as blocked
+        #[arg(long, default_value_t = 0.0001)]
+        fpr: f64,
+
+        /// json, binary, compressed or msgpack (default: from the extension, else json)
+        #[arg(long)]
+        format: Option<FileFormat>,
+    },
+    /// Check URLs or hosts, and their parent domains, against a built blocklist
+    Check {
+        /// Filter saved by 'blocklist build'
+        #[arg(long)]
+        filter: String,
+
+        /// URLs or hosts to check (default: one per line from stdin)
+        targets: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PasswordAction {
+    /// Hash a breached-password corpus into a filter sized for it
+    Build {
+        /// Passwords, one per line, or hex digests with --hashed (a trailing :count is ignored)
+        corpus: String,
+
+        /// Where to save the filter
+        #[arg(long)]
+        out: String,
+
+        /// The corpus lines are already hex digests
+        #[arg(long)]
+        hashed: bool,
+
+        /// sha1 or sha256
+        #[arg(long, default_value_t = PasswordDigest::Sha1)]
+        digest: PasswordDigest,
+
+        /// Target false-positive rate: the share of safe passwords reported as breached
+        #[arg(long, default_value_t = 0.001)]
+        fpr: f64,
+
+        /// json, binary, compressed or msgpack (default: from the extension, else json)
+        #[arg(long)]
+        format: Option<FileFormat>,
+    },
+    /// Check passwords typed at a hidden prompt, or one per line on piped stdin
+    Check {
+        /// Filter saved by 'passwords build'
+        #[arg(long)]
+        filter: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum KmerAction {
+    /// Insert every k-mer of FASTA/FASTQ files (optionally .gz) into a filter sized for them
+    Build {
+        /// Where to save the filter
+        #[arg(long)]
+        out: String,
+
+        /// FASTA/FASTQ files
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// K-mer length, at most 32
+        #[arg(long, default_value_t = 31)]
+        k: usize,
+
+        /// Keep each strand's k-mers apart instead of folding them onto the canonical strand
+        #[arg(long)]
+        forward_only: bool,
+
+        /// Target false-positive rate per k-mer
+        #[arg(long, default_value_t = 0.01)]
+        fpr: f64,
+
+        /// json, binary, compressed or msgpack (default: from the extension, else json)
+        #[arg(long)]
+        format: Option<FileFormat>,
+    },
+    /// Report how many of each sequence's k-mers a built filter contains
+    Query {
+        /// Filter saved by 'kmers build'
+        #[arg(long)]
+        filter: String,
+
+        /// FASTA/FASTQ file (optionally .gz)
+        input: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum NgramAction {
+    /// Insert every n-gram of each document into a filter sized for them
+    Build {
+        /// Where to save the filter
+        #[arg(long)]
+        out: String,
+
+        /// Text files, each read as one document
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// Characters or words per n-gram
+        #[arg(long, default_value_t = 3)]
+        n: usize,
+
+        /// chars or words
+        #[arg(long, default_value_t = NgramUnit::Chars)]
+        unit: NgramUnit,
+
+        /// Target false-positive rate per n-gram
+        #[arg(long, default_value_t = 0.01)]
+        fpr: f64,
+
+        /// json, binary, compressed or msgpack (default: from the extension, else json)
+        #[arg(long)]
+        format: Option<FileFormat>,
+    },
+    /// Report the fraction of each document's n-grams a built filter contains
+    Query {
+        /// Filter saved by 'ngrams build'
+        #[arg(long)]
+        filter: String,
+
+        /// Text files, each read as one document
+        #[arg(required = true)]
+        inputs: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum FilterAction {
+    /// List the filters
+    List,
+    /// Copy a filter under a new name
+    Copy { from: String, to: String },
+    /// Rename a filter
+    Rename { from: String, to: String },
+}
 
 fn main() {
     // Initialize the logger
     env_logger::init();
 
-    println!("Welcome to the Bloom Filter CLI!");
+    let cli = Cli::parse();
+    #[cfg(feature = "tui")]
+    let tui = matches!(cli.command, Some(Commands::Tui));
+    #[cfg(not(feature = "tui"))]
+    let tui = false;
+    if let Some(command) = cli.command.filter(|_| !tui) {
+        let result = match command {
+            Commands::Filters { action } => match &cli.store {
+                None => Err("'filters' needs --store DIR".to_string()),
+                Some(dir) => match action {
+                    FilterAction::List => commands::list_filters(dir),
+                    FilterAction::Copy { from, to } => commands::copy_filter(dir, &from, &to),
+                    FilterAction::Rename { from, to } => commands::rename_filter(dir, &from, &to),
+                },
+            },
+            Commands::Bench {
+                items,
+                fpr,
+                array_size,
+                hash_functions,
+                seed,
+            } => commands::bench(&BenchParams {
+                items,
+                false_positive_rate: fpr,
+                array_size,
+                num_hash_functions: hash_functions,
+                seed,
+            }),
+            Commands::Tune {
+                items,
+                fpr,
+                max_bytes,
+                max_hash_functions,
+                simulate,
+                seed,
+            } => commands::tune(&TuningParams {
+                expected_items: items,
+                target_false_positive_rate: fpr,
+                max_bytes,
+                max_hash_functions,
+                simulate_probes: simulate,
+                seed,
+            }),
+            Commands::Generate { input, fpr, out, format } => commands::generate(&GenerateParams {
+                input,
+                output: out,
+                false_positive_rate: fpr,
+                format,
+            }),
+            Commands::Compare { first, second } => commands::compare(&first, &second),
+            Commands::Diff { first, second } => commands::diff(&first, &second),
+            Commands::Visualize { filter, width, ascii } => commands::visualize(&filter, width, ascii),
+            Commands::Merge { output, inputs, format } => commands::merge(&output, &inputs, format),
+            Commands::Similarity {
+                first,
+                second,
+                permutations,
+            } => commands::similarity(&first, &second, permutations),
+            Commands::TopK { source, k, epsilon } => commands::top_k(&source, k, epsilon),
+            Commands::Create {
+                out,
+                variant,
+                array_size,
+                hash_functions,
+                levels,
+                window_secs,
+            } => commands::create_variant(
+                &out,
+                variant,
+                &VariantParams {
+                    num_levels: levels,
+                    array_size,
+                    num_hash_functions: hash_functions,
+                    window: Duration::from_secs(window_secs),
+                },
+            ),
+            Commands::Add { path, items } => commands::add_items(&path, &items),
+            Commands::Contains { path, items } => commands::contains_items(&path, &items),
+            Commands::Follow {
+                input,
+                filter,
+                from_start,
+                save_secs,
+                poll_ms,
+                idle_secs,
+            } => commands::follow(&FollowParams {
+                input,
+                filter,
+                levels: cli.levels,
+                array_size: cli.array_size,
+                num_hash_functions: cli.hash_functions,
+                from_start,
+                save_interval: Duration::from_secs(save_secs),
+                poll_interval: Duration::from_millis(poll_ms),
+                idle_timeout: idle_secs.map(Duration::from_secs),
+            }),
+            Commands::Spellcheck { input, dict, fpr, save } => commands::spellcheck(&SpellcheckParams {
+                dict,
+                input,
+                false_positive_rate: fpr,
+                save,
+            }),
+            Commands::Blocklist { action } => match action {
+                BlocklistAction::Build { out, lists, fpr, format } => commands::build_blocklist(&out, &lists, fpr, format),
+                BlocklistAction::Check { filter, targets } => commands::check_blocklist(&filter, &targets),
+            },
+            Commands::Passwords { action } => match action {
+                PasswordAction::Build {
+                    corpus,
+                    out,
+                    hashed,
+                    digest,
+                    fpr,
+                    format,
+                } => commands::build_password_filter(&PasswordCorpusParams {
+                    corpus,
+                    output: out,
+                    hashed,
+                    digest,
+                    false_positive_rate: fpr,
+                    format,
+                }),
+                PasswordAction::Check { filter } => {
+                    read_passwords().and_then(|candidates| commands::check_passwords(&filter, &candidates))
+                }
+            },
+            Commands::Kmers { action } => match action {
+                KmerAction::Build {
+                    out,
+                    inputs,
+                    k,
+                    forward_only,
+                    fpr,
+                    format,
+                } => KmerMode::new(k, !forward_only)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mode| commands::build_kmers(&out, &inputs, &mode, fpr, format)),
+                KmerAction::Query { filter, input } => commands::query_kmers(&filter, &input),
+            },
+            Commands::Ngrams { action } => match action {
+                NgramAction::Build {
+                    out,
+                    inputs,
+                    n,
+                    unit,
+                    fpr,
+                    format,
+                } => NgramMode::new(n, unit)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mode| commands::build_ngrams(&out, &inputs, &mode, fpr, format)),
+                NgramAction::Query { filter, inputs } => commands::query_ngrams(&filter, &inputs),
+            },
+            #[cfg(unix)]
+            Commands::Daemon { socket } => commands::daemon(
+                &socket,
+                cli.store.as_deref(),
+                &cli.name,
+                cli.levels,
+                cli.array_size,
+                cli.hash_functions,
+            ),
+            #[cfg(feature = "tui")]
+            Commands::Tui => unreachable!("tui starts a session like the REPL"),
+        };
+        if let Err(e) = &result {
+            error!("{}", e);
+        }
+        println!("{}", repl::render(&result, cli.json));
+        std::process::exit(if result.is_ok() { 0 } else { 1 });
+    }
+
+    if !cli.json {
+        println!("Welcome to the Bloom Filter CLI!");
+    }
+
+    let mut store = match &cli.store {
+        Some(dir) => match FilterStore::open(dir) {
+            Ok(store) => store,
+            Err(e) => {
+                error!("Failed to open store {}: {}", dir, e);
+                println!("{}", repl::render(&Err(format!("Failed to open store {}: {}", dir, e)), cli.json));
+                std::process::exit(1);
+            }
+        },
+        None => FilterStore::in_memory(),
+    };
+
+    // Resume a stored filter instead of creating one
+    if store.contains(&cli.name) {
+        let session = Session::new(store, &cli.name).expect("the store contains the filter");
+        run_repl(session, cli.json, tui);
+        return;
+    }
 
     // Prompt user for number of hash functions
-    let num_hash_functions = loop {
-        let num = read_usize_input("Enter the number of hash functions to use (3 or 4): ");
-        if num >= 3 && num <= 4 {
+    let num_hash_functions = cli.hash_functions.unwrap_or_else(|| loop {
+        let num = read_usize_input(&format!(
+            "Enter the number of hash functions to use (1 to {}): ",
+            bloom::MAX_HASH_FUNCTIONS
+        ));
+        if (1..=bloom::MAX_HASH_FUNCTIONS).contains(&num) {
             break num;
         } else {
-            println!("Number of hash functions must be 3 or 4.");
+            println!("Number of hash functions must be between 1 and {}.", bloom::MAX_HASH_FUNCTIONS);
         }
-    };
+    });
 
     // Prompt user for array size
-    let array_size = read_usize_input("Enter the size of the bit array (positive integer): ");
+    let array_size = cli
+        .array_size
+        .unwrap_or_else(|| read_usize_input("Enter the size of the bit array (positive integer): "));
 
     // Prompt user for number of levels
-    let num_levels = read_usize_input("Enter the number of levels (positive integer): ");
+    let num_levels = cli
+        .levels
+        .unwrap_or_else(|| read_usize_input("Enter the number of levels (positive integer): "));
 
     // Create the BloomFilter
-    let mut bloom_filter = match BloomFilter::new(num_levels, array_size, num_hash_functions) {
-        Ok(bf) => {
-            println!("Bloom Filter created successfully!");
-            bf
-        },
+    let created = BloomFilter::builder()
+        .levels(num_levels)
+        .array_size(array_size)
+        .hash_functions(num_hash_functions)
+        .normalization(cli.normalize)
+        .build()
+        .and_then(|bf| store.create(&cli.name, bf).map(|bf| bf.stats()));
+    match created {
+        Ok(stats) => println!("{}", repl::render(&Ok(Outcome::Create { stats }), cli.json)),
         Err(e) => {
             error!("Error creating BloomFilter: {}", e);
-            println!("Error creating BloomFilter: {}", e);
-            return;
+            println!("{}", repl::render(&Err(format!("Error creating BloomFilter: {}", e)), cli.json));
+            std::process::exit(1);
         }
     };
 
-    // Allow operations on the bloom filter
-    loop {
-        let selection = select_operation();
+    let session = Session::new(store, &cli.name).expect("the filter was just created");
+    run_repl(session, cli.json, tui);
+}
 
-        match selection {
-            0 => { // Insert item
-                let item = read_string_input("Enter item to insert: ");
-                bloom_filter.insert(&item);
-                println!("Item inserted successfully.");
-            },
-            1 => { // Query item
-                let item = read_string_input("Enter item to query: ");
-                let levels_to_search = loop {
-                    let levels = read_usize_input("Enter number of levels to search: ");
-                    if levels > 0 && levels <= num_levels {
-                        break levels;
-                    } else {
-                        println!("Number of levels to search must be between 1 and {}.", num_levels);
-                    }
-                };
-                let found = bloom_filter.query(&item, levels_to_search);
-                if found {
-                    println!("Item may be present.");
-                } else {
-                    println!("Item is not present.");
-                }
-            },
-            2 => { // Save Bloom Filter
-                let filepath = read_string_input("Enter the filepath to save the Bloom Filter (e.g., bloom.json): ");
-                if let Err(e) = bloom_filter.save_to_file(&filepath) {
-                    error!("Failed to save BloomFilter: {}", e);
-                    println!("Failed to save BloomFilter: {}", e);
-                } else {
-                    println!("Bloom Filter saved successfully.");
-                }
-            },
-            3 => { // Load Bloom Filter
-                let filepath = read_string_input("Enter the filepath to load the Bloom Filter from (e.g., bloom.json): ");
-                if !Path::new(&filepath).exists() {
-                    println!("File does not exist. Please enter a valid filepath.");
-                    continue;
-                }
-                match BloomFilter::load_from_file(&filepath) {
-                    Ok(bf) => {
-                        bloom_filter = bf;
-                        println!("Bloom Filter loaded successfully.");
-                    },
-                    Err(e) => {
-                        error!("Failed to load BloomFilter: {}", e);
-                        println!("Failed to load BloomFilter: {}", e);
-                    }
-                }
-            },
-            4 => { // Exit
-                println!("Exiting the Bloom Filter CLI. Goodbye!");
-                break;
-            },
-            _ => {
-                println!("Invalid choice. Please try again.");
-            }
+/// Reads candidate passwords without echoing them: a hidden prompt on a terminal, else one per line of stdin.
+///
+/// Passwords are never taken as arguments, which would leave them in shell history and process listings.
+fn read_passwords() -> Result<Vec<String>, String> {
+    if io::stdin().is_terminal() {
+        dialoguer::Password::new()
+            .with_prompt("Password to check")
+            .interact()
+            .map(|password| vec![password])
+            .map_err(|e| format!("Failed to read password: {}", e))
+    } else {
+        io::stdin()
+            .lock()
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.is_empty()))
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read passwords: {}", e))
+    }
+}
+
+/// Hands the session to the interactive REPL, or to the dashboard for `bloom tui`.
+fn run_repl(session: Session, json: bool, tui: bool) {
+    #[cfg(feature = "tui")]
+    if tui {
+        if let Err(e) = bloom::tui::run(session) {
+            error!("Dashboard failed: {}", e);
+            println!("{}", repl::render(&Err(format!("Dashboard failed: {}", e)), json));
+            std::process::exit(1);
         }
+        return;
+    }
+    #[cfg(not(feature = "tui"))]
+    let _ = tui;
+    if let Err(e) = repl::run(session, json) {
+        error!("REPL failed: {}", e);
+        println!("{}", repl::render(&Err(format!("REPL failed: {}", e)), json));
+        std::process::exit(1);
     }
 }
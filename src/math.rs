@@ -0,0 +1,77 @@
+// src/math.rs
+
+use std::f64::consts::LN_2;
+
+/// Number of bits needed to hold `n` items with false-positive rate `p`.
+///
+/// `m = -n * ln(p) / ln(2)^2`, rounded up and never less than one bit. No
+/// items, or a rate outside (0, 1), gives one bit.
+pub fn optimal_bits(n: usize, p: f64) -> usize {
+    if n == 0 || p <= 0.0 || p >= 1.0 {
+        return 1;
+    }
+    let m = -(n as f64) * p.ln() / (LN_2 * LN_2);
+    (m.ceil() as usize).max(1)
+}
+
+/// Number of hash functions minimizing the false-positive rate for `m` bits and `n` items.
+///
+/// `k = (m / n) * ln(2)`, rounded and never less than one.
+pub fn optimal_hashes(m: usize, n: usize) -> usize {
+    if n == 0 {
+        return 1;
+    }
+    let k = (m as f64 / n as f64) * LN_2;
+    (k.round() as usize).max(1)
+}
+
+/// Expected false-positive rate after inserting `n` items into `m` bits with `k` hash functions.
+///
+/// `p = (1 - e^(-k * n / m))^k`.
+pub fn false_positive_rate(m: usize, n: usize, k: usize) -> f64 {
+    if m == 0 {
+        return 1.0;
+    }
+    (1.0 - (-(k as f64) * n as f64 / m as f64).exp()).powi(k as i32)
+}
+
+/// Estimated number of distinct items in `m` bits with `k` hash functions and `ones` bits set.
+///
+/// `n = -(m / k) * ln(1 - ones / m)`; a full array gives infinity.
+pub fn estimated_items(m: usize, k: usize, ones: usize) -> f64 {
+    if m == 0 || k == 0 {
+        return 0.0;
+    }
+    -(m as f64 / k as f64) * (1.0 - ones as f64 / m as f64).ln()
+}
+
+/// False-positive rate of a level with the given fraction of bits set.
+///
+/// A query matches when all `k` probed bits are set: `fill^k`.
+pub fn estimated_false_positive_rate(fill_ratio: f64, k: usize) -> f64 {
+    fill_ratio.powi(k as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_textbook_sizing() {
+        // 1M items at 1% needs ~9.59M bits and 7 hash functions.
+        let m = optimal_bits(1_000_000, 0.01);
+        assert_eq!(m, 9_585_059);
+        assert_eq!(optimal_hashes(m, 1_000_000), 7);
+        assert!((false_positive_rate(m, 1_000_000, 7) - 0.01).abs() < 0.0005);
+    }
+
+    #[test]
+    fn test_degenerate_inputs() {
+        assert_eq!(optimal_bits(0, 0.01), 1);
+        assert_eq!(optimal_bits(100, 1.5), 1);
+        assert_eq!(optimal_hashes(1000, 0), 1);
+        assert_eq!(optimal_hashes(1, 1000), 1);
+        assert_eq!(false_positive_rate(0, 10, 3), 1.0);
+        assert_eq!(false_positive_rate(1000, 0, 3), 0.0);
+    }
+}
@@ -0,0 +1,158 @@
+// src/metrics.rs
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::stats::FilterStats;
+
+/// Insert and query counters with cumulative latencies, for a `/metrics` endpoint.
+///
+/// Counters are atomics, so one instance can be shared by every thread
+/// serving a filter. Render them with `encode_prometheus`.
+#[derive(Debug, Default)]
+pub struct FilterMetrics {
+    inserts: AtomicU64,
+    queries: AtomicU64,
+    insert_nanos: AtomicU64,
+    query_nanos: AtomicU64,
+}
+
+impl FilterMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one insert that took `elapsed`.
+    pub fn record_insert(&self, elapsed: Duration) {
+        self.inserts.fetch_add(1, Ordering::Relaxed);
+        self.insert_nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Records one query that took `elapsed`.
+    pub fn record_query(&self, elapsed: Duration) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        self.query_nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the number of inserts recorded.
+    pub fn inserts(&self) -> u64 {
+        self.inserts.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of queries recorded.
+    pub fn queries(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+}
+
+/// Renders metrics for named filters in the Prometheus text exposition format.
+///
+/// Each entry pairs a filter name (the `filter` label) with a snapshot of its
+/// stats and its counters. Per-level gauges also carry a `level` label
+/// (numbered from 1), so alerts can fire when any level nears saturation.
+pub fn encode_prometheus(filters: &[(&str, &FilterStats, &FilterMetrics)]) -> String {
+    let mut out = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, samples: &mut dyn Iterator<Item = (String, f64)>| {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    };
+    let filter_label = |name: &str| format!("filter=\"{}\"", escape(name));
+    let level_label = |name: &str, level: usize| format!("{},level=\"{}\"", filter_label(name), level + 1);
+
+    family(
+        "bloom_inserts_total",
+        "counter",
+        "Items inserted.",
+        &mut filters.iter().map(|(name, _, m)| (filter_label(name), m.inserts() as f64)),
+    );
+    family(
+        "bloom_queries_total",
+        "counter",
+        "Queries answered.",
+        &mut filters.iter().map(|(name, _, m)| (filter_label(name), m.queries() as f64)),
+    );
+    family(
+        "bloom_insert_duration_seconds_total",
+        "counter",
+        "Total time spent inserting.",
+        &mut filters
+            .iter()
+            .map(|(name, _, m)| (filter_label(name), seconds(&m.insert_nanos))),
+    );
+    family(
+        "bloom_query_duration_seconds_total",
+        "counter",
+        "Total time spent answering queries.",
+        &mut filters
+            .iter()
+            .map(|(name, _, m)| (filter_label(name), seconds(&m.query_nanos))),
+    );
+    family(
+        "bloom_estimated_false_positive_rate",
+        "gauge",
+        "Estimated false-positive rate of a query across all unexpired levels.",
+        &mut filters
+            .iter()
+            .map(|(name, stats, _)| (filter_label(name), stats.estimated_false_positive_rate)),
+    );
+    family(
+        "bloom_level_fill_ratio",
+        "gauge",
+        "Fraction of a level's bits that are set.",
+        &mut filters.iter().flat_map(|(name, stats, _)| {
+            stats
+                .level_stats
+                .iter()
+                .enumerate()
+                .map(move |(level, level_stats)| (level_label(name, level), level_stats.fill_ratio))
+        }),
+    );
+    family(
+        "bloom_level_items",
+        "gauge",
+        "Inserts that set at least one new bit in a level.",
+        &mut filters.iter().flat_map(|(name, stats, _)| {
+            stats
+                .level_stats
+                .iter()
+                .enumerate()
+                .map(move |(level, level_stats)| (level_label(name, level), level_stats.item_count as f64))
+        }),
+    );
+    out
+}
+
+fn seconds(nanos: &AtomicU64) -> f64 {
+    nanos.load(Ordering::Relaxed) as f64 / 1e9
+}
+
+/// Escapes a label value as the exposition format requires.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bloom_filter::BloomFilter;
+
+    #[test]
+    fn test_encode_prometheus() {
+        let mut bf = BloomFilter::new(2, 100, 3).unwrap();
+        let metrics = FilterMetrics::new();
+        bf.insert("apple");
+        metrics.record_insert(Duration::from_micros(5));
+        metrics.record_query(Duration::from_micros(2));
+        metrics.record_query(Duration::from_micros(3));
+
+        let text = encode_prometheus(&[("users \"eu\"", &bf.stats(), &metrics)]);
+        assert!(text.contains("# TYPE bloom_inserts_total counter\n"));
+        assert!(text.contains("bloom_queries_total{filter=\"users \\\"eu\\\"\"} 2\n"));
+        assert!(text.contains("bloom_level_fill_ratio{filter=\"users \\\"eu\\\"\",level=\"2\"} 0.03\n"));
+        assert!(text.contains("bloom_level_items{filter=\"users \\\"eu\\\"\",level=\"1\"} 1\n"));
+    }
+}
@@ -0,0 +1,158 @@
+// src/minhash.rs
+
+use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher13;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter};
+use tracing::{error, info};
+
+use crate::bloom_filter::{splitmix64, BloomFilterError};
+
+const MINHASH_KEY: u64 = 0x4d49_4e48_4153_4831;
+
+/// A MinHash signature for estimating the Jaccard similarity of two sets.
+///
+/// Each of the `num_permutations` slots keeps the smallest value of its own
+/// hash function over the items inserted. Two sets' slots agree with
+/// probability equal to their Jaccard similarity, so the share of agreeing
+/// slots estimates it with standard error about `1 / sqrt(num_permutations)`.
+/// Signatures with the same size and seed combine with `union_with`, giving
+/// the signature of the union.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MinHash {
+    #[serde(default)]
+    seed: u64,
+    mins: Vec<u64>,
+}
+
+impl MinHash {
+    /// Creates an empty signature with `num_permutations` slots.
+    pub fn new(num_permutations: usize) -> Result<Self, BloomFilterError> {
+        info!("Creating MinHash: permutations={}", num_permutations);
+        if num_permutations == 0 {
+            error!("Requested zero permutations");
+            return Err(BloomFilterError::ZeroHashFunctions);
+        }
+        Ok(MinHash {
+            seed: 0,
+            mins: vec![u64::MAX; num_permutations],
+        })
+    }
+
+    /// Sets the seed perturbing every hash, before anything is inserted.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Adds an item to the set.
+    pub fn insert(&mut self, item: &str) {
+        self.insert_bytes(item.as_bytes());
+    }
+
+    /// Adds a binary key to the set.
+    pub fn insert_bytes(&mut self, item: &[u8]) {
+        let mut hasher = SipHasher13::new_with_keys(self.seed, MINHASH_KEY);
+        hasher.write(item);
+        let hash = hasher.finish();
+        for (slot, min) in self.mins.iter_mut().enumerate() {
+            let value = splitmix64(hash ^ splitmix64(slot as u64));
+            *min = (*min).min(value);
+        }
+    }
+
+    /// Estimates the Jaccard similarity (shared items over all items) of the two sets.
+    ///
+    /// Two empty sets count as identical.
+    pub fn jaccard(&self, other: &MinHash) -> Result<f64, BloomFilterError> {
+        self.check_compatible(other)?;
+        let agreeing = self.mins.iter().zip(&other.mins).filter(|(a, b)| a == b).count();
+        Ok(agreeing as f64 / self.mins.len() as f64)
+    }
+
+    /// Merges another signature into this one, giving the signature of the union of the sets.
+    pub fn union_with(&mut self, other: &MinHash) -> Result<(), BloomFilterError> {
+        self.check_compatible(other)?;
+        for (min, &theirs) in self.mins.iter_mut().zip(&other.mins) {
+            *min = (*min).min(theirs);
+        }
+        Ok(())
+    }
+
+    /// Returns the number of slots.
+    pub fn num_permutations(&self) -> usize {
+        self.mins.len()
+    }
+
+    /// Returns true if nothing has been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.mins.iter().all(|&min| min == u64::MAX)
+    }
+
+    /// Returns the signature's slot values.
+    pub fn signature(&self) -> &[u64] {
+        &self.mins
+    }
+
+    /// Saves the signature as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving MinHash to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a signature saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading MinHash from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let minhash: Self = serde_json::from_reader(reader)?;
+        if minhash.mins.is_empty() {
+            return Err(BloomFilterError::ZeroHashFunctions);
+        }
+        Ok(minhash)
+    }
+
+    fn check_compatible(&self, other: &MinHash) -> Result<(), BloomFilterError> {
+        if self.mins.len() != other.mins.len() || self.seed != other.seed {
+            return Err(BloomFilterError::IncompatibleFilters(format!(
+                "{} permutations with seed {} vs {} with seed {}",
+                self.mins.len(),
+                self.seed,
+                other.mins.len(),
+                other.seed
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jaccard_estimate() {
+        let mut first = MinHash::new(256).unwrap();
+        let mut second = MinHash::new(256).unwrap();
+        // 0..600 and 200..800 share 400 of 800 items.
+        (0..600).for_each(|i| first.insert(&format!("word-{}", i)));
+        (200..800).for_each(|i| second.insert(&format!("word-{}", i)));
+        let jaccard = first.jaccard(&second).unwrap();
+        assert!((jaccard - 0.5).abs() < 0.1, "estimated {}", jaccard);
+        assert_eq!(first.jaccard(&first).unwrap(), 1.0);
+
+        let mut union = first.clone();
+        union.union_with(&second).unwrap();
+        let mut all = MinHash::new(256).unwrap();
+        (0..800).for_each(|i| all.insert(&format!("word-{}", i)));
+        assert_eq!(union, all);
+        assert!(first.jaccard(&MinHash::new(128).unwrap()).is_err());
+
+        let path = std::env::temp_dir().join("test_bloom_minhash.json");
+        let path = path.to_str().unwrap();
+        union.save_to_file(path).unwrap();
+        assert_eq!(MinHash::load_from_file(path).unwrap(), union);
+        std::fs::remove_file(path).unwrap();
+    }
+}
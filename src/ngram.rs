@@ -0,0 +1,172 @@
+// src/ngram.rs
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// Level metadata keys recording how an n-gram filter was built.
+pub const N_METADATA_KEY: &str = "ngram-n";
+pub const UNIT_METADATA_KEY: &str = "ngram-unit";
+
+/// What an n-gram is made of.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NgramUnit {
+    /// Runs of `n` consecutive characters, spaces included.
+    #[default]
+    Chars,
+    /// Runs of `n` consecutive whitespace-separated words, joined by single spaces.
+    Words,
+}
+
+impl fmt::Display for NgramUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NgramUnit::Chars => "chars",
+            NgramUnit::Words => "words",
+        })
+    }
+}
+
+impl FromStr for NgramUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chars" | "char" => Ok(NgramUnit::Chars),
+            "words" | "word" => Ok(NgramUnit::Words),
+            _ => Err(format!("unknown n-gram unit '{}' (expected chars or words)", s)),
+        }
+    }
+}
+
+/// How items are cut into n-grams for fuzzy containment checks.
+///
+/// Inserting an item's n-grams instead of the item lets a query ask how much
+/// of another text the filter has seen: a near-copy shares most of its
+/// n-grams, an unrelated text almost none. Each distinct n-gram counts once,
+/// and an item shorter than `n` units is its own single n-gram.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NgramMode {
+    n: usize,
+    unit: NgramUnit,
+}
+
+impl NgramMode {
+    pub fn new(n: usize, unit: NgramUnit) -> Result<Self, BloomFilterError> {
+        if n == 0 {
+            return Err(BloomFilterError::InvalidNgram("n must be at least 1".to_string()));
+        }
+        Ok(NgramMode { n, unit })
+    }
+
+    /// Reads the mode recorded by `prepare` from a filter's metadata.
+    pub fn from_filter(filter: &BloomFilter) -> Result<Self, BloomFilterError> {
+        let metadata = filter.levels().first().map(|level| level.metadata());
+        let value = |key: &str| metadata.and_then(|metadata| metadata.get(key));
+        let n = value(N_METADATA_KEY)
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| BloomFilterError::InvalidNgram("no n-gram length recorded".to_string()))?;
+        let unit = value(UNIT_METADATA_KEY)
+            .map_or(Ok(NgramUnit::default()), |unit| unit.parse())
+            .map_err(BloomFilterError::InvalidNgram)?;
+        NgramMode::new(n, unit)
+    }
+
+    /// Records the mode in the filter's metadata, so `from_filter` can query it the same way.
+    pub fn prepare(&self, filter: &mut BloomFilter) -> Result<(), BloomFilterError> {
+        filter.set_level_metadata(0, N_METADATA_KEY, &self.n.to_string())?;
+        filter.set_level_metadata(0, UNIT_METADATA_KEY, &self.unit.to_string())
+    }
+
+    /// Returns the n-gram length.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Returns what the n-grams are made of.
+    pub fn unit(&self) -> NgramUnit {
+        self.unit
+    }
+
+    /// Returns the distinct n-grams of an item, sorted.
+    pub fn ngrams(&self, item: &str) -> BTreeSet<String> {
+        match self.unit {
+            NgramUnit::Chars => {
+                let chars: Vec<char> = item.chars().collect();
+                windows(&chars, self.n).map(|gram| gram.iter().collect()).collect()
+            }
+            NgramUnit::Words => {
+                let words: Vec<&str> = item.split_whitespace().collect();
+                windows(&words, self.n).map(|gram| gram.join(" ")).collect()
+            }
+        }
+    }
+
+    /// Inserts every n-gram of an item, returning how many there were.
+    pub fn insert(&self, filter: &mut BloomFilter, item: &str) -> usize {
+        let item = filter.normalize(item).into_owned();
+        self.ngrams(&item).iter().map(|gram| filter.insert_bytes(gram.as_bytes())).count()
+    }
+
+    /// Queries every n-gram of an item, returning how many there were and how many matched.
+    pub fn query(&self, filter: &BloomFilter, item: &str) -> (usize, usize) {
+        let levels = filter.levels().len();
+        self.ngrams(&filter.normalize(item)).iter().fold((0, 0), |(total, present), gram| {
+            (total + 1, present + usize::from(filter.query_bytes(gram.as_bytes(), levels)))
+        })
+    }
+}
+
+/// Every run of `n` consecutive units, or the whole slice if it is shorter but not empty.
+fn windows<T>(units: &[T], n: usize) -> std::slice::Windows<'_, T> {
+    units.windows(n.min(units.len()).max(1))
+}
+
+impl BloomFilter {
+    /// Inserts every n-gram of an item instead of the item itself, returning the number of n-grams.
+    pub fn insert_ngrams(&mut self, item: &str, mode: &NgramMode) -> usize {
+        let inserted = mode.insert(self, item);
+        info!("Inserted {} {}-grams of {}", inserted, mode.n(), mode.unit());
+        inserted
+    }
+
+    /// Returns the fraction of an item's n-grams the filter contains, from 0.0 to 1.0.
+    ///
+    /// False positives can only raise the fraction. An item with no n-grams
+    /// (an empty string) scores 0.0.
+    pub fn query_ngrams(&self, item: &str, mode: &NgramMode) -> f64 {
+        match mode.query(self, item) {
+            (0, _) => 0.0,
+            (total, present) => present as f64 / total as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ngram_containment() {
+        let trigrams = NgramMode::new(3, NgramUnit::Chars).unwrap();
+        assert_eq!(trigrams.ngrams("abcab").into_iter().collect::<Vec<_>>(), ["abc", "bca", "cab"]);
+        assert_eq!(trigrams.ngrams("hé").into_iter().collect::<Vec<_>>(), ["hé"]);
+        assert!(trigrams.ngrams("").is_empty());
+        let bigrams = NgramMode::new(2, NgramUnit::Words).unwrap();
+        assert_eq!(bigrams.ngrams("the  quick\nbrown").into_iter().collect::<Vec<_>>(), ["quick brown", "the quick"]);
+        assert!(NgramMode::new(0, NgramUnit::Words).is_err());
+
+        let document = "the quick brown fox jumps over the lazy dog";
+        let mut filter = BloomFilter::new(1, 10_000, 3).unwrap();
+        bigrams.prepare(&mut filter).unwrap();
+        assert_eq!(filter.insert_ngrams(document, &bigrams), 8);
+        assert_eq!(filter.query_ngrams("quick brown fox jumps", &bigrams), 1.0);
+        assert_eq!(filter.query_ngrams("the quick red fox", &bigrams), 1.0 / 3.0);
+        assert_eq!(filter.query_ngrams("", &bigrams), 0.0);
+        assert!(!filter.query(document, 1));
+        assert_eq!(NgramMode::from_filter(&filter).unwrap(), bigrams);
+    }
+}
@@ -0,0 +1,171 @@
+// src/normalize.rs
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+/// A Unicode normalization form applied to string keys.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnicodeForm {
+    #[default]
+    None,
+    /// Canonical composition: "é" typed as `e` + combining accent matches the precomposed "é".
+    Nfc,
+    /// Compatibility composition: additionally folds ligatures, full-width and other variant forms.
+    Nfkc,
+}
+
+/// How a filter rewrites string keys before hashing them.
+///
+/// The same rewriting is applied on insert and on query, and is saved with
+/// the filter, so "Alice", " alice" and "alice" can be made one key. It only
+/// affects the `&str` methods; binary and integer keys are hashed as given.
+/// The Unicode form is applied first, then lowercasing, then trimming.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Normalization {
+    /// Strip leading and trailing whitespace.
+    #[serde(default)]
+    pub trim: bool,
+    /// Lowercase, by Unicode rules.
+    #[serde(default)]
+    pub lowercase: bool,
+    #[serde(default)]
+    pub unicode: UnicodeForm,
+}
+
+impl Normalization {
+    /// Returns true if keys are hashed unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Normalization::default()
+    }
+
+    /// Rewrites a key, borrowing it when nothing changes.
+    pub fn apply<'a>(&self, item: &'a str) -> Cow<'a, str> {
+        let mut key = match self.unicode {
+            UnicodeForm::Nfc if is_nfc_quick(item.chars()) != IsNormalized::Yes => Cow::Owned(item.nfc().collect()),
+            UnicodeForm::Nfkc if is_nfkc_quick(item.chars()) != IsNormalized::Yes => Cow::Owned(item.nfkc().collect()),
+            _ => Cow::Borrowed(item),
+        };
+        if self.lowercase && key.chars().any(|c| c.to_lowercase().ne(std::iter::once(c))) {
+            key = Cow::Owned(key.to_lowercase());
+        }
+        if self.trim {
+            key = match key {
+                Cow::Borrowed(key) => Cow::Borrowed(key.trim()),
+                Cow::Owned(key) if key.trim().len() == key.len() => Cow::Owned(key),
+                Cow::Owned(key) => Cow::Owned(key.trim().to_string()),
+            };
+        }
+        key
+    }
+
+    /// Packs the options into one byte, for the binary format.
+    pub(crate) fn to_code(self) -> u8 {
+        let form = match self.unicode {
+            UnicodeForm::None => 0,
+            UnicodeForm::Nfc => 1,
+            UnicodeForm::Nfkc => 2,
+        };
+        u8::from(self.trim) | u8::from(self.lowercase) << 1 | form << 2
+    }
+
+    /// Unpacks a byte written by `to_code`.
+    pub(crate) fn from_code(code: u8) -> Option<Self> {
+        let unicode = match code >> 2 {
+            0 => UnicodeForm::None,
+            1 => UnicodeForm::Nfc,
+            2 => UnicodeForm::Nfkc,
+            _ => return None,
+        };
+        Some(Normalization {
+            trim: code & 1 != 0,
+            lowercase: code & 2 != 0,
+            unicode,
+        })
+    }
+}
+
+impl fmt::Display for Normalization {
+    /// Writes the options as `from_str` accepts them, e.g. `nfkc,lowercase,trim`, or `none`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = Vec::new();
+        match self.unicode {
+            UnicodeForm::None => {}
+            UnicodeForm::Nfc => options.push("nfc"),
+            UnicodeForm::Nfkc => options.push("nfkc"),
+        }
+        if self.lowercase {
+            options.push("lowercase");
+        }
+        if self.trim {
+            options.push("trim");
+        }
+        match options.is_empty() {
+            true => f.write_str("none"),
+            false => f.write_str(&options.join(",")),
+        }
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    /// Parses a comma-separated list of `trim`, `lowercase`, `nfc` and `nfkc`, or `none`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut normalization = Normalization::default();
+        for option in s.split(',').map(str::trim).filter(|option| !option.is_empty()) {
+            match option.to_ascii_lowercase().as_str() {
+                "none" => {}
+                "trim" => normalization.trim = true,
+                "lowercase" => normalization.lowercase = true,
+                "nfc" => normalization.unicode = UnicodeForm::Nfc,
+                "nfkc" => normalization.unicode = UnicodeForm::Nfkc,
+                other => {
+                    return Err(format!(
+                        "unknown normalization '{}' (expected trim, lowercase, nfc, nfkc or none)",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(normalization)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bloom_filter::BloomFilter;
+
+    #[test]
+    fn test_normalized_keys_match_and_survive_saving() {
+        let normalization: Normalization = "nfkc,lowercase,trim".parse().unwrap();
+        assert_eq!(normalization.to_string(), "nfkc,lowercase,trim");
+        assert_eq!(Normalization::from_code(normalization.to_code()), Some(normalization));
+        assert_eq!(normalization.apply("  Ｃａｆｅ\u{301} "), "café");
+        assert!(matches!(normalization.apply("plain"), Cow::Borrowed("plain")));
+        // The Unicode form comes first, as docs/portable-format.md specifies.
+        assert_eq!(normalization.apply("ℌ"), "h");
+        assert!("nfd".parse::<Normalization>().is_err());
+
+        let mut bf = BloomFilter::builder().array_size(1000).normalization(normalization).build().unwrap();
+        bf.insert(" Café");
+        assert!(bf.query("cafe\u{301}", 1) && bf.query("CAFÉ ", 1));
+        assert!(!bf.query_bytes(" Café".as_bytes(), 1));
+
+        let path = std::env::temp_dir().join("test_bloom_normalization.json");
+        let path = path.to_str().unwrap();
+        bf.save_to_file(path).unwrap();
+        let loaded = BloomFilter::load_from_file(path).unwrap();
+        assert_eq!(loaded.normalization(), normalization);
+        assert!(loaded.query("café", 1));
+        std::fs::remove_file(path).unwrap();
+
+        let binary = BloomFilter::from_binary(&bf.to_binary()).unwrap();
+        assert!(binary.query("CAFÉ", 1));
+        assert!(bf.check_compatible(&BloomFilter::builder().array_size(1000).build().unwrap()).is_err());
+        assert!(bf.freeze().query("Cafe\u{301}", 1));
+    }
+}
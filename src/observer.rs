@@ -0,0 +1,93 @@
+// src/observer.rs
+
+use std::sync::Arc;
+
+use crate::bloom_filter::BloomFilter;
+
+/// Receives a filter's inserts, queries and saves as they happen.
+///
+/// Register an implementation with `BloomFilter::add_observer` to count,
+/// sample or audit operations without wrapping every call site. Every
+/// method has an empty default, so an observer implements only the events
+/// it cares about. Keys are passed as the bytes that were hashed: string
+/// keys after normalization, integer keys as their little-endian bytes.
+/// Callbacks run on the calling thread, inside the operation, so they should
+/// be quick; queries take `&self`, so any state needs interior mutability.
+pub trait Observer: Send + Sync {
+    /// An item was inserted; `newly_set` is what the insert returned.
+    fn on_insert(&self, _item: &[u8], _newly_set: bool) {}
+
+    /// An item was queried; `hit` is true if the filter (probably) contains it.
+    fn on_query(&self, _item: &[u8], _hit: bool) {}
+
+    /// The filter was saved to `path` successfully.
+    fn on_save(&self, _path: &str) {}
+}
+
+impl BloomFilter {
+    /// Registers an observer for this filter's inserts, queries and saves.
+    ///
+    /// Observers are not saved with the filter; register them again after loading.
+    pub fn add_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    /// Removes every registered observer.
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    pub(crate) fn notify(&self, event: impl Fn(&dyn Observer)) {
+        for observer in &self.observers {
+            event(observer.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct AuditLog(Mutex<Vec<String>>);
+
+    impl Observer for AuditLog {
+        fn on_insert(&self, item: &[u8], newly_set: bool) {
+            self.0.lock().unwrap().push(format!("insert {} {}", String::from_utf8_lossy(item), newly_set));
+        }
+
+        fn on_query(&self, item: &[u8], hit: bool) {
+            self.0.lock().unwrap().push(format!("query {} {}", String::from_utf8_lossy(item), hit));
+        }
+
+        fn on_save(&self, _path: &str) {
+            self.0.lock().unwrap().push("save".to_string());
+        }
+    }
+
+    #[test]
+    fn test_observer_sees_inserts_queries_and_saves() {
+        let log = Arc::new(AuditLog::default());
+        let mut bf = BloomFilter::builder().array_size(1000).normalization("lowercase".parse().unwrap()).build().unwrap();
+        bf.add_observer(log.clone());
+        bf.insert("Alpha");
+        bf.insert("alpha");
+        bf.query("ALPHA", 1);
+        bf.query_bytes(b"beta", 1);
+        let path = std::env::temp_dir().join("test_bloom_observer.json");
+        let path = path.to_str().unwrap();
+        bf.save_to_file(path).unwrap();
+        assert_eq!(
+            *log.0.lock().unwrap(),
+            ["insert alpha true", "insert alpha false", "query alpha true", "query beta false", "save"]
+        );
+
+        let mut loaded = BloomFilter::load_from_file(path).unwrap();
+        loaded.insert("gamma");
+        bf.clear_observers();
+        bf.insert("gamma");
+        assert_eq!(log.0.lock().unwrap().len(), 5);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+// src/ops.rs
+
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// Set-algebra operators over compatible filters: `|` is `union_with`, `&` is `intersect_with`.
+///
+/// The operators take the left filter by value and reuse it for the result,
+/// so `a | &b` leaves `b` untouched. They panic if the filters fail
+/// `check_compatible` or have different level counts; use `try_union` and
+/// `try_intersection` (or the `_with` methods) when that can happen.
+impl BloomFilter {
+    /// Returns the union of two filters, or why they cannot be combined.
+    pub fn try_union(mut self, other: &BloomFilter) -> Result<Self, BloomFilterError> {
+        self.union_with(other)?;
+        Ok(self)
+    }
+
+    /// Returns the intersection of two filters, or why they cannot be combined.
+    pub fn try_intersection(mut self, other: &BloomFilter) -> Result<Self, BloomFilterError> {
+        self.intersect_with(other)?;
+        Ok(self)
+    }
+}
+
+impl BitOrAssign<&BloomFilter> for BloomFilter {
+    fn bitor_assign(&mut self, other: &BloomFilter) {
+        if let Err(e) = self.union_with(other) {
+            panic!("cannot union filters: {}", e);
+        }
+    }
+}
+
+impl BitOrAssign for BloomFilter {
+    fn bitor_assign(&mut self, other: BloomFilter) {
+        *self |= &other;
+    }
+}
+
+impl BitOr<&BloomFilter> for BloomFilter {
+    type Output = BloomFilter;
+
+    fn bitor(mut self, other: &BloomFilter) -> BloomFilter {
+        self |= other;
+        self
+    }
+}
+
+impl BitOr for BloomFilter {
+    type Output = BloomFilter;
+
+    fn bitor(self, other: BloomFilter) -> BloomFilter {
+        self | &other
+    }
+}
+
+impl BitAndAssign<&BloomFilter> for BloomFilter {
+    fn bitand_assign(&mut self, other: &BloomFilter) {
+        if let Err(e) = self.intersect_with(other) {
+            panic!("cannot intersect filters: {}", e);
+        }
+    }
+}
+
+impl BitAndAssign for BloomFilter {
+    fn bitand_assign(&mut self, other: BloomFilter) {
+        *self &= &other;
+    }
+}
+
+impl BitAnd<&BloomFilter> for BloomFilter {
+    type Output = BloomFilter;
+
+    fn bitand(mut self, other: &BloomFilter) -> BloomFilter {
+        self &= other;
+        self
+    }
+}
+
+impl BitAnd for BloomFilter {
+    type Output = BloomFilter;
+
+    fn bitand(self, other: BloomFilter) -> BloomFilter {
+        self & &other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter_of(items: &[&str]) -> BloomFilter {
+        let mut filter = BloomFilter::new(2, 2000, 3).unwrap();
+        items.iter().for_each(|item| {
+            filter.insert(item);
+        });
+        filter
+    }
+
+    #[test]
+    fn test_operators_union_and_intersect() {
+        let (a, b) = (filter_of(&["apple", "banana"]), filter_of(&["banana", "cherry"]));
+        let both = filter_of(&["apple", "banana"]) & &b;
+        assert!(both.query("banana", 2) && !both.query("apple", 2) && !both.query("cherry", 2));
+        assert_eq!(both.levels()[0].item_count(), 2);
+
+        let mut either = a | b;
+        assert!(["apple", "banana", "cherry"].iter().all(|item| either.query(item, 2)));
+        either &= filter_of(&["cherry"]);
+        assert!(either.query("cherry", 2) && !either.query("apple", 2));
+        either |= &filter_of(&["date"]);
+        assert!(either.query("date", 2));
+
+        let one_level = BloomFilter::new(1, 2000, 3).unwrap();
+        assert!(matches!(either.try_union(&one_level), Err(BloomFilterError::IncompatibleFilters(_))));
+        assert!(filter_of(&[]).try_intersection(&BloomFilter::new(2, 1000, 3).unwrap()).is_err());
+        let panicked = std::panic::catch_unwind(|| filter_of(&[]) | BloomFilter::new(2, 1000, 3).unwrap());
+        assert!(panicked.is_err());
+    }
+}
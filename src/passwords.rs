@@ -0,0 +1,203 @@
+// src/passwords.rs
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError, RebuildParams};
+use crate::hashing::HashAlgorithm;
+
+/// Level metadata key recording which digest a password filter holds.
+pub const DIGEST_METADATA_KEY: &str = "password-digest";
+
+/// The digest a breached-password filter stores instead of the passwords themselves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PasswordDigest {
+    /// SHA-1, as published by Have I Been Pwned.
+    #[default]
+    Sha1,
+    Sha256,
+}
+
+impl PasswordDigest {
+    /// Every digest, in the order offered to users.
+    pub const ALL: [PasswordDigest; 2] = [PasswordDigest::Sha1, PasswordDigest::Sha256];
+
+    /// Returns the digest's name, as accepted by `from_str` and stored in the filter's metadata.
+    pub fn name(self) -> &'static str {
+        match self {
+            PasswordDigest::Sha1 => "sha1",
+            PasswordDigest::Sha256 => "sha256",
+        }
+    }
+
+    /// Returns the length of a digest in bytes.
+    pub fn byte_len(self) -> usize {
+        match self {
+            PasswordDigest::Sha1 => 20,
+            PasswordDigest::Sha256 => 32,
+        }
+    }
+
+    /// Hashes a password.
+    pub fn digest(self, password: &str) -> Vec<u8> {
+        match self {
+            PasswordDigest::Sha1 => Sha1::digest(password.as_bytes()).to_vec(),
+            PasswordDigest::Sha256 => Sha256::digest(password.as_bytes()).to_vec(),
+        }
+    }
+
+    /// Parses a hex digest, ignoring case and a trailing `:count` as in
+    /// Have I Been Pwned downloads.
+    pub fn parse_hex(self, line: &str) -> Option<Vec<u8>> {
+        let hex = line.split(':').next().unwrap_or_default().trim();
+        if hex.len() != self.byte_len() * 2 || !hex.is_ascii() {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect()
+    }
+}
+
+impl fmt::Display for PasswordDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for PasswordDigest {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PasswordDigest::ALL
+            .into_iter()
+            .find(|digest| digest.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown digest '{}' (expected sha1 or sha256)", s))
+    }
+}
+
+/// How the entries of a breached-password corpus are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CorpusFormat {
+    /// One password per line, hashed on ingestion.
+    Plain(PasswordDigest),
+    /// One hex digest per line, optionally followed by `:count`.
+    Hex(PasswordDigest),
+}
+
+impl CorpusFormat {
+    /// Returns the digest the filter will hold.
+    pub fn digest(self) -> PasswordDigest {
+        match self {
+            CorpusFormat::Plain(digest) | CorpusFormat::Hex(digest) => digest,
+        }
+    }
+}
+
+/// A filter of breached-password digests, for checking candidates offline.
+///
+/// Only digests go into the filter, and candidates are hashed locally, so
+/// neither the corpus nor the passwords being checked leave the machine.
+/// A match means the password is in the corpus or, with probability about
+/// the false-positive rate, a false alarm; a miss is certain.
+pub struct BreachedPasswords {
+    filter: BloomFilter,
+    digest: PasswordDigest,
+}
+
+impl BreachedPasswords {
+    /// Creates an empty filter sized for `expected_entries` at the target false-positive rate.
+    pub fn new(expected_entries: usize, false_positive_rate: f64, digest: PasswordDigest) -> Result<Self, BloomFilterError> {
+        info!("Creating breached-password filter for {} {} digests", expected_entries, digest);
+        let sizing = RebuildParams::for_capacity(expected_entries.max(1), false_positive_rate);
+        let mut filter = BloomFilter::builder()
+            .array_size(sizing.array_size.unwrap_or(1))
+            .hash_functions(sizing.num_hash_functions.unwrap_or(1))
+            .hash_algorithm(HashAlgorithm::Fnv1a)
+            .build()?;
+        filter.set_level_metadata(0, DIGEST_METADATA_KEY, digest.name())?;
+        Ok(BreachedPasswords { filter, digest })
+    }
+
+    /// Wraps a filter built by `new`, reading its digest from the metadata.
+    pub fn from_filter(filter: BloomFilter) -> Result<Self, BloomFilterError> {
+        let digest = filter
+            .levels()
+            .first()
+            .and_then(|level| level.metadata().get(DIGEST_METADATA_KEY))
+            .ok_or_else(|| BloomFilterError::InvalidPasswordFilter("no password digest recorded".to_string()))?
+            .parse()
+            .map_err(BloomFilterError::InvalidPasswordFilter)?;
+        Ok(BreachedPasswords { filter, digest })
+    }
+
+    /// Adds one corpus entry, returning `false` if it is not a valid entry for the format.
+    ///
+    /// Empty lines are invalid, and so are hex digests of the wrong length or
+    /// for a different digest than the filter holds.
+    pub fn insert_entry(&mut self, entry: &str, format: CorpusFormat) -> bool {
+        if format.digest() != self.digest {
+            return false;
+        }
+        let digest = match format {
+            CorpusFormat::Plain(digest) if !entry.is_empty() => digest.digest(entry),
+            CorpusFormat::Hex(digest) => match digest.parse_hex(entry) {
+                Some(bytes) => bytes,
+                None => return false,
+            },
+            CorpusFormat::Plain(_) => return false,
+        };
+        self.filter.insert_bytes(&digest);
+        true
+    }
+
+    /// Checks whether a password may be in the corpus.
+    pub fn contains(&self, password: &str) -> bool {
+        self.filter.query_bytes(&self.digest.digest(password), self.filter.levels().len())
+    }
+
+    /// Returns the digest the filter holds.
+    pub fn digest(&self) -> PasswordDigest {
+        self.digest
+    }
+
+    /// Returns the underlying filter, e.g. to save it.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_and_hex_corpora_match_candidates() {
+        // SHA-1 of "password", as listed by Have I Been Pwned.
+        let hibp = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004";
+        assert_eq!(PasswordDigest::Sha1.parse_hex(hibp), Some(PasswordDigest::Sha1.digest("password")));
+        assert_eq!(PasswordDigest::Sha256.parse_hex(hibp), None);
+
+        let mut sha1 = BreachedPasswords::new(100, 0.001, PasswordDigest::Sha1).unwrap();
+        assert!(sha1.insert_entry(hibp, CorpusFormat::Hex(PasswordDigest::Sha1)));
+        assert!(sha1.insert_entry("letmein", CorpusFormat::Plain(PasswordDigest::Sha1)));
+        assert!(!sha1.insert_entry("letmein", CorpusFormat::Plain(PasswordDigest::Sha256)));
+        assert!(!sha1.insert_entry("not hex", CorpusFormat::Hex(PasswordDigest::Sha1)));
+        assert!(sha1.contains("password") && sha1.contains("letmein"));
+        assert!(!sha1.contains("correct horse battery staple"));
+
+        let binary = sha1.filter().to_binary();
+        let loaded = BreachedPasswords::from_filter(BloomFilter::from_binary(&binary).unwrap()).unwrap();
+        assert_eq!(loaded.digest(), PasswordDigest::Sha1);
+        assert!(loaded.contains("password"));
+        assert!(BreachedPasswords::from_filter(BloomFilter::new(1, 100, 3).unwrap()).is_err());
+
+        let mut sha256 = BreachedPasswords::new(100, 0.001, PasswordDigest::Sha256).unwrap();
+        assert!(sha256.insert_entry("hunter2", CorpusFormat::Plain(PasswordDigest::Sha256)));
+        assert!(sha256.contains("hunter2") && !sha256.contains("password"));
+    }
+}
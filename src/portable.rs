@@ -0,0 +1,365 @@
+// src/portable.rs
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use tracing::{error, info};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError, BloomLevel, HashFunction, InsertMode};
+use crate::format::{algorithm_code, algorithm_from_code};
+use crate::hashing::HashAlgorithm;
+use crate::normalize::Normalization;
+use crate::saturation::CapacityLimit;
+use crate::stats::Counters;
+
+/// Magic bytes opening the portable format.
+pub const PORTABLE_MAGIC: [u8; 4] = *b"BLMP";
+/// Current version of the portable format.
+pub const PORTABLE_VERSION: u16 = 1;
+/// Bytes in a version 1 header.
+pub const PORTABLE_HEADER_LEN: usize = 64;
+/// Header flag: the filter hashes keys with a secret SipHash key, which is not in the file.
+const FLAG_KEYED: u8 = 1;
+/// Header flag: the active level advances after `max_items` items.
+const FLAG_MAX_ITEMS: u8 = 2;
+/// Header flag: the active level advances at `max_fill_ratio` set bits.
+const FLAG_MAX_FILL_RATIO: u8 = 4;
+
+/// The fixed header opening a portable file, which `docs/portable-format.md` specifies for other languages.
+///
+/// Every integer is little-endian. Offsets are in bytes:
+///
+/// | offset | size | field |
+/// |-------:|-----:|-------|
+/// | 0  | 4 | magic `BLMP` |
+/// | 4  | 2 | `version` (`u16`) |
+/// | 6  | 2 | header length (`u16`, 64); the body starts here |
+/// | 8  | 8 | `array_size`, bits per level (`u64`) |
+/// | 16 | 4 | `levels` (`u32`) |
+/// | 20 | 4 | `hash_functions` (`u32`) |
+/// | 24 | 1 | `hash_algorithm`: 0 polynomial, 1 FNV-1a, 2 XXH3, 3 Murmur3 |
+/// | 25 | 1 | `normalization`: bit 0 trim, bit 1 lowercase, bits 2-3 Unicode form (0 none, 1 NFC, 2 NFKC) |
+/// | 26 | 1 | flags: bit 0 keyed, bit 1 `max_items` set, bit 2 `max_fill_ratio` set |
+/// | 27 | 1 | `insert_mode`: 0 all levels, 1 active level |
+/// | 28 | 4 | `active_level` (`u32`) |
+/// | 32 | 8 | `seed` (`u64`) |
+/// | 40 | 8 | key fingerprint (`u64`), zero unless keyed |
+/// | 48 | 8 | active level `max_items` (`u64`), zero unless set |
+/// | 56 | 8 | active level `max_fill_ratio` (`f64` bits), zero unless set |
+///
+/// The body holds `hash_functions` polynomial multipliers (`u64`), then
+/// `levels` item counts (`u64`), then each level's bits in
+/// `ceil(array_size / 64) * 8` bytes: bit `i` is `byte[i / 8] >> (i % 8) & 1`,
+/// the same as bit `i % 64` of little-endian word `i / 64`, and padding bits
+/// are zero. A CRC-32 (IEEE, as in zlib) of everything before it closes the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortableHeader {
+    pub version: u16,
+    pub array_size: u64,
+    pub levels: u32,
+    pub hash_functions: u32,
+    pub hash_algorithm: HashAlgorithm,
+    pub normalization: Normalization,
+    pub insert_mode: InsertMode,
+    pub active_level: u32,
+    pub seed: u64,
+    /// Fingerprint of the SipHash key of a keyed filter; the key itself is never written.
+    pub key_fingerprint: Option<u64>,
+}
+
+impl PortableHeader {
+    /// Encodes the header in its 64-byte layout.
+    pub fn to_bytes(&self) -> [u8; PORTABLE_HEADER_LEN] {
+        let mut out = [0u8; PORTABLE_HEADER_LEN];
+        out[..4].copy_from_slice(&PORTABLE_MAGIC);
+        out[4..6].copy_from_slice(&self.version.to_le_bytes());
+        out[6..8].copy_from_slice(&(PORTABLE_HEADER_LEN as u16).to_le_bytes());
+        out[8..16].copy_from_slice(&self.array_size.to_le_bytes());
+        out[16..20].copy_from_slice(&self.levels.to_le_bytes());
+        out[20..24].copy_from_slice(&self.hash_functions.to_le_bytes());
+        out[24] = algorithm_code(self.hash_algorithm);
+        out[25] = self.normalization.to_code();
+        out[26] = if self.key_fingerprint.is_some() { FLAG_KEYED } else { 0 };
+        if let InsertMode::ActiveLevel {
+            max_items,
+            max_fill_ratio,
+        } = self.insert_mode
+        {
+            out[27] = 1;
+            if let Some(max_items) = max_items {
+                out[26] |= FLAG_MAX_ITEMS;
+                out[48..56].copy_from_slice(&(max_items as u64).to_le_bytes());
+            }
+            if let Some(max_fill_ratio) = max_fill_ratio {
+                out[26] |= FLAG_MAX_FILL_RATIO;
+                out[56..64].copy_from_slice(&max_fill_ratio.to_bits().to_le_bytes());
+            }
+        }
+        out[28..32].copy_from_slice(&self.active_level.to_le_bytes());
+        out[32..40].copy_from_slice(&self.seed.to_le_bytes());
+        out[40..48].copy_from_slice(&self.key_fingerprint.unwrap_or(0).to_le_bytes());
+        out
+    }
+
+    /// Decodes a header, returning it with the offset where the body starts.
+    ///
+    /// A longer header from a later minor revision is skipped, so old
+    /// readers keep working.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BloomFilterError> {
+        if bytes.len() < PORTABLE_HEADER_LEN || bytes[..4] != PORTABLE_MAGIC {
+            return Err(portable_error("missing magic bytes"));
+        }
+        let u16_at = |offset: usize| u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap());
+        let u32_at = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        let version = u16_at(4);
+        if version != PORTABLE_VERSION {
+            return Err(portable_error(format!("unsupported version {}", version)));
+        }
+        let header_len = u16_at(6) as usize;
+        if header_len < PORTABLE_HEADER_LEN {
+            return Err(portable_error(format!("header of {} bytes is too short", header_len)));
+        }
+        let normalization = Normalization::from_code(bytes[25])
+            .ok_or_else(|| portable_error(format!("unknown key normalization {}", bytes[25])))?;
+        let flags = bytes[26];
+        let insert_mode = match bytes[27] {
+            0 => InsertMode::AllLevels,
+            1 => InsertMode::ActiveLevel {
+                max_items: (flags & FLAG_MAX_ITEMS != 0).then(|| u64_at(48) as usize),
+                max_fill_ratio: (flags & FLAG_MAX_FILL_RATIO != 0).then(|| f64::from_bits(u64_at(56))),
+            },
+            mode => return Err(portable_error(format!("unknown insert mode {}", mode))),
+        };
+        let header = PortableHeader {
+            version,
+            array_size: u64_at(8),
+            levels: u32_at(16),
+            hash_functions: u32_at(20),
+            hash_algorithm: algorithm_from_code(bytes[24]).map_err(|e| match e {
+                BloomFilterError::UnsupportedHashAlgorithm(_) => e,
+                _ => portable_error(format!("unknown hash algorithm {}", bytes[24])),
+            })?,
+            normalization,
+            insert_mode,
+            active_level: u32_at(28),
+            seed: u64_at(32),
+            key_fingerprint: (flags & FLAG_KEYED != 0).then(|| u64_at(40)),
+        };
+        Ok((header, header_len))
+    }
+
+    /// Returns the bytes each level's bits take: the array rounded up to whole 64-bit words.
+    pub fn level_stride(&self) -> usize {
+        self.array_size.div_ceil(64) as usize * 8
+    }
+}
+
+/// A stable binary layout meant to be read and written byte-for-byte by
+/// implementations in other languages; see `PortableHeader` for the layout.
+///
+/// Unlike the binary format it has no variable-length fields: a level's bits
+/// start at a fixed, 8-byte-aligned offset computed from the header alone.
+/// It carries what is needed to query and keep inserting into the filter,
+/// including its insert mode, but not level labels, metadata or TTLs.
+impl BloomFilter {
+    /// Returns the header `to_portable` writes for this filter.
+    pub fn portable_header(&self) -> PortableHeader {
+        PortableHeader {
+            version: PORTABLE_VERSION,
+            array_size: self.array_size as u64,
+            levels: self.levels.len() as u32,
+            hash_functions: self.hash_functions.len() as u32,
+            hash_algorithm: self.hash_algorithm,
+            normalization: self.normalization,
+            insert_mode: self.insert_mode,
+            active_level: self.active_level as u32,
+            seed: self.seed(),
+            key_fingerprint: self.key_fingerprint,
+        }
+    }
+
+    /// Encodes the filter in the portable format.
+    pub fn to_portable(&self) -> Vec<u8> {
+        let header = self.portable_header();
+        let body_len = 8 * (self.hash_functions.len() + self.levels.len()) + self.levels.len() * header.level_stride();
+        let mut out = Vec::with_capacity(PORTABLE_HEADER_LEN + body_len + 4);
+        out.extend_from_slice(&header.to_bytes());
+        for hash_function in &self.hash_functions {
+            out.extend_from_slice(&(hash_function.multiplier as u64).to_le_bytes());
+        }
+        for level in &self.levels {
+            out.extend_from_slice(&(level.item_count as u64).to_le_bytes());
+        }
+        for level in &self.levels {
+            let mut bits = level.to_bytes();
+            bits.resize(header.level_stride(), 0);
+            out.extend_from_slice(&bits);
+        }
+        let checksum = crc32fast::hash(&out);
+        out.extend_from_slice(&checksum.to_le_bytes());
+        out
+    }
+
+    /// Decodes and validates a filter in the portable format.
+    pub fn from_portable(bytes: &[u8]) -> Result<Self, BloomFilterError> {
+        let Some(split) = bytes.len().checked_sub(4) else {
+            return Err(portable_error("unexpected end of data"));
+        };
+        let (data, trailer) = bytes.split_at(split);
+        let (header, body_start) = PortableHeader::from_bytes(data)?;
+        let expected = u32::from_le_bytes(trailer.try_into().unwrap());
+        let actual = crc32fast::hash(data);
+        if actual != expected {
+            error!("Portable filter checksum mismatch: stored {:08x}, computed {:08x}", expected, actual);
+            return Err(BloomFilterError::CorruptedFile { expected, actual });
+        }
+        let array_size = usize::try_from(header.array_size).map_err(|_| portable_error("array size too large"))?;
+        let (num_hashes, num_levels) = (header.hash_functions as usize, header.levels as usize);
+        let body_len = num_hashes
+            .checked_add(num_levels)
+            .and_then(|words| words.checked_mul(8))
+            .and_then(|len| num_levels.checked_mul(header.level_stride()).and_then(|bits| len.checked_add(bits)));
+        let body = data.get(body_start..).unwrap_or_default();
+        if body_len != Some(body.len()) {
+            return Err(portable_error(format!(
+                "body is {} bytes, expected {}",
+                body.len(),
+                body_len.map_or_else(|| "more than fits in memory".to_string(), |len| len.to_string())
+            )));
+        }
+        let words: Vec<u64> = body[..8 * (num_hashes + num_levels)]
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        let hash_functions = words[..num_hashes]
+            .iter()
+            .map(|&multiplier| HashFunction::with_seed(multiplier as usize, header.seed))
+            .collect();
+        let mut levels = Vec::with_capacity(num_levels);
+        let bits = body[8 * (num_hashes + num_levels)..].chunks_exact(header.level_stride().max(1));
+        for (&item_count, bits) in words[num_hashes..].iter().zip(bits) {
+            let mut level = BloomLevel::from_bytes(&bits[..array_size.div_ceil(8)], array_size)?;
+            level.item_count = item_count as usize;
+            levels.push(level);
+        }
+        let bloom_filter = BloomFilter {
+            levels,
+            hash_functions,
+            array_size,
+            insert_mode: header.insert_mode,
+            active_level: header.active_level as usize,
+            hash_algorithm: header.hash_algorithm,
+            key_fingerprint: header.key_fingerprint,
+            hash_key: None,
+            normalization: header.normalization,
+            observers: Vec::new(),
+            counters: Counters::default(),
+            capacity: CapacityLimit::default(),
+        };
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Saves the filter to a file in the portable format.
+    pub fn save_portable(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving BloomFilter to portable file: {}", filepath);
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        writer.write_all(&self.to_portable())?;
+        writer.flush()?;
+        self.notify(|observer| observer.on_save(filepath));
+        Ok(())
+    }
+
+    /// Loads a filter from a file in the portable format.
+    pub fn load_portable(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading BloomFilter from portable file: {}", filepath);
+        Self::from_portable(&std::fs::read(filepath)?)
+    }
+}
+
+fn portable_error(message: impl Into<String>) -> BloomFilterError {
+    BloomFilterError::Encoding {
+        format: "portable",
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One level of 16 bits, 2 FNV-1a hash functions, seed 0, holding "a".
+    const TEST_VECTOR: &str = concat!(
+        "424c4d50010040001000000000000000010000000200000001000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000001f00000000000000250000000000000001",
+        "00000000000000081000000000000041c04d0b"
+    );
+
+    #[test]
+    fn test_portable_round_trip_and_test_vector() {
+        // The test vector in docs/portable-format.md.
+        let mut tiny = BloomFilter::builder()
+            .array_size(16)
+            .hash_functions(2)
+            .hash_algorithm(HashAlgorithm::Fnv1a)
+            .build()
+            .unwrap();
+        tiny.insert("a");
+        let hex: String = tiny.to_portable().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, TEST_VECTOR);
+
+        let mut bf = BloomFilter::builder()
+            .levels(2)
+            .array_size(100)
+            .seed(7)
+            .hash_algorithm(HashAlgorithm::Fnv1a)
+            .normalization("trim,lowercase".parse().unwrap())
+            .build()
+            .unwrap();
+        bf.insert(" Apple");
+        let bytes = bf.to_portable();
+        assert_eq!(bytes.len(), 64 + 8 * (3 + 2) + 2 * 16 + 4);
+        let (header, body_start) = PortableHeader::from_bytes(&bytes).unwrap();
+        assert_eq!((header, body_start), (bf.portable_header(), 64));
+
+        let filepath = std::env::temp_dir().join("test_filter.blmp");
+        let filepath = filepath.to_str().unwrap();
+        bf.save_portable(filepath).unwrap();
+        let loaded = BloomFilter::load_portable(filepath).unwrap();
+        std::fs::remove_file(filepath).unwrap();
+        assert!(loaded.query("apple", 2) && !loaded.query("banana", 2));
+        assert_eq!((loaded.seed(), loaded.levels()[1].item_count()), (7, 1));
+        assert_eq!(loaded.normalization(), bf.normalization());
+
+        let mut corrupted = bytes.clone();
+        corrupted[64 + 40] ^= 1;
+        assert!(matches!(BloomFilter::from_portable(&corrupted), Err(BloomFilterError::CorruptedFile { .. })));
+        assert!(BloomFilter::from_portable(&bytes[..60]).is_err());
+        assert!(BloomFilter::from_portable(&bf.to_binary()).is_err());
+    }
+
+    #[test]
+    fn test_portable_keeps_active_level_mode() {
+        let mode = InsertMode::ActiveLevel {
+            max_items: Some(2),
+            max_fill_ratio: Some(0.5),
+        };
+        let mut bf = BloomFilter::builder().levels(3).array_size(200).insert_mode(mode).build().unwrap();
+        for item in ["a", "b", "c"] {
+            bf.insert(item);
+        }
+        let mut loaded = BloomFilter::from_portable(&bf.to_portable()).unwrap();
+        assert_eq!((loaded.insert_mode(), loaded.active_level()), (mode, 1));
+        loaded.insert("d");
+        assert!(!loaded.levels()[0].contains_positions(&loaded.positions(b"d")));
+        assert_eq!(loaded.levels()[1].item_count(), 2);
+
+        let items_only = InsertMode::ActiveLevel {
+            max_items: None,
+            max_fill_ratio: Some(0.25),
+        };
+        bf.set_insert_mode(items_only);
+        let header = bf.portable_header();
+        assert_eq!(PortableHeader::from_bytes(&header.to_bytes()).unwrap().0, header);
+    }
+}
@@ -0,0 +1,188 @@
+// src/prefix.rs
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError, RebuildParams};
+
+/// A Bloom filter answering "does any inserted key start with this prefix?".
+///
+/// A flat filter only knows whole keys. This one keeps a level per
+/// configured prefix length and inserts each key's prefix of that length
+/// into it, so a prefix query checks the query's own prefix at every
+/// configured length up to its size and matches only if all are present.
+/// Queries whose length is configured are as precise as an ordinary filter
+/// lookup; other lengths are answered from the shorter configured lengths,
+/// which can only add false positives. A prefix shorter than every
+/// configured length matches as soon as anything is inserted, so including
+/// length 1 keeps short queries meaningful. Lengths count bytes of the key.
+#[derive(Serialize, Deserialize)]
+pub struct PrefixBloomFilter {
+    filter: BloomFilter,
+    /// Prefix length stored in each level, strictly increasing.
+    prefix_lengths: Vec<usize>,
+    num_keys: usize,
+}
+
+impl PrefixBloomFilter {
+    /// Creates a filter of one level of `array_size` bits per prefix length.
+    pub fn new(prefix_lengths: &[usize], array_size: usize, num_hash_functions: usize) -> Result<Self, BloomFilterError> {
+        info!(
+            "Creating PrefixBloomFilter: prefix_lengths={:?}, array_size={}",
+            prefix_lengths, array_size
+        );
+        let mut prefix_lengths = prefix_lengths.to_vec();
+        prefix_lengths.sort_unstable();
+        prefix_lengths.dedup();
+        let filter = PrefixBloomFilter {
+            filter: BloomFilter::new(prefix_lengths.len().max(1), array_size, num_hash_functions)?,
+            prefix_lengths,
+            num_keys: 0,
+        };
+        filter.check_lengths()?;
+        Ok(filter)
+    }
+
+    /// Creates a filter whose levels each hold `expected_keys` prefixes at the target false-positive rate.
+    pub fn with_capacity(
+        prefix_lengths: &[usize],
+        expected_keys: usize,
+        false_positive_rate: f64,
+    ) -> Result<Self, BloomFilterError> {
+        let sizing = RebuildParams::for_capacity(expected_keys.max(1), false_positive_rate);
+        Self::new(
+            prefix_lengths,
+            sizing.array_size.unwrap_or(1),
+            sizing.num_hash_functions.unwrap_or(1),
+        )
+    }
+
+    /// Inserts a key's prefixes, returning `true` if any bit was newly set.
+    pub fn insert(&mut self, key: &str) -> bool {
+        let key = self.filter.normalize(key).into_owned();
+        self.insert_bytes(key.as_bytes())
+    }
+
+    /// Inserts a binary key's prefixes.
+    ///
+    /// A key shorter than some prefix length has no prefix of that length
+    /// and is left out of that level.
+    pub fn insert_bytes(&mut self, key: &[u8]) -> bool {
+        let mut newly_set = false;
+        for (level, &length) in self.prefix_lengths.iter().enumerate() {
+            if key.len() < length {
+                break;
+            }
+            let positions = self.filter.positions(&key[..length]);
+            newly_set |= self.filter.levels[level].insert_positions(&positions);
+            self.filter.levels[level].item_count += 1;
+        }
+        self.num_keys += 1;
+        newly_set
+    }
+
+    /// Checks whether some inserted key (probably) starts with `prefix`.
+    pub fn query_prefix(&self, prefix: &str) -> bool {
+        self.query_prefix_bytes(self.filter.normalize(prefix).as_bytes())
+    }
+
+    /// Checks whether some inserted binary key (probably) starts with `prefix`.
+    pub fn query_prefix_bytes(&self, prefix: &[u8]) -> bool {
+        self.num_keys > 0
+            && self
+                .prefix_lengths
+                .iter()
+                .enumerate()
+                .take_while(|&(_, &length)| length <= prefix.len())
+                .all(|(level, &length)| {
+                    self.filter.levels()[level].contains_positions(&self.filter.positions(&prefix[..length]))
+                })
+    }
+
+    /// Returns the configured prefix lengths, shortest first.
+    pub fn prefix_lengths(&self) -> &[usize] {
+        &self.prefix_lengths
+    }
+
+    /// Returns the number of keys inserted.
+    pub fn num_keys(&self) -> usize {
+        self.num_keys
+    }
+
+    /// Returns the underlying filter, one level per prefix length.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+
+    /// Saves the filter and its prefix lengths as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving PrefixBloomFilter to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a filter saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading PrefixBloomFilter from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let filter: Self = serde_json::from_reader(reader)?;
+        filter.filter.validate()?;
+        filter.check_lengths()?;
+        Ok(filter)
+    }
+
+    fn check_lengths(&self) -> Result<(), BloomFilterError> {
+        let invalid = |reason: String| Err(BloomFilterError::InvalidPrefixLengths(reason));
+        if self.prefix_lengths.is_empty() {
+            return invalid("at least one prefix length is needed".to_string());
+        }
+        if self.prefix_lengths[0] == 0 {
+            return invalid("prefix lengths must be at least 1".to_string());
+        }
+        if !self.prefix_lengths.windows(2).all(|pair| pair[0] < pair[1]) {
+            return invalid(format!("prefix lengths {:?} are not increasing", self.prefix_lengths));
+        }
+        if self.prefix_lengths.len() != self.filter.levels().len() {
+            return invalid(format!(
+                "{} prefix lengths for {} levels",
+                self.prefix_lengths.len(),
+                self.filter.levels().len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_queries_at_and_between_lengths() {
+        let mut filter = PrefixBloomFilter::with_capacity(&[8, 1, 4], 100, 0.001).unwrap();
+        assert_eq!(filter.prefix_lengths(), [1, 4, 8]);
+        assert!(!filter.query_prefix("/"));
+        for key in ["/usr/bin/env", "/usr/lib", "/etc/hosts", "/e"] {
+            filter.insert(key);
+        }
+        assert!(filter.query_prefix("/usr") && filter.query_prefix("/usr/bin") && filter.query_prefix("/etc/h"));
+        assert!(filter.query_prefix("/e") && filter.query_prefix(""));
+        assert!(!filter.query_prefix("/var") && !filter.query_prefix("/usr/sbin") && !filter.query_prefix("x"));
+        // A key never holds more than the prefix lengths, so the whole key does not count.
+        assert!(!filter.filter().query("/usr/bin/env", 3));
+
+        let path = std::env::temp_dir().join("test_bloom_prefix.json");
+        let path = path.to_str().unwrap();
+        filter.save_to_file(path).unwrap();
+        let loaded = PrefixBloomFilter::load_from_file(path).unwrap();
+        assert_eq!(loaded.num_keys(), 4);
+        assert!(loaded.query_prefix("/usr/lib") && !loaded.query_prefix("/opt"));
+        std::fs::remove_file(path).unwrap();
+
+        assert!(PrefixBloomFilter::new(&[], 100, 3).is_err());
+        assert!(PrefixBloomFilter::new(&[0, 2], 100, 3).is_err());
+    }
+}
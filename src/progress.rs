@@ -0,0 +1,83 @@
+// src/progress.rs
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::format::FileFormat;
+use crate::source::{self, ItemSource};
+
+/// Items read from a source at a time.
+const BATCH_SIZE: usize = 1024;
+
+// Progress bars for the CLI's long-running operations.
+//
+// Bars draw on stderr and indicatif hides them when stderr is not a
+// terminal, so they never mix with `--json` output or piped stdout.
+
+/// A bar counting items, with throughput, and an ETA when the total `len` is known.
+pub(crate) fn items_bar(len: Option<u64>, message: &str) -> ProgressBar {
+    let (bar, template) = match len {
+        Some(len) => (
+            ProgressBar::new(len),
+            "{msg} [{bar:40}] {human_pos}/{human_len} items ({per_sec}, ETA {eta})",
+        ),
+        None => (ProgressBar::no_length(), "{spinner} {msg} {human_pos} items ({per_sec})"),
+    };
+    bar.set_style(
+        ProgressStyle::with_template(template)
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    bar.with_message(message.to_string())
+}
+
+/// A bar counting bytes, with throughput and (when `len` is known) ETA.
+pub(crate) fn bytes_bar(len: Option<u64>, message: &str) -> ProgressBar {
+    let (bar, template) = match len {
+        Some(len) => (
+            ProgressBar::new(len),
+            "{msg} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta})",
+        ),
+        None => (ProgressBar::no_length(), "{spinner} {msg} {bytes} ({bytes_per_sec})"),
+    };
+    bar.set_style(
+        ProgressStyle::with_template(template)
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    bar.with_message(message.to_string())
+}
+
+/// Calls `f` for each item from the source, advancing `bar` after each one.
+///
+/// Returns the number of items visited.
+pub(crate) fn for_each_item(source: &mut dyn ItemSource, bar: &ProgressBar, mut f: impl FnMut(&str)) -> io::Result<usize> {
+    let count = source::for_each_item(source, BATCH_SIZE, |item| {
+        f(item);
+        bar.inc(1);
+    })?;
+    bar.finish_and_clear();
+    Ok(count)
+}
+
+/// `BloomFilter::save_as` with a progress bar over the bytes written.
+pub(crate) fn save(filter: &BloomFilter, path: &str, format: FileFormat) -> Result<(), BloomFilterError> {
+    info!("Saving BloomFilter to file: {} ({})", path, format);
+    let bar = bytes_bar(None, &format!("Saving {}", path));
+    let result = filter.save_to_writer_as(bar.wrap_write(BufWriter::new(File::create(path)?)), format);
+    bar.finish_and_clear();
+    result
+}
+
+/// `BloomFilter::load_auto` with a progress bar over the bytes read.
+pub(crate) fn load(path: &str) -> Result<(BloomFilter, FileFormat), BloomFilterError> {
+    info!("Loading BloomFilter from file: {} (detecting format)", path);
+    let file = File::open(path)?;
+    let bar = bytes_bar(Some(file.metadata()?.len()), &format!("Loading {}", path));
+    let result = BloomFilter::load_auto_from_reader(bar.wrap_read(file));
+    bar.finish_and_clear();
+    result
+}
@@ -0,0 +1,263 @@
+// src/proto.rs
+
+// Protobuf messages for `proto/bloom_filter.proto`.
+//
+// The structs are kept in step with the schema by hand (so building needs no
+// `protoc`); encode and decode them with `prost::Message`.
+
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilterError, HashFunction, InsertMode};
+use crate::hashing;
+use crate::normalize;
+use crate::saturation::CapacityLimit;
+use crate::stats::Counters;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum HashAlgorithm {
+    Polynomial = 0,
+    Fnv1a = 1,
+    Xxh3 = 2,
+    Murmur3 = 3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum UnicodeForm {
+    None = 0,
+    Nfc = 1,
+    Nfkc = 2,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct KeyNormalization {
+    #[prost(bool, tag = "1")]
+    pub trim: bool,
+    #[prost(bool, tag = "2")]
+    pub lowercase: bool,
+    #[prost(enumeration = "UnicodeForm", tag = "3")]
+    pub unicode_form: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActiveLevelMode {
+    #[prost(uint64, optional, tag = "1")]
+    pub max_items: Option<u64>,
+    #[prost(double, optional, tag = "2")]
+    pub max_fill_ratio: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BloomLevel {
+    #[prost(bytes = "vec", tag = "1")]
+    pub bits: Vec<u8>,
+    #[prost(string, optional, tag = "2")]
+    pub label: Option<String>,
+    #[prost(btree_map = "string, string", tag = "3")]
+    pub metadata: BTreeMap<String, String>,
+    #[prost(uint64, optional, tag = "4")]
+    pub created_at: Option<u64>,
+    #[prost(uint64, optional, tag = "5")]
+    pub ttl_secs: Option<u64>,
+    #[prost(uint64, tag = "6")]
+    pub item_count: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BloomFilter {
+    #[prost(uint64, tag = "1")]
+    pub array_size: u64,
+    #[prost(uint64, repeated, tag = "2")]
+    pub multipliers: Vec<u64>,
+    #[prost(uint64, tag = "3")]
+    pub seed: u64,
+    #[prost(enumeration = "HashAlgorithm", tag = "4")]
+    pub hash_algorithm: i32,
+    #[prost(message, repeated, tag = "5")]
+    pub levels: Vec<BloomLevel>,
+    #[prost(message, optional, tag = "6")]
+    pub active_level_mode: Option<ActiveLevelMode>,
+    #[prost(uint64, tag = "7")]
+    pub active_level: u64,
+    #[prost(uint64, optional, tag = "8")]
+    pub key_fingerprint: Option<u64>,
+    #[prost(message, optional, tag = "9")]
+    pub normalization: Option<KeyNormalization>,
+}
+
+impl crate::BloomFilter {
+    /// Converts the filter to its protobuf message.
+    ///
+    /// Levels are always sent as packed bits, so sparse levels arrive dense.
+    pub fn to_proto(&self) -> BloomFilter {
+        let hash_algorithm = match self.hash_algorithm {
+            hashing::HashAlgorithm::Polynomial => HashAlgorithm::Polynomial,
+            hashing::HashAlgorithm::Fnv1a => HashAlgorithm::Fnv1a,
+            hashing::HashAlgorithm::Xxh3 => HashAlgorithm::Xxh3,
+            hashing::HashAlgorithm::Murmur3 => HashAlgorithm::Murmur3,
+        };
+        let active_level_mode = match self.insert_mode {
+            InsertMode::AllLevels => None,
+            InsertMode::ActiveLevel {
+                max_items,
+                max_fill_ratio,
+            } => Some(ActiveLevelMode {
+                max_items: max_items.map(|max| max as u64),
+                max_fill_ratio,
+            }),
+        };
+        BloomFilter {
+            array_size: self.array_size as u64,
+            multipliers: self.hash_functions.iter().map(|hf| hf.multiplier as u64).collect(),
+            seed: self.seed(),
+            hash_algorithm: hash_algorithm as i32,
+            levels: self
+                .levels
+                .iter()
+                .map(|level| BloomLevel {
+                    bits: level.to_bytes(),
+                    label: level.label.clone(),
+                    metadata: level.metadata.clone(),
+                    created_at: level.created_at,
+                    ttl_secs: level.ttl,
+                    item_count: level.item_count as u64,
+                })
+                .collect(),
+            active_level_mode,
+            active_level: self.active_level as u64,
+            key_fingerprint: self.key_fingerprint,
+            normalization: (!self.normalization.is_identity()).then_some(KeyNormalization {
+                trim: self.normalization.trim,
+                lowercase: self.normalization.lowercase,
+                unicode_form: match self.normalization.unicode {
+                    normalize::UnicodeForm::None => UnicodeForm::None,
+                    normalize::UnicodeForm::Nfc => UnicodeForm::Nfc,
+                    normalize::UnicodeForm::Nfkc => UnicodeForm::Nfkc,
+                } as i32,
+            }),
+        }
+    }
+
+    /// Builds and validates a filter from its protobuf message.
+    pub fn from_proto(message: BloomFilter) -> Result<Self, BloomFilterError> {
+        info!("Converting BloomFilter from protobuf: {} levels", message.levels.len());
+        let filter = Self::from_proto_unvalidated(message)?;
+        filter.validate()?;
+        Ok(filter)
+    }
+
+    /// Like `from_proto`, for a keyed-hashing filter whose key is never part of the message.
+    pub fn from_proto_keyed(message: BloomFilter, hash_key: [u8; 16]) -> Result<Self, BloomFilterError> {
+        let mut filter = Self::from_proto_unvalidated(message)?;
+        filter.restore_hash_key(hash_key)?;
+        filter.validate()?;
+        Ok(filter)
+    }
+
+    fn from_proto_unvalidated(message: BloomFilter) -> Result<Self, BloomFilterError> {
+        let array_size = usize::try_from(message.array_size)
+            .map_err(|_| BloomFilterError::InvalidProto(format!("array size {} is too large", message.array_size)))?;
+        let hash_algorithm = match HashAlgorithm::try_from(message.hash_algorithm) {
+            Ok(HashAlgorithm::Polynomial) => hashing::HashAlgorithm::Polynomial,
+            Ok(HashAlgorithm::Fnv1a) => hashing::HashAlgorithm::Fnv1a,
+            Ok(HashAlgorithm::Xxh3) => hashing::HashAlgorithm::Xxh3,
+            Ok(HashAlgorithm::Murmur3) => hashing::HashAlgorithm::Murmur3,
+            Err(_) => {
+                return Err(BloomFilterError::InvalidProto(format!(
+                    "unknown hash algorithm {}",
+                    message.hash_algorithm
+                )))
+            }
+        };
+        let levels = message
+            .levels
+            .into_iter()
+            .map(|proto_level| {
+                let mut level = crate::bloom_filter::BloomLevel::from_bytes(&proto_level.bits, array_size)?;
+                level.label = proto_level.label;
+                level.metadata = proto_level.metadata;
+                level.created_at = proto_level.created_at;
+                level.ttl = proto_level.ttl_secs;
+                level.item_count = proto_level.item_count as usize;
+                Ok(level)
+            })
+            .collect::<Result<Vec<_>, BloomFilterError>>()?;
+        let insert_mode = match message.active_level_mode {
+            None => InsertMode::AllLevels,
+            Some(mode) => InsertMode::ActiveLevel {
+                max_items: mode.max_items.map(|max| max as usize),
+                max_fill_ratio: mode.max_fill_ratio,
+            },
+        };
+        let normalization = match message.normalization {
+            None => normalize::Normalization::default(),
+            Some(normalization) => normalize::Normalization {
+                trim: normalization.trim,
+                lowercase: normalization.lowercase,
+                unicode: match UnicodeForm::try_from(normalization.unicode_form) {
+                    Ok(UnicodeForm::None) => normalize::UnicodeForm::None,
+                    Ok(UnicodeForm::Nfc) => normalize::UnicodeForm::Nfc,
+                    Ok(UnicodeForm::Nfkc) => normalize::UnicodeForm::Nfkc,
+                    Err(_) => {
+                        return Err(BloomFilterError::InvalidProto(format!(
+                            "unknown unicode form {}",
+                            normalization.unicode_form
+                        )))
+                    }
+                },
+            },
+        };
+        Ok(crate::BloomFilter {
+            levels,
+            hash_functions: message
+                .multipliers
+                .iter()
+                .map(|&multiplier| HashFunction::with_seed(multiplier as usize, message.seed))
+                .collect(),
+            array_size,
+            insert_mode,
+            active_level: message.active_level as usize,
+            hash_algorithm,
+            key_fingerprint: message.key_fingerprint,
+            hash_key: None,
+            normalization,
+            observers: Vec::new(),
+            counters: Counters::default(),
+            capacity: CapacityLimit::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    #[test]
+    fn test_proto_round_trip() {
+        let mut bf = crate::BloomFilter::builder()
+            .levels(2)
+            .array_size(100)
+            .seed(5)
+            .hash_algorithm(hashing::HashAlgorithm::Fnv1a)
+            .build()
+            .unwrap();
+        bf.set_level_label(0, Some("hot".to_string())).unwrap();
+        bf.set_level_metadata(0, "source", "kafka").unwrap();
+        bf.insert("event-1");
+
+        let bytes = bf.to_proto().encode_to_vec();
+        let loaded = crate::BloomFilter::from_proto(BloomFilter::decode(bytes.as_slice()).unwrap()).unwrap();
+        assert!(loaded.query("event-1", 2));
+        assert!(!loaded.query("event-2", 2));
+        assert_eq!(loaded.seed(), 5);
+        assert_eq!(loaded.hash_algorithm(), hashing::HashAlgorithm::Fnv1a);
+        assert_eq!(loaded.levels()[0].metadata().get("source").map(String::as_str), Some("kafka"));
+
+        let mut truncated = bf.to_proto();
+        truncated.levels[1].bits.pop();
+        assert!(crate::BloomFilter::from_proto(truncated).is_err());
+    }
+}
@@ -0,0 +1,495 @@
+// src/redis.rs
+
+use tracing::info;
+
+use crate::bloom_filter::BloomFilterError;
+
+/// RedisBloom option: size links exactly instead of rounding up to a power of two.
+pub const BLOOM_OPT_NOROUND: u32 = 1;
+/// RedisBloom option: `entries` was given in bits rather than items.
+pub const BLOOM_OPT_ENTS_IS_BITS: u32 = 2;
+/// RedisBloom option: use 64-bit MurmurHash64A instead of 32-bit MurmurHash2.
+pub const BLOOM_OPT_FORCE64: u32 = 4;
+/// RedisBloom option: never add links once the first one is full.
+pub const BLOOM_OPT_NO_SCALING: u32 = 8;
+
+/// Default chunk size used by RedisBloom's `BF.SCANDUMP` (16 MiB).
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+const ERROR_TIGHTENING_RATIO: f64 = 0.5;
+const HEADER_SIZE: usize = 8 + 4 + 4 + 4;
+const LINK_SIZE: usize = 8 + 8 + 8 + 8 + 8 + 4 + 8 + 1;
+
+/// A scalable Bloom filter laid out exactly like a RedisBloom `SBChain`.
+///
+/// The hashing and bit layout mirror RedisBloom, so chunks produced by
+/// `BF.SCANDUMP` can be loaded with `from_chunks` and queried offline, and the
+/// output of `to_chunks` can be replayed with `BF.LOADCHUNK`. This is separate
+/// from `BloomFilter`, whose hash functions are not compatible with Redis.
+pub struct RedisBloomFilter {
+    size: u64,
+    options: u32,
+    growth: u32,
+    links: Vec<RedisBloomLink>,
+}
+
+/// A single sub-filter of a RedisBloom chain.
+struct RedisBloomLink {
+    bytes: u64,
+    bits: u64,
+    size: u64,
+    error: f64,
+    bpe: f64,
+    hashes: u32,
+    entries: u64,
+    n2: u8,
+    bf: Vec<u8>,
+}
+
+impl RedisBloomFilter {
+    /// Creates a filter with the same defaults as `BF.RESERVE key error_rate capacity`.
+    pub fn new(capacity: u64, error_rate: f64) -> Result<Self, BloomFilterError> {
+        Self::with_options(capacity, error_rate, BLOOM_OPT_NOROUND | BLOOM_OPT_FORCE64, 2)
+    }
+
+    /// Creates a filter with explicit RedisBloom options and growth factor.
+    pub fn with_options(capacity: u64, error_rate: f64, options: u32, growth: u32) -> Result<Self, BloomFilterError> {
+        info!(
+            "Creating RedisBloomFilter: capacity={}, error_rate={}, options={}, growth={}",
+            capacity, error_rate, options, growth
+        );
+        let link = RedisBloomLink::new(capacity, error_rate, options)?;
+        Ok(RedisBloomFilter {
+            size: 0,
+            options,
+            growth,
+            links: vec![link],
+        })
+    }
+
+    /// Adds an item, returning `Ok(false)` if it may already have been present.
+    ///
+    /// Like `BF.ADD`, a new link with a tighter error rate is appended when the
+    /// current one is full, unless the filter was created with `BLOOM_OPT_NO_SCALING`.
+    pub fn insert(&mut self, item: impl AsRef<[u8]>) -> Result<bool, BloomFilterError> {
+        let hash = self.hash(item.as_ref());
+        if self.links.iter().rev().any(|link| link.check(hash)) {
+            return Ok(false);
+        }
+        let (entries, error, full) = {
+            let cur = self.current();
+            (cur.entries, cur.error, cur.size >= cur.entries)
+        };
+        if full {
+            if self.options & BLOOM_OPT_NO_SCALING != 0 {
+                return Err(BloomFilterError::RedisDump("non-scaling filter is full".to_string()));
+            }
+            let entries = entries
+                .checked_mul(u64::from(self.growth))
+                .ok_or_else(|| BloomFilterError::RedisDump("next link's capacity overflows".to_string()))?;
+            let link = RedisBloomLink::new(entries, error * ERROR_TIGHTENING_RATIO, self.options)?;
+            self.links.push(link);
+        }
+        let cur = self.links.last_mut().expect("chain always has a link");
+        cur.add(hash);
+        cur.size += 1;
+        self.size += 1;
+        Ok(true)
+    }
+
+    /// Checks whether an item may be present (`BF.EXISTS`).
+    pub fn query(&self, item: impl AsRef<[u8]>) -> bool {
+        let hash = self.hash(item.as_ref());
+        self.links.iter().rev().any(|link| link.check(hash))
+    }
+
+    /// Returns the number of items added to the chain.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns true if no items have been added.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns the number of links (sub-filters) in the chain.
+    pub fn num_links(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Produces the `(iterator, data)` pairs that `BF.SCANDUMP` would return.
+    ///
+    /// The first pair is the header at iterator 1; each following pair carries
+    /// at most `max_chunk_size` bytes of a single link. Feed the pairs, in
+    /// order, to `BF.LOADCHUNK` to recreate the filter inside Redis.
+    pub fn to_chunks(&self, max_chunk_size: usize) -> Vec<(i64, Vec<u8>)> {
+        let max_chunk_size = max_chunk_size.max(1);
+        let mut chunks = vec![(1, self.encode_header())];
+        let mut iter: i64 = 1;
+        for link in &self.links {
+            for data in link.bf.chunks(max_chunk_size) {
+                iter += data.len() as i64;
+                chunks.push((iter, data.to_vec()));
+            }
+        }
+        chunks
+    }
+
+    /// Rebuilds a filter from `BF.SCANDUMP` output, as `BF.LOADCHUNK` would.
+    ///
+    /// The header chunk (iterator 1) must come first; data chunks are placed
+    /// using their iterator, which marks the end of the chunk in the
+    /// concatenation of all link bit arrays. Chunks must arrive in order, as
+    /// `BF.SCANDUMP` returns them, and cover every link: link arrays grow as
+    /// data arrives, so memory follows the dump's size, not its header.
+    pub fn from_chunks<I>(chunks: I) -> Result<Self, BloomFilterError>
+    where
+        I: IntoIterator<Item = (i64, Vec<u8>)>,
+    {
+        let mut chunks = chunks.into_iter();
+        let mut filter = match chunks.next() {
+            Some((1, header)) => Self::decode_header(&header)?,
+            _ => return Err(BloomFilterError::RedisDump("first chunk must be the header at iterator 1".to_string())),
+        };
+        for (iter, data) in chunks {
+            if iter == 0 && data.is_empty() {
+                break;
+            }
+            filter.load_chunk(iter, &data)?;
+        }
+        if let Some(index) = filter.links.iter().position(|link| link.bf.len() as u64 != link.bytes) {
+            return Err(BloomFilterError::RedisDump(format!("dump ends before link {} is complete", index)));
+        }
+        Ok(filter)
+    }
+
+    fn load_chunk(&mut self, iter: i64, data: &[u8]) -> Result<(), BloomFilterError> {
+        let start = iter.checked_sub(data.len() as i64 + 1).filter(|&start| start >= 0);
+        let Some(start) = start else {
+            return Err(BloomFilterError::RedisDump(format!("invalid chunk iterator {}", iter)));
+        };
+        let mut offset = start as u64;
+        for link in &mut self.links {
+            if offset < link.bytes {
+                if offset + data.len() as u64 > link.bytes {
+                    return Err(BloomFilterError::RedisDump(format!(
+                        "chunk at iterator {} overruns its link",
+                        iter
+                    )));
+                }
+                let offset = offset as usize;
+                if offset > link.bf.len() {
+                    return Err(BloomFilterError::RedisDump(format!("chunk at iterator {} is out of order", iter)));
+                }
+                let end = offset + data.len();
+                if end > link.bf.len() {
+                    link.bf.resize(end, 0);
+                }
+                link.bf[offset..end].copy_from_slice(data);
+                return Ok(());
+            }
+            offset -= link.bytes;
+        }
+        Err(BloomFilterError::RedisDump(format!("chunk iterator {} is past the end of the filter", iter)))
+    }
+
+    fn encode_header(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_SIZE + LINK_SIZE * self.links.len());
+        out.extend_from_slice(&self.size.to_le_bytes());
+        out.extend_from_slice(&(self.links.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.options.to_le_bytes());
+        out.extend_from_slice(&self.growth.to_le_bytes());
+        for link in &self.links {
+            out.extend_from_slice(&link.bytes.to_le_bytes());
+            out.extend_from_slice(&link.bits.to_le_bytes());
+            out.extend_from_slice(&link.size.to_le_bytes());
+            out.extend_from_slice(&link.error.to_le_bytes());
+            out.extend_from_slice(&link.bpe.to_le_bytes());
+            out.extend_from_slice(&link.hashes.to_le_bytes());
+            out.extend_from_slice(&link.entries.to_le_bytes());
+            out.push(link.n2);
+        }
+        out
+    }
+
+    fn decode_header(buf: &[u8]) -> Result<Self, BloomFilterError> {
+        let mut reader = ByteReader { buf, pos: 0 };
+        let size = reader.u64()?;
+        let nfilters = reader.u32()? as usize;
+        let options = reader.u32()?;
+        let growth = reader.u32()?;
+        if nfilters == 0 || buf.len() != HEADER_SIZE + LINK_SIZE * nfilters {
+            return Err(BloomFilterError::RedisDump(format!(
+                "header of {} bytes does not describe {} links",
+                buf.len(),
+                nfilters
+            )));
+        }
+        let mut links = Vec::with_capacity(nfilters);
+        for _ in 0..nfilters {
+            let bytes = reader.u64()?;
+            let bits = reader.u64()?;
+            let size = reader.u64()?;
+            let error = reader.f64()?;
+            let bpe = reader.f64()?;
+            let hashes = reader.u32()?;
+            let entries = reader.u64()?;
+            let n2 = reader.u8()?;
+            let capacity = bytes.saturating_mul(8);
+            if hashes == 0 || bits == 0 || bits > capacity || (n2 > 0 && (n2 > 63 || 1u64 << n2 > capacity)) {
+                return Err(BloomFilterError::RedisDump(format!(
+                    "inconsistent link geometry: bytes={}, bits={}, n2={}",
+                    bytes, bits, n2
+                )));
+            }
+            links.push(RedisBloomLink {
+                bytes,
+                bits,
+                size,
+                error,
+                bpe,
+                hashes,
+                entries,
+                n2,
+                // Filled in by `load_chunk` as data arrives.
+                bf: Vec::new(),
+            });
+        }
+        Ok(RedisBloomFilter {
+            size,
+            options,
+            growth,
+            links,
+        })
+    }
+
+    fn current(&self) -> &RedisBloomLink {
+        self.links.last().expect("chain always has a link")
+    }
+
+    fn hash(&self, item: &[u8]) -> RedisHash {
+        if self.options & BLOOM_OPT_FORCE64 != 0 {
+            let a = murmur_hash64a(item, 0xc6a4a7935bd1e995);
+            let b = murmur_hash64a(item, a);
+            RedisHash { a, b }
+        } else {
+            let a = murmur_hash2(item, 0x9747b28c);
+            let b = murmur_hash2(item, a);
+            RedisHash {
+                a: u64::from(a),
+                b: u64::from(b),
+            }
+        }
+    }
+}
+
+impl RedisBloomLink {
+    /// Sizes a link the way RedisBloom's `bloom_init` does.
+    fn new(entries: u64, error: f64, options: u32) -> Result<Self, BloomFilterError> {
+        if entries < 1 || error <= 0.0 || error >= 1.0 {
+            return Err(BloomFilterError::RedisDump(format!(
+                "invalid capacity {} or error rate {}",
+                entries, error
+            )));
+        }
+        let bpe = -(error.ln() / std::f64::consts::LN_2.powi(2));
+        let mut entries_adj = entries;
+        let (bits, n2) = if options & BLOOM_OPT_ENTS_IS_BITS != 0 {
+            if entries > 63 {
+                return Err(BloomFilterError::RedisDump(format!("2^{} bits is too large", entries)));
+            }
+            let bits = 1u64 << entries;
+            entries_adj = (bits as f64 / bpe) as u64;
+            (bits, entries as u8)
+        } else if options & BLOOM_OPT_NOROUND != 0 {
+            ((((entries as f64) * bpe) as u64).max(1), 0)
+        } else {
+            let bn2 = ((entries as f64) * bpe).log2().floor();
+            if bn2 > 62.0 {
+                return Err(BloomFilterError::RedisDump("filter would exceed 2^63 bits".to_string()));
+            }
+            let n2 = bn2 as u8 + 1;
+            let bits = 1u64 << n2;
+            let bit_diff = bits as f64 - (entries as f64) * bpe;
+            entries_adj += (bit_diff / bpe) as u64;
+            (bits, n2)
+        };
+        let bytes = bits.div_ceil(64) * 8;
+        Ok(RedisBloomLink {
+            bytes,
+            bits: bytes * 8,
+            size: 0,
+            error,
+            bpe,
+            hashes: (std::f64::consts::LN_2 * bpe).ceil() as u32,
+            entries: entries_adj,
+            n2,
+            bf: vec![0; bytes as usize],
+        })
+    }
+
+    /// Yields the bit positions probed for a hash (`CHECK_ADD_FUNC` in RedisBloom).
+    fn positions(&self, hash: RedisHash) -> impl Iterator<Item = u64> + '_ {
+        let modulus = if self.n2 > 0 { 1u64 << self.n2 } else { self.bits };
+        (0..u64::from(self.hashes)).map(move |i| hash.a.wrapping_add(i.wrapping_mul(hash.b)) % modulus)
+    }
+
+    fn check(&self, hash: RedisHash) -> bool {
+        self.positions(hash)
+            .all(|x| self.bf[(x >> 3) as usize] & (1 << (x % 8)) != 0)
+    }
+
+    fn add(&mut self, hash: RedisHash) {
+        let positions: Vec<u64> = self.positions(hash).collect();
+        for x in positions {
+            self.bf[(x >> 3) as usize] |= 1 << (x % 8);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct RedisHash {
+    a: u64,
+    b: u64,
+}
+
+/// Little-endian cursor over a dumped header.
+struct ByteReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], BloomFilterError> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| BloomFilterError::RedisDump("truncated header".to_string()))?;
+        self.pos += N;
+        Ok(bytes.try_into().expect("slice has length N"))
+    }
+
+    fn u8(&mut self) -> Result<u8, BloomFilterError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, BloomFilterError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, BloomFilterError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn f64(&mut self) -> Result<f64, BloomFilterError> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+}
+
+/// MurmurHash64A, as used by RedisBloom when `BLOOM_OPT_FORCE64` is set.
+fn murmur_hash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut blocks = key.chunks_exact(8);
+    for block in &mut blocks {
+        let mut k = u64::from_le_bytes(block.try_into().expect("block is 8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= u64::from(b) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+/// 32-bit MurmurHash2, used by RedisBloom filters without `BLOOM_OPT_FORCE64`.
+fn murmur_hash2(key: &[u8], seed: u32) -> u32 {
+    const M: u32 = 0x5bd1e995;
+    const R: u32 = 24;
+    let mut h = seed ^ key.len() as u32;
+    let mut blocks = key.chunks_exact(4);
+    for block in &mut blocks {
+        let mut k = u32::from_le_bytes(block.try_into().expect("block is 4 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= u32::from(b) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_round_trip_with_scaling() {
+        let mut rb = RedisBloomFilter::new(10, 0.01).unwrap();
+        for i in 0..50 {
+            rb.insert(format!("item-{}", i)).unwrap();
+        }
+        assert!(rb.num_links() > 1);
+        assert!(!rb.insert("item-7").unwrap());
+
+        let chunks = rb.to_chunks(7);
+        assert_eq!(chunks[0].0, 1);
+        let loaded = RedisBloomFilter::from_chunks(chunks).unwrap();
+        assert_eq!(loaded.len(), rb.len());
+        assert_eq!(loaded.num_links(), rb.num_links());
+        for i in 0..50 {
+            assert!(loaded.query(format!("item-{}", i)));
+        }
+        assert!(!loaded.query("missing"));
+    }
+
+    #[test]
+    fn test_rejects_bad_dumps() {
+        assert!(RedisBloomFilter::from_chunks(vec![(5, vec![0u8; 4])]).is_err());
+        let rb = RedisBloomFilter::new(100, 0.01).unwrap();
+        let mut chunks = rb.to_chunks(DEFAULT_MAX_CHUNK_SIZE);
+        chunks[0].1.truncate(10);
+        assert!(RedisBloomFilter::from_chunks(chunks).is_err());
+
+        // A header claiming a huge link is rejected without allocating it.
+        let mut chunks = rb.to_chunks(DEFAULT_MAX_CHUNK_SIZE);
+        chunks[0].1[HEADER_SIZE..HEADER_SIZE + 8].copy_from_slice(&(1u64 << 60).to_le_bytes());
+        chunks.truncate(1);
+        assert!(RedisBloomFilter::from_chunks(chunks).is_err());
+        let mut chunks = rb.to_chunks(8);
+        chunks.remove(1);
+        assert!(RedisBloomFilter::from_chunks(chunks).is_err());
+        assert!(RedisBloomFilter::from_chunks(vec![rb.to_chunks(8).remove(0), (i64::MIN, vec![0])]).is_err());
+    }
+
+    #[test]
+    fn test_legacy_32bit_hashing() {
+        let mut rb = RedisBloomFilter::with_options(100, 0.01, 0, 2).unwrap();
+        rb.insert("hello").unwrap();
+        let loaded = RedisBloomFilter::from_chunks(rb.to_chunks(16)).unwrap();
+        assert!(loaded.query("hello"));
+        assert!(!loaded.query("world"));
+    }
+}
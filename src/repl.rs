@@ -0,0 +1,1123 @@
+// src/repl.rs
+
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::error;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::{Context, Editor, Helper, Highlighter, Hinter, Validator};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::compare::{FilterComparison, FilterDiff};
+use crate::format::FileFormat;
+use crate::progress;
+use crate::source;
+use crate::stats::FilterStats;
+use crate::store::FilterStore;
+use crate::blocklist;
+use crate::commands::{BlocklistLookup, KmerHits, Misspelling, NgramContainment};
+use crate::top_k::HeavyHitter;
+use crate::tuning::TuningCandidate;
+
+/// Command names offered by tab completion.
+const COMMANDS: [&str; 15] = [
+    "insert", "query", "blocked", "import", "check", "stats", "save", "load", "label", "filters", "use", "copy",
+    "rename", "help", "exit",
+];
+
+const HELP: &str = "\
+Commands:
+  insert <item>                     insert an item
+  query <item> [--levels N]         query the first N levels (default: all)
+  blocked <url-or-host>             check a URL's host and its parent domains
+                                    against a filter built by 'bloom blocklist'
+  import <source>                   insert every line of a source
+  check <source> [--levels N]       query every line of a source and count matches
+                                    (a file path, - for stdin, or tcp://host:port)
+  stats                             show parameters and per-level fill
+  save <path> [--format FORMAT]     save as json, binary, compressed or msgpack
+                                    (default: from the extension, else json)
+  load <path>                       load a filter, detecting its format
+  label <level> <name>              name a level (levels are numbered from 1)
+  filters                           list the filters in this session
+  use <name>                        switch the filter commands act on
+  copy <from> <to>                  copy a filter under a new name
+  rename <from> <to>                rename a filter
+  help                              show this message
+  exit                              leave the REPL
+Items containing spaces can be quoted: insert \"hello world\"";
+
+/// A parsed REPL command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Insert(String),
+    Query { item: String, levels: Option<usize> },
+    Blocked(String),
+    Import(String),
+    Check { path: String, levels: Option<usize> },
+    Stats,
+    Save { path: String, format: Option<FileFormat> },
+    Load(String),
+    Label { level: usize, label: String },
+    Filters,
+    Use(String),
+    Copy { from: String, to: String },
+    Rename { from: String, to: String },
+    Help,
+    Exit,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(line)?;
+        let (name, args) = tokens.split_first().ok_or("empty command")?;
+        let (positional, levels, format) = split_options(args)?;
+        let expect = |count: usize, usage: &str| {
+            if positional.len() == count {
+                Ok(())
+            } else {
+                Err(format!("usage: {}", usage))
+            }
+        };
+        let no_options = |allowed_levels: bool, allowed_format: bool| {
+            if (levels.is_some() && !allowed_levels) || (format.is_some() && !allowed_format) {
+                Err(format!("'{}' does not take that option", name))
+            } else {
+                Ok(())
+            }
+        };
+        match name.as_str() {
+            "insert" => {
+                expect(1, "insert <item>")?;
+                no_options(false, false)?;
+                Ok(Command::Insert(positional[0].clone()))
+            }
+            "query" => {
+                expect(1, "query <item> [--levels N]")?;
+                no_options(true, false)?;
+                Ok(Command::Query {
+                    item: positional[0].clone(),
+                    levels,
+                })
+            }
+            "blocked" => {
+                expect(1, "blocked <url-or-host>")?;
+                no_options(false, false)?;
+                Ok(Command::Blocked(positional[0].clone()))
+            }
+            "import" => {
+                expect(1, "import <source>")?;
+                no_options(false, false)?;
+                Ok(Command::Import(positional[0].clone()))
+            }
+            "check" => {
+                expect(1, "check <source> [--levels N]")?;
+                no_options(true, false)?;
+                Ok(Command::Check {
+                    path: positional[0].clone(),
+                    levels,
+                })
+            }
+            "stats" => {
+                expect(0, "stats")?;
+                no_options(false, false)?;
+                Ok(Command::Stats)
+            }
+            "save" => {
+                expect(1, "save <path> [--format FORMAT]")?;
+                no_options(false, true)?;
+                Ok(Command::Save {
+                    path: positional[0].clone(),
+                    format,
+                })
+            }
+            "load" => {
+                expect(1, "load <path>")?;
+                no_options(false, false)?;
+                Ok(Command::Load(positional[0].clone()))
+            }
+            "label" => {
+                expect(2, "label <level> <name>")?;
+                no_options(false, false)?;
+                let level = positional[0]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&level| level > 0)
+                    .ok_or_else(|| format!("invalid level '{}'", positional[0]))?;
+                Ok(Command::Label {
+                    level,
+                    label: positional[1].clone(),
+                })
+            }
+            "filters" => {
+                expect(0, "filters")?;
+                no_options(false, false)?;
+                Ok(Command::Filters)
+            }
+            "use" => {
+                expect(1, "use <name>")?;
+                no_options(false, false)?;
+                Ok(Command::Use(positional[0].clone()))
+            }
+            "copy" => {
+                expect(2, "copy <from> <to>")?;
+                no_options(false, false)?;
+                Ok(Command::Copy {
+                    from: positional[0].clone(),
+                    to: positional[1].clone(),
+                })
+            }
+            "rename" => {
+                expect(2, "rename <from> <to>")?;
+                no_options(false, false)?;
+                Ok(Command::Rename {
+                    from: positional[0].clone(),
+                    to: positional[1].clone(),
+                })
+            }
+            "help" | "?" => Ok(Command::Help),
+            "exit" | "quit" => Ok(Command::Exit),
+            other => Err(format!("unknown command '{}' (type 'help' for a list)", other)),
+        }
+    }
+}
+
+/// Splits a line on whitespace, keeping double-quoted sections together.
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_token = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+type Options = (Vec<String>, Option<usize>, Option<FileFormat>);
+
+/// Separates `--levels N` and `--format F` from positional arguments.
+fn split_options(args: &[String]) -> Result<Options, String> {
+    let mut positional = Vec::new();
+    let mut levels = None;
+    let mut format = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--levels" => {
+                let value = args.next().ok_or("--levels needs a value")?;
+                levels = Some(
+                    value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&levels| levels > 0)
+                        .ok_or_else(|| format!("invalid level count '{}'", value))?,
+                );
+            }
+            "--format" => {
+                let value = args.next().ok_or("--format needs a value")?;
+                format = Some(value.parse()?);
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+    Ok((positional, levels, format))
+}
+
+/// The result of a successful command, shown as text or, with `--json`, as a JSON object.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Outcome {
+    Create { stats: FilterStats },
+    Insert { item: String, new: bool },
+    Query {
+        item: String,
+        present: bool,
+        /// 1-based number of the first matching level.
+        level: Option<usize>,
+        label: Option<String>,
+    },
+    Import { path: String, items: usize, new: usize },
+    Check { path: String, items: usize, present: usize },
+    Stats { stats: FilterStats },
+    Save { path: String, format: String },
+    Load { path: String, format: String, stats: FilterStats },
+    Label { level: usize, label: String },
+    Filters { active: Option<String>, names: Vec<String> },
+    Use { name: String },
+    Copy { from: String, to: String },
+    Rename { from: String, to: String },
+    Help { text: String },
+    Bench {
+        items: usize,
+        array_size: usize,
+        hash_functions: usize,
+        inserts_per_second: f64,
+        queries_per_second: f64,
+        false_positives: usize,
+        measured_false_positive_rate: f64,
+        theoretical_false_positive_rate: f64,
+    },
+    Tune {
+        items: usize,
+        target_false_positive_rate: f64,
+        candidates: Vec<TuningCandidate>,
+    },
+    Generate {
+        path: String,
+        format: String,
+        items: usize,
+        target_false_positive_rate: f64,
+        stats: FilterStats,
+    },
+    Compare {
+        first: String,
+        second: String,
+        compatible: bool,
+        /// Why the filters cannot be compared, when they are not compatible.
+        reason: Option<String>,
+        comparison: Option<FilterComparison>,
+    },
+    Diff {
+        first: String,
+        second: String,
+        diff: FilterDiff,
+    },
+    Visualize {
+        path: String,
+        rendering: String,
+    },
+    Merge {
+        path: String,
+        format: String,
+        inputs: usize,
+        stats: FilterStats,
+    },
+    Similarity {
+        first: String,
+        second: String,
+        items_first: usize,
+        items_second: usize,
+        permutations: usize,
+        jaccard: f64,
+    },
+    TopK {
+        source: String,
+        items: usize,
+        top: Vec<HeavyHitter>,
+    },
+    CreateVariant { path: String, variant: String },
+    Add {
+        path: String,
+        variant: String,
+        items: usize,
+        new: usize,
+    },
+    Contains {
+        path: String,
+        variant: String,
+        present: Vec<String>,
+        absent: Vec<String>,
+        estimated_false_positive_rate: f64,
+    },
+    Shutdown,
+    Follow {
+        input: String,
+        path: String,
+        items: usize,
+        new: usize,
+        saves: usize,
+    },
+    Spellcheck {
+        dict: String,
+        path: String,
+        words: usize,
+        misspelled: Vec<Misspelling>,
+        estimated_false_positive_rate: f64,
+    },
+    Blocked {
+        target: String,
+        host: String,
+        blocked_by: Option<String>,
+    },
+    BlocklistBuild {
+        path: String,
+        format: String,
+        lists: usize,
+        hosts: usize,
+        skipped: usize,
+        stats: FilterStats,
+    },
+    BlocklistCheck {
+        path: String,
+        blocked: usize,
+        lookups: Vec<BlocklistLookup>,
+    },
+    PasswordBuild {
+        path: String,
+        format: String,
+        digest: String,
+        entries: usize,
+        skipped: usize,
+        stats: FilterStats,
+    },
+    PasswordCheck {
+        path: String,
+        breached: usize,
+        /// Whether each candidate, in order, was found in the corpus.
+        found: Vec<bool>,
+    },
+    KmerBuild {
+        path: String,
+        format: String,
+        k: usize,
+        canonical: bool,
+        sequences: usize,
+        kmers: usize,
+        stats: FilterStats,
+    },
+    KmerQuery {
+        path: String,
+        k: usize,
+        records: Vec<KmerHits>,
+    },
+    NgramBuild {
+        path: String,
+        format: String,
+        n: usize,
+        unit: String,
+        documents: usize,
+        ngrams: usize,
+        stats: FilterStats,
+    },
+    NgramQuery {
+        path: String,
+        n: usize,
+        unit: String,
+        documents: Vec<NgramContainment>,
+    },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Create { stats } => write!(f, "Bloom Filter created successfully!\n{}", stats),
+            Outcome::Insert { new: true, .. } => write!(f, "Item inserted successfully."),
+            Outcome::Insert { new: false, .. } => write!(f, "Item inserted (it may already have been present)."),
+            Outcome::Query { present: false, .. } => write!(f, "Item is not present."),
+            Outcome::Query { level, label, .. } => {
+                write!(f, "Item may be present (matched level {}", level.unwrap_or(0))?;
+                if let Some(label) = label {
+                    write!(f, " \"{}\"", label)?;
+                }
+                write!(f, ").")
+            }
+            Outcome::Import { items, new, .. } => write!(f, "Imported {} items ({} new).", items, new),
+            Outcome::Check { items, present, .. } => {
+                write!(f, "{} of {} items may be present.", present, items)
+            }
+            Outcome::Stats { stats } => write!(f, "{}", stats),
+            Outcome::Save { format, .. } => write!(f, "Bloom Filter saved successfully ({}).", format),
+            Outcome::Load { format, stats, .. } => write!(f, "Bloom Filter loaded successfully ({}).\n{}", format, stats),
+            Outcome::Label { .. } => write!(f, "Level labelled successfully."),
+            Outcome::Filters { active, names } => {
+                let listed: Vec<String> = names
+                    .iter()
+                    .map(|name| {
+                        if active.as_ref() == Some(name) {
+                            format!("* {}", name)
+                        } else {
+                            format!("  {}", name)
+                        }
+                    })
+                    .collect();
+                write!(f, "{}", listed.join("\n"))
+            }
+            Outcome::Use { name } => write!(f, "Now using filter '{}'.", name),
+            Outcome::Copy { from, to } => write!(f, "Copied filter '{}' to '{}'.", from, to),
+            Outcome::Rename { from, to } => write!(f, "Renamed filter '{}' to '{}'.", from, to),
+            Outcome::Help { text } => write!(f, "{}", text),
+            Outcome::Bench {
+                items,
+                array_size,
+                hash_functions,
+                inserts_per_second,
+                queries_per_second,
+                false_positives,
+                measured_false_positive_rate,
+                theoretical_false_positive_rate,
+            } => write!(
+                f,
+                "{} items, {} bits, {} hash functions\n\
+                 inserts: {:.0}/s\n\
+                 queries: {:.0}/s\n\
+                 false positives: {} of {} ({:.4}%), theoretical {:.4}%",
+                items,
+                array_size,
+                hash_functions,
+                inserts_per_second,
+                queries_per_second,
+                false_positives,
+                items,
+                measured_false_positive_rate * 100.0,
+                theoretical_false_positive_rate * 100.0
+            ),
+            Outcome::Tune {
+                items,
+                target_false_positive_rate,
+                candidates,
+            } => {
+                write!(f, "{} items, target FPR {:.4}%", items, target_false_positive_rate * 100.0)?;
+                for candidate in candidates {
+                    write!(
+                        f,
+                        "\n{} bits ({} bytes), {} hash functions: FPR {:.4}%",
+                        candidate.array_size,
+                        candidate.bytes,
+                        candidate.num_hash_functions,
+                        candidate.theoretical_false_positive_rate * 100.0
+                    )?;
+                    if let Some(measured) = candidate.measured_false_positive_rate {
+                        write!(f, ", measured {:.4}%", measured * 100.0)?;
+                    }
+                    if !candidate.meets_target {
+                        write!(f, " (misses target; largest within budget)")?;
+                    }
+                }
+                Ok(())
+            }
+            Outcome::Generate {
+                path,
+                format,
+                items,
+                target_false_positive_rate,
+                stats,
+            } => write!(
+                f,
+                "Generated {} ({}) from {} items, target FPR {:.4}%\n{}",
+                path,
+                format,
+                items,
+                target_false_positive_rate * 100.0,
+                stats
+            ),
+            Outcome::Compare {
+                comparison: Some(comparison),
+                ..
+            } => write!(f, "Filters are compatible.\n{}", comparison),
+            Outcome::Compare { reason, .. } => {
+                write!(f, "Filters are not compatible: {}", reason.as_deref().unwrap_or("unknown"))
+            }
+            Outcome::Diff { first, second, diff } if diff.is_identical() => {
+                write!(f, "{} and {} have identical bits ({} set).", first, second, diff.shared)
+            }
+            Outcome::Diff { first, second, diff } => write!(f, "{} vs {}: {}", first, second, diff),
+            Outcome::Visualize { path, rendering } => write!(f, "{}\n{}", path, rendering),
+            Outcome::Merge {
+                path,
+                format,
+                inputs,
+                stats,
+            } => write!(f, "Merged {} filters into {} ({}).\n{}", inputs, path, format, stats),
+            Outcome::Similarity {
+                first,
+                second,
+                items_first,
+                items_second,
+                permutations,
+                jaccard,
+            } => write!(
+                f,
+                "{} ({} items) and {} ({} items): estimated Jaccard similarity {:.4} over {} permutations",
+                first, items_first, second, items_second, jaccard, permutations
+            ),
+            Outcome::TopK { source, items, top } => {
+                write!(f, "Most frequent of {} items in {}:", items, source)?;
+                for (rank, hitter) in top.iter().enumerate() {
+                    write!(f, "\n{:>3}. {} ({})", rank + 1, hitter.item, hitter.count)?;
+                }
+                Ok(())
+            }
+            Outcome::CreateVariant { path, variant } => write!(f, "Created an empty {} filter in {}", variant, path),
+            Outcome::Add {
+                path,
+                variant,
+                items,
+                new,
+            } => write!(f, "Inserted {} items into the {} filter in {} ({} new)", items, variant, path, new),
+            Outcome::Contains {
+                path,
+                variant,
+                present,
+                absent,
+                estimated_false_positive_rate,
+            } => {
+                write!(
+                    f,
+                    "{} filter in {} (estimated false-positive rate {:.6}):",
+                    variant, path, estimated_false_positive_rate
+                )?;
+                for item in present {
+                    write!(f, "\n  {}: maybe present", item)?;
+                }
+                for item in absent {
+                    write!(f, "\n  {}: not present", item)?;
+                }
+                Ok(())
+            }
+            Outcome::Shutdown => write!(f, "Daemon stopped; filters saved"),
+            Outcome::Follow {
+                input,
+                path,
+                items,
+                new,
+                saves,
+            } => write!(
+                f,
+                "Inserted {} lines from {} into {} ({} new), saved {} times",
+                items, input, path, new, saves
+            ),
+            Outcome::Spellcheck {
+                dict,
+                path,
+                words,
+                misspelled,
+                estimated_false_positive_rate,
+            } => {
+                write!(
+                    f,
+                    "{} of {} words in {} are not in {} (false-positive rate {:.6})",
+                    misspelled.len(),
+                    words,
+                    path,
+                    dict,
+                    estimated_false_positive_rate
+                )?;
+                for misspelling in misspelled {
+                    write!(f, "\n  line {}: {}", misspelling.line, misspelling.word)?;
+                }
+                Ok(())
+            }
+            Outcome::Blocked {
+                host,
+                blocked_by: Some(domain),
+                ..
+            } => write!(f, "{} is blocked (listed: {})", host, domain),
+            Outcome::Blocked { host, blocked_by: None, .. } => write!(f, "{} is not blocked", host),
+            Outcome::BlocklistBuild {
+                path,
+                format,
+                lists,
+                hosts,
+                skipped,
+                stats,
+            } => write!(
+                f,
+                "Saved {} hosts from {} lists to {} as {} ({} unrecognized entries skipped)\n{}",
+                hosts, lists, path, format, skipped, stats
+            ),
+            Outcome::BlocklistCheck { path, blocked, lookups } => {
+                write!(f, "{} of {} targets are blocked by {}:", blocked, lookups.len(), path)?;
+                for lookup in lookups {
+                    match (&lookup.host, &lookup.blocked_by) {
+                        (None, _) => write!(f, "\n  {}: not a URL or host name", lookup.target)?,
+                        (Some(host), Some(domain)) => write!(f, "\n  {}: blocked (listed: {})", host, domain)?,
+                        (Some(host), None) => write!(f, "\n  {}: not blocked", host)?,
+                    }
+                }
+                Ok(())
+            }
+            Outcome::PasswordBuild {
+                path,
+                format,
+                digest,
+                entries,
+                skipped,
+                stats,
+            } => write!(
+                f,
+                "Saved {} {} digests to {} as {} ({} invalid entries skipped)\n{}",
+                entries, digest, path, format, skipped, stats
+            ),
+            Outcome::PasswordCheck { path, breached, found } => {
+                write!(f, "{} of {} passwords appear in {}", breached, found.len(), path)?;
+                for (number, found) in found.iter().enumerate() {
+                    let verdict = if *found { "BREACHED, do not use" } else { "not found" };
+                    write!(f, "\n  password {}: {}", number + 1, verdict)?;
+                }
+                Ok(())
+            }
+            Outcome::KmerBuild {
+                path,
+                format,
+                k,
+                canonical,
+                sequences,
+                kmers,
+                stats,
+            } => write!(
+                f,
+                "Saved {} {}{}-mers from {} sequences to {} as {}\n{}",
+                kmers,
+                if *canonical { "canonical " } else { "" },
+                k,
+                sequences,
+                path,
+                format,
+                stats
+            ),
+            Outcome::KmerQuery { path, k, records } => {
+                write!(f, "{}-mers of {} sequences found in {}:", k, records.len(), path)?;
+                for hits in records {
+                    let share = if hits.kmers == 0 { 0.0 } else { hits.present as f64 / hits.kmers as f64 };
+                    write!(f, "\n  {}: {}/{} ({:.1}%)", hits.id, hits.present, hits.kmers, share * 100.0)?;
+                }
+                Ok(())
+            }
+            Outcome::NgramBuild {
+                path,
+                format,
+                n,
+                unit,
+                documents,
+                ngrams,
+                stats,
+            } => write!(
+                f,
+                "Saved {} {}-grams of {} from {} documents to {} as {}\n{}",
+                ngrams, n, unit, documents, path, format, stats
+            ),
+            Outcome::NgramQuery {
+                path,
+                n,
+                unit,
+                documents,
+            } => {
+                write!(f, "{}-grams of {} found in {}:", n, unit, path)?;
+                for document in documents {
+                    write!(
+                        f,
+                        "\n  {}: {}/{} ({:.1}%)",
+                        document.path,
+                        document.present,
+                        document.ngrams,
+                        document.fraction * 100.0
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Formats a command result for output: plain text, or a single-line JSON
+/// object with an `ok` field and either the outcome's fields or `error`.
+pub fn render(result: &Result<Outcome, String>, json: bool) -> String {
+    #[derive(Serialize)]
+    struct Response<'a> {
+        ok: bool,
+        #[serde(flatten, skip_serializing_if = "Option::is_none")]
+        outcome: Option<&'a Outcome>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<&'a str>,
+    }
+
+    match (result, json) {
+        (Ok(outcome), false) => outcome.to_string(),
+        (Err(message), false) => message.clone(),
+        (result, true) => {
+            let response = Response {
+                ok: result.is_ok(),
+                outcome: result.as_ref().ok(),
+                error: result.as_ref().err().map(String::as_str),
+            };
+            serde_json::to_string(&response).expect("command results always serialize")
+        }
+    }
+}
+
+/// The filters a REPL session works with, and the one commands act on.
+pub struct Session {
+    store: FilterStore,
+    active: String,
+}
+
+impl Session {
+    /// Name of the filter in a session started with `with_filter`.
+    pub const DEFAULT_FILTER: &'static str = "default";
+
+    /// Starts a session on the filter called `active`, which must exist in `store`.
+    pub fn new(store: FilterStore, active: &str) -> Result<Self, BloomFilterError> {
+        if !store.contains(active) {
+            return Err(BloomFilterError::FilterNotFound(active.to_string()));
+        }
+        Ok(Session {
+            store,
+            active: active.to_string(),
+        })
+    }
+
+    /// Starts a session on a single in-memory filter named `default`.
+    pub fn with_filter(filter: BloomFilter) -> Self {
+        let mut store = FilterStore::in_memory();
+        store
+            .create(Self::DEFAULT_FILTER, filter)
+            .expect("an empty store accepts any unkeyed filter");
+        Session {
+            store,
+            active: Self::DEFAULT_FILTER.to_string(),
+        }
+    }
+
+    /// Returns the name of the filter commands act on.
+    pub fn active(&self) -> &str {
+        &self.active
+    }
+
+    /// Returns the session's filters.
+    pub fn store(&mut self) -> &mut FilterStore {
+        &mut self.store
+    }
+}
+
+/// Runs one command in the session, against its active filter where relevant.
+///
+/// Returns `None` for `exit`.
+pub fn execute(session: &mut Session, command: Command) -> Option<Result<Outcome, String>> {
+    execute_in(&mut session.store, &mut session.active, command)
+}
+
+/// Like `execute`, for callers that keep the store and the active filter's
+/// name apart, such as the daemon, whose clients share a store but each pick
+/// their own filter.
+pub(crate) fn execute_in(
+    store: &mut FilterStore,
+    active: &mut String,
+    command: Command,
+) -> Option<Result<Outcome, String>> {
+    let result = match command {
+        Command::Filters => match store.list() {
+            Ok(names) => Ok(Outcome::Filters {
+                active: Some(active.clone()),
+                names,
+            }),
+            Err(e) => Err(format!("Failed to list filters: {}", e)),
+        },
+        Command::Use(name) => match store.get(&name) {
+            Ok(_) => {
+                *active = name.clone();
+                Ok(Outcome::Use { name })
+            }
+            Err(e) => Err(format!("Failed to open filter: {}", e)),
+        },
+        Command::Copy { from, to } => match store.copy(&from, &to) {
+            Ok(()) => Ok(Outcome::Copy { from, to }),
+            Err(e) => Err(format!("Failed to copy filter: {}", e)),
+        },
+        Command::Rename { from, to } => match store.rename(&from, &to) {
+            Ok(()) => {
+                if *active == from {
+                    *active = to.clone();
+                }
+                Ok(Outcome::Rename { from, to })
+            }
+            Err(e) => Err(format!("Failed to rename filter: {}", e)),
+        },
+        Command::Help => Ok(Outcome::Help { text: HELP.to_string() }),
+        Command::Exit => return None,
+        command => match store.get_mut(active) {
+            Ok(filter) => execute_on(filter, command),
+            Err(e) => Err(format!("Failed to open filter '{}': {}", active, e)),
+        },
+    };
+    Some(result)
+}
+
+/// Runs a command that acts on a single filter.
+fn execute_on(filter: &mut BloomFilter, command: Command) -> Result<Outcome, String> {
+    match command {
+        Command::Insert(item) => {
+            let new = filter.insert(&item);
+            Ok(Outcome::Insert { item, new })
+        }
+        Command::Query { item, levels } => {
+            let num_levels = filter.levels().len();
+            let levels = levels.unwrap_or(num_levels);
+            if levels > num_levels {
+                Err(format!("Number of levels to search must be between 1 and {}.", num_levels))
+            } else {
+                let level = filter.query_level(&item, levels);
+                Ok(Outcome::Query {
+                    present: level.is_some(),
+                    level: level.map(|level| level + 1),
+                    label: level.and_then(|level| filter.levels()[level].label().map(str::to_string)),
+                    item,
+                })
+            }
+        }
+        Command::Blocked(target) => match blocklist::lookup(filter, &target) {
+            Ok((host, blocked_by)) => Ok(Outcome::Blocked {
+                target,
+                host,
+                blocked_by,
+            }),
+            Err(e) => Err(e.to_string()),
+        },
+        Command::Import(path) => {
+            let mut new = 0;
+            let result = source::open(&path).and_then(|mut source| {
+                let bar = progress::items_bar(source.remaining().map(|total| total as u64), "Importing");
+                progress::for_each_item(source.as_mut(), &bar, |item| new += usize::from(filter.insert(item)))
+            });
+            match result {
+                Ok(items) => Ok(Outcome::Import { path, items, new }),
+                Err(e) => Err(format!("Failed to import {}: {}", path, e)),
+            }
+        }
+        Command::Check { path, levels } => {
+            let num_levels = filter.levels().len();
+            let levels = levels.unwrap_or(num_levels);
+            if levels > num_levels {
+                Err(format!("Number of levels to search must be between 1 and {}.", num_levels))
+            } else {
+                let mut present = 0;
+                let result = source::open(&path).and_then(|mut source| {
+                    let bar = progress::items_bar(source.remaining().map(|total| total as u64), "Checking");
+                    progress::for_each_item(source.as_mut(), &bar, |item| {
+                        present += usize::from(filter.query(item, levels))
+                    })
+                });
+                match result {
+                    Ok(items) => Ok(Outcome::Check { path, items, present }),
+                    Err(e) => Err(format!("Failed to check {}: {}", path, e)),
+                }
+            }
+        }
+        Command::Stats => Ok(Outcome::Stats { stats: filter.stats() }),
+        Command::Save { path, format } => {
+            let format = format.unwrap_or_else(|| FileFormat::for_path(&path));
+            match progress::save(filter, &path, format) {
+                Ok(()) => Ok(Outcome::Save {
+                    path,
+                    format: format.to_string(),
+                }),
+                Err(e) => Err(format!("Failed to save BloomFilter: {}", e)),
+            }
+        }
+        Command::Load(path) => match progress::load(&path) {
+            Ok((loaded, format)) => {
+                *filter = loaded;
+                Ok(Outcome::Load {
+                    path,
+                    format: format.to_string(),
+                    stats: filter.stats(),
+                })
+            }
+            Err(e) => Err(format!("Failed to load BloomFilter: {}", e)),
+        },
+        Command::Label { level, label } => match filter.set_level_label(level - 1, Some(label.clone())) {
+            Ok(()) => Ok(Outcome::Label { level, label }),
+            Err(e) => Err(format!("Failed to label level: {}", e)),
+        },
+        Command::Filters
+        | Command::Use(_)
+        | Command::Copy { .. }
+        | Command::Rename { .. }
+        | Command::Help
+        | Command::Exit => unreachable!("session commands are handled by execute"),
+    }
+}
+
+/// Completes command names, and file paths for `save`, `load`, `import` and `check`.
+#[derive(Helper, Highlighter, Hinter, Validator)]
+struct ReplHelper {
+    filenames: FilenameCompleter,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        match before.split_once(char::is_whitespace) {
+            None => Ok((
+                0,
+                COMMANDS
+                    .iter()
+                    .filter(|command| command.starts_with(before))
+                    .map(|command| Pair {
+                        display: command.to_string(),
+                        replacement: format!("{} ", command),
+                    })
+                    .collect(),
+            )),
+            Some(("save" | "load" | "import" | "check", _)) => self.filenames.complete(line, pos, ctx),
+            Some(_) => Ok((pos, Vec::new())),
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".bloom_history"))
+}
+
+/// Runs the interactive REPL until `exit` or end of input.
+///
+/// With `json`, every command prints one JSON object per line and nothing
+/// else is written to stdout.
+pub fn run(mut session: Session, json: bool) -> rustyline::Result<()> {
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ReplHelper {
+        filenames: FilenameCompleter::new(),
+    }));
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing history file just means this is the first session.
+        let _ = editor.load_history(path);
+    }
+    if !json {
+        println!("Type 'help' for a list of commands.");
+    }
+    loop {
+        let line = match editor.readline("bloom> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line.as_str())?;
+        let result = match line.parse::<Command>() {
+            Ok(command) => match execute(&mut session, command) {
+                Some(result) => result,
+                None => break,
+            },
+            Err(e) => Err(e),
+        };
+        if let Err(message) = &result {
+            error!("{}", message);
+        }
+        println!("{}", render(&result, json));
+    }
+    if let Some(path) = &history {
+        if let Err(e) = editor.save_history(path) {
+            error!("Failed to save history: {}", e);
+        }
+    }
+    if let Err(e) = session.store.flush() {
+        error!("Failed to save filters: {}", e);
+        println!("{}", render(&Err(format!("Failed to save filters: {}", e)), json));
+    }
+    if !json {
+        println!("Exiting the Bloom Filter CLI. Goodbye!");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!("insert foo".parse(), Ok(Command::Insert("foo".to_string())));
+        assert_eq!("insert \"hello world\"".parse(), Ok(Command::Insert("hello world".to_string())));
+        assert_eq!(
+            "query foo --levels 2".parse(),
+            Ok(Command::Query {
+                item: "foo".to_string(),
+                levels: Some(2)
+            })
+        );
+        assert_eq!(
+            "save out.bin".parse(),
+            Ok(Command::Save {
+                path: "out.bin".to_string(),
+                format: None
+            })
+        );
+        assert_eq!(
+            "label 2 archive".parse(),
+            Ok(Command::Label {
+                level: 2,
+                label: "archive".to_string()
+            })
+        );
+        assert!("query".parse::<Command>().is_err());
+        assert!("query foo --levels 0".parse::<Command>().is_err());
+        assert!("insert foo --format json".parse::<Command>().is_err());
+        assert!("frobnicate".parse::<Command>().is_err());
+        assert_eq!(FileFormat::for_path("out.bin"), FileFormat::Binary);
+    }
+
+    #[test]
+    fn test_execute_commands() {
+        let mut session = Session::with_filter(BloomFilter::new(2, 100, 3).unwrap());
+        let mut run = |line: &str| execute(&mut session, line.parse().unwrap());
+        assert!(run("insert foo").unwrap().is_ok());
+        let query = run("query foo --levels 1").unwrap();
+        assert_eq!(render(&query, false), "Item may be present (matched level 1).");
+        assert_eq!(
+            render(&query, true),
+            r#"{"ok":true,"command":"query","item":"foo","present":true,"level":1,"label":null}"#
+        );
+        let error = run("query foo --levels 3").unwrap();
+        assert_eq!(
+            render(&error, true),
+            r#"{"ok":false,"error":"Number of levels to search must be between 1 and 2."}"#
+        );
+        assert!(run("label 3 nope").unwrap().is_err());
+
+        let items = std::env::temp_dir().join("test_repl_import.txt");
+        std::fs::write(&items, "a\nb\r\n\nfoo\n").unwrap();
+        let path = items.to_str().unwrap();
+        assert_eq!(render(&run(&format!("import {}", path)).unwrap(), false), "Imported 3 items (2 new).");
+        assert_eq!(render(&run(&format!("check {}", path)).unwrap(), false), "3 of 3 items may be present.");
+        std::fs::remove_file(&items).unwrap();
+        assert!(run("exit").is_none());
+    }
+
+    #[test]
+    fn test_session_commands() {
+        let mut session = Session::with_filter(BloomFilter::new(1, 100, 3).unwrap());
+        let mut run = |line: &str| execute(&mut session, line.parse().unwrap()).unwrap();
+        run("insert apple").unwrap();
+        run("copy default backup").unwrap();
+        run("insert banana").unwrap();
+        assert_eq!(render(&run("use backup"), false), "Now using filter 'backup'.");
+        assert_eq!(render(&run("query banana"), false), "Item is not present.");
+        run("rename backup old").unwrap();
+        assert_eq!(render(&run("filters"), false), "  default\n* old");
+        assert!(run("use missing").is_err());
+        assert!(run("copy old default").is_err());
+        assert_eq!(session.active(), "old");
+    }
+}
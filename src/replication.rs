@@ -0,0 +1,202 @@
+// src/replication.rs
+
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, Receiver, Sender};
+use tracing::{info, warn};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// A message from a primary to its replicas.
+///
+/// Events serialize with serde, so a server can forward them over any
+/// transport; in-process replicas receive them over a channel.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReplicationEvent {
+    /// The primary's full state in the binary format, sent first to bootstrap a new replica.
+    Snapshot { sequence: u64, filter: Vec<u8> },
+    /// A key inserted on the primary after the snapshot with the previous sequence number.
+    Insert { sequence: u64, item: Vec<u8> },
+}
+
+/// A filter that streams every insert to its subscribed replicas.
+///
+/// Each insert gets the next sequence number. A new subscriber first receives
+/// a snapshot tagged with the current sequence, then every later insert, so
+/// it never misses or double-applies one. Replicas whose receiver has been
+/// dropped are forgotten on the next insert.
+pub struct ReplicationPrimary {
+    filter: BloomFilter,
+    sequence: u64,
+    replicas: Vec<Sender<ReplicationEvent>>,
+}
+
+impl ReplicationPrimary {
+    /// Starts replicating `filter`.
+    ///
+    /// Snapshots use the binary format, which leaves out a keyed filter's
+    /// hash key, so keyed filters cannot bootstrap replicas.
+    pub fn new(filter: BloomFilter) -> Self {
+        ReplicationPrimary {
+            filter,
+            sequence: 0,
+            replicas: Vec::new(),
+        }
+    }
+
+    /// Registers a replica, returning the channel it receives events on, starting with a snapshot.
+    pub fn subscribe(&mut self) -> Receiver<ReplicationEvent> {
+        let (sender, receiver) = mpsc::channel();
+        info!("Bootstrapping replica {} at sequence {}", self.replicas.len(), self.sequence);
+        // The receiver is still alive, so this send cannot fail.
+        let _ = sender.send(ReplicationEvent::Snapshot {
+            sequence: self.sequence,
+            filter: self.filter.to_binary(),
+        });
+        self.replicas.push(sender);
+        receiver
+    }
+
+    /// Inserts an item and streams it to every replica.
+    pub fn insert(&mut self, item: &str) -> bool {
+        let item = self.filter.normalize(item);
+        self.insert_bytes(item.as_bytes())
+    }
+
+    /// Inserts a binary key and streams it to every replica.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        let newly_set = self.filter.insert_bytes(item);
+        self.sequence += 1;
+        let event = ReplicationEvent::Insert {
+            sequence: self.sequence,
+            item: item.to_vec(),
+        };
+        let before = self.replicas.len();
+        self.replicas.retain(|replica| replica.send(event.clone()).is_ok());
+        if self.replicas.len() < before {
+            warn!("Dropped {} disconnected replicas", before - self.replicas.len());
+        }
+        newly_set
+    }
+
+    /// Queries an item across the specified number of levels.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.filter.query(item, num_levels_to_search)
+    }
+
+    /// Returns the sequence number of the latest insert.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the number of connected replicas.
+    pub fn replicas(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Returns the underlying filter.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+}
+
+/// A read replica kept up to date by a primary's events.
+#[derive(Default)]
+pub struct Replica {
+    filter: Option<BloomFilter>,
+    sequence: u64,
+}
+
+impl Replica {
+    /// Creates a replica that answers no queries until it receives a snapshot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies one event from the primary.
+    ///
+    /// A snapshot replaces the replica's state. Inserts must arrive in
+    /// sequence; a gap means events were lost and the replica must be
+    /// bootstrapped again.
+    pub fn apply(&mut self, event: ReplicationEvent) -> Result<(), BloomFilterError> {
+        match event {
+            ReplicationEvent::Snapshot { sequence, filter } => {
+                self.filter = Some(BloomFilter::from_binary(&filter)?);
+                self.sequence = sequence;
+            }
+            ReplicationEvent::Insert { sequence, item } => {
+                let filter = self.filter.as_mut().ok_or_else(|| {
+                    BloomFilterError::DeltaMismatch("insert received before snapshot".to_string())
+                })?;
+                if sequence != self.sequence + 1 {
+                    return Err(BloomFilterError::DeltaMismatch(format!(
+                        "expected sequence {}, received {}",
+                        self.sequence + 1,
+                        sequence
+                    )));
+                }
+                filter.insert_bytes(&item);
+                self.sequence = sequence;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies every event waiting on `events` without blocking, returning how many were applied.
+    pub fn catch_up(&mut self, events: &Receiver<ReplicationEvent>) -> Result<usize, BloomFilterError> {
+        let mut applied = 0;
+        for event in events.try_iter() {
+            self.apply(event)?;
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Queries an item; a replica that has not been bootstrapped yet matches nothing.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.filter
+            .as_ref()
+            .is_some_and(|filter| filter.query(item, num_levels_to_search))
+    }
+
+    /// Returns the sequence number of the last applied event.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the replicated filter, once bootstrapped.
+    pub fn filter(&self) -> Option<&BloomFilter> {
+        self.filter.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replica_follows_primary() {
+        let mut primary = ReplicationPrimary::new(BloomFilter::new(2, 1000, 3).unwrap());
+        primary.insert("before");
+
+        let events = primary.subscribe();
+        let mut replica = Replica::new();
+        assert!(!replica.query("before", 2));
+        primary.insert("after");
+        primary.insert_bytes(b"raw");
+        assert_eq!(replica.catch_up(&events).unwrap(), 3);
+        assert!(replica.query("before", 2));
+        assert!(replica.query("after", 2));
+        assert_eq!(replica.sequence(), primary.sequence());
+        assert_eq!(replica.filter().unwrap().to_binary(), primary.filter().to_binary());
+
+        let gap = ReplicationEvent::Insert {
+            sequence: primary.sequence() + 2,
+            item: b"lost".to_vec(),
+        };
+        assert!(replica.apply(gap).is_err());
+
+        drop(events);
+        primary.insert("unheard");
+        assert_eq!(primary.replicas(), 0);
+    }
+}
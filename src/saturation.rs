@@ -0,0 +1,275 @@
+// src/saturation.rs
+
+use std::fmt;
+use tracing::warn;
+
+use crate::bloom_filter::{unix_now, BloomFilter, BloomFilterError, InsertMode};
+
+/// A saturation threshold a filter has crossed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SaturationAlert {
+    /// The fullest live level has `fill_ratio` of its bits set.
+    FillRatio { level: usize, fill_ratio: f64, threshold: f64 },
+    /// The filter's estimated false-positive rate, from its current fill.
+    FalsePositiveRate { estimated: f64, threshold: f64 },
+    /// The levels being inserted into hold `items` items, the most a `CapacityLimit` allows.
+    ItemCount { items: usize, max_items: usize },
+}
+
+impl fmt::Display for SaturationAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaturationAlert::FillRatio {
+                level,
+                fill_ratio,
+                threshold,
+            } => write!(f, "level {} is {:.1}% full (threshold {:.1}%)", level, fill_ratio * 100.0, threshold * 100.0),
+            SaturationAlert::FalsePositiveRate { estimated, threshold } => {
+                write!(f, "estimated false-positive rate {:.6} exceeds {:.6}", estimated, threshold)
+            }
+            SaturationAlert::ItemCount { items, max_items } => {
+                write!(f, "{} items reach the limit of {}", items, max_items)
+            }
+        }
+    }
+}
+
+/// Bounds past which `BloomFilter::try_insert` refuses items.
+///
+/// Set them with the builder's `max_items` and `max_false_positive_rate`, or
+/// `set_capacity_limit`. They live in memory only and are not saved with the
+/// filter. The default has no bounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CapacityLimit {
+    /// Most items the levels being inserted into may hold: every level, or
+    /// the active one in `InsertMode::ActiveLevel`.
+    pub max_items: Option<usize>,
+    /// Highest estimated false-positive rate inserts may push the filter past.
+    pub max_false_positive_rate: Option<f64>,
+}
+
+type Callback = Box<dyn FnMut(&SaturationAlert) + Send>;
+
+/// Watches a filter's fill ratio and estimated false-positive rate against thresholds.
+///
+/// Call `check` after inserts, every so often or on a timer. It returns the
+/// thresholds currently exceeded, as a watermark check, and runs the
+/// registered callbacks once when a threshold is first crossed, so a service
+/// can alert or start a rebuild before accuracy degrades. A threshold that
+/// drops back below its limit (after a rebuild, or as levels expire) fires
+/// again the next time it is crossed.
+#[derive(Default)]
+pub struct SaturationMonitor {
+    max_fill_ratio: Option<f64>,
+    max_false_positive_rate: Option<f64>,
+    callbacks: Vec<Callback>,
+    fill_ratio_exceeded: bool,
+    false_positive_rate_exceeded: bool,
+}
+
+impl SaturationMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Alerts when any live level has more than `threshold` of its bits set.
+    pub fn max_fill_ratio(mut self, threshold: f64) -> Self {
+        self.max_fill_ratio = Some(threshold);
+        self
+    }
+
+    /// Alerts when the estimated false-positive rate exceeds `threshold`.
+    pub fn max_false_positive_rate(mut self, threshold: f64) -> Self {
+        self.max_false_positive_rate = Some(threshold);
+        self
+    }
+
+    /// Registers a callback run once for each threshold crossing.
+    pub fn on_saturation<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&SaturationAlert) + Send + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Returns the thresholds the filter exceeds, running the callbacks for newly crossed ones.
+    pub fn check(&mut self, filter: &BloomFilter) -> Vec<SaturationAlert> {
+        let alerts = self.exceeded(filter);
+        let fill_ratio = alerts.iter().find(|alert| matches!(alert, SaturationAlert::FillRatio { .. }));
+        let false_positive_rate = alerts
+            .iter()
+            .find(|alert| matches!(alert, SaturationAlert::FalsePositiveRate { .. }));
+        for (alert, exceeded) in [
+            (fill_ratio, &mut self.fill_ratio_exceeded),
+            (false_positive_rate, &mut self.false_positive_rate_exceeded),
+        ] {
+            if let (Some(alert), false) = (alert, *exceeded) {
+                warn!("Filter saturation: {}", alert);
+                for callback in &mut self.callbacks {
+                    callback(alert);
+                }
+            }
+            *exceeded = alert.is_some();
+        }
+        alerts
+    }
+
+    /// Returns the thresholds the filter exceeds, without running callbacks or changing state.
+    pub fn exceeded(&self, filter: &BloomFilter) -> Vec<SaturationAlert> {
+        let mut alerts = Vec::new();
+        if let Some(threshold) = self.max_fill_ratio {
+            let now = unix_now();
+            let fullest = filter
+                .levels()
+                .iter()
+                .enumerate()
+                .filter(|(_, level)| !level.is_expired_at(now))
+                .map(|(index, level)| (index, level.fill_ratio()))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((level, fill_ratio)) = fullest.filter(|&(_, fill_ratio)| fill_ratio > threshold) {
+                alerts.push(SaturationAlert::FillRatio {
+                    level,
+                    fill_ratio,
+                    threshold,
+                });
+            }
+        }
+        if let Some(threshold) = self.max_false_positive_rate {
+            let estimated = filter.estimated_false_positive_rate();
+            if estimated > threshold {
+                alerts.push(SaturationAlert::FalsePositiveRate { estimated, threshold });
+            }
+        }
+        alerts
+    }
+}
+
+impl BloomFilter {
+    /// Sets the bounds `try_insert` enforces; `CapacityLimit::default()` removes them.
+    pub fn set_capacity_limit(&mut self, limit: CapacityLimit) {
+        self.capacity = limit;
+    }
+
+    /// Returns the bounds `try_insert` enforces.
+    pub fn capacity_limit(&self) -> CapacityLimit {
+        self.capacity
+    }
+
+    /// Returns the capacity bound the filter has reached, if any.
+    ///
+    /// The item bound is reached once the levels being inserted into hold
+    /// `max_items` items; the rate bound once the estimated false-positive
+    /// rate exceeds `max_false_positive_rate`.
+    pub fn saturation(&self) -> Option<SaturationAlert> {
+        if let Some(max_items) = self.capacity.max_items {
+            let items = match self.insert_mode {
+                InsertMode::AllLevels => self.levels.iter().map(|level| level.item_count).max().unwrap_or(0),
+                InsertMode::ActiveLevel { .. } => {
+                    self.levels.get(self.active_level).map_or(0, |level| level.item_count)
+                }
+            };
+            if items >= max_items {
+                return Some(SaturationAlert::ItemCount { items, max_items });
+            }
+        }
+        let threshold = self.capacity.max_false_positive_rate?;
+        let estimated = self.estimated_false_positive_rate();
+        (estimated > threshold).then_some(SaturationAlert::FalsePositiveRate { estimated, threshold })
+    }
+
+    /// Inserts an item unless the filter has reached its capacity limit.
+    ///
+    /// Fails with `Saturated` instead of inserting, so a service can reject
+    /// the item, rotate to a new filter or rebuild a larger one rather than
+    /// let accuracy degrade. Plain `insert` ignores the limit but logs a
+    /// warning when an insert reaches it.
+    pub fn try_insert(&mut self, item: &str) -> Result<bool, BloomFilterError> {
+        self.check_capacity()?;
+        Ok(self.insert(item))
+    }
+
+    /// Like `try_insert`, for a binary key.
+    pub fn try_insert_bytes(&mut self, item: &[u8]) -> Result<bool, BloomFilterError> {
+        self.check_capacity()?;
+        Ok(self.insert_bytes(item))
+    }
+
+    /// Like `try_insert`, for an integer key.
+    pub fn try_insert_u64(&mut self, item: u64) -> Result<bool, BloomFilterError> {
+        self.check_capacity()?;
+        Ok(self.insert_u64(item))
+    }
+
+    fn check_capacity(&self) -> Result<(), BloomFilterError> {
+        match self.saturation() {
+            Some(alert) => Err(BloomFilterError::Saturated(alert)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_callbacks_fire_once_per_crossing() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&fired);
+        let mut monitor = SaturationMonitor::new()
+            .max_fill_ratio(0.5)
+            .max_false_positive_rate(0.01)
+            .on_saturation(move |alert| log.lock().unwrap().push(*alert));
+
+        let mut filter = BloomFilter::new(1, 1000, 3).unwrap();
+        assert!(monitor.check(&filter).is_empty());
+        let mut i = 0;
+        while monitor.check(&filter).len() < 2 {
+            filter.insert(&format!("item{}", i));
+            i += 1;
+        }
+        // The false-positive rate passes 1% well before half the bits are set.
+        let alerts = fired.lock().unwrap().clone();
+        assert_eq!(alerts.len(), 2);
+        assert!(matches!(alerts[0], SaturationAlert::FalsePositiveRate { threshold, .. } if threshold == 0.01));
+        assert!(matches!(alerts[1], SaturationAlert::FillRatio { level: 0, fill_ratio, .. } if fill_ratio > 0.5));
+
+        filter.insert("more");
+        assert_eq!(monitor.check(&filter).len(), 2);
+        assert_eq!(fired.lock().unwrap().len(), 2);
+
+        filter.levels[0].clear();
+        assert!(monitor.check(&filter).is_empty());
+        (0..i).for_each(|i| {
+            filter.insert(&format!("item{}", i));
+        });
+        assert_eq!(monitor.check(&filter).len(), 2);
+        assert_eq!(fired.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_try_insert_stops_at_capacity() {
+        let mut filter = BloomFilter::builder().array_size(1000).max_items(3).build().unwrap();
+        for item in ["a", "b", "c"] {
+            assert!(filter.try_insert(item).unwrap());
+        }
+        let err = filter.try_insert("d").unwrap_err();
+        assert!(matches!(err, BloomFilterError::Saturated(SaturationAlert::ItemCount { items: 3, max_items: 3 })));
+        assert!(!filter.query("d", 1));
+        // Plain inserts still go through.
+        filter.insert("d");
+        assert!(filter.query("d", 1));
+
+        let mut filter = BloomFilter::builder().array_size(1000).max_false_positive_rate(0.01).build().unwrap();
+        let mut i = 0;
+        while filter.try_insert_u64(i).is_ok() {
+            i += 1;
+        }
+        assert!(filter.estimated_false_positive_rate() > 0.01 && i > 10);
+        assert!(matches!(filter.saturation(), Some(SaturationAlert::FalsePositiveRate { .. })));
+        filter.set_capacity_limit(CapacityLimit::default());
+        assert!(filter.try_insert_bytes(b"more").is_ok());
+    }
+}
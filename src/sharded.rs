@@ -0,0 +1,142 @@
+// src/sharded.rs
+
+use siphasher::sip::SipHasher13;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::builder::BloomFilterBuilder;
+
+/// Key for the hash that picks a shard, chosen so it is independent of the bit positions.
+const SHARD_KEYS: (u64, u64) = (0x5348_4152_4431_3233, 0x9e37_79b9_7f4a_7c15);
+
+/// A Bloom filter split into independently locked shards for concurrent use.
+///
+/// Each key is routed to one shard by a hash of its bytes, and only that
+/// shard is locked, so threads touching different shards never contend.
+/// Shards share one configuration, which lets `merge_shards` flatten them
+/// into a single ordinary filter.
+pub struct ShardedBloomFilter {
+    shards: Vec<RwLock<BloomFilter>>,
+    /// Configuration shared by every shard, kept to build the merged filter.
+    builder: BloomFilterBuilder,
+}
+
+impl ShardedBloomFilter {
+    /// Creates `num_shards` shards, each with the given levels, array size and hash functions.
+    pub fn new(
+        num_shards: usize,
+        num_levels: usize,
+        array_size: usize,
+        num_hash_functions: usize,
+    ) -> Result<Self, BloomFilterError> {
+        Self::with_builder(
+            num_shards,
+            &BloomFilter::builder()
+                .levels(num_levels)
+                .array_size(array_size)
+                .hash_functions(num_hash_functions),
+        )
+    }
+
+    /// Creates `num_shards` shards, each built from `builder`.
+    pub fn with_builder(num_shards: usize, builder: &BloomFilterBuilder) -> Result<Self, BloomFilterError> {
+        if num_shards == 0 {
+            return Err(BloomFilterError::ZeroShards);
+        }
+        info!("Creating ShardedBloomFilter: shards={}", num_shards);
+        let shards = (0..num_shards)
+            .map(|_| builder.clone().build().map(RwLock::new))
+            .collect::<Result<_, _>>()?;
+        Ok(ShardedBloomFilter {
+            shards,
+            builder: builder.clone(),
+        })
+    }
+
+    /// Returns the number of shards.
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Inserts an item into its shard.
+    pub fn insert(&self, item: &str) -> bool {
+        // Normalize before routing, so every spelling of a key lands on the same shard.
+        self.insert_bytes(self.builder.normalization.apply(item).as_bytes())
+    }
+
+    /// Inserts a binary key into its shard.
+    pub fn insert_bytes(&self, item: &[u8]) -> bool {
+        self.write(item).insert_bytes(item)
+    }
+
+    /// Queries an item across the specified number of levels of its shard.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.query_bytes(self.builder.normalization.apply(item).as_bytes(), num_levels_to_search)
+    }
+
+    /// Queries a binary key across the specified number of levels of its shard.
+    pub fn query_bytes(&self, item: &[u8], num_levels_to_search: usize) -> bool {
+        self.read(item).query_bytes(item, num_levels_to_search)
+    }
+
+    /// Flattens the shards into one filter holding every inserted item.
+    ///
+    /// The result has a single shard's array size but all shards' items, so
+    /// its false-positive rate is higher than any shard's; size shards for the
+    /// total item count if the merged filter must meet a target rate.
+    pub fn merge_shards(&self) -> Result<BloomFilter, BloomFilterError> {
+        info!("Merging {} shards", self.shards.len());
+        let mut merged = self.builder.clone().build()?;
+        for shard in &self.shards {
+            merged.union_with(&lock_read(shard))?;
+        }
+        Ok(merged)
+    }
+
+    fn shard_index(&self, item: &[u8]) -> usize {
+        let hash = SipHasher13::new_with_keys(SHARD_KEYS.0, SHARD_KEYS.1).hash(item);
+        (hash % self.shards.len() as u64) as usize
+    }
+
+    fn read(&self, item: &[u8]) -> RwLockReadGuard<'_, BloomFilter> {
+        lock_read(&self.shards[self.shard_index(item)])
+    }
+
+    fn write(&self, item: &[u8]) -> RwLockWriteGuard<'_, BloomFilter> {
+        self.shards[self.shard_index(item)]
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// A panic mid-insert can at worst leave some of an item's bits set, which
+// never causes a false negative, so poisoned shards stay usable.
+fn lock_read(shard: &RwLock<BloomFilter>) -> RwLockReadGuard<'_, BloomFilter> {
+    shard.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_inserts_and_merge() {
+        let sharded = ShardedBloomFilter::new(4, 2, 2000, 3).unwrap();
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let sharded = &sharded;
+                scope.spawn(move || {
+                    for i in 0..100 {
+                        sharded.insert(&format!("item-{}-{}", thread, i));
+                    }
+                });
+            }
+        });
+        assert!((0..4).all(|thread| (0..100).all(|i| sharded.query(&format!("item-{}-{}", thread, i), 2))));
+
+        let merged = sharded.merge_shards().unwrap();
+        assert!((0..4).all(|thread| (0..100).all(|i| merged.query(&format!("item-{}-{}", thread, i), 2))));
+        assert!(matches!(ShardedBloomFilter::new(0, 1, 100, 3), Err(BloomFilterError::ZeroShards)));
+    }
+}
@@ -0,0 +1,139 @@
+// src/signing.rs
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey, SIGNATURE_LENGTH};
+use std::fs::{self, File};
+use std::io::Write;
+use tracing::{error, info};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// Magic bytes opening a signed filter file.
+const SIGNED_MAGIC: &[u8; 4] = b"BLMS";
+/// Current version of the signed format.
+const SIGNED_VERSION: u8 = 1;
+
+/// Derives the ed25519 public key that verifies files signed with `secret_key`.
+pub fn public_key(secret_key: &[u8; 32]) -> [u8; 32] {
+    SigningKey::from_bytes(secret_key).verifying_key().to_bytes()
+}
+
+impl BloomFilter {
+    /// Encodes the filter in the binary format and signs it with an ed25519 secret key.
+    ///
+    /// Layout: magic `BLMS`, version, the 64-byte signature, then the binary
+    /// encoding. The signature covers the header and the encoding, so a
+    /// publishing pipeline can hand out only the public key and consumers can
+    /// refuse any file it did not produce.
+    pub fn to_signed(&self, secret_key: &[u8; 32]) -> Vec<u8> {
+        let binary = self.to_binary();
+        let signature = SigningKey::from_bytes(secret_key).sign(&signed_message(&binary));
+        let mut out = Vec::with_capacity(SIGNED_MAGIC.len() + 1 + SIGNATURE_LENGTH + binary.len());
+        out.extend_from_slice(SIGNED_MAGIC);
+        out.push(SIGNED_VERSION);
+        out.extend_from_slice(&signature.to_bytes());
+        out.extend_from_slice(&binary);
+        out
+    }
+
+    /// Verifies the signature with `public_key`, then decodes and validates the filter.
+    pub fn from_signed(bytes: &[u8], public_key: &[u8; 32]) -> Result<Self, BloomFilterError> {
+        let bloom_filter = Self::from_binary_unvalidated(verify(bytes, public_key)?)?;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Like `from_signed`, for a keyed-hashing filter whose key is never part of the file.
+    pub fn from_signed_keyed(bytes: &[u8], public_key: &[u8; 32], hash_key: [u8; 16]) -> Result<Self, BloomFilterError> {
+        let mut bloom_filter = Self::from_binary_unvalidated(verify(bytes, public_key)?)?;
+        bloom_filter.restore_hash_key(hash_key)?;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Signs the filter (see `to_signed`) and writes it to a file.
+    pub fn save_signed(&self, filepath: &str, secret_key: &[u8; 32]) -> Result<(), BloomFilterError> {
+        info!("Saving signed BloomFilter to file: {}", filepath);
+        let mut file = File::create(filepath)?;
+        file.write_all(&self.to_signed(secret_key))?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Loads a filter saved with `save_signed`, rejecting it unless `public_key` verifies the signature.
+    pub fn load_signed(filepath: &str, public_key: &[u8; 32]) -> Result<Self, BloomFilterError> {
+        info!("Loading signed BloomFilter from file: {}", filepath);
+        Self::from_signed(&fs::read(filepath)?, public_key)
+    }
+}
+
+/// The bytes covered by the signature: the header followed by the binary encoding.
+fn signed_message(binary: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(SIGNED_MAGIC.len() + 1 + binary.len());
+    message.extend_from_slice(SIGNED_MAGIC);
+    message.push(SIGNED_VERSION);
+    message.extend_from_slice(binary);
+    message
+}
+
+/// Checks the signature of a signed file, returning the binary encoding it covers.
+fn verify<'a>(bytes: &'a [u8], public_key: &[u8; 32]) -> Result<&'a [u8], BloomFilterError> {
+    let header_len = SIGNED_MAGIC.len() + 1;
+    if bytes.len() < header_len + SIGNATURE_LENGTH || &bytes[..SIGNED_MAGIC.len()] != SIGNED_MAGIC {
+        return Err(signed_error("not a signed filter"));
+    }
+    let version = bytes[SIGNED_MAGIC.len()];
+    if version != SIGNED_VERSION {
+        return Err(signed_error(format!("unsupported version {}", version)));
+    }
+    let (signature, binary) = bytes[header_len..].split_at(SIGNATURE_LENGTH);
+    let verifying_key = VerifyingKey::from_bytes(public_key).map_err(|e| signed_error(e.to_string()))?;
+    let signature = Signature::from_slice(signature).map_err(|e| signed_error(e.to_string()))?;
+    verifying_key
+        .verify_strict(&signed_message(binary), &signature)
+        .map_err(|_| {
+            error!("Filter signature does not verify with the trusted public key");
+            BloomFilterError::InvalidSignature
+        })?;
+    Ok(binary)
+}
+
+fn signed_error(message: impl Into<String>) -> BloomFilterError {
+    BloomFilterError::Encoding {
+        format: "signed",
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_round_trip() {
+        let secret_key = [5u8; 32];
+        let trusted = public_key(&secret_key);
+        let mut bf = BloomFilter::new(2, 200, 3).unwrap();
+        bf.insert("blocked.example");
+
+        let path = std::env::temp_dir().join("test_bloom_signed.bin");
+        let path = path.to_str().unwrap();
+        bf.save_signed(path, &secret_key).unwrap();
+        let loaded = BloomFilter::load_signed(path, &trusted).unwrap();
+        assert!(loaded.query("blocked.example", 2));
+        std::fs::remove_file(path).unwrap();
+
+        let signed = bf.to_signed(&secret_key);
+        let untrusted = public_key(&[6u8; 32]);
+        assert!(matches!(
+            BloomFilter::from_signed(&signed, &untrusted),
+            Err(BloomFilterError::InvalidSignature)
+        ));
+        let mut tampered = signed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            BloomFilter::from_signed(&tampered, &trusted),
+            Err(BloomFilterError::InvalidSignature)
+        ));
+        assert!(BloomFilter::from_signed(&bf.to_binary(), &trusted).is_err());
+    }
+}
@@ -0,0 +1,387 @@
+// src/sled_backend.rs
+
+use serde::{Deserialize, Serialize};
+use sled::transaction::{ConflictableTransactionResult, TransactionError};
+use sled::Transactional;
+use std::collections::BTreeSet;
+use std::io;
+use tracing::{error, info};
+
+use crate::bits::BitStorage;
+use crate::bloom_filter::{BloomFilter, BloomFilterError, BloomLevel};
+use crate::format::FileFormat;
+
+/// Bytes of packed bits per stored page.
+const PAGE_BYTES: usize = 512;
+/// Key of the compressed filter the pages are applied to.
+const BASE_KEY: &[u8] = b"base";
+/// Key of the level bookkeeping that changes with inserts.
+const STATE_KEY: &[u8] = b"state";
+const PAGES_TREE: &[u8] = b"pages";
+
+/// The per-level fields an insert can change besides the bits.
+#[derive(Serialize, Deserialize)]
+struct State {
+    active_level: usize,
+    levels: Vec<LevelState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LevelState {
+    item_count: usize,
+    created_at: Option<u64>,
+}
+
+/// A Bloom filter whose bits live in a sled database, updated page by page.
+///
+/// The database holds the filter as it was first opened, compressed, plus
+/// a 512-byte page of bits for every page an insert has touched since.
+/// Each insert writes only its touched pages, in one atomic batch, and
+/// flushes before returning, so it is durable without rewriting a
+/// snapshot. A level cleared by its TTL, or by the active level wrapping
+/// round onto it, is rewritten in full. Opening the
+/// database again applies the pages to the base filter. Queries read an
+/// in-memory copy. Keyed filters cannot be stored, since their key would
+/// have to be written to the database.
+pub struct SledBloomFilter {
+    db: sled::Db,
+    pages: sled::Tree,
+    filter: BloomFilter,
+}
+
+impl SledBloomFilter {
+    /// Opens (or initializes) a filter stored in the sled database at `path`.
+    ///
+    /// If the database already holds a filter it is loaded and `filter` is
+    /// ignored; otherwise `filter`, with its current bits, is stored.
+    pub fn open(path: &str, filter: BloomFilter) -> Result<Self, BloomFilterError> {
+        info!("Opening sled-backed BloomFilter at {}", path);
+        Self::with_db(sled::open(path).map_err(storage_error)?, filter)
+    }
+
+    /// Like `open`, for a database that is already open; the filter uses its default tree and a `pages` tree.
+    pub fn with_db(db: sled::Db, filter: BloomFilter) -> Result<Self, BloomFilterError> {
+        let pages = db.open_tree(PAGES_TREE).map_err(storage_error)?;
+        let filter = match db.get(BASE_KEY).map_err(storage_error)? {
+            Some(base) => load(&base, &pages, db.get(STATE_KEY).map_err(storage_error)?.as_deref())?,
+            None => {
+                if filter.hash_key().is_some() {
+                    error!("Cannot store a keyed filter in a sled database");
+                    return Err(BloomFilterError::KeyedFilterNotStorable("sled database".to_string()));
+                }
+                let mut base = Vec::new();
+                filter.save_to_writer_as(&mut base, FileFormat::Compressed)?;
+                db.insert(BASE_KEY, base).map_err(storage_error)?;
+                db.flush().map_err(storage_error)?;
+                filter
+            }
+        };
+        Ok(SledBloomFilter { db, pages, filter })
+    }
+
+    /// Inserts an item and makes it durable, returning whether it was definitely new.
+    pub fn insert(&mut self, item: &str) -> Result<bool, BloomFilterError> {
+        let item = self.filter.normalize(item).into_owned();
+        self.insert_bytes(item.as_bytes())
+    }
+
+    /// Inserts a binary key and makes it durable.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> Result<bool, BloomFilterError> {
+        let before = self.state();
+        let newly_set = self.filter.insert_bytes(item);
+        let mut touched = BTreeSet::new();
+        self.touch(&self.filter.positions(item), &before, &mut touched);
+        self.persist(&touched)?;
+        Ok(newly_set)
+    }
+
+    /// Inserts a batch of items with one durable write, returning how many were new.
+    pub fn insert_batch<I, S>(&mut self, items: I) -> Result<usize, BloomFilterError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut touched = BTreeSet::new();
+        let mut new = 0;
+        for item in items {
+            let item = self.filter.normalize(item.as_ref()).into_owned();
+            let before = self.state();
+            new += usize::from(self.filter.insert_bytes(item.as_bytes()));
+            self.touch(&self.filter.positions(item.as_bytes()), &before, &mut touched);
+        }
+        self.persist(&touched)?;
+        Ok(new)
+    }
+
+    /// Queries an item across the specified number of levels.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.filter.query(item, num_levels_to_search)
+    }
+
+    /// Queries a binary key across the specified number of levels.
+    pub fn query_bytes(&self, item: &[u8], num_levels_to_search: usize) -> bool {
+        self.filter.query_bytes(item, num_levels_to_search)
+    }
+
+    /// Returns the in-memory copy of the filter.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+
+    /// Returns the number of pages written since the filter was first stored.
+    pub fn stored_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn state(&self) -> State {
+        State {
+            active_level: self.filter.active_level(),
+            levels: self
+                .filter
+                .levels()
+                .iter()
+                .map(|level| LevelState {
+                    item_count: level.item_count,
+                    created_at: level.created_at,
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds the pages holding `positions` in every level, and every page of levels cleared since `before`.
+    ///
+    /// A level was cleared if its TTL restarted, its item count went down, or
+    /// the active level moved onto it.
+    fn touch(&self, positions: &[usize], before: &State, touched: &mut BTreeSet<(usize, usize)>) {
+        let num_pages = self.filter.array_size().div_ceil(PAGE_BYTES * 8);
+        let active = self.filter.active_level();
+        for (index, level) in self.filter.levels().iter().enumerate() {
+            let previous = &before.levels[index];
+            let cleared = level.created_at != previous.created_at
+                || level.item_count < previous.item_count
+                || (active != before.active_level && index == active);
+            if cleared {
+                touched.extend((0..num_pages).map(|page| (index, page)));
+            } else {
+                touched.extend(positions.iter().map(|position| (index, position / (PAGE_BYTES * 8))));
+            }
+        }
+    }
+
+    fn persist(&self, touched: &BTreeSet<(usize, usize)>) -> Result<(), BloomFilterError> {
+        let mut batch = sled::Batch::default();
+        for &(level, page) in touched {
+            batch.insert(&page_key(level, page)[..], page_bytes(&self.filter.levels()[level], page));
+        }
+        let state = self.state();
+        // The pages tree and the default tree are updated in one transaction,
+        // so the state never describes bits that were not written.
+        (&*self.db, &self.pages)
+            .transaction(|(db, pages)| -> ConflictableTransactionResult<(), sled::Error> {
+                pages.apply_batch(&batch)?;
+                db.insert(STATE_KEY, serde_json::to_vec(&state).expect("state serializes"))?;
+                Ok(())
+            })
+            .map_err(|e: TransactionError| storage_error(e))?;
+        self.db.flush().map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+/// One level's bits in a sled tree, for use as a `BloomLevel`'s storage.
+///
+/// The tree holds a page of packed bits per key. Every page is read on
+/// open; `set` only marks its page dirty, and `flush` writes the dirty pages
+/// in one batch and waits for them to reach disk.
+pub struct SledBits {
+    tree: sled::Tree,
+    len: usize,
+    bytes: Vec<u8>,
+    dirty: BTreeSet<usize>,
+}
+
+impl SledBits {
+    /// Opens `len` bits stored in `tree`, which starts out empty for a new level.
+    pub fn open(tree: sled::Tree, len: usize) -> Result<Self, BloomFilterError> {
+        let mut bytes = vec![0u8; len.div_ceil(8)];
+        for entry in tree.iter() {
+            let (key, page) = entry.map_err(storage_error)?;
+            let start = key
+                .as_ref()
+                .try_into()
+                .map(|key| u32::from_be_bytes(key) as usize * PAGE_BYTES)
+                .ok()
+                .filter(|&start| start + page.len() <= bytes.len())
+                .ok_or_else(|| storage_error(format!("page {:?} does not fit {} bits", key, len)))?;
+            bytes[start..start + page.len()].copy_from_slice(&page);
+        }
+        Ok(SledBits {
+            tree,
+            len,
+            bytes,
+            dirty: BTreeSet::new(),
+        })
+    }
+}
+
+impl BitStorage for SledBits {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> bool {
+        index < self.len && self.bytes[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    fn set(&mut self, index: usize) -> bool {
+        if index >= self.len || self.get(index) {
+            return false;
+        }
+        self.bytes[index / 8] |= 1 << (index % 8);
+        self.dirty.insert(index / (PAGE_BYTES * 8));
+        true
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut batch = sled::Batch::default();
+        for &page in &self.dirty {
+            let start = page * PAGE_BYTES;
+            let end = self.bytes.len().min(start + PAGE_BYTES);
+            batch.insert(&(page as u32).to_be_bytes()[..], &self.bytes[start..end]);
+        }
+        self.tree.apply_batch(batch)?;
+        self.tree.flush()?;
+        self.dirty.clear();
+        Ok(())
+    }
+
+    fn count_ones(&self) -> usize {
+        self.bytes.iter().map(|byte| byte.count_ones() as usize).sum()
+    }
+}
+
+/// Rebuilds the filter from its base, its pages and its state.
+fn load(base: &[u8], pages: &sled::Tree, state: Option<&[u8]>) -> Result<BloomFilter, BloomFilterError> {
+    let (mut filter, _) = BloomFilter::load_auto_from_reader(base)?;
+    for entry in pages.iter() {
+        let (key, bits) = entry.map_err(storage_error)?;
+        let (level, page) = parse_page_key(&key)
+            .filter(|&(level, _)| level < filter.levels().len())
+            .ok_or_else(|| storage_error(format!("invalid page key {:?}", key)))?;
+        filter.levels[level].overwrite_bytes(page * PAGE_BYTES, &bits);
+    }
+    if let Some(state) = state {
+        let state: State = serde_json::from_slice(state)?;
+        if state.levels.len() != filter.levels().len() {
+            return Err(storage_error(format!(
+                "stored state has {} levels, the filter {}",
+                state.levels.len(),
+                filter.levels().len()
+            )));
+        }
+        filter.active_level = state.active_level;
+        for (level, saved) in filter.levels.iter_mut().zip(state.levels) {
+            level.item_count = saved.item_count;
+            level.created_at = saved.created_at;
+        }
+    }
+    filter.validate()?;
+    info!("Loaded sled-backed BloomFilter with {} pages", pages.len());
+    Ok(filter)
+}
+
+/// Big-endian, so pages sort by level and then position.
+fn page_key(level: usize, page: usize) -> [u8; 8] {
+    let mut key = [0u8; 8];
+    key[..4].copy_from_slice(&(level as u32).to_be_bytes());
+    key[4..].copy_from_slice(&(page as u32).to_be_bytes());
+    key
+}
+
+fn parse_page_key(key: &[u8]) -> Option<(usize, usize)> {
+    let level = u32::from_be_bytes(key.get(..4)?.try_into().ok()?);
+    let page = u32::from_be_bytes(key.get(4..8)?.try_into().ok()?);
+    Some((level as usize, page as usize))
+}
+
+/// Packs one page of a level's bits, LSB-first as in `BloomLevel::to_bytes`.
+fn page_bytes(level: &BloomLevel, page: usize) -> Vec<u8> {
+    let start = page * PAGE_BYTES * 8;
+    let end = level.len().min(start + PAGE_BYTES * 8);
+    let mut bytes = vec![0u8; (end - start).div_ceil(8)];
+    for position in (start..end).filter(|&position| level.bit_array.get(position)) {
+        bytes[(position - start) / 8] |= 1 << ((position - start) % 8);
+    }
+    bytes
+}
+
+fn storage_error(error: impl ToString) -> BloomFilterError {
+    BloomFilterError::Storage(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bloom_filter::InsertMode;
+
+    #[test]
+    fn test_inserts_survive_reopening_page_by_page() {
+        let dir = std::env::temp_dir().join("test_bloom_sled");
+        let _ = std::fs::remove_dir_all(&dir);
+        let db = sled::open(&dir).unwrap();
+
+        let mut initial = BloomFilter::new(2, 20_000, 3).unwrap();
+        initial.insert("before");
+        {
+            let mut filter = SledBloomFilter::with_db(db.clone(), initial).unwrap();
+            assert!(filter.insert("alpha").unwrap());
+            assert_eq!(filter.insert_batch(["beta", "gamma", "alpha"]).unwrap(), 2);
+            // Three keys touch at most 3 pages each in each of 2 levels, out of 5 pages per level.
+            assert!(filter.stored_pages() <= 9);
+        }
+
+        let filter = SledBloomFilter::with_db(db.clone(), BloomFilter::new(1, 10, 1).unwrap()).unwrap();
+        assert_eq!(filter.filter().levels().len(), 2);
+        assert!(["before", "alpha", "beta", "gamma"].iter().all(|item| filter.query(item, 2)));
+        assert!(!filter.query("delta", 2));
+        assert_eq!(filter.filter().levels()[0].item_count(), 4);
+
+        // A level over sled storage keeps only the pages its inserts touched.
+        let hashes = BloomFilter::new(1, 100_000, 3).unwrap();
+        let tree = || db.open_tree("level").unwrap();
+        let mut level = BloomLevel::with_storage(SledBits::open(tree(), 100_000).unwrap());
+        assert!(level.insert("alpha", hashes.hash_functions(), 100_000));
+        level.flush().unwrap();
+        let level = BloomLevel::with_storage(SledBits::open(tree(), 100_000).unwrap());
+        assert!(level.query("alpha", hashes.hash_functions(), 100_000));
+        assert!(!level.query("beta", hashes.hash_functions(), 100_000));
+        assert_eq!(level.count_ones(), 3);
+        assert!(tree().len() <= 3);
+
+        // Wrapping round clears the oldest level; its old items must stay gone after reopening.
+        let mode = InsertMode::ActiveLevel {
+            max_items: Some(2),
+            max_fill_ratio: None,
+        };
+        let rotating = BloomFilter::builder().levels(2).array_size(200_000).insert_mode(mode).build().unwrap();
+        let rotating_db = || sled::Config::new().path(dir.join("rotating")).open().unwrap();
+        {
+            let mut filter = SledBloomFilter::with_db(rotating_db(), rotating).unwrap();
+            filter.insert_batch(["old-1", "old-2"]).unwrap();
+            filter.insert("mid-1").unwrap();
+            // Fills level 2, so level 1 and its old items are cleared.
+            filter.insert("mid-2").unwrap();
+            assert_eq!(filter.filter().active_level(), 0);
+            filter.insert("new-1").unwrap();
+        }
+        let reopened = SledBloomFilter::with_db(rotating_db(), BloomFilter::new(1, 10, 1).unwrap()).unwrap();
+        assert!(!reopened.query("old-1", 2) && !reopened.query("old-2", 2));
+        assert!(["mid-1", "mid-2", "new-1"].iter().all(|item| reopened.query(item, 2)));
+        drop(reopened);
+
+        let keyed = BloomFilter::builder().array_size(100).hash_key([7; 16]).build().unwrap();
+        assert!(SledBloomFilter::with_db(sled::Config::new().temporary(true).open().unwrap(), keyed).is_err());
+        drop((filter, level, db));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
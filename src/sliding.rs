@@ -0,0 +1,207 @@
+// src/sliding.rs
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError, InsertMode};
+
+/// A Bloom filter answering "was this item seen within the last `window`?".
+///
+/// The window is split into buckets, one level each. Inserts go to the active
+/// bucket, and every `window / num_buckets` the oldest bucket is cleared and
+/// becomes the active one. Rotation happens lazily on insert (or when `rotate`
+/// is called from a timer); queries ignore buckets that are due for rotation,
+/// so answers stay correct between rotations. An item is remembered for at
+/// least `window - window / num_buckets` and at most `window`.
+#[derive(Serialize, Deserialize)]
+pub struct SlidingBloomFilter {
+    filter: BloomFilter,
+    bucket_duration_ms: u64,
+    last_rotation_ms: u64,
+}
+
+impl SlidingBloomFilter {
+    /// Creates a sliding filter covering `window`, split into `num_buckets` buckets.
+    pub fn new(
+        window: Duration,
+        num_buckets: usize,
+        array_size: usize,
+        num_hash_functions: usize,
+    ) -> Result<Self, BloomFilterError> {
+        Self::new_at(window, num_buckets, array_size, num_hash_functions, SystemTime::now())
+    }
+
+    /// Like `new`, but starts the first bucket at `now`.
+    pub fn new_at(
+        window: Duration,
+        num_buckets: usize,
+        array_size: usize,
+        num_hash_functions: usize,
+        now: SystemTime,
+    ) -> Result<Self, BloomFilterError> {
+        info!("Creating SlidingBloomFilter: window={:?}, buckets={}", window, num_buckets);
+        let mut filter = BloomFilter::new(num_buckets, array_size, num_hash_functions)?;
+        filter.set_insert_mode(InsertMode::ActiveLevel {
+            max_items: None,
+            max_fill_ratio: None,
+        });
+        let bucket_duration_ms = (window.as_millis() / num_buckets as u128).max(1) as u64;
+        Ok(SlidingBloomFilter {
+            filter,
+            bucket_duration_ms,
+            last_rotation_ms: unix_millis(now),
+        })
+    }
+
+    /// Inserts an item into the current bucket, rotating first if needed.
+    pub fn insert(&mut self, item: &str) -> bool {
+        self.insert_at(item, SystemTime::now())
+    }
+
+    /// Like `insert`, evaluated at time `now`.
+    pub fn insert_at(&mut self, item: &str, now: SystemTime) -> bool {
+        self.rotate_at(now);
+        self.filter.insert(item)
+    }
+
+    /// Inserts a binary key into the current bucket, rotating first if needed.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        self.rotate_at(SystemTime::now());
+        self.filter.insert_bytes(item)
+    }
+
+    /// Checks whether the item was (probably) inserted within the window.
+    pub fn query(&self, item: &str) -> bool {
+        self.query_at(item, SystemTime::now())
+    }
+
+    /// Like `query`, evaluated at time `now`.
+    pub fn query_at(&self, item: &str, now: SystemTime) -> bool {
+        self.query_bytes_at(self.filter.normalize(item).as_bytes(), now)
+    }
+
+    /// Checks whether the binary key was (probably) inserted within the window.
+    pub fn query_bytes(&self, item: &[u8]) -> bool {
+        self.query_bytes_at(item, SystemTime::now())
+    }
+
+    fn query_bytes_at(&self, item: &[u8], now: SystemTime) -> bool {
+        let num_buckets = self.filter.levels().len();
+        let stale = self.pending_rotations(now).min(num_buckets);
+        let active = self.filter.active_level();
+        // Buckets active+1 ..= active+stale (wrapping) are the oldest ones and
+        // would already have been cleared by a rotation at `now`.
+        (0..num_buckets - stale)
+            .map(|age| (active + num_buckets - age) % num_buckets)
+            .any(|bucket| self.filter.query_bytes_range(item, bucket..bucket + 1))
+    }
+
+    /// Rotates out every bucket whose time has passed. Returns the number of rotations.
+    pub fn rotate(&mut self) -> usize {
+        self.rotate_at(SystemTime::now())
+    }
+
+    /// Like `rotate`, evaluated at time `now`.
+    pub fn rotate_at(&mut self, now: SystemTime) -> usize {
+        let pending = self.pending_rotations(now);
+        let num_buckets = self.filter.levels().len();
+        for _ in 0..pending.min(num_buckets) {
+            self.filter.advance_level();
+        }
+        self.last_rotation_ms += pending as u64 * self.bucket_duration_ms;
+        pending
+    }
+
+    /// Returns the duration covered by each bucket.
+    pub fn bucket_duration(&self) -> Duration {
+        Duration::from_millis(self.bucket_duration_ms)
+    }
+
+    /// Returns the underlying multi-level filter.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+
+    /// Saves the sliding filter, including its rotation clock, as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving SlidingBloomFilter to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a sliding filter saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading SlidingBloomFilter from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let sliding: Self = serde_json::from_reader(reader)?;
+        sliding.validate()?;
+        Ok(sliding)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), BloomFilterError> {
+        self.filter.validate()?;
+        let rotating = InsertMode::ActiveLevel {
+            max_items: None,
+            max_fill_ratio: None,
+        };
+        if self.bucket_duration_ms == 0 || self.filter.insert_mode() != rotating {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "sliding filter needs a non-zero bucket duration and time-based rotation, found {} ms and {:?}",
+                self.bucket_duration_ms,
+                self.filter.insert_mode()
+            )));
+        }
+        Ok(())
+    }
+
+    fn pending_rotations(&self, now: SystemTime) -> usize {
+        let elapsed = unix_millis(now).saturating_sub(self.last_rotation_ms);
+        (elapsed / self.bucket_duration_ms) as usize
+    }
+}
+
+pub(crate) fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_items_slide_out_of_window() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut sbf = SlidingBloomFilter::new_at(Duration::from_secs(60), 3, 1000, 3, start).unwrap();
+
+        sbf.insert_at("early", at(5));
+        sbf.insert_at("middle", at(25));
+        assert!(sbf.query_at("early", at(30)));
+
+        // At t=60 the bucket holding "early" is due for rotation.
+        assert!(!sbf.query_at("early", at(60)));
+        assert!(sbf.query_at("middle", at(60)));
+
+        assert_eq!(sbf.rotate_at(at(61)), 2);
+        assert!(!sbf.query_at("early", at(61)));
+        assert!(sbf.query_at("middle", at(61)));
+        assert!(!sbf.query_at("middle", at(1000)));
+
+        let path = std::env::temp_dir().join("test_bloom_sliding.json");
+        let path = path.to_str().unwrap();
+        sbf.save_to_file(path).unwrap();
+        assert!(SlidingBloomFilter::load_from_file(path).unwrap().query_at("middle", at(61)));
+        sbf.bucket_duration_ms = 0;
+        sbf.save_to_file(path).unwrap();
+        assert!(SlidingBloomFilter::load_from_file(path).is_err());
+        sbf.bucket_duration_ms = 20_000;
+        sbf.filter.set_insert_mode(InsertMode::AllLevels);
+        sbf.save_to_file(path).unwrap();
+        assert!(SlidingBloomFilter::load_from_file(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+// src/snapshot.rs
+
+use arc_swap::ArcSwap;
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::info;
+
+use crate::bloom_filter::BloomFilter;
+use crate::frozen::FrozenBloomFilter;
+
+/// A filter with wait-free reads and batched, copy-then-swap writes.
+///
+/// Readers query an immutable `FrozenBloomFilter` snapshot loaded through
+/// an `ArcSwap`, so they never block or contend with writers. Writers take a
+/// lock on a private mutable copy, apply their changes, and publish a fresh
+/// snapshot with one atomic swap. Each publish re-freezes the whole filter,
+/// so batch inserts rather than publishing per item.
+pub struct SnapshotBloomFilter {
+    published: ArcSwap<FrozenBloomFilter>,
+    writer: Mutex<BloomFilter>,
+}
+
+impl SnapshotBloomFilter {
+    /// Wraps a filter, publishing its current contents as the first snapshot.
+    pub fn new(filter: BloomFilter) -> Self {
+        SnapshotBloomFilter {
+            published: ArcSwap::from_pointee(filter.freeze()),
+            writer: Mutex::new(filter),
+        }
+    }
+
+    /// Queries the published snapshot across the specified number of levels.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.published.load().query(item, num_levels_to_search)
+    }
+
+    /// Queries a binary key in the published snapshot.
+    pub fn query_bytes(&self, item: &[u8], num_levels_to_search: usize) -> bool {
+        self.published.load().query_bytes(item, num_levels_to_search)
+    }
+
+    /// Returns the current snapshot; it stays valid, and unchanged, after later publishes.
+    pub fn snapshot(&self) -> Arc<FrozenBloomFilter> {
+        self.published.load_full()
+    }
+
+    /// Inserts a batch of items and publishes them together, returning how many were new.
+    pub fn insert_batch<I, S>(&self, items: I) -> usize
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.update(|filter| items.into_iter().filter(|item| filter.insert(item.as_ref())).count())
+    }
+
+    /// Applies `f` to the private copy, then publishes the result.
+    ///
+    /// Writers are serialized; readers keep seeing the previous snapshot
+    /// until the swap.
+    pub fn update<R>(&self, f: impl FnOnce(&mut BloomFilter) -> R) -> R {
+        // A poisoned lock means an earlier update panicked part-way; its bits
+        // are at worst partially set, which never causes a false negative.
+        let mut filter = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let result = f(&mut filter);
+        info!("Publishing BloomFilter snapshot");
+        self.published.store(Arc::new(filter.freeze()));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readers_see_published_batches() {
+        let filter = SnapshotBloomFilter::new(BloomFilter::new(1, 1000, 3).unwrap());
+        let before = filter.snapshot();
+        assert_eq!(filter.insert_batch(["apple", "banana"]), 2);
+        assert!(filter.query("apple", 1));
+        assert!(!before.query("apple", 1));
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| filter.insert_batch((0..50).map(|i| format!("item-{}", i))));
+            scope.spawn(|| {
+                for _ in 0..50 {
+                    assert!(filter.query("banana", 1));
+                }
+            });
+        });
+        assert!((0..50).all(|i| filter.query(&format!("item-{}", i), 1)));
+    }
+}
@@ -0,0 +1,301 @@
+// src/source.rs
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Stdin, StdinLock};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+/// Prefix selecting a TCP source in `open`.
+const TCP_PREFIX: &str = "tcp://";
+
+/// A supply of items to insert or check, read a batch at a time.
+///
+/// New kinds of input (a message queue, an object store) plug into bulk
+/// import by implementing this trait.
+pub trait ItemSource {
+    /// Appends up to `max` items to `batch`, returning how many were added; 0 means the source is exhausted.
+    fn next_batch(&mut self, batch: &mut Vec<String>, max: usize) -> io::Result<usize>;
+
+    /// Returns the number of items left, when the source can tell cheaply.
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Newline-separated items from any buffered reader.
+///
+/// Like `BloomFilter::insert_from_reader`, a trailing `\r` is stripped and
+/// empty lines are skipped.
+pub struct LineSource<R> {
+    reader: R,
+    remaining: Option<usize>,
+}
+
+/// Items from a file, one per line.
+pub type FileSource = LineSource<BufReader<File>>;
+/// Items from standard input, one per line.
+pub type StdinSource = LineSource<StdinLock<'static>>;
+/// Items from a TCP connection, one per line, until the peer closes it.
+pub type TcpSource = LineSource<BufReader<TcpStream>>;
+
+impl<R: BufRead> LineSource<R> {
+    pub fn new(reader: R) -> Self {
+        LineSource {
+            reader,
+            remaining: None,
+        }
+    }
+}
+
+impl FileSource {
+    /// Opens a file, counting its items first so `remaining` is known.
+    pub fn open(path: &str) -> io::Result<Self> {
+        let mut count = 0;
+        for line in BufReader::new(File::open(path)?).lines() {
+            if !line?.trim_end_matches('\r').is_empty() {
+                count += 1;
+            }
+        }
+        Ok(LineSource {
+            reader: BufReader::new(File::open(path)?),
+            remaining: Some(count),
+        })
+    }
+}
+
+impl StdinSource {
+    pub fn stdin(stdin: Stdin) -> Self {
+        Self::new(stdin.lock())
+    }
+}
+
+impl TcpSource {
+    /// Connects to a server that streams items.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(TcpStream::connect(addr)?)))
+    }
+
+    /// Waits for one client on `listener` and reads the items it sends.
+    pub fn accept(listener: &TcpListener) -> io::Result<Self> {
+        let (stream, peer) = listener.accept()?;
+        info!("Accepted item stream from {}", peer);
+        Ok(Self::new(BufReader::new(stream)))
+    }
+}
+
+impl<R: BufRead> ItemSource for LineSource<R> {
+    fn next_batch(&mut self, batch: &mut Vec<String>, max: usize) -> io::Result<usize> {
+        let mut added = 0;
+        let mut line = String::new();
+        while added < max {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            let item = line.trim_end_matches('\n').trim_end_matches('\r');
+            if item.is_empty() {
+                continue;
+            }
+            batch.push(item.to_string());
+            added += 1;
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(added);
+        }
+        Ok(added)
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        self.remaining
+    }
+}
+
+/// Opens the source named by `spec`: `-` for stdin, `tcp://host:port` to connect to a TCP server, otherwise a file path.
+pub fn open(spec: &str) -> io::Result<Box<dyn ItemSource>> {
+    if spec == "-" {
+        Ok(Box::new(StdinSource::stdin(io::stdin())))
+    } else if let Some(addr) = spec.strip_prefix(TCP_PREFIX) {
+        Ok(Box::new(TcpSource::connect(addr)?))
+    } else {
+        Ok(Box::new(FileSource::open(spec)?))
+    }
+}
+
+/// Calls `f` for every item in the source, `batch_size` items at a time, returning the number visited.
+pub fn for_each_item(
+    source: &mut dyn ItemSource,
+    batch_size: usize,
+    mut f: impl FnMut(&str),
+) -> io::Result<usize> {
+    let mut batch = Vec::with_capacity(batch_size.max(1));
+    let mut count = 0;
+    loop {
+        batch.clear();
+        if source.next_batch(&mut batch, batch_size.max(1))? == 0 {
+            return Ok(count);
+        }
+        batch.iter().for_each(|item| f(item));
+        count += batch.len();
+    }
+}
+
+/// Lines appended to a file, read as they appear, like `tail -f`.
+///
+/// Each `read_new` returns the complete lines written since the last call;
+/// a line still being written is held back until its newline arrives. If the
+/// file is truncated, reading restarts from the beginning; if it is replaced
+/// (log rotation), the rest of the old file is read and then the new one is
+/// opened from its start.
+pub struct FileFollower {
+    path: String,
+    reader: BufReader<File>,
+    offset: u64,
+    partial: String,
+}
+
+impl FileFollower {
+    /// Opens a file to follow, skipping its existing lines unless `from_start` is set.
+    pub fn open(path: &str, from_start: bool) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let offset = if from_start { 0 } else { reader.seek(SeekFrom::End(0))? };
+        info!("Following {} from byte {}", path, offset);
+        Ok(FileFollower {
+            path: path.to_string(),
+            reader,
+            offset,
+            partial: String::new(),
+        })
+    }
+
+    /// Appends the lines completed since the last call to `batch`, returning how many were added.
+    ///
+    /// Returns 0 when nothing new has been written; the caller decides how
+    /// long to wait before asking again. Empty lines are skipped.
+    pub fn read_new(&mut self, batch: &mut Vec<String>) -> io::Result<usize> {
+        let mut added = self.drain(batch)?;
+        match fs::metadata(&self.path) {
+            Ok(metadata) if is_same_file(&metadata, self.reader.get_ref())? => {
+                if metadata.len() < self.offset {
+                    info!("{} was truncated; reading from the start", self.path);
+                    self.offset = self.reader.seek(SeekFrom::Start(0))?;
+                    self.partial.clear();
+                    added += self.drain(batch)?;
+                }
+            }
+            Ok(_) => {
+                info!("{} was replaced; following the new file", self.path);
+                self.reader = BufReader::new(File::open(&self.path)?);
+                self.offset = 0;
+                self.partial.clear();
+                added += self.drain(batch)?;
+            }
+            // Between a rotation's rename and the new file's creation there is nothing to follow yet.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        Ok(added)
+    }
+
+    fn drain(&mut self, batch: &mut Vec<String>) -> io::Result<usize> {
+        let mut added = 0;
+        loop {
+            let read = self.reader.read_line(&mut self.partial)?;
+            if read == 0 {
+                return Ok(added);
+            }
+            self.offset += read as u64;
+            if !self.partial.ends_with('\n') {
+                continue;
+            }
+            let item = self.partial.trim_end_matches('\n').trim_end_matches('\r');
+            if !item.is_empty() {
+                batch.push(item.to_string());
+                added += 1;
+            }
+            self.partial.clear();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(metadata: &fs::Metadata, file: &File) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let open = file.metadata()?;
+    Ok(metadata.dev() == open.dev() && metadata.ino() == open.ino())
+}
+
+// Without inode numbers, rotation is indistinguishable from truncation.
+#[cfg(not(unix))]
+fn is_same_file(_metadata: &fs::Metadata, _file: &File) -> io::Result<bool> {
+    Ok(true)
+}
+
+impl BloomFilter {
+    /// Inserts every item from a source, returning the number of items read.
+    pub fn insert_from_source(&mut self, source: &mut dyn ItemSource) -> Result<usize, BloomFilterError> {
+        let count = for_each_item(source, 1024, |item| {
+            self.insert(item);
+        })?;
+        info!("Inserted {} items from source", count);
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_sources_yield_lines() {
+        let path = std::env::temp_dir().join("test_bloom_source.txt");
+        std::fs::write(&path, "alpha\r\n\nbeta\ngamma").unwrap();
+        let mut file = FileSource::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(file.remaining(), Some(3));
+        let mut batch = Vec::new();
+        assert_eq!(file.next_batch(&mut batch, 2).unwrap(), 2);
+        assert_eq!(batch, ["alpha", "beta"]);
+        assert_eq!(file.remaining(), Some(1));
+        std::fs::remove_file(&path).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sender = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"one\ntwo\nthree\n").unwrap();
+        });
+        let mut tcp = TcpSource::accept(&listener).unwrap();
+        let mut bf = BloomFilter::new(1, 1000, 3).unwrap();
+        assert_eq!(bf.insert_from_source(&mut tcp).unwrap(), 3);
+        sender.join().unwrap();
+        assert!(bf.query("two", 1));
+        assert!(!bf.query("four", 1));
+    }
+
+    #[test]
+    fn test_follower_reads_appended_lines() {
+        let path = std::env::temp_dir().join("test_bloom_follow.log");
+        std::fs::write(&path, "old\n").unwrap();
+        let path = path.to_str().unwrap();
+        let mut follower = FileFollower::open(path, false).unwrap();
+        let mut batch = Vec::new();
+        assert_eq!(follower.read_new(&mut batch).unwrap(), 0);
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(b"alpha\nbeta\ngam").unwrap();
+        assert_eq!(follower.read_new(&mut batch).unwrap(), 2);
+        file.write_all(b"ma\n").unwrap();
+        assert_eq!(follower.read_new(&mut batch).unwrap(), 1);
+        assert_eq!(batch, ["alpha", "beta", "gamma"]);
+
+        // A rotated-in file is read from its start.
+        std::fs::remove_file(path).unwrap();
+        std::fs::write(path, "delta\n").unwrap();
+        batch.clear();
+        assert_eq!(follower.read_new(&mut batch).unwrap(), 1);
+        assert_eq!(batch, ["delta"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+// src/static_filter.rs
+
+use crate::bloom_filter::{splitmix64, BloomFilter, BloomFilterError};
+use crate::hashing::{fnv1a, HashAlgorithm};
+use crate::math;
+
+/// A single-level Bloom filter of `M` bits and `K` hash functions, both fixed at compile time.
+///
+/// The bits live inline in the struct, one byte per bit like a dense level,
+/// so a filter can sit on the stack or in a `static` with no allocation, and
+/// the probe loop has a constant trip count the compiler can unroll. Keys are
+/// hashed with FNV-1a and double hashing, exactly like a `BloomFilter` built
+/// with `HashAlgorithm::Fnv1a`, `M` bits and `K` hash functions, so
+/// `to_filter` and `from_filter` convert without rehashing anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticBloomFilter<const M: usize, const K: usize> {
+    bits: [bool; M],
+    /// Number of inserts that set at least one new bit, like a level's item count.
+    item_count: usize,
+}
+
+impl<const M: usize, const K: usize> StaticBloomFilter<M, K> {
+    /// Fails the build for a filter with no bits or no hash functions.
+    const VALID: () = assert!(M > 0 && K > 0, "a StaticBloomFilter needs at least one bit and one hash function");
+
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        StaticBloomFilter {
+            bits: [false; M],
+            item_count: 0,
+        }
+    }
+
+    /// Inserts an item, returning `true` if any bit was newly set.
+    pub fn insert(&mut self, item: &str) -> bool {
+        self.insert_bytes(item.as_bytes())
+    }
+
+    /// Inserts a binary key.
+    pub fn insert_bytes(&mut self, item: &[u8]) -> bool {
+        let mut newly_set = false;
+        for position in Self::positions(item) {
+            newly_set |= !self.bits[position];
+            self.bits[position] = true;
+        }
+        self.item_count += usize::from(newly_set);
+        newly_set
+    }
+
+    /// Checks whether an item is (probably) present.
+    pub fn query(&self, item: &str) -> bool {
+        self.query_bytes(item.as_bytes())
+    }
+
+    /// Checks whether a binary key is (probably) present.
+    pub fn query_bytes(&self, item: &[u8]) -> bool {
+        Self::positions(item).iter().all(|&position| self.bits[position])
+    }
+
+    /// Clears every bit.
+    pub fn clear(&mut self) {
+        self.bits = [false; M];
+        self.item_count = 0;
+    }
+
+    /// Returns the number of inserts that set at least one new bit.
+    pub fn item_count(&self) -> usize {
+        self.item_count
+    }
+
+    /// Returns the number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.bits.iter().filter(|&&bit| bit).count()
+    }
+
+    /// Returns the fraction of bits set.
+    pub fn fill_ratio(&self) -> f64 {
+        self.count_ones() as f64 / M as f64
+    }
+
+    /// Estimates the false-positive rate from the current fill.
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        math::estimated_false_positive_rate(self.fill_ratio(), K)
+    }
+
+    /// Copies the bits into a one-level `BloomFilter`, e.g. to save it.
+    pub fn to_filter(&self) -> BloomFilter {
+        let mut filter = Self::empty_filter().expect("M and K are checked at compile time");
+        let positions: Vec<usize> = (0..M).filter(|&index| self.bits[index]).collect();
+        filter.levels[0].insert_positions(&positions);
+        filter.levels[0].item_count = self.item_count;
+        filter
+    }
+
+    /// Copies the bits of a one-level filter with the same parameters, as made by `to_filter`.
+    pub fn from_filter(filter: &BloomFilter) -> Result<Self, BloomFilterError> {
+        filter.check_compatible(&Self::empty_filter()?)?;
+        if filter.levels().len() != 1 {
+            return Err(BloomFilterError::IncompatibleFilters(format!(
+                "a static filter has one level, not {}",
+                filter.levels().len()
+            )));
+        }
+        let mut bits = [false; M];
+        for (index, bit) in bits.iter_mut().enumerate() {
+            *bit = filter.levels[0].bit_array.get(index);
+        }
+        Ok(StaticBloomFilter {
+            bits,
+            item_count: filter.levels[0].item_count,
+        })
+    }
+
+    fn empty_filter() -> Result<BloomFilter, BloomFilterError> {
+        BloomFilter::builder()
+            .array_size(M)
+            .hash_functions(K)
+            .hash_algorithm(HashAlgorithm::Fnv1a)
+            .build()
+    }
+
+    /// The `K` bit positions of a key, as `HashAlgorithm::Fnv1a` computes them.
+    fn positions(item: &[u8]) -> [usize; K] {
+        let h1 = fnv1a(item, 0);
+        let h2 = splitmix64(h1) | 1;
+        std::array::from_fn(|i| (h1.wrapping_add((i as u64).wrapping_mul(h2)) % M as u64) as usize)
+    }
+}
+
+impl<const M: usize, const K: usize> Default for StaticBloomFilter<M, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EMPTY: StaticBloomFilter<64, 3> = StaticBloomFilter::new();
+
+    #[test]
+    fn test_static_filter_matches_dynamic_fnv1a_filter() {
+        assert!(!EMPTY.query("anything"));
+        let mut filter = StaticBloomFilter::<1024, 4>::new();
+        assert!(filter.insert("alpha"));
+        assert!(!filter.insert("alpha"));
+        filter.insert_bytes(b"beta");
+        assert!(filter.query("alpha") && filter.query("beta") && !filter.query("gamma"));
+        assert!(filter.count_ones() <= 8 && filter.estimated_false_positive_rate() > 0.0);
+
+        let dynamic = filter.to_filter();
+        assert_eq!(dynamic.levels()[0].item_count(), 2);
+        assert!(dynamic.query("alpha", 1) && dynamic.query_bytes(b"beta", 1));
+        let mut same = StaticBloomFilter::<1024, 4>::new();
+        same.insert("alpha");
+        same.insert("beta");
+        assert_eq!(StaticBloomFilter::<1024, 4>::from_filter(&dynamic).unwrap(), same);
+        assert!(StaticBloomFilter::<1024, 5>::from_filter(&dynamic).is_err());
+        assert!(StaticBloomFilter::<1024, 4>::from_filter(&BloomFilter::new(1, 1024, 4).unwrap()).is_err());
+
+        filter.clear();
+        assert_eq!(filter, StaticBloomFilter::default());
+    }
+}
@@ -0,0 +1,241 @@
+// src/stats.rs
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::bloom_filter::{unix_now, BloomFilter, HashFunction, InsertMode};
+use crate::hashing::HashAlgorithm;
+use crate::math;
+
+/// A point-in-time summary of a filter's configuration and fill.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FilterStats {
+    pub levels: usize,
+    pub array_size: usize,
+    pub hash_functions: usize,
+    pub hash_algorithm: HashAlgorithm,
+    pub seed: u64,
+    pub keyed: bool,
+    pub insert_mode: InsertMode,
+    pub active_level: usize,
+    /// Estimated false-positive rate of a query across all unexpired levels.
+    pub estimated_false_positive_rate: f64,
+    pub counts: OperationCounts,
+    pub level_stats: Vec<LevelStats>,
+}
+
+/// Fill and usage of a single level.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelStats {
+    pub label: Option<String>,
+    pub bits_set: usize,
+    pub fill_ratio: f64,
+    pub item_count: usize,
+    pub estimated_false_positive_rate: f64,
+    pub expired: bool,
+    /// Queries this level answered first; each hit is credited to one level.
+    pub hits: u64,
+}
+
+/// Inserts and queries a filter has served since it was created or loaded, or its counters were reset.
+///
+/// The counters live in memory only and are not saved with the filter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationCounts {
+    pub inserts: u64,
+    pub queries: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl OperationCounts {
+    /// Returns the share of queries that matched, or `None` before the first query.
+    pub fn hit_rate(&self) -> Option<f64> {
+        (self.queries > 0).then(|| self.hits as f64 / self.queries as f64)
+    }
+}
+
+/// The live counters behind `OperationCounts`; atomics, so queries can count through `&self`.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    pub(crate) inserts: AtomicU64,
+    pub(crate) queries: AtomicU64,
+    pub(crate) hits: AtomicU64,
+}
+
+/// Approximate bytes of memory a filter occupies.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MemoryUsage {
+    /// Bytes per level: its bits plus label, metadata and bookkeeping.
+    pub levels: Vec<usize>,
+    /// All levels plus the filter's own fields and hash functions.
+    pub total: usize,
+}
+
+impl BloomFilter {
+    /// Returns the inserts, queries, hits and misses counted so far.
+    pub fn counts(&self) -> OperationCounts {
+        let queries = self.counters.queries.load(Ordering::Relaxed);
+        let hits = self.counters.hits.load(Ordering::Relaxed);
+        OperationCounts {
+            inserts: self.counters.inserts.load(Ordering::Relaxed),
+            queries,
+            hits,
+            misses: queries.saturating_sub(hits),
+        }
+    }
+
+    /// Sets the operation counters, including each level's hits, back to zero.
+    pub fn reset_counters(&self) {
+        self.counters.inserts.store(0, Ordering::Relaxed);
+        self.counters.queries.store(0, Ordering::Relaxed);
+        self.counters.hits.store(0, Ordering::Relaxed);
+        for level in &self.levels {
+            level.hits.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Reports the memory used by each level and by the filter as a whole.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let levels: Vec<usize> = self.levels.iter().map(|level| level.memory_usage()).collect();
+        let total = std::mem::size_of::<BloomFilter>()
+            + self.hash_functions.capacity() * std::mem::size_of::<HashFunction>()
+            + levels.iter().sum::<usize>();
+        MemoryUsage { levels, total }
+    }
+
+    /// Summarizes the filter's parameters and per-level fill.
+    pub fn stats(&self) -> FilterStats {
+        let now = unix_now();
+        let k = self.hash_functions.len();
+        FilterStats {
+            levels: self.levels.len(),
+            array_size: self.array_size,
+            hash_functions: k,
+            hash_algorithm: self.hash_algorithm,
+            seed: self.seed(),
+            keyed: self.hash_key.is_some(),
+            insert_mode: self.insert_mode,
+            active_level: self.active_level,
+            estimated_false_positive_rate: self.estimated_false_positive_rate(),
+            counts: self.counts(),
+            level_stats: self
+                .levels
+                .iter()
+                .map(|level| LevelStats {
+                    label: level.label.clone(),
+                    bits_set: level.count_ones(),
+                    fill_ratio: level.fill_ratio(),
+                    item_count: level.item_count,
+                    estimated_false_positive_rate: math::estimated_false_positive_rate(level.fill_ratio(), k),
+                    expired: level.is_expired_at(now),
+                    hits: level.hits.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A multi-line summary: one header line, then one line per level.
+impl fmt::Display for FilterStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BloomFilter: {} levels x {} bits, {} hash functions ({:?}), estimated FPR {:.4}%",
+            self.levels,
+            self.array_size,
+            self.hash_functions,
+            self.hash_algorithm,
+            self.estimated_false_positive_rate * 100.0
+        )?;
+        if let Some(hit_rate) = self.counts.hit_rate() {
+            write!(
+                f,
+                "\n  {} inserts, {} queries: {} hits, {} misses ({:.1}% hit rate)",
+                self.counts.inserts,
+                self.counts.queries,
+                self.counts.hits,
+                self.counts.misses,
+                hit_rate * 100.0
+            )?;
+        }
+        for (index, level) in self.level_stats.iter().enumerate() {
+            write!(f, "\n  level {}", index + 1)?;
+            if let Some(label) = &level.label {
+                write!(f, " \"{}\"", label)?;
+            }
+            write!(
+                f,
+                ": {}/{} bits set ({:.1}%), {} items, FPR {:.4}%",
+                level.bits_set,
+                self.array_size,
+                level.fill_ratio * 100.0,
+                level.item_count,
+                level.estimated_false_positive_rate * 100.0
+            )?;
+            if level.hits > 0 {
+                write!(f, ", {} hits", level.hits)?;
+            }
+            if level.expired {
+                write!(f, " (expired)")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_budget_is_respected() {
+        for budget in [4096, 100_000, 1 << 20] {
+            let bf = BloomFilter::with_memory_budget(budget, 1000).unwrap();
+            let usage = bf.memory_usage();
+            assert!(usage.total <= budget, "{} > {}", usage.total, budget);
+            assert!(usage.total > budget * 9 / 10);
+            assert_eq!(usage.levels.len(), 1);
+        }
+        let bf = BloomFilter::with_memory_budget(100_000, 1000).unwrap();
+        assert!(bf.num_hash_functions().abs_diff(math::optimal_hashes(bf.array_size(), 1000)) <= 1);
+        assert!(BloomFilter::with_memory_budget(16, 1000).is_err());
+    }
+
+    #[test]
+    fn test_counters_track_hits_per_level() {
+        let mut bf = BloomFilter::builder()
+            .levels(3)
+            .array_size(1000)
+            .insert_mode(InsertMode::ActiveLevel {
+                max_items: Some(1),
+                max_fill_ratio: None,
+            })
+            .build()
+            .unwrap();
+        bf.insert("first");
+        bf.insert("second");
+        for item in ["first", "second", "second", "missing"] {
+            bf.query(item, 3);
+        }
+        let stats = bf.stats();
+        assert_eq!(
+            stats.counts,
+            OperationCounts {
+                inserts: 2,
+                queries: 4,
+                hits: 3,
+                misses: 1
+            }
+        );
+        assert_eq!(stats.counts.hit_rate(), Some(0.75));
+        assert_eq!(stats.level_stats.iter().map(|level| level.hits).collect::<Vec<_>>(), [1, 2, 0]);
+        assert!(stats.to_string().contains("4 queries: 3 hits, 1 misses (75.0% hit rate)"));
+
+        bf.reset_counters();
+        assert_eq!(bf.counts(), OperationCounts::default());
+        assert_eq!(bf.stats().level_stats[1].hits, 0);
+        assert_eq!(bf.counts().hit_rate(), None);
+    }
+}
@@ -0,0 +1,257 @@
+// src/store.rs
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::format::FileFormat;
+
+const FILTER_EXTENSION: &str = "bin";
+
+/// A directory of named filters, one binary-format file per filter.
+///
+/// A store made with `in_memory` has no directory: filters live only as
+/// long as the store and `flush` does nothing. Otherwise filters are loaded
+/// on first access and kept in memory. Changes reach the
+/// disk on `flush`, which writes every created or mutably borrowed filter
+/// through a temporary file and a rename, so a crash mid-flush leaves the
+/// previous file intact. Names may contain ASCII letters, digits, `-`, `_`
+/// and `.`, and must not start with `.`. Keyed-hashing filters cannot be
+/// stored, since their key is never written to disk.
+pub struct FilterStore {
+    dir: Option<PathBuf>,
+    loaded: BTreeMap<String, Entry>,
+}
+
+struct Entry {
+    filter: BloomFilter,
+    /// Changed (or never written) since the last flush.
+    dirty: bool,
+}
+
+impl FilterStore {
+    /// Opens the store in `dir`, creating the directory if needed.
+    pub fn open(dir: &str) -> Result<Self, BloomFilterError> {
+        info!("Opening FilterStore in {}", dir);
+        fs::create_dir_all(dir)?;
+        Ok(FilterStore {
+            dir: Some(PathBuf::from(dir)),
+            loaded: BTreeMap::new(),
+        })
+    }
+
+    /// Creates a store that keeps its filters in memory only.
+    pub fn in_memory() -> Self {
+        FilterStore {
+            dir: None,
+            loaded: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the directory backing the store, if it has one.
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Adds a new filter under `name`; it is written on the next `flush`.
+    pub fn create(&mut self, name: &str, filter: BloomFilter) -> Result<&mut BloomFilter, BloomFilterError> {
+        check_name(name)?;
+        if self.contains(name) {
+            return Err(BloomFilterError::FilterExists(name.to_string()));
+        }
+        if filter.hash_key().is_some() {
+            return Err(BloomFilterError::KeyedFilterNotStorable(name.to_string()));
+        }
+        info!("Creating filter '{}' in store", name);
+        let entry = self.loaded.entry(name.to_string()).or_insert(Entry { filter, dirty: true });
+        Ok(&mut entry.filter)
+    }
+
+    /// Returns true if a filter called `name` exists, in memory or on disk.
+    pub fn contains(&self, name: &str) -> bool {
+        self.loaded.contains_key(name) || (check_name(name).is_ok() && self.path(name).is_some_and(|path| path.exists()))
+    }
+
+    /// Returns the filter called `name`, loading it if needed.
+    pub fn get(&mut self, name: &str) -> Result<&BloomFilter, BloomFilterError> {
+        Ok(&self.entry(name)?.filter)
+    }
+
+    /// Returns the filter called `name` for modification; it is rewritten on the next `flush`.
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut BloomFilter, BloomFilterError> {
+        let entry = self.entry(name)?;
+        entry.dirty = true;
+        Ok(&mut entry.filter)
+    }
+
+    /// Lists the names of all filters, in memory or on disk, sorted.
+    pub fn list(&self) -> Result<Vec<String>, BloomFilterError> {
+        let mut names: Vec<String> = self.loaded.keys().cloned().collect();
+        let Some(dir) = &self.dir else {
+            return Ok(names);
+        };
+        for dir_entry in fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(FILTER_EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                if check_name(name).is_ok() && !self.loaded.contains_key(name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Removes the filter called `name` from memory and disk.
+    pub fn delete(&mut self, name: &str) -> Result<(), BloomFilterError> {
+        check_name(name)?;
+        let was_loaded = self.loaded.remove(name).is_some();
+        match self.path(name) {
+            Some(path) if path.exists() => fs::remove_file(&path)?,
+            _ if was_loaded => {}
+            _ => return Err(BloomFilterError::FilterNotFound(name.to_string())),
+        }
+        info!("Deleted filter '{}' from store", name);
+        Ok(())
+    }
+
+    /// Stores a copy of the filter called `from` under the new name `to`.
+    pub fn copy(&mut self, from: &str, to: &str) -> Result<(), BloomFilterError> {
+        check_name(to)?;
+        if self.contains(to) {
+            return Err(BloomFilterError::FilterExists(to.to_string()));
+        }
+        let copy = BloomFilter::from_binary(&self.get(from)?.to_binary())?;
+        self.create(to, copy)?;
+        Ok(())
+    }
+
+    /// Moves the filter called `from` to the new name `to`.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), BloomFilterError> {
+        check_name(to)?;
+        if self.contains(to) {
+            return Err(BloomFilterError::FilterExists(to.to_string()));
+        }
+        self.entry(from)?;
+        let mut entry = self.loaded.remove(from).expect("entry was just loaded");
+        // Write the new file before removing the old one, so a crash in
+        // between leaves both names rather than neither.
+        if let Some(dir) = &self.dir {
+            write_entry(dir, to, &mut entry)?;
+        }
+        if let Some(path) = self.path(from).filter(|path| path.exists()) {
+            fs::remove_file(path)?;
+        }
+        self.loaded.insert(to.to_string(), entry);
+        info!("Renamed filter '{}' to '{}'", from, to);
+        Ok(())
+    }
+
+    /// Writes every filter changed since the last flush.
+    pub fn flush(&mut self) -> Result<(), BloomFilterError> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        for (name, entry) in self.loaded.iter_mut().filter(|(_, entry)| entry.dirty) {
+            write_entry(dir, name, entry)?;
+        }
+        Ok(())
+    }
+
+    fn entry(&mut self, name: &str) -> Result<&mut Entry, BloomFilterError> {
+        check_name(name)?;
+        if !self.loaded.contains_key(name) {
+            let path = self
+                .path(name)
+                .filter(|path| path.exists())
+                .ok_or_else(|| BloomFilterError::FilterNotFound(name.to_string()))?;
+            let (filter, _) = BloomFilter::load_auto(&path.to_string_lossy())?;
+            self.loaded.insert(name.to_string(), Entry { filter, dirty: false });
+        }
+        Ok(self.loaded.get_mut(name).expect("entry was just loaded"))
+    }
+
+    fn path(&self, name: &str) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.{}", name, FILTER_EXTENSION)))
+    }
+}
+
+/// Writes a filter through a temporary file and a rename, then marks it clean.
+fn write_entry(dir: &Path, name: &str, entry: &mut Entry) -> Result<(), BloomFilterError> {
+    let path = dir.join(format!("{}.{}", name, FILTER_EXTENSION));
+    info!("Flushing filter '{}' to {}", name, path.display());
+    let tmp_path = path.with_extension(format!("{}.tmp", FILTER_EXTENSION));
+    entry.filter.save_as(&tmp_path.to_string_lossy(), FileFormat::Binary)?;
+    File::open(&tmp_path)?.sync_all()?;
+    fs::rename(&tmp_path, &path)?;
+    entry.dirty = false;
+    Ok(())
+}
+
+/// Rejects names that are empty, hidden, or could escape the store's directory.
+fn check_name(name: &str) -> Result<(), BloomFilterError> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(BloomFilterError::InvalidFilterName(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_lifecycle() {
+        let dir = std::env::temp_dir().join("test_bloom_store");
+        let _ = fs::remove_dir_all(&dir);
+        let dir_str = dir.to_str().unwrap();
+
+        {
+            let mut store = FilterStore::open(dir_str).unwrap();
+            store.create("tenant-a", BloomFilter::new(1, 100, 3).unwrap()).unwrap().insert("apple");
+            store.create("tenant-b", BloomFilter::new(2, 200, 3).unwrap()).unwrap();
+            assert!(matches!(
+                store.create("tenant-a", BloomFilter::new(1, 100, 3).unwrap()),
+                Err(BloomFilterError::FilterExists(_))
+            ));
+            assert!(store.create("../escape", BloomFilter::new(1, 100, 3).unwrap()).is_err());
+            store.flush().unwrap();
+        }
+
+        let mut store = FilterStore::open(dir_str).unwrap();
+        assert_eq!(store.list().unwrap(), vec!["tenant-a", "tenant-b"]);
+        assert!(store.get("tenant-a").unwrap().query("apple", 1));
+        store.get_mut("tenant-b").unwrap().insert("banana");
+        store.delete("tenant-a").unwrap();
+        store.copy("tenant-b", "tenant-c").unwrap();
+        store.rename("tenant-b", "tenant-d").unwrap();
+        assert!(matches!(store.rename("tenant-c", "tenant-d"), Err(BloomFilterError::FilterExists(_))));
+        store.flush().unwrap();
+        assert!(matches!(store.get("tenant-a"), Err(BloomFilterError::FilterNotFound(_))));
+
+        let mut reopened = FilterStore::open(dir_str).unwrap();
+        assert_eq!(reopened.list().unwrap(), vec!["tenant-c", "tenant-d"]);
+        assert!(reopened.get("tenant-c").unwrap().query("banana", 2));
+        assert!(reopened.get("tenant-d").unwrap().query("banana", 2));
+
+        let mut scratch = FilterStore::in_memory();
+        scratch.create("tmp", BloomFilter::new(1, 100, 3).unwrap()).unwrap();
+        scratch.flush().unwrap();
+        assert_eq!(scratch.list().unwrap(), vec!["tmp"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+// src/sync.rs
+
+use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher13;
+use std::hash::{Hash, Hasher};
+use tracing::info;
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+const DIGEST_KEYS: (u64, u64) = (0x5359_4e43_4449_4745, 0x2545_f491_4f6c_dd1d);
+
+/// Per-block hashes of a replica's bits, sent to the source of truth to ask what differs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncDigest {
+    pub array_size: usize,
+    /// Bytes of packed bits per block; the last block of a level may be shorter.
+    pub block_size: usize,
+    /// Hash of the hash configuration, so replicas of different filters never sync.
+    pub config: u64,
+    /// One hash per block, per level.
+    pub levels: Vec<Vec<u64>>,
+}
+
+/// The blocks of packed bits that differ from a replica's digest, plus per-level item counts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncPatch {
+    pub array_size: usize,
+    pub block_size: usize,
+    pub config: u64,
+    pub blocks: Vec<PatchBlock>,
+    pub item_counts: Vec<usize>,
+}
+
+/// A block of packed bits (the layout of `BloomLevel::to_bytes`) replacing the replica's.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PatchBlock {
+    pub level: usize,
+    pub block: usize,
+    pub bits: Vec<u8>,
+}
+
+impl BloomFilter {
+    /// Hashes each `block_size`-byte block of every level's packed bits.
+    ///
+    /// Syncing a replica takes one round trip: the replica sends its digest,
+    /// the source answers with `sync_patch`, and the replica applies it with
+    /// `apply_sync_patch`. Only blocks whose hashes differ are transferred.
+    pub fn sync_digest(&self, block_size: usize) -> SyncDigest {
+        let block_size = block_size.max(1);
+        SyncDigest {
+            array_size: self.array_size(),
+            block_size,
+            config: self.config_hash(),
+            levels: self
+                .levels()
+                .iter()
+                .map(|level| level.to_bytes().chunks(block_size).map(block_hash).collect())
+                .collect(),
+        }
+    }
+
+    /// Collects the blocks where this filter differs from the replica that sent `digest`.
+    pub fn sync_patch(&self, digest: &SyncDigest) -> Result<SyncPatch, BloomFilterError> {
+        self.check_sync(digest.array_size, digest.config, digest.levels.len())?;
+        let block_size = digest.block_size.max(1);
+        let mut blocks = Vec::new();
+        for (index, (level, theirs)) in self.levels().iter().zip(&digest.levels).enumerate() {
+            for (block, bits) in level.to_bytes().chunks(block_size).enumerate() {
+                if theirs.get(block) != Some(&block_hash(bits)) {
+                    blocks.push(PatchBlock {
+                        level: index,
+                        block,
+                        bits: bits.to_vec(),
+                    });
+                }
+            }
+        }
+        info!("Sync patch has {} differing blocks of {} bytes", blocks.len(), block_size);
+        Ok(SyncPatch {
+            array_size: self.array_size(),
+            block_size,
+            config: digest.config,
+            blocks,
+            item_counts: self.levels().iter().map(|level| level.item_count()).collect(),
+        })
+    }
+
+    /// Overwrites this replica's differing blocks with the source's, returning the number applied.
+    ///
+    /// The whole patch is checked before any bits change. Newly set bits are
+    /// recorded for `save_delta`, but cleared ones cannot be, so take a fresh
+    /// snapshot after syncing when using deltas.
+    pub fn apply_sync_patch(&mut self, patch: &SyncPatch) -> Result<usize, BloomFilterError> {
+        self.check_sync(patch.array_size, patch.config, patch.item_counts.len())?;
+        let level_bytes = self.array_size().div_ceil(8);
+        for block in &patch.blocks {
+            let offset = block.block.checked_mul(patch.block_size);
+            let fits = offset.is_some_and(|offset| {
+                block.bits.len() <= patch.block_size && offset + block.bits.len() <= level_bytes
+            });
+            if block.level >= self.levels.len() || !fits {
+                return Err(BloomFilterError::DeltaMismatch(format!(
+                    "block {} of level {} is out of range",
+                    block.block, block.level
+                )));
+            }
+        }
+        for block in &patch.blocks {
+            self.levels[block.level].overwrite_bytes(block.block * patch.block_size, &block.bits);
+        }
+        for (level, &item_count) in self.levels.iter_mut().zip(&patch.item_counts) {
+            level.item_count = item_count;
+        }
+        info!("Applied {} sync blocks", patch.blocks.len());
+        Ok(patch.blocks.len())
+    }
+
+    fn check_sync(&self, array_size: usize, config: u64, levels: usize) -> Result<(), BloomFilterError> {
+        if array_size != self.array_size() || levels != self.levels().len() {
+            return Err(BloomFilterError::IncompatibleFilters(format!(
+                "{} levels of {} bits vs {} levels of {} bits",
+                self.levels().len(),
+                self.array_size(),
+                levels,
+                array_size
+            )));
+        }
+        if config != self.config_hash() {
+            return Err(BloomFilterError::IncompatibleFilters(
+                "hash configurations differ".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn config_hash(&self) -> u64 {
+        let mut hasher = SipHasher13::new_with_keys(DIGEST_KEYS.0, DIGEST_KEYS.1);
+        self.array_size().hash(&mut hasher);
+        for hash_function in self.hash_functions() {
+            hash_function.multiplier.hash(&mut hasher);
+        }
+        self.seed().hash(&mut hasher);
+        format!("{:?}", self.hash_algorithm()).hash(&mut hasher);
+        self.key_fingerprint.hash(&mut hasher);
+        self.normalization.to_code().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+fn block_hash(bits: &[u8]) -> u64 {
+    let mut hasher = SipHasher13::new_with_keys(DIGEST_KEYS.0, DIGEST_KEYS.1);
+    hasher.write(bits);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_transfers_only_differing_blocks() {
+        let mut source = BloomFilter::new(2, 8000, 3).unwrap();
+        let mut replica = BloomFilter::new(2, 8000, 3).unwrap();
+        for i in 0..200 {
+            source.insert(&format!("shared-{}", i));
+            replica.insert(&format!("shared-{}", i));
+        }
+        source.insert("only-on-source");
+        replica.insert("only-on-replica");
+
+        let digest = replica.sync_digest(100);
+        assert_eq!(digest.levels[0].len(), 10);
+        let patch = source.sync_patch(&digest).unwrap();
+        assert!(!patch.blocks.is_empty() && patch.blocks.len() <= 12);
+        let json = serde_json::to_string(&patch).unwrap();
+        let patch: SyncPatch = serde_json::from_str(&json).unwrap();
+        replica.apply_sync_patch(&patch).unwrap();
+
+        assert!(replica.query("only-on-source", 2));
+        assert_eq!(replica.to_binary(), source.to_binary());
+        assert!(source.sync_patch(&replica.sync_digest(100)).unwrap().blocks.is_empty());
+
+        let other = BloomFilter::builder().levels(2).array_size(8000).seed(1).build().unwrap();
+        assert!(other.sync_patch(&digest).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+// src/top_k.rs
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use tracing::{error, info};
+
+use crate::bloom_filter::BloomFilterError;
+use crate::count_min::CountMinSketch;
+
+/// An item and its approximate count.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeavyHitter {
+    pub item: String,
+    pub count: u64,
+}
+
+/// Tracks the `k` most frequent items of a stream with approximate counts.
+///
+/// Every item is counted in a conservative-update `CountMinSketch`, and the
+/// `k` items with the highest estimates so far are kept by name. An item
+/// displaces the current minimum once its estimate exceeds it, so counts
+/// never undercount, and items much more frequent than `total / width` are
+/// reliably reported.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TopK {
+    k: usize,
+    sketch: CountMinSketch,
+    candidates: HashMap<String, u64>,
+}
+
+impl TopK {
+    /// Tracks the `k` most frequent items, counting in a sketch with the given error bounds.
+    ///
+    /// See `CountMinSketch::for_error` for `epsilon` and `delta`.
+    pub fn new(k: usize, epsilon: f64, delta: f64) -> Result<Self, BloomFilterError> {
+        info!("Creating TopK: k={}, epsilon={}, delta={}", k, epsilon, delta);
+        if k == 0 {
+            error!("Requested top 0 items");
+            return Err(BloomFilterError::ZeroArraySize);
+        }
+        let mut sketch = CountMinSketch::for_error(epsilon, delta)?;
+        sketch.set_conservative_update(true);
+        Ok(TopK {
+            k,
+            sketch,
+            candidates: HashMap::with_capacity(k + 1),
+        })
+    }
+
+    /// Counts one occurrence of an item.
+    pub fn insert(&mut self, item: &str) {
+        self.add(item, 1);
+    }
+
+    /// Counts `count` occurrences of an item.
+    pub fn add(&mut self, item: &str, count: u64) {
+        self.sketch.add(item, count);
+        let estimate = self.sketch.estimate(item);
+        if let Some(tracked) = self.candidates.get_mut(item) {
+            *tracked = estimate;
+            return;
+        }
+        if self.candidates.len() < self.k {
+            self.candidates.insert(item.to_string(), estimate);
+            return;
+        }
+        let (smallest, &min) = self
+            .candidates
+            .iter()
+            .min_by_key(|&(_, &count)| count)
+            .expect("k is at least 1");
+        if estimate > min {
+            let smallest = smallest.clone();
+            self.candidates.remove(&smallest);
+            self.candidates.insert(item.to_string(), estimate);
+        }
+    }
+
+    /// Returns the tracked items, most frequent first.
+    pub fn top(&self) -> Vec<HeavyHitter> {
+        let mut top: Vec<HeavyHitter> = self
+            .candidates
+            .iter()
+            .map(|(item, &count)| HeavyHitter {
+                item: item.clone(),
+                count,
+            })
+            .collect();
+        top.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.item.cmp(&b.item)));
+        top
+    }
+
+    /// Estimates any item's count, tracked or not.
+    pub fn estimate(&self, item: &str) -> u64 {
+        self.sketch.estimate(item)
+    }
+
+    /// Returns the number of items counted.
+    pub fn total(&self) -> u64 {
+        self.sketch.total()
+    }
+
+    /// Returns how many items are tracked.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Saves the tracker, including its sketch, as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving TopK to file: {}", filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a tracker saved with `save_to_file`.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading TopK from file: {}", filepath);
+        let reader = BufReader::new(File::open(filepath)?);
+        let top_k: Self = serde_json::from_reader(reader)?;
+        if top_k.k == 0 || top_k.candidates.len() > top_k.k {
+            return Err(BloomFilterError::InvalidBitArray(format!(
+                "top-k tracker holds {} items for k={}",
+                top_k.candidates.len(),
+                top_k.k
+            )));
+        }
+        Ok(top_k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heavy_hitters_rise_to_the_top() {
+        let mut top_k = TopK::new(3, 0.001, 0.01).unwrap();
+        for i in 0..5000 {
+            top_k.insert(&format!("noise-{}", i));
+            if i % 10 == 0 {
+                top_k.insert("frequent");
+            }
+            if i % 25 == 0 {
+                top_k.insert("common");
+            }
+        }
+        top_k.add("bulk", 1000);
+
+        let top = top_k.top();
+        let names: Vec<&str> = top.iter().map(|hitter| hitter.item.as_str()).collect();
+        assert_eq!(names, ["bulk", "frequent", "common"]);
+        assert!(top[1].count >= 500 && top[1].count < 520);
+        assert_eq!(top_k.total(), 5000 + 500 + 200 + 1000);
+
+        let path = std::env::temp_dir().join("test_bloom_top_k.json");
+        let path = path.to_str().unwrap();
+        top_k.save_to_file(path).unwrap();
+        assert_eq!(TopK::load_from_file(path).unwrap().top(), top);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+// src/tui.rs
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Margin, Position};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Gauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::bloom_filter::unix_now;
+use crate::repl::{execute, render, Command, Session};
+use crate::stats::OperationCounts;
+
+/// How often the panels redraw while no key is pressed.
+const TICK: Duration = Duration::from_millis(250);
+/// How far apart rate samples are taken.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// Number of output lines kept for the log panel.
+const LOG_LINES: usize = 200;
+
+/// Inserts and queries per second, measured between counter samples.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Rates {
+    inserts: f64,
+    queries: f64,
+}
+
+/// State of the dashboard: the session, the command being typed and recent output.
+struct Dashboard {
+    session: Session,
+    input: String,
+    history: Vec<String>,
+    /// Index into `history` while browsing it with the arrow keys.
+    recalled: Option<usize>,
+    log: Vec<String>,
+    /// The last counter sample: when it was taken, for which filter, and the counts.
+    sample: (Instant, String, OperationCounts),
+    rates: Rates,
+}
+
+impl Dashboard {
+    fn new(mut session: Session) -> Self {
+        let active = session.active().to_string();
+        let counts = session.store().get(&active).map(|filter| filter.counts()).unwrap_or_default();
+        Dashboard {
+            session,
+            input: String::new(),
+            history: Vec::new(),
+            recalled: None,
+            log: vec!["Type 'help' for a list of commands, Esc to leave.".to_string()],
+            sample: (Instant::now(), active, counts),
+            rates: Rates::default(),
+        }
+    }
+
+    /// Runs a command line and logs its output; returns false for `exit`.
+    fn submit(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        if line.is_empty() {
+            return true;
+        }
+        self.history.push(line.to_string());
+        self.log.push(format!("bloom> {}", line));
+        let result = match line.parse::<Command>() {
+            Ok(command) => match execute(&mut self.session, command) {
+                Some(result) => result,
+                None => return false,
+            },
+            Err(e) => Err(e),
+        };
+        self.log.extend(render(&result, false).lines().map(str::to_string));
+        let excess = self.log.len().saturating_sub(LOG_LINES);
+        self.log.drain(..excess);
+        true
+    }
+
+    /// Takes a new counter sample once `RATE_WINDOW` has passed and updates the rates.
+    ///
+    /// Switching filters starts over, since the counts belong to the old filter.
+    fn sample(&mut self, now: Instant) {
+        let active = self.session.active().to_string();
+        let counts = self.session.store().get(&active).map(|filter| filter.counts()).unwrap_or_default();
+        let (taken, filter, previous) = &self.sample;
+        let elapsed = now.saturating_duration_since(*taken);
+        if *filter != active {
+            self.rates = Rates::default();
+        } else if elapsed >= RATE_WINDOW {
+            let per_second = |now: u64, before: u64| now.saturating_sub(before) as f64 / elapsed.as_secs_f64();
+            self.rates = Rates {
+                inserts: per_second(counts.inserts, previous.inserts),
+                queries: per_second(counts.queries, previous.queries),
+            };
+        } else {
+            return;
+        }
+        self.sample = (now, active, counts);
+    }
+
+    /// Handles a key press; returns false when the dashboard should close.
+    fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        match code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c' | 'd') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Enter => {
+                let line = std::mem::take(&mut self.input);
+                self.recalled = None;
+                return self.submit(&line);
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Up if !self.history.is_empty() => {
+                let index = self.recalled.map_or(self.history.len() - 1, |index| index.saturating_sub(1));
+                self.recalled = Some(index);
+                self.input = self.history[index].clone();
+            }
+            KeyCode::Down => {
+                self.recalled = self.recalled.map(|index| index + 1).filter(|&index| index < self.history.len());
+                self.input = self.recalled.map(|index| self.history[index].clone()).unwrap_or_default();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let active = self.session.active().to_string();
+        let filter = match self.session.store().get(&active) {
+            Ok(filter) => filter,
+            Err(e) => {
+                frame.render_widget(Paragraph::new(format!("Failed to open filter '{}': {}", active, e)), frame.area());
+                return;
+            }
+        };
+        let now = unix_now();
+        let levels = filter.levels();
+        let [gauges_area, side_area, log_area, input_area] = {
+            let [top, log, input] = Layout::vertical([
+                // Room for up to 8 gauges, and never less than the activity panel needs.
+                Constraint::Length((levels.len().min(8) as u16 * 2).max(6) + 2),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ])
+            .areas(frame.area());
+            let [gauges, side] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(top);
+            [gauges, side, log, input]
+        };
+
+        let title = format!(
+            " {} - {} levels x {} bits, {} hash functions ",
+            active,
+            levels.len(),
+            filter.array_size(),
+            filter.hash_functions().len()
+        );
+        frame.render_widget(Block::default().borders(Borders::ALL).title(title), gauges_area);
+        let rows = Layout::vertical(vec![Constraint::Length(2); levels.len().min(8)])
+            .split(gauges_area.inner(Margin::new(1, 1)));
+        for (index, (level, row)) in levels.iter().zip(rows.iter()).enumerate() {
+            let mut name = format!("level {}", index + 1);
+            if let Some(label) = &level.label {
+                name.push_str(&format!(" \"{}\"", label));
+            }
+            if level.is_expired_at(now) {
+                name.push_str(" (expired)");
+            }
+            let [label_row, gauge_row] = Layout::vertical([Constraint::Length(1); 2]).areas(*row);
+            frame.render_widget(Paragraph::new(format!("{} - {} items", name, level.item_count())), label_row);
+            let fill = level.fill_ratio();
+            let color = match fill {
+                fill if fill >= 0.75 => Color::Red,
+                fill if fill >= 0.5 => Color::Yellow,
+                _ => Color::Green,
+            };
+            frame.render_widget(
+                Gauge::default()
+                    .gauge_style(Style::default().fg(color))
+                    .ratio(fill.clamp(0.0, 1.0))
+                    .label(format!("{:.1}%", fill * 100.0)),
+                gauge_row,
+            );
+        }
+
+        let counts = filter.counts();
+        let mut side = vec![
+            Line::from(format!("inserts/s  {:.1}", self.rates.inserts)),
+            Line::from(format!("queries/s  {:.1}", self.rates.queries)),
+            Line::from(format!("inserts    {}", counts.inserts)),
+            Line::from(format!("queries    {}", counts.queries)),
+        ];
+        if let Some(hit_rate) = counts.hit_rate() {
+            side.push(Line::from(format!("hit rate   {:.1}%", hit_rate * 100.0)));
+        }
+        side.push(Line::from(format!("est. FPR   {:.4}%", filter.estimated_false_positive_rate() * 100.0)));
+        frame.render_widget(
+            Paragraph::new(side).block(Block::default().borders(Borders::ALL).title(" activity ")),
+            side_area,
+        );
+
+        let visible = log_area.height.saturating_sub(2) as usize;
+        let log: Vec<Line> = self.log[self.log.len().saturating_sub(visible)..]
+            .iter()
+            .map(|line| Line::from(line.as_str()))
+            .collect();
+        frame.render_widget(Paragraph::new(log).block(Block::default().borders(Borders::ALL).title(" output ")), log_area);
+
+        let prompt = format!("bloom> {}", self.input);
+        let cursor_x = input_area.x + 1 + prompt.chars().count() as u16;
+        frame.render_widget(Paragraph::new(prompt).block(Block::default().borders(Borders::ALL)), input_area);
+        frame.set_cursor_position(Position::new(cursor_x.min(input_area.right().saturating_sub(2)), input_area.y + 1));
+    }
+}
+
+/// Runs the full-screen dashboard until Esc, Ctrl-C or `exit`, then saves the session's filters.
+///
+/// The panels show the active filter's per-level fill (up to eight levels),
+/// insert and query rates, and estimated false-positive rate, refreshed
+/// several times a second; the bottom line takes the same commands as the REPL.
+pub fn run(session: Session) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_dashboard(&mut terminal, Dashboard::new(session));
+    ratatui::restore();
+    let mut session = result?;
+    session.store().flush().map_err(|e| io::Error::other(format!("Failed to save filters: {}", e)))
+}
+
+fn run_dashboard(terminal: &mut DefaultTerminal, mut dashboard: Dashboard) -> io::Result<Session> {
+    loop {
+        dashboard.sample(Instant::now());
+        terminal.draw(|frame| dashboard.draw(frame))?;
+        if !event::poll(TICK)? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !dashboard.key(key.code, key.modifiers) {
+                return Ok(dashboard.session);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bloom_filter::BloomFilter;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn test_dashboard_runs_commands_and_draws_panels() {
+        let mut dashboard = Dashboard::new(Session::with_filter(BloomFilter::new(2, 100, 3).unwrap()));
+        let start = dashboard.sample.0;
+        "insert apple".chars().for_each(|c| {
+            dashboard.key(KeyCode::Char(c), KeyModifiers::NONE);
+        });
+        assert!(dashboard.key(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(dashboard.submit("query apple"));
+        assert!(dashboard.submit("frobnicate"));
+        assert_eq!(dashboard.log[1..3], ["bloom> insert apple", "Item inserted successfully."]);
+        dashboard.key(KeyCode::Up, KeyModifiers::NONE);
+        dashboard.key(KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(dashboard.input, "query apple");
+
+        dashboard.sample(start + Duration::from_secs(2));
+        assert_eq!(dashboard.rates, Rates { inserts: 0.5, queries: 0.5 });
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        terminal.draw(|frame| dashboard.draw(frame)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("level 1 - 1 items") && screen.contains("level 2 - 1 items"));
+        assert!(screen.contains("inserts/s  0.5") && screen.contains("est. FPR"));
+        assert!(screen.contains("bloom> query apple"));
+        assert!(!dashboard.submit("exit"));
+        assert!(!dashboard.key(KeyCode::Esc, KeyModifiers::NONE));
+    }
+}
@@ -0,0 +1,164 @@
+// src/tuning.rs
+
+use serde::Serialize;
+use tracing::info;
+
+use crate::bloom_filter::{splitmix64, BloomFilter, MAX_HASH_FUNCTIONS};
+use crate::math;
+
+/// What a filter must hold and the limits it must respect.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuningParams {
+    pub expected_items: usize,
+    /// Target false-positive rate, between 0 and 1 (exclusive).
+    pub target_false_positive_rate: f64,
+    /// Memory budget for one level's packed bits; `None` means unlimited.
+    pub max_bytes: Option<usize>,
+    /// Most hash functions per insert or query, which bounds their latency.
+    pub max_hash_functions: usize,
+    /// Random never-inserted items to probe each candidate with after
+    /// inserting `expected_items` random items; 0 skips the simulation.
+    pub simulate_probes: usize,
+    /// Seeds the synthetic items, so simulations can be repeated exactly.
+    pub seed: u64,
+}
+
+impl TuningParams {
+    pub fn new(expected_items: usize, target_false_positive_rate: f64) -> Self {
+        TuningParams {
+            expected_items,
+            target_false_positive_rate,
+            max_bytes: None,
+            max_hash_functions: MAX_HASH_FUNCTIONS,
+            simulate_probes: 0,
+            seed: 0,
+        }
+    }
+}
+
+/// One recommended array size and hash count.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TuningCandidate {
+    pub array_size: usize,
+    pub num_hash_functions: usize,
+    /// Size of the packed bits, as saved in the binary format.
+    pub bytes: usize,
+    pub theoretical_false_positive_rate: f64,
+    /// Observed rate on synthetic data, when a simulation was requested.
+    pub measured_false_positive_rate: Option<f64>,
+    /// Whether the theoretical rate reaches the target; only false for the
+    /// fallback returned when no configuration fits the budget.
+    pub meets_target: bool,
+}
+
+/// Sweeps hash counts and array sizes for configurations that meet the target.
+///
+/// For each hash count `k` up to the limit, the smallest array reaching the
+/// target is `m = -k * n / ln(1 - p^(1/k))`. Raising `k` shrinks `m` until the
+/// optimum, after which both memory and latency grow, so only hash counts up
+/// to the optimum are kept: each is a trade of memory for fewer hash
+/// functions. Candidates are ordered smallest first. When the budget rules
+/// out all of them, the single best configuration that fits is returned
+/// instead, marked as missing the target. Invalid targets or zero items give
+/// no candidates.
+pub fn recommend(params: &TuningParams) -> Vec<TuningCandidate> {
+    let n = params.expected_items;
+    let p = params.target_false_positive_rate;
+    if n == 0 || !(p > 0.0 && p < 1.0) {
+        return Vec::new();
+    }
+    let max_bits = params.max_bytes.map(|bytes| bytes.saturating_mul(8));
+    let max_k = params.max_hash_functions.clamp(1, MAX_HASH_FUNCTIONS);
+
+    let mut candidates = Vec::new();
+    let mut smallest = usize::MAX;
+    for k in 1..=max_k {
+        let m = min_bits(n, p, k);
+        if m >= smallest {
+            break;
+        }
+        smallest = m;
+        if max_bits.is_none_or(|max_bits| m <= max_bits) {
+            candidates.push(candidate(m, k, n, true));
+        }
+    }
+    candidates.reverse();
+
+    if candidates.is_empty() {
+        if let Some(m) = max_bits.filter(|&m| m > 0) {
+            let k = math::optimal_hashes(m, n).min(max_k);
+            candidates.push(candidate(m, k, n, false));
+        }
+    }
+    if params.simulate_probes > 0 {
+        for candidate in &mut candidates {
+            candidate.measured_false_positive_rate = Some(simulate(candidate, params));
+        }
+    }
+    info!("Tuning for {} items at {} found {} candidates", n, p, candidates.len());
+    candidates
+}
+
+/// Smallest array reaching rate `p` for `n` items with `k` hash functions.
+fn min_bits(n: usize, p: f64, k: usize) -> usize {
+    let m = -(k as f64) * n as f64 / (1.0 - p.powf(1.0 / k as f64)).ln();
+    (m.ceil() as usize).max(1)
+}
+
+fn candidate(m: usize, k: usize, n: usize, meets_target: bool) -> TuningCandidate {
+    TuningCandidate {
+        array_size: m,
+        num_hash_functions: k,
+        bytes: m.div_ceil(8),
+        theoretical_false_positive_rate: math::false_positive_rate(m, n, k),
+        measured_false_positive_rate: None,
+        meets_target,
+    }
+}
+
+/// Builds the candidate, inserts `expected_items` random items and measures the rate on fresh ones.
+fn simulate(candidate: &TuningCandidate, params: &TuningParams) -> f64 {
+    let mut filter = match BloomFilter::new(1, candidate.array_size, candidate.num_hash_functions) {
+        Ok(filter) => filter,
+        Err(_) => return 1.0,
+    };
+    // Inserted and probe items get different prefixes, so the sets are disjoint.
+    let mut state = params.seed;
+    let mut next = |prefix: char| {
+        state = splitmix64(state);
+        format!("{}{:016x}", prefix, state)
+    };
+    for _ in 0..params.expected_items {
+        filter.insert(&next('i'));
+    }
+    let probes: Vec<String> = (0..params.simulate_probes).map(|_| next('p')).collect();
+    filter.measure_false_positive_rate(&probes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommendations_trade_memory_for_hashes() {
+        let params = TuningParams::new(1000, 0.01);
+        let candidates = recommend(&params);
+        // The optimum for 1% is 7 hash functions; fewer cost more memory.
+        assert_eq!(candidates[0].num_hash_functions, 7);
+        assert_eq!(candidates.last().unwrap().num_hash_functions, 1);
+        assert!(candidates.windows(2).all(|pair| pair[0].array_size < pair[1].array_size));
+        assert!(candidates.iter().all(|c| c.meets_target && c.theoretical_false_positive_rate <= 0.01));
+
+        let limited = recommend(&TuningParams { max_hash_functions: 3, ..params.clone() });
+        assert_eq!(limited[0].num_hash_functions, 3);
+
+        let tight = recommend(&TuningParams { max_bytes: Some(500), simulate_probes: 2000, ..params.clone() });
+        assert_eq!(tight.len(), 1);
+        assert!(!tight[0].meets_target);
+        assert_eq!(tight[0].array_size, 4000);
+        let measured = tight[0].measured_false_positive_rate.unwrap();
+        assert!((measured - tight[0].theoretical_false_positive_rate).abs() < 0.03);
+
+        assert!(recommend(&TuningParams::new(1000, 1.5)).is_empty());
+    }
+}
@@ -1,7 +1,7 @@
 // src/utils.rs
 
-use dialoguer::{Input, Select};
-use log::error;
+use dialoguer::Input;
+use tracing::error;
 
 /// Reads a positive integer from the user with a prompt.
 pub fn read_usize_input(prompt: &str) -> usize {
@@ -51,25 +51,3 @@ pub fn read_string_input(prompt: &str) -> String {
         }
     }
 }
-
-/// Presents a selection menu to the user and returns the selected index.
-pub fn select_operation() -> usize {
-    let operations = vec![
-        "Insert item",
-        "Query item",
-        "Save Bloom Filter",
-        "Load Bloom Filter",
-        "Exit",
-    ];
-    let selection = Select::new()
-        .with_prompt("Choose an operation")
-        .items(&operations)
-        .default(0)
-        .interact_opt()
-        .unwrap_or(None);
-
-    match selection {
-        Some(index) => index,
-        None => 4, // Default to "Exit" if no selection is made
-    }
-}
@@ -0,0 +1,273 @@
+// src/variant.rs
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::age_partitioned::AgePartitionedBloomFilter;
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+use crate::counting::{CounterWidth, CountingBloomFilter};
+use crate::deletable::DeletableBloomFilter;
+use crate::filter::Filter;
+use crate::sliding::SlidingBloomFilter;
+
+/// The filter variants an `AnyFilter` can hold.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterVariant {
+    /// A multi-level `BloomFilter`.
+    #[default]
+    Standard,
+    /// A `CountingBloomFilter`.
+    Counting,
+    /// A `DeletableBloomFilter`.
+    Deletable,
+    /// An `AgePartitionedBloomFilter`.
+    AgePartitioned,
+    /// A `SlidingBloomFilter`.
+    Sliding,
+}
+
+impl FilterVariant {
+    /// Every variant, in the order offered to users.
+    pub const ALL: [FilterVariant; 5] = [
+        FilterVariant::Standard,
+        FilterVariant::Counting,
+        FilterVariant::Deletable,
+        FilterVariant::AgePartitioned,
+        FilterVariant::Sliding,
+    ];
+
+    /// Returns the variant's name, as accepted by `from_str` and written as the tag of a saved `AnyFilter`.
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterVariant::Standard => "standard",
+            FilterVariant::Counting => "counting",
+            FilterVariant::Deletable => "deletable",
+            FilterVariant::AgePartitioned => "age-partitioned",
+            FilterVariant::Sliding => "sliding",
+        }
+    }
+}
+
+impl fmt::Display for FilterVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FilterVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FilterVariant::ALL
+            .into_iter()
+            .find(|variant| variant.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown variant '{}' (expected standard, counting, deletable, age-partitioned or sliding)", s)
+            })
+    }
+}
+
+/// Sizing for `AnyFilter::new`, shared by every variant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariantParams {
+    /// Levels of a standard filter, generations an age-partitioned filter
+    /// remembers beyond its hash count, or buckets of a sliding filter.
+    pub num_levels: usize,
+    /// Bits per level (per slice for age-partitioned filters).
+    pub array_size: usize,
+    pub num_hash_functions: usize,
+    /// Time a sliding filter remembers items; ignored by the other variants.
+    pub window: Duration,
+}
+
+/// A filter of any variant, saved with a tag naming the variant.
+///
+/// The JSON is `{"variant": "counting", "filter": {...}}`, where `filter` is
+/// what the variant's own `save_to_file` writes, so a saved file can be
+/// loaded and used through `Filter` without knowing its type in advance.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "variant", content = "filter", rename_all = "kebab-case")]
+pub enum AnyFilter {
+    Standard(BloomFilter),
+    Counting(CountingBloomFilter),
+    Deletable(DeletableBloomFilter),
+    AgePartitioned(AgePartitionedBloomFilter),
+    Sliding(SlidingBloomFilter),
+}
+
+impl AnyFilter {
+    /// Creates an empty filter of the given variant.
+    ///
+    /// Counting filters get 4-bit counters and deletable filters one region
+    /// per 16 bits. Age-partitioned filters use `num_hash_functions` slices
+    /// per item plus `num_levels` more, with generations sized to fill a
+    /// slice about halfway.
+    pub fn new(variant: FilterVariant, params: &VariantParams) -> Result<Self, BloomFilterError> {
+        info!("Creating {} filter", variant);
+        let VariantParams {
+            num_levels,
+            array_size,
+            num_hash_functions,
+            window,
+        } = *params;
+        Ok(match variant {
+            FilterVariant::Standard => AnyFilter::Standard(BloomFilter::new(num_levels, array_size, num_hash_functions)?),
+            FilterVariant::Counting => AnyFilter::Counting(CountingBloomFilter::new(
+                array_size,
+                num_hash_functions,
+                CounterWidth::Four,
+            )?),
+            FilterVariant::Deletable => AnyFilter::Deletable(DeletableBloomFilter::new(
+                array_size,
+                num_hash_functions,
+                array_size.div_ceil(16),
+            )?),
+            FilterVariant::AgePartitioned => {
+                let generation_items = array_size as f64 * std::f64::consts::LN_2 / num_hash_functions.max(1) as f64;
+                AnyFilter::AgePartitioned(AgePartitionedBloomFilter::new(
+                    num_hash_functions,
+                    num_levels,
+                    array_size,
+                    (generation_items as usize).max(1),
+                )?)
+            }
+            FilterVariant::Sliding => AnyFilter::Sliding(SlidingBloomFilter::new(
+                window,
+                num_levels,
+                array_size,
+                num_hash_functions,
+            )?),
+        })
+    }
+
+    /// Returns which variant this is.
+    pub fn variant(&self) -> FilterVariant {
+        match self {
+            AnyFilter::Standard(_) => FilterVariant::Standard,
+            AnyFilter::Counting(_) => FilterVariant::Counting,
+            AnyFilter::Deletable(_) => FilterVariant::Deletable,
+            AnyFilter::AgePartitioned(_) => FilterVariant::AgePartitioned,
+            AnyFilter::Sliding(_) => FilterVariant::Sliding,
+        }
+    }
+
+    /// Returns the filter behind the variant-independent interface.
+    pub fn as_filter(&self) -> &dyn Filter {
+        match self {
+            AnyFilter::Standard(filter) => filter,
+            AnyFilter::Counting(filter) => filter,
+            AnyFilter::Deletable(filter) => filter,
+            AnyFilter::AgePartitioned(filter) => filter,
+            AnyFilter::Sliding(filter) => filter,
+        }
+    }
+
+    /// Mutable counterpart of `as_filter`.
+    pub fn as_filter_mut(&mut self) -> &mut dyn Filter {
+        match self {
+            AnyFilter::Standard(filter) => filter,
+            AnyFilter::Counting(filter) => filter,
+            AnyFilter::Deletable(filter) => filter,
+            AnyFilter::AgePartitioned(filter) => filter,
+            AnyFilter::Sliding(filter) => filter,
+        }
+    }
+
+    /// Saves the filter and its variant tag as JSON.
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), BloomFilterError> {
+        info!("Saving {} filter to file: {}", self.variant(), filepath);
+        let writer = BufWriter::new(File::create(filepath)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Loads a filter saved with `save_to_file`, whatever its variant.
+    ///
+    /// Untagged JSON saved by `BloomFilter::save_to_file` loads as a standard filter.
+    pub fn load_from_file(filepath: &str) -> Result<Self, BloomFilterError> {
+        info!("Loading tagged filter from file: {}", filepath);
+        let bytes = fs::read(filepath)?;
+        let any = match serde_json::from_slice::<AnyFilter>(&bytes) {
+            Ok(any) => any,
+            Err(e) => match serde_json::from_slice::<BloomFilter>(&bytes) {
+                Ok(filter) => AnyFilter::Standard(filter),
+                Err(_) => {
+                    error!("Failed to parse {} as a tagged filter: {}", filepath, e);
+                    return Err(e.into());
+                }
+            },
+        };
+        any.validate()?;
+        Ok(any)
+    }
+
+    fn validate(&self) -> Result<(), BloomFilterError> {
+        match self {
+            AnyFilter::Standard(filter) => filter.validate(),
+            AnyFilter::Counting(filter) => filter.validate(),
+            AnyFilter::Deletable(filter) => filter.validate(),
+            AnyFilter::AgePartitioned(filter) => filter.validate(),
+            AnyFilter::Sliding(filter) => filter.validate(),
+        }
+    }
+}
+
+impl Filter for AnyFilter {
+    fn insert(&mut self, item: &[u8]) -> bool {
+        self.as_filter_mut().insert(item)
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.as_filter().contains(item)
+    }
+
+    fn estimated_fpr(&self) -> f64 {
+        self.as_filter().estimated_fpr()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BloomFilterError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_without_knowing_the_variant() {
+        let params = VariantParams {
+            num_levels: 2,
+            array_size: 1000,
+            num_hash_functions: 3,
+            window: Duration::from_secs(60),
+        };
+        for variant in FilterVariant::ALL {
+            assert_eq!(variant.name().parse::<FilterVariant>(), Ok(variant));
+            let mut any = AnyFilter::new(variant, &params).unwrap();
+            any.insert(b"alpha");
+
+            let path = std::env::temp_dir().join(format!("test_bloom_variant_{}.json", variant));
+            let path = path.to_str().unwrap();
+            any.save_to_file(path).unwrap();
+            let loaded = AnyFilter::load_from_file(path).unwrap();
+            assert_eq!(loaded.variant(), variant);
+            assert!(loaded.contains(b"alpha") && !loaded.contains(b"beta"));
+            std::fs::remove_file(path).unwrap();
+        }
+        assert!("cuckoo".parse::<FilterVariant>().is_err());
+
+        let path = std::env::temp_dir().join("test_bloom_variant_untagged.json");
+        let path = path.to_str().unwrap();
+        let mut plain = BloomFilter::new(1, 1000, 3).unwrap();
+        plain.insert("alpha");
+        plain.save_to_file(path).unwrap();
+        assert_eq!(AnyFilter::load_from_file(path).unwrap().variant(), FilterVariant::Standard);
+        std::fs::remove_file(path).unwrap();
+    }
+}
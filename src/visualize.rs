@@ -0,0 +1,139 @@
+// src/visualize.rs
+
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::bloom_filter::{unix_now, BloomFilter};
+
+/// The characters `visualize` shades blocks with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shading {
+    /// Unicode shade blocks: ` ░▒▓█`.
+    #[default]
+    Unicode,
+    /// Plain ASCII for terminals without Unicode: ` .:#@`.
+    Ascii,
+}
+
+impl Shading {
+    /// Shades from an empty block to a full one.
+    fn ramp(self) -> [char; 5] {
+        match self {
+            Shading::Unicode => [' ', '░', '▒', '▓', '█'],
+            Shading::Ascii => [' ', '.', ':', '#', '@'],
+        }
+    }
+
+    /// Picks the shade for a block with `ones` of `len` bits set.
+    ///
+    /// Only an empty block is blank and only a full one solid; the partly
+    /// filled ones split the middle shades evenly.
+    fn shade(self, ones: usize, len: usize) -> char {
+        let ramp = self.ramp();
+        let index = match ones {
+            0 => 0,
+            ones if ones >= len => ramp.len() - 1,
+            ones => 1 + (ones * (ramp.len() - 2) / len).min(ramp.len() - 3),
+        };
+        ramp[index]
+    }
+}
+
+impl fmt::Display for Shading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Shading::Unicode => "unicode",
+            Shading::Ascii => "ascii",
+        })
+    }
+}
+
+impl FromStr for Shading {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unicode" => Ok(Shading::Unicode),
+            "ascii" => Ok(Shading::Ascii),
+            _ => Err(format!("unknown shading '{}' (expected unicode or ascii)", s)),
+        }
+    }
+}
+
+impl BloomFilter {
+    /// Draws each level's bit array as 64 blocks shaded by how full they are.
+    pub fn visualize(&self) -> String {
+        self.visualize_with(64, Shading::Unicode)
+    }
+
+    /// Draws each level's bit array as `width` blocks shaded by how full they are.
+    ///
+    /// Each level is one line: the blocks between bars, then the level's fill
+    /// and the range of block fills. A good hash spreads bits evenly, so the
+    /// blocks look alike and the range is narrow; dark and light bands, or a
+    /// wide range, point to clustering. Arrays smaller than `width` get one
+    /// block per bit.
+    pub fn visualize_with(&self, width: usize, shading: Shading) -> String {
+        let now = unix_now();
+        let blocks = width.clamp(1, self.array_size.max(1));
+        // Block `b` covers bits `start(b)..start(b + 1)`, the same split as `bit * blocks / array_size`.
+        let start = |block: usize| (block * self.array_size).div_ceil(blocks);
+        let mut out = String::new();
+        for (index, level) in self.levels.iter().enumerate() {
+            let mut ones = vec![0; blocks];
+            for bit in level.bit_array.iter_ones() {
+                ones[bit * blocks / self.array_size] += 1;
+            }
+            let fills: Vec<f64> = (0..blocks)
+                .map(|block| ones[block] as f64 / (start(block + 1) - start(block)).max(1) as f64)
+                .collect();
+            let cells: String = (0..blocks)
+                .map(|block| shading.shade(ones[block], start(block + 1) - start(block)))
+                .collect();
+            let name = level.label.as_deref().map(|label| format!(" \"{}\"", label)).unwrap_or_default();
+            let _ = write!(
+                out,
+                "{}level {}{} |{}| {:.1}% set, blocks {:.1}%-{:.1}%",
+                if index > 0 { "\n" } else { "" },
+                index + 1,
+                name,
+                cells,
+                level.fill_ratio() * 100.0,
+                fills.iter().copied().fold(f64::INFINITY, f64::min) * 100.0,
+                fills.iter().copied().fold(0.0, f64::max) * 100.0
+            );
+            if level.is_expired_at(now) {
+                out.push_str(" (expired)");
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visualize_shades_blocks_by_fill() {
+        let mut bf = BloomFilter::new(2, 100, 1).unwrap();
+        bf.set_level_label(1, Some("cold".to_string())).unwrap();
+        // Fill the first quarter of level 1 only.
+        let positions: Vec<usize> = (0..25).collect();
+        bf.levels[0].insert_positions(&positions);
+        let picture = bf.visualize_with(4, Shading::Ascii);
+        assert_eq!(
+            picture,
+            "level 1 |@   | 25.0% set, blocks 0.0%-100.0%\nlevel 2 \"cold\" |    | 0.0% set, blocks 0.0%-0.0%"
+        );
+
+        bf.levels[0].insert_positions(&[30, 31, 60]);
+        let first = bf.visualize_with(4, Shading::Unicode);
+        assert_eq!(first.lines().next(), Some("level 1 |█░░ | 28.0% set, blocks 0.0%-100.0%"));
+        assert_eq!(bf.visualize().lines().count(), 2);
+        // One block per bit when the array is narrower than the width.
+        let wide = bf.visualize_with(1000, Shading::Ascii);
+        assert_eq!(wide.split('|').nth(1).map(|cells| cells.chars().filter(|&c| c == '@').count()), Some(28));
+        assert_eq!("ASCII".parse::<Shading>(), Ok(Shading::Ascii));
+    }
+}
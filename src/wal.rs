@@ -0,0 +1,207 @@
+// src/wal.rs
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::bloom_filter::{BloomFilter, BloomFilterError};
+
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+const WAL_FILE: &str = "filter.wal";
+
+/// A Bloom filter persisted as a periodic checkpoint plus a write-ahead log.
+///
+/// Every insert is appended to the log and synced to disk before it is
+/// acknowledged, and the full filter is checkpointed every
+/// `checkpoint_interval` inserts, after which the log is truncated. Opening the same directory again loads the checkpoint and
+/// replays the log, so a crash loses at most the insert that was being written.
+pub struct WalBloomFilter {
+    filter: BloomFilter,
+    checkpoint_path: PathBuf,
+    wal_path: PathBuf,
+    wal: BufWriter<File>,
+    checkpoint_interval: usize,
+    pending: usize,
+}
+
+impl WalBloomFilter {
+    /// Opens (or initializes) a WAL-backed filter stored in `dir`.
+    ///
+    /// If `dir` already holds a checkpoint it is loaded and `filter` is ignored;
+    /// otherwise `filter` becomes the initial state. Any logged inserts are
+    /// replayed on top of the checkpoint.
+    pub fn open(dir: &str, filter: BloomFilter, checkpoint_interval: usize) -> Result<Self, BloomFilterError> {
+        let dir = Path::new(dir);
+        fs::create_dir_all(dir)?;
+        let checkpoint_path = dir.join(CHECKPOINT_FILE);
+        let wal_path = dir.join(WAL_FILE);
+
+        let mut filter = if checkpoint_path.exists() {
+            BloomFilter::load_from_file(&checkpoint_path.to_string_lossy())?
+        } else {
+            filter
+        };
+
+        let mut pending = 0;
+        if wal_path.exists() {
+            pending = replay(&mut filter, &wal_path)?;
+            info!("Replayed {} inserts from {}", pending, wal_path.display());
+        }
+
+        let wal = OpenOptions::new().create(true).append(true).open(&wal_path)?;
+        sync_dir(dir)?;
+        Ok(WalBloomFilter {
+            filter,
+            checkpoint_path,
+            wal_path,
+            wal: BufWriter::new(wal),
+            checkpoint_interval: checkpoint_interval.max(1),
+            pending,
+        })
+    }
+
+    /// Logs and inserts an item, checkpointing once the interval is reached.
+    ///
+    /// The log entry is synced before the item is inserted, so an acknowledged
+    /// insert survives a crash or power loss. Returns whether the item was definitely new, as `BloomFilter::insert` does.
+    pub fn insert(&mut self, item: &str) -> Result<bool, BloomFilterError> {
+        serde_json::to_writer(&mut self.wal, item)?;
+        self.wal.write_all(b"\n")?;
+        self.wal.flush()?;
+        self.wal.get_ref().sync_data()?;
+        let newly_set = self.filter.insert(item);
+        self.pending += 1;
+        if self.pending >= self.checkpoint_interval {
+            self.checkpoint()?;
+        }
+        Ok(newly_set)
+    }
+
+    /// Queries an item across the specified number of levels.
+    pub fn query(&self, item: &str, num_levels_to_search: usize) -> bool {
+        self.filter.query(item, num_levels_to_search)
+    }
+
+    /// Writes a full checkpoint and truncates the log.
+    ///
+    /// The checkpoint is written to a temporary file, synced, and renamed into
+    /// place, and the directory is synced before the log is truncated, so a
+    /// crash at any point leaves either the previous checkpoint and log or the
+    /// new checkpoint.
+    pub fn checkpoint(&mut self) -> Result<(), BloomFilterError> {
+        info!("Checkpointing WAL filter to {}", self.checkpoint_path.display());
+        let tmp_path = self.checkpoint_path.with_extension("json.tmp");
+        let mut tmp = BufWriter::new(File::create(&tmp_path)?);
+        self.filter.save_to_writer(&mut tmp)?;
+        tmp.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp_path, &self.checkpoint_path)?;
+        if let Some(dir) = self.checkpoint_path.parent() {
+            sync_dir(dir)?;
+        }
+
+        let wal = File::create(&self.wal_path)?;
+        wal.sync_all()?;
+        self.wal = BufWriter::new(OpenOptions::new().append(true).open(&self.wal_path)?);
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Returns the number of inserts logged since the last checkpoint.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Returns the underlying filter.
+    pub fn filter(&self) -> &BloomFilter {
+        &self.filter
+    }
+}
+
+/// Syncs a directory, making renames and newly created files in it durable.
+///
+/// Only Unix can open a directory to sync it; elsewhere this does nothing.
+fn sync_dir(dir: &Path) -> Result<(), BloomFilterError> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Replays a log into `filter`, returning the number of entries applied.
+///
+/// A torn final entry (from a crash mid-append) is skipped with a warning and
+/// truncated away so later appends start on a clean line.
+fn replay(filter: &mut BloomFilter, wal_path: &Path) -> Result<usize, BloomFilterError> {
+    let mut reader = BufReader::new(File::open(wal_path)?);
+    let mut line = Vec::new();
+    let mut count = 0;
+    let mut valid_len = 0u64;
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        let complete = line.last() == Some(&b'\n');
+        match serde_json::from_slice::<String>(&line) {
+            Ok(item) if complete => {
+                filter.insert(&item);
+                count += 1;
+                valid_len += read as u64;
+            }
+            Ok(_) => {
+                warn!("Ignoring unterminated WAL entry at end of {}", wal_path.display());
+                break;
+            }
+            Err(e) if !complete => {
+                warn!("Ignoring torn WAL entry at end of {}: {}", wal_path.display(), e);
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    OpenOptions::new().write(true).open(wal_path)?.set_len(valid_len)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wal_replay_and_checkpoint() {
+        let dir = std::env::temp_dir().join("test_bloom_wal");
+        let _ = fs::remove_dir_all(&dir);
+        let dir_str = dir.to_str().unwrap();
+
+        {
+            let mut wal = WalBloomFilter::open(dir_str, BloomFilter::new(1, 100, 3).unwrap(), 2).unwrap();
+            wal.insert("alpha").unwrap();
+            wal.insert("beta").unwrap();
+            assert_eq!(wal.pending(), 0);
+            wal.insert("gamma").unwrap();
+            assert_eq!(wal.pending(), 1);
+        }
+
+        // Simulate a torn write after the last complete entry.
+        let mut log = OpenOptions::new().append(true).open(dir.join(WAL_FILE)).unwrap();
+        log.write_all(b"\"del").unwrap();
+        drop(log);
+
+        let mut wal = WalBloomFilter::open(dir_str, BloomFilter::new(1, 100, 3).unwrap(), 10).unwrap();
+        assert_eq!(wal.pending(), 1);
+        assert!(wal.query("alpha", 1));
+        assert!(wal.query("gamma", 1));
+        assert!(!wal.query("delta", 1));
+        wal.insert("epsilon").unwrap();
+        drop(wal);
+
+        let wal = WalBloomFilter::open(dir_str, BloomFilter::new(1, 100, 3).unwrap(), 10).unwrap();
+        assert_eq!(wal.pending(), 2);
+        assert!(wal.query("epsilon", 1));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}